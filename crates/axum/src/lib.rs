@@ -10,14 +10,18 @@ use std::{collections::HashMap, sync::Arc};
 use axum::{
     body::{to_bytes, Body},
     extract::{Request, State},
-    http::{request::Parts, Method, Response, StatusCode},
+    http::{request::Parts, response::Builder, Method, Response, StatusCode},
     response::IntoResponse,
     routing::{on, MethodFilter},
     RequestExt, Router,
 };
 use extractors::TCtxFunc;
 use rspc::internal::{
-    jsonrpc::{self, handle_json_rpc, RequestId, Sender, SubscriptionMap},
+    jsonrpc::{
+        self, build_context, handle_json_rpc, pre_context, rewrite_frame, with_headers,
+        with_http_response, with_transport, Headers, HttpResponse, RequestId, Sender,
+        SubscriptionMap, Transport,
+    },
     ProcedureKind,
 };
 use serde_json::Value;
@@ -47,20 +51,21 @@ where
                             #[cfg(feature = "ws")]
                             {
                                 let mut req = req;
-                                return req
+                                let upgrade = req
                                     .extract_parts::<axum::extract::ws::WebSocketUpgrade>()
                                     .await
-                                    .unwrap() // TODO: error handling
+                                    .unwrap(); // TODO: error handling
+                                let parts = req.into_parts().0;
+                                let format =
+                                    jsonrpc::negotiate_format(&router, &request_headers(&parts));
+                                upgrade
+                                    .protocols(format.as_ref().map(|format| format.protocol()))
                                     .on_upgrade(|socket| {
                                         handle_websocket(
-                                            ctx_fn,
-                                            socket,
-                                            req.into_parts().0,
-                                            router,
-                                            state.0,
+                                            ctx_fn, socket, parts, router, format, state.0,
                                         )
                                     })
-                                    .into_response();
+                                    .into_response()
                             }
 
                             #[cfg(not(feature = "ws"))]
@@ -101,14 +106,22 @@ where
 {
     let procedure_name = req.uri().path()[1..].to_string(); // Has to be allocated because `TCtxFn` takes ownership of `req`
     let (parts, body) = req.into_parts();
+    let headers = request_headers(&parts);
     let input = match parts.method {
-        Method::GET => parts
-            .uri
-            .query()
-            .map(|query| form_urlencoded::parse(query.as_bytes()))
-            .and_then(|mut params| params.find(|e| e.0 == "input").map(|e| e.1))
-            .map(|v| serde_json::from_str(&v))
-            .unwrap_or(Ok(None as Option<Value>)),
+        Method::GET => {
+            let params = form_urlencoded::parse(parts.uri.query().unwrap_or_default().as_bytes())
+                .collect::<Vec<_>>();
+            match params.iter().find(|(name, _)| name == "input") {
+                Some((_, input)) => serde_json::from_str(input),
+                // REST-style parameters (Eg. `?page=2`) for queries whose input is a flat struct
+                None if !params.is_empty() && matches!(kind, ProcedureKind::Query) => Ok(router
+                    .query_params_input(
+                        &procedure_name,
+                        params.iter().map(|(name, value)| (&**name, &**value)),
+                    )),
+                None => Ok(None as Option<Value>),
+            }
+        }
         Method::POST => {
             // TODO: Limit body size?
             let body = to_bytes(body, usize::MAX).await.unwrap(); // TODO: error handling
@@ -146,39 +159,40 @@ where
         input
     );
 
-    let mut resp = Sender::Response(None);
-
-    let ctx = match ctx_fn.exec(parts, &state).await {
-        Ok(ctx) => ctx,
-        Err(_err) => {
-            #[cfg(feature = "tracing")]
-            tracing::error!("Error executing context function: {}", _err);
+    let request = jsonrpc::Request {
+        jsonrpc: None,
+        id: RequestId::Null,
+        inner: match kind {
+            ProcedureKind::Query => jsonrpc::RequestInner::Query {
+                path: procedure_name.to_string(), // TODO: Lifetime instead of allocate?
+                input,
+            },
+            ProcedureKind::Mutation => jsonrpc::RequestInner::Mutation {
+                path: procedure_name.to_string(), // TODO: Lifetime instead of allocate?
+                input,
+            },
+            ProcedureKind::Subscription => {
+                #[cfg(feature = "tracing")]
+                tracing::error!("Attempted to execute a subscription operation with HTTP");
 
-            return Response::builder()
-                .status(StatusCode::INTERNAL_SERVER_ERROR)
-                .header("Content-Type", "application/json")
-                .body(Body::from(b"[]".as_slice()))
-                .unwrap();
-        }
+                return Response::builder()
+                    .status(StatusCode::INTERNAL_SERVER_ERROR)
+                    .header("Content-Type", "application/json")
+                    .body(Body::from(b"[]".as_slice()))
+                    .unwrap();
+            }
+        },
     };
 
-    handle_json_rpc(
-        ctx,
-        jsonrpc::Request {
-            jsonrpc: None,
-            id: RequestId::Null,
-            inner: match kind {
-                ProcedureKind::Query => jsonrpc::RequestInner::Query {
-                    path: procedure_name.to_string(), // TODO: Lifetime instead of allocate?
-                    input,
-                },
-                ProcedureKind::Mutation => jsonrpc::RequestInner::Mutation {
-                    path: procedure_name.to_string(), // TODO: Lifetime instead of allocate?
-                    input,
-                },
-                ProcedureKind::Subscription => {
+    let mut resp = Sender::Response(None);
+
+    let http = match pre_context(router, &request).await {
+        Ok(()) => {
+            let ctx = match build_context(router, ctx_fn.exec(parts, &state)).await {
+                Ok(ctx) => ctx,
+                Err(_err) => {
                     #[cfg(feature = "tracing")]
-                    tracing::error!("Attempted to execute a subscription operation with HTTP");
+                    tracing::error!("Error executing context function: {}", _err);
 
                     return Response::builder()
                         .status(StatusCode::INTERNAL_SERVER_ERROR)
@@ -186,71 +200,194 @@ where
                         .body(Body::from(b"[]".as_slice()))
                         .unwrap();
                 }
-            },
-        },
-        router,
-        &mut resp,
-        &mut SubscriptionMap::None,
-    )
-    .await;
+            };
+
+            let (_, http) = with_transport(
+                Transport::Http,
+                with_headers(
+                    headers.clone(),
+                    with_http_response(handle_json_rpc(
+                        ctx,
+                        request,
+                        router,
+                        &mut resp,
+                        &mut SubscriptionMap::None,
+                    )),
+                ),
+            )
+            .await;
+            http
+        }
+        // A pre-context middleware rejected the request
+        Err(rejected) => {
+            resp = Sender::Response(Some(rejected));
+            HttpResponse::default()
+        }
+    };
+
+    // The resolver returned a `rspc::Redirect`
+    if let (
+        Some(location),
+        Sender::Response(Some(jsonrpc::Response {
+            result: jsonrpc::ResponseInner::Response(_),
+            ..
+        })),
+    ) = (&http.redirect, &resp)
+    {
+        return Response::builder()
+            .status(StatusCode::FOUND)
+            .header("Location", location)
+            .body(Body::empty())
+            .unwrap();
+    }
+
+    // The resolver returned a `rspc::RawStream`
+    if let (
+        Some(body),
+        Sender::Response(Some(jsonrpc::Response {
+            result: jsonrpc::ResponseInner::Response(_),
+            ..
+        })),
+    ) = (http.body, &resp)
+    {
+        let (content_type, stream) = body.into_parts();
+        return with_cache_control(Response::builder(), http.cache_control.as_deref())
+            .status(StatusCode::OK)
+            .header("Content-Type", content_type)
+            .body(Body::from_stream(stream))
+            .unwrap();
+    }
+
+    // The `Cache-Control` of a query is only sent if it succeeded
+    let cache_control = match &resp {
+        Sender::Response(Some(jsonrpc::Response {
+            result: jsonrpc::ResponseInner::Response(_) | jsonrpc::ResponseInner::NoContent,
+            ..
+        })) => http.cache_control.as_deref(),
+        _ => None,
+    };
 
     match resp {
-        Sender::Response(Some(resp)) => match serde_json::to_vec(&resp) {
-            Ok(v) => Response::builder()
-                .status(StatusCode::OK)
-                .header("Content-Type", "application/json")
-                .body(Body::from(v))
-                .unwrap(),
-            Err(_err) => {
-                #[cfg(feature = "tracing")]
-                tracing::error!("Error serializing response: {}", _err);
+        Sender::Response(Some(resp)) => {
+            let resp = rewrite_frame(router, &headers, resp);
+
+            // The resolver returned a `rspc::NoContent`
+            if let jsonrpc::ResponseInner::NoContent = resp.result {
+                return with_cache_control(Response::builder(), cache_control)
+                    .status(StatusCode::NO_CONTENT)
+                    .body(Body::empty())
+                    .unwrap();
+            }
 
-                Response::builder()
-                    .status(StatusCode::INTERNAL_SERVER_ERROR)
+            match serde_json::to_vec(&resp) {
+                Ok(v) => with_cache_control(Response::builder(), cache_control)
+                    .status(StatusCode::OK)
                     .header("Content-Type", "application/json")
-                    .body(Body::from(b"[]".as_slice()))
-                    .unwrap()
+                    .body(Body::from(v))
+                    .unwrap(),
+                Err(_err) => {
+                    #[cfg(feature = "tracing")]
+                    tracing::error!("Error serializing response: {}", _err);
+
+                    Response::builder()
+                        .status(StatusCode::INTERNAL_SERVER_ERROR)
+                        .header("Content-Type", "application/json")
+                        .body(Body::from(b"[]".as_slice()))
+                        .unwrap()
+                }
             }
-        },
+        }
         _ => unreachable!(),
     }
 }
 
+// The `Accept` header can select the result's representation (See `rspc::representation`), so caches have to key the response by it
+fn with_cache_control(builder: Builder, directive: Option<&str>) -> Builder {
+    match directive {
+        Some(directive) => builder
+            .header("Cache-Control", directive)
+            .header("Vary", "Accept"),
+        None => builder,
+    }
+}
+
+// Headers which aren't valid UTF-8 are skipped
+fn request_headers(parts: &Parts) -> Headers {
+    parts
+        .headers
+        .iter()
+        .filter_map(|(name, value)| Some((name.as_str(), value.to_str().ok()?)))
+        .collect()
+}
+
 #[cfg(feature = "ws")]
 async fn handle_websocket<TCtx, TCtxFn, TCtxFnMarker, TState>(
     ctx_fn: TCtxFn,
     mut socket: axum::extract::ws::WebSocket,
     parts: Parts,
     router: Arc<rspc::Router<TCtx>>,
+    format: Option<Arc<dyn rspc::BinaryFormat>>,
     state: TState,
 ) where
     TCtx: Send + Sync + 'static,
     TCtxFn: TCtxFunc<TCtx, TState, TCtxFnMarker>,
     TState: Send + Sync,
 {
-    use axum::extract::ws::Message;
+    use axum::extract::ws::{CloseFrame, Message};
     use futures::StreamExt;
+    use rspc::internal::jsonrpc::{
+        close_frame, decode_frame, Connection, Frame, RequestQueue, Sender2,
+    };
     use tokio::sync::mpsc;
 
     #[cfg(feature = "tracing")]
     tracing::debug!("Accepting websocket connection");
 
-    let mut subscriptions = HashMap::new();
-    let (mut tx, mut rx) = mpsc::channel::<jsonrpc::Response>(100);
+    let headers = request_headers(&parts);
+    let subscriptions = Arc::new(tokio::sync::Mutex::new(HashMap::new()));
+    let (tx, mut rx) = mpsc::channel::<jsonrpc::Response>(100);
+    let connection =
+        Connection::new(&router).with_notifier(rspc::Notifier::new(Sender2::Channel(tx.clone())));
+    // Requests are executed in the order they're received, with queries executed concurrently (bounded by `Config::max_concurrent_requests`)
+    let (queue, execute) = RequestQueue::new(
+        router.clone(),
+        connection.clone(),
+        subscriptions.clone(),
+        tx.clone(),
+    );
+    tokio::spawn(with_transport(
+        Transport::WebSocket,
+        with_headers(headers.clone(), execute),
+    ));
 
     loop {
         tokio::select! {
             biased; // Note: Order is important here
             msg = rx.recv() => {
-                match socket.send(Message::Text(match serde_json::to_string(&msg) {
-                    Ok(v) => v,
+                let Some(msg) = msg else {
+                    continue;
+                };
+                // Taken before the frame is rewritten and encoded, and released however it leaves the queue
+                let reserved = Connection::reserved_len(&msg);
+                let msg = rewrite_frame(&router, &headers, msg);
+                let (msg, len) = match frame_message(format.as_deref(), &msg) {
+                    Ok(msg) => msg,
                     Err(_err) => {
                         #[cfg(feature = "tracing")]
                         tracing::error!("Error serializing websocket message: {}", _err);
 
+                        if reserved > 0 {
+                            connection.record_flushed(reserved);
+                        }
                         continue;
                     }
-                })).await {
+                };
+                let within_limit = connection.record_sent(len);
+                let sent = socket.send(msg).await;
+                if reserved > 0 {
+                    connection.record_flushed(reserved);
+                }
+                match sent {
                     Ok(_) => {}
                     Err(_err) => {
                         #[cfg(feature = "tracing")]
@@ -259,44 +396,73 @@ async fn handle_websocket<TCtx, TCtxFn, TCtxFnMarker, TState>(
                         continue;
                     }
                 }
+
+                if !within_limit {
+                    let frame = rspc::CloseFrame::NORMAL;
+                    let _ = socket.send(Message::Close(Some(CloseFrame { code: frame.code, reason: frame.wire_reason() }))).await;
+                    return;
+                }
             }
             msg = socket.next() => {
                 match msg {
                     Some(Ok(msg)) => {
                        let res = match msg {
-                            Message::Text(text) => serde_json::from_str::<Value>(&text),
-                            Message::Binary(binary) => serde_json::from_slice(&binary),
+                            Message::Text(text) => decode_frame(format.as_deref(), &Frame::Text(text)),
+                            Message::Binary(binary) => decode_frame(format.as_deref(), &Frame::Binary(binary)),
                             Message::Ping(_) | Message::Pong(_) | Message::Close(_) => {
                                 continue;
                             }
                         };
 
-                        match res.and_then(|v| match v.is_array() {
+                        let res = res.and_then(|v| match v.is_array() {
                             true => serde_json::from_value::<Vec<jsonrpc::Request>>(v),
                             false => serde_json::from_value::<jsonrpc::Request>(v).map(|v| vec![v]),
-                        }) {
+                        }.map_err(rspc::ExecError::InvalidRequest));
+                        match res {
                             Ok(reqs) => {
                                 for request in reqs {
-                                    let ctx = match ctx_fn.exec(parts.clone(), &state).await {
+                                    if let Err(rejected) = pre_context(&router, &request).await {
+                                        // Sent straight to the socket, as this task is the only reader of `rx` so waiting for room in it could deadlock
+                                        let rejected = rewrite_frame(&router, &headers, rejected);
+                                        let Ok((msg, len)) = frame_message(format.as_deref(), &rejected) else {
+                                            continue;
+                                        };
+                                        connection.record_sent(len);
+                                        let _ = socket.send(msg).await;
+                                        continue;
+                                    }
+
+                                    let ctx = match build_context(&router, ctx_fn.exec(parts.clone(), &state)).await {
                                         Ok(ctx) => {
                                             ctx
                                         },
-                                        Err(_err) => {
+                                        Err(err) => {
 
                                             #[cfg(feature = "tracing")]
-                                            tracing::error!("Error executing context function: {}", _err);
+                                            tracing::error!("Error executing context function: {}", err);
+
+                                            if let Some(frame) = close_frame(&router, &err) {
+                                                let _ = socket.send(Message::Close(Some(CloseFrame { code: frame.code, reason: frame.wire_reason() }))).await;
+                                                connection.close();
+                                                return;
+                                            }
 
                                             continue;
                                         }
                                     };
 
-                                    handle_json_rpc(ctx, request, &router, &mut Sender::Channel(&mut tx),
-                                    &mut SubscriptionMap::Ref(&mut subscriptions)).await;
+                                    queue.push(ctx, request).await;
                                 }
                             },
-                            Err(_err) => {
+                            Err(err) => {
                                 #[cfg(feature = "tracing")]
-                                tracing::error!("Error parsing websocket message: {}", _err);
+                                tracing::error!("Error parsing websocket message: {}", err);
+
+                                if let Some(frame) = close_frame(&router, &err) {
+                                    let _ = socket.send(Message::Close(Some(CloseFrame { code: frame.code, reason: frame.wire_reason() }))).await;
+                                    connection.close();
+                                    return;
+                                }
 
                                 // TODO: Send report of error to frontend
 
@@ -318,6 +484,7 @@ async fn handle_websocket<TCtx, TCtxFn, TCtxFnMarker, TState>(
 
                         // TODO: Send report of error to frontend
 
+                        connection.close();
                         return;
                     },
                 }
@@ -325,3 +492,24 @@ async fn handle_websocket<TCtx, TCtxFn, TCtxFnMarker, TState>(
         }
     }
 }
+
+/// Encode a response into a websocket message, along with its length which counts towards `Config::max_bytes_per_connection`.
+#[cfg(feature = "ws")]
+fn frame_message(
+    format: Option<&dyn rspc::BinaryFormat>,
+    resp: &jsonrpc::Response,
+) -> Result<(axum::extract::ws::Message, usize), Box<dyn std::error::Error + Send + Sync>> {
+    use axum::extract::ws::Message;
+    use rspc::internal::jsonrpc::{encode_frame, Frame};
+
+    Ok(match encode_frame(format, resp)? {
+        Frame::Text(text) => {
+            let len = text.len();
+            (Message::Text(text), len)
+        }
+        Frame::Binary(bytes) => {
+            let len = bytes.len();
+            (Message::Binary(bytes), len)
+        }
+    })
+}
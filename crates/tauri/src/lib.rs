@@ -14,8 +14,8 @@ use tauri::{
 use tokio::sync::{mpsc, Mutex};
 
 use rspc::{
-    internal::jsonrpc::{self, handle_json_rpc, Sender, SubscriptionMap},
-    Router,
+    internal::jsonrpc::{self, handle_json_rpc, with_transport, Sender, SubscriptionMap},
+    Router, Transport,
 };
 
 pub fn plugin<R: Runtime, TCtx, TMeta>(
@@ -42,12 +42,15 @@ where
                         let mut resp_tx = resp_tx.clone();
                         let subscriptions = subscriptions.clone();
                         tokio::spawn(async move {
-                            handle_json_rpc(
-                                ctx,
-                                req,
-                                &router,
-                                &mut Sender::ResponseChannel(&mut resp_tx),
-                                &mut SubscriptionMap::Mutex(subscriptions.borrow()),
+                            with_transport(
+                                Transport::Tauri,
+                                handle_json_rpc(
+                                    ctx,
+                                    req,
+                                    &router,
+                                    &mut Sender::ResponseChannel(&mut resp_tx),
+                                    &mut SubscriptionMap::Mutex(subscriptions.borrow()),
+                                ),
                             )
                             .await;
                         });
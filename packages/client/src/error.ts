@@ -1,9 +1,15 @@
 export class RSPCError {
   code: number;
   message: string;
+  // The `ErrorKind` exported into your bindings. This is `undefined` for servers which don't send it.
+  kind?: string;
+  // Extra information about the error. For input validation errors this is `{ errors: FieldError[] }`.
+  data?: any;
 
-  constructor(code: number, message: string) {
+  constructor(code: number, message: string, kind?: string, data?: any) {
     this.code = code;
     this.message = message;
+    this.kind = kind;
+    this.data = data;
   }
 }
@@ -55,11 +55,14 @@ export class FetchTransport implements Transport {
       }
     );
 
+    // The procedure returned `NoContent`
+    if (resp.status === 204) return undefined;
+
     const respBody = await resp.json();
     const { type, data } = respBody.result;
     if (type === "error") {
-      const { code, message } = data;
-      throw new RSPCError(code, message);
+      const { code, message, kind, data: errorData } = data;
+      throw new RSPCError(code, message, kind, errorData);
     }
     return data;
   }
@@ -80,6 +83,25 @@ export class WebsocketTransport implements Transport {
     }
   >();
   clientSubscriptionCallback?: (id: string, value: any) => void;
+  // Called with each log line of a procedure returning `WithLogs`, before it's response.
+  clientLogCallback?: (id: string, value: any) => void;
+  // Called with each patch of a procedure returning `Partial`, before it's response. `value` should be merged into the result at the JSON Pointer `path`.
+  clientPatchCallback?: (
+    id: string,
+    patch: { path: string; value: any }
+  ) => void;
+  // Called with the trailer of a subscription with an `on_complete` hook, after it's last event.
+  clientTrailerCallback?: (id: string, value: any) => void;
+  // Called once a replayed or snapshot subscription has sent it's history and switches to live events.
+  clientCaughtUpCallback?: (id: string) => void;
+  // Called once a subscription stopped with `subscriptionStop` has been cleaned up by the server. No more frames are sent for it.
+  clientCancelledCallback?: (id: string) => void;
+  // Called once a subscription has completed by itself, after it's last event. No more frames are sent for it.
+  clientCompleteCallback?: (id: string) => void;
+  // Called instead of `clientCompleteCallback` once a subscription was dropped by the server for falling behind, after it's buffered events. No more frames are sent for it.
+  clientLaggedCallback?: (id: string) => void;
+  // Called with each notification pushed by the server which isn't tied to a request or subscription.
+  clientNotificationCallback?: (method: string, params: any) => void;
 
   constructor(url: string) {
     this.url = url;
@@ -98,6 +120,34 @@ export class WebsocketTransport implements Transport {
       if (result.type === "event") {
         if (this.clientSubscriptionCallback)
           this.clientSubscriptionCallback(id, result.data);
+      } else if (result.type === "log") {
+        if (this.clientLogCallback) this.clientLogCallback(id, result.data);
+      } else if (result.type === "patch") {
+        if (this.clientPatchCallback) this.clientPatchCallback(id, result.data);
+      } else if (result.type === "trailer") {
+        if (this.clientTrailerCallback)
+          this.clientTrailerCallback(id, result.data);
+      } else if (result.type === "caughtUp") {
+        if (this.clientCaughtUpCallback) this.clientCaughtUpCallback(id);
+      } else if (result.type === "cancelled") {
+        if (this.clientCancelledCallback) this.clientCancelledCallback(id);
+      } else if (result.type === "complete") {
+        if (this.clientCompleteCallback) this.clientCompleteCallback(id);
+      } else if (result.type === "lagged") {
+        if (this.clientLaggedCallback) this.clientLaggedCallback(id);
+      } else if (result.type === "notification") {
+        if (this.clientNotificationCallback)
+          this.clientNotificationCallback(
+            result.data.method,
+            result.data.params
+          );
+      } else if (result.type === "noContent") {
+        if (this.requestMap.has(id)) {
+          this.requestMap
+            .get(id)
+            ?.cb({ type: "response", result: undefined });
+          this.requestMap.delete(id);
+        }
       } else if (result.type === "response") {
         if (this.requestMap.has(id)) {
           this.requestMap
@@ -106,9 +156,9 @@ export class WebsocketTransport implements Transport {
           this.requestMap.delete(id);
         }
       } else if (result.type === "error") {
-        const { message, code } = result.data;
+        const { message, code, kind, data } = result.data;
         if (this.requestMap.has(id)) {
-          this.requestMap.get(id)?.cb({ type: "error", message, code });
+          this.requestMap.get(id)?.cb({ type: "error", message, code, kind, data });
           this.requestMap.delete(id);
         }
       } else {
@@ -117,13 +167,15 @@ export class WebsocketTransport implements Transport {
     });
 
     this.ws.addEventListener("close", (event) => {
-      this.reconnect();
+      // The server can ask us to back off with a `;retry-after=<milliseconds>` suffix on the close reason
+      const retryAfter = /;retry-after=(\d+)$/.exec(event.reason)?.[1];
+      this.reconnect(0, retryAfter ? Number(retryAfter) : undefined);
     });
   }
 
-  async reconnect(timeoutIndex = 0) {
+  async reconnect(timeoutIndex = 0, retryAfter?: number) {
     let timeout =
-      (timeouts[timeoutIndex] ?? timeouts[timeouts.length - 1]) +
+      (retryAfter ?? timeouts[timeoutIndex] ?? timeouts[timeouts.length - 1]) +
       (Math.floor(Math.random() * 5000 /* 5 Seconds */) + 1);
 
     setTimeout(() => {
@@ -190,8 +242,8 @@ export class WebsocketTransport implements Transport {
 
     const body = (await promise) as any;
     if (body.type === "error") {
-      const { code, message } = body;
-      throw new RSPCError(code, message);
+      const { code, message, kind, data } = body;
+      throw new RSPCError(code, message, kind, data);
     } else if (body.type === "response") {
       return body.result;
     } else {
@@ -6,7 +6,13 @@ export type OperationType =
 	| "subscriptionStop";
 
 // TODO
-export type ProcedureDef = { key: string; input: any; result: any };
+export type ProcedureDef = {
+  key: string;
+  input: any;
+  result: any;
+  logs?: any;
+  trailer?: any;
+};
 
 /**
  * This type represents the Typescript bindings which are generated from the router by Rust.
@@ -0,0 +1,262 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, PoisonError,
+    },
+};
+
+use tokio::sync::oneshot;
+
+use crate::{internal::jsonrpc::RequestId, Error};
+
+/// Identifies a single long-lived connection (Eg. a WebSocket). See [`Connection::id`](crate::internal::jsonrpc::Connection::id).
+///
+/// Get the id of the connection the current request was received over from within a resolver (or middleware) using [`connection_id`](crate::connection_id).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ConnectionId(u64);
+
+impl ConnectionId {
+    pub(crate) fn next() -> Self {
+        static NEXT: AtomicU64 = AtomicU64::new(0);
+        Self(NEXT.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+type Key = (ConnectionId, RequestId);
+
+#[derive(Default)]
+struct State {
+    // Distinguishes subscriptions which reused the id of one which ended, so a stale registration doesn't remove the new one.
+    next_token: u64,
+    subscriptions: HashMap<Key, (u64, oneshot::Sender<Error>)>,
+}
+
+/// A handle to the subscriptions which are currently running on every connection, for cancelling them from the server (Eg. when a user is banned).
+///
+/// Get this from [`Router::active_subscriptions`](crate::Router::active_subscriptions). It's cheap to clone and is `Send + Sync` so it can be moved into your resolvers.
+///
+/// Subscriptions are keyed by the [`ConnectionId`] of the connection they were started on and the id the client chose for them.
+/// Only subscriptions started through [`handle_json_rpc_with_connection`](crate::internal::jsonrpc::handle_json_rpc_with_connection) (Eg. over a WebSocket) are tracked.
+#[derive(Clone, Default)]
+pub struct ActiveSubscriptions(Arc<Mutex<State>>);
+
+impl ActiveSubscriptions {
+    /// Cancel the subscription `id` running on `connection`, sending `error` to the client as it's final frame.
+    ///
+    /// The subscription is stopped the same way as when the client stops it, so it's `.on_unsubscribe` hook runs and it's `.on_complete` hook doesn't.
+    /// Returns `false` if no such subscription is running.
+    pub fn cancel(&self, connection: ConnectionId, id: &RequestId, error: Error) -> bool {
+        let mut state = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        match state.subscriptions.remove(&(connection, id.clone())) {
+            // The subscription may have ended after it was removed, in which case it has already been cleaned up
+            Some((_, cancel)) => cancel.send(error).is_ok(),
+            None => false,
+        }
+    }
+
+    /// Cancel every subscription running on `connection`, sending a clone of `error` to the client for each. See [`ActiveSubscriptions::cancel`].
+    ///
+    /// Returns the number of subscriptions which were cancelled.
+    pub fn cancel_connection(&self, connection: ConnectionId, error: Error) -> usize {
+        let mut state = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        let keys = state
+            .subscriptions
+            .keys()
+            .filter(|(c, _)| *c == connection)
+            .cloned()
+            .collect::<Vec<_>>();
+        keys.into_iter()
+            .filter_map(|key| state.subscriptions.remove(&key))
+            .map(|(_, cancel)| cancel.send(error.clone()).is_ok())
+            .filter(|cancelled| *cancelled)
+            .count()
+    }
+
+    /// The ids of the subscriptions currently running on `connection`.
+    pub fn ids(&self, connection: ConnectionId) -> Vec<RequestId> {
+        let state = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        state
+            .subscriptions
+            .keys()
+            .filter(|(c, _)| *c == connection)
+            .map(|(_, id)| id.clone())
+            .collect()
+    }
+
+    /// Track a subscription which is starting. It's tracked until the returned [`Registration`] is dropped.
+    pub(crate) fn register(
+        &self,
+        connection: ConnectionId,
+        id: RequestId,
+    ) -> (Registration, oneshot::Receiver<Error>) {
+        let (tx, rx) = oneshot::channel();
+        let mut state = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        let token = state.next_token;
+        state.next_token += 1;
+        state
+            .subscriptions
+            .insert((connection, id.clone()), (token, tx));
+        (
+            Registration {
+                subscriptions: self.clone(),
+                key: (connection, id),
+                token,
+            },
+            rx,
+        )
+    }
+}
+
+impl std::fmt::Debug for ActiveSubscriptions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ActiveSubscriptions")
+            .finish_non_exhaustive()
+    }
+}
+
+/// Stops tracking a subscription when it ends, however it ends.
+pub(crate) struct Registration {
+    subscriptions: ActiveSubscriptions,
+    key: Key,
+    token: u64,
+}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        let mut state = self
+            .subscriptions
+            .0
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        if matches!(state.subscriptions.get(&self.key), Some((token, _)) if *token == self.token) {
+            state.subscriptions.remove(&self.key);
+        }
+    }
+}
+
+// The tests use the default runtime
+#[cfg(all(test, feature = "runtime-tokio"))]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        sync::{Arc, Mutex as StdMutex},
+    };
+
+    use futures::{stream, StreamExt};
+    use tokio::sync::{mpsc, Mutex};
+
+    use crate::{
+        connection_id,
+        internal::jsonrpc::{
+            self, handle_json_rpc_with_connection, Connection, RequestId, ResponseInner, Sender,
+            SubscriptionMap,
+        },
+        Error, ErrorCode, Router,
+    };
+
+    #[tokio::test]
+    async fn test_cancel() {
+        let connections = Arc::new(StdMutex::new(Vec::new()));
+        let unsubscribed = Arc::new(StdMutex::new(0));
+        let router = Arc::new(
+            <Router>::new()
+                .subscription("ticks", {
+                    let (connections, unsubscribed) = (connections.clone(), unsubscribed.clone());
+                    move |t| {
+                        let (connections, unsubscribed) =
+                            (connections.clone(), unsubscribed.clone());
+                        t(move |_, _: ()| {
+                            connections.lock().unwrap().push(connection_id().unwrap());
+                            stream::iter(0..).then(|i| async move {
+                                tokio::task::yield_now().await;
+                                i
+                            })
+                        })
+                        .on_unsubscribe(move |_, _| {
+                            *unsubscribed.lock().unwrap() += 1;
+                            async {}
+                        })
+                    }
+                })
+                .build(),
+        );
+
+        let (mut tx, mut rx) = mpsc::unbounded_channel();
+        let subscriptions = Mutex::new(HashMap::new());
+        let connection = Connection::new(&router);
+        for id in [1, 2] {
+            handle_json_rpc_with_connection(
+                (),
+                jsonrpc::Request {
+                    jsonrpc: None,
+                    id: RequestId::Null,
+                    inner: jsonrpc::RequestInner::Subscription {
+                        path: "ticks".into(),
+                        input: (RequestId::Number(id), None),
+                    },
+                },
+                &router,
+                &mut Sender::ResponseChannel(&mut tx),
+                &mut SubscriptionMap::Mutex(&subscriptions),
+                &connection,
+            )
+            .await;
+        }
+        assert_eq!(*connections.lock().unwrap(), [connection.id(); 2]);
+
+        let active = router.active_subscriptions();
+        let mut ids = active.ids(connection.id());
+        ids.sort_by_key(|id| format!("{id:?}"));
+        assert_eq!(ids, [RequestId::Number(1), RequestId::Number(2)]);
+
+        // Wait for the subscription to start emitting
+        while !matches!(rx.recv().await.unwrap().result, ResponseInner::Event(_)) {}
+        let banned = Error::new(ErrorCode::Forbidden, "banned".into());
+        assert!(active.cancel(connection.id(), &RequestId::Number(1), banned));
+        assert!(!active.cancel(
+            connection.id(),
+            &RequestId::Number(1),
+            Error::new(ErrorCode::Forbidden, "banned".into())
+        ));
+
+        // The cancelled subscription sends an error and no more events
+        let mut after_cancel = Vec::new();
+        loop {
+            let resp = rx.recv().await.unwrap();
+            if resp.id != RequestId::Number(1) {
+                continue;
+            }
+            let done = matches!(resp.result, ResponseInner::Error(_));
+            after_cancel.push(resp.result);
+            if done {
+                break;
+            }
+        }
+        for _ in 0..20 {
+            tokio::task::yield_now().await;
+        }
+        while let Ok(resp) = rx.try_recv() {
+            assert_ne!(resp.id, RequestId::Number(1));
+        }
+        assert!(
+            matches!(after_cancel.last(), Some(ResponseInner::Error(err)) if err.message == "banned")
+        );
+        assert_eq!(*unsubscribed.lock().unwrap(), 1);
+        assert_eq!(active.ids(connection.id()), [RequestId::Number(2)]);
+
+        assert_eq!(
+            active.cancel_connection(
+                connection.id(),
+                Error::new(ErrorCode::Forbidden, "banned".into())
+            ),
+            1
+        );
+        for _ in 0..20 {
+            tokio::task::yield_now().await;
+        }
+        assert_eq!(*unsubscribed.lock().unwrap(), 2);
+        assert!(active.ids(connection.id()).is_empty());
+    }
+}
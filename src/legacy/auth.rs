@@ -0,0 +1,228 @@
+use std::{ops::Deref, sync::Arc};
+
+use futures::future::BoxFuture;
+use serde_json::Value;
+
+use crate::{
+    internal::{
+        jsonrpc::{headers, Headers},
+        Layer, LayerResult, RequestContext,
+    },
+    ExecError, MiddlewareLike,
+};
+
+/// Extracts the authentication of a request (Eg. a user from a bearer token, a session cookie or a query parameter).
+///
+/// Extraction is async so it can look the credentials up (Eg. in a session store). It receives the request's [`Headers`] (which include it's cookies) and it's raw params (which hold the query string parameters of an HTTP query).
+/// Returning `None` means the request isn't authenticated.
+pub trait Authenticator: Send + Sync + 'static {
+    type Auth: Send + Sync + 'static;
+
+    fn authenticate<'a>(
+        &'a self,
+        headers: &'a Headers,
+        params: &'a Value,
+    ) -> BoxFuture<'a, Option<Self::Auth>>;
+}
+
+/// Middleware which authenticates every request with an [`Authenticator`] before it reaches the procedures registered after it, wrapping the context in [`WithAuth`].
+///
+/// Procedures registered after [`Authenticate::required`] are only run for authenticated requests, every other request fails with [`ExecError::Unauthorized`] before the resolver runs.
+/// Procedures registered after [`Authenticate::optional`] run either way and receive `None` from [`WithAuth::auth`] when the request isn't authenticated. Use a [`RouterGroup`](crate::RouterGroup) to mix both in one router.
+///
+/// ```rust
+/// use futures::{future::BoxFuture, FutureExt};
+/// use rspc::{Authenticate, Authenticator, Headers};
+/// use serde_json::Value;
+///
+/// struct BearerToken;
+///
+/// impl Authenticator for BearerToken {
+///     type Auth = String;
+///
+///     fn authenticate<'a>(&'a self, headers: &'a Headers, _: &'a Value) -> BoxFuture<'a, Option<String>> {
+///         async move {
+///             let token = headers.get("authorization")?.strip_prefix("Bearer ")?;
+///             Some(format!("user for {token}"))
+///         }
+///         .boxed()
+///     }
+/// }
+///
+/// let router = <rspc::Router>::new()
+///     .middleware(|_| Authenticate::required(BearerToken))
+///     .query("me", |t| t(|ctx, _: ()| ctx.auth().cloned()))
+///     .build();
+/// ```
+pub struct Authenticate<A> {
+    authenticator: Arc<A>,
+    required: bool,
+}
+
+impl<A: Authenticator> Authenticate<A> {
+    /// Reject every request which isn't authenticated.
+    pub fn required(authenticator: A) -> Self {
+        Self {
+            authenticator: Arc::new(authenticator),
+            required: true,
+        }
+    }
+
+    /// Run every request, authenticated or not.
+    pub fn optional(authenticator: A) -> Self {
+        Self {
+            authenticator: Arc::new(authenticator),
+            required: false,
+        }
+    }
+}
+
+impl<A> Clone for Authenticate<A> {
+    fn clone(&self) -> Self {
+        Self {
+            authenticator: self.authenticator.clone(),
+            required: self.required,
+        }
+    }
+}
+
+impl<TCtx, A> MiddlewareLike<TCtx> for Authenticate<A>
+where
+    TCtx: Send + 'static,
+    A: Authenticator,
+{
+    type State = ();
+    type NewCtx = WithAuth<TCtx, A::Auth>;
+
+    fn handle<TMiddleware: Layer<Self::NewCtx> + 'static>(
+        &self,
+        ctx: TCtx,
+        input: Value,
+        req: RequestContext,
+        next: Arc<TMiddleware>,
+    ) -> Result<LayerResult, ExecError> {
+        let (authenticator, required) = (self.authenticator.clone(), self.required);
+        Ok(LayerResult::FutureValueOrStream(Box::pin(async move {
+            let auth = authenticator.authenticate(&headers(), req.params()).await;
+            if required && auth.is_none() {
+                return Err(ExecError::Unauthorized);
+            }
+
+            next.call(WithAuth { ctx, auth }, input, req)?
+                .into_value_or_stream()
+                .await
+        })))
+    }
+}
+
+/// The context of a request which passed through the [`Authenticate`] middleware. This derefs to the original context.
+pub struct WithAuth<TCtx, TAuth> {
+    ctx: TCtx,
+    auth: Option<TAuth>,
+}
+
+impl<TCtx, TAuth> WithAuth<TCtx, TAuth> {
+    /// The authentication extracted by the [`Authenticator`], or `None` if the request isn't authenticated.
+    ///
+    /// This is always `Some` behind [`Authenticate::required`].
+    pub fn auth(&self) -> Option<&TAuth> {
+        self.auth.as_ref()
+    }
+
+    /// Unwrap the original context.
+    pub fn into_inner(self) -> TCtx {
+        self.ctx
+    }
+}
+
+impl<TCtx, TAuth> Deref for WithAuth<TCtx, TAuth> {
+    type Target = TCtx;
+
+    fn deref(&self) -> &Self::Target {
+        &self.ctx
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use futures::{future::BoxFuture, FutureExt};
+    use serde_json::{json, Value};
+
+    use crate::{
+        internal::jsonrpc::{with_headers, Headers},
+        Authenticate, Authenticator, ErrorCode, ExecError, ExecKind, Router,
+    };
+
+    struct BearerToken;
+
+    impl Authenticator for BearerToken {
+        type Auth = String;
+
+        fn authenticate<'a>(
+            &'a self,
+            headers: &'a Headers,
+            _: &'a Value,
+        ) -> BoxFuture<'a, Option<String>> {
+            async move {
+                tokio::task::yield_now().await;
+                match headers.get("authorization")? {
+                    "Bearer secret" => Some("oscar".to_string()),
+                    _ => None,
+                }
+            }
+            .boxed()
+        }
+    }
+
+    fn authorization(value: Option<&str>) -> Headers {
+        value
+            .map(|value| ("authorization", value))
+            .into_iter()
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_authenticate() {
+        let optional = Router::<u32>::new()
+            .middleware(|_| Authenticate::optional(BearerToken))
+            .query("me", |t| t(|ctx, _: ()| (*ctx, ctx.auth().cloned())))
+            .build();
+        let required = Router::<u32>::new()
+            .middleware(|_| Authenticate::required(BearerToken))
+            .query("me", |t| t(|ctx, _: ()| (*ctx, ctx.auth().cloned())))
+            .build();
+
+        // Authenticated
+        for router in [&optional, &required] {
+            let result = with_headers(
+                authorization(Some("Bearer secret")),
+                router.exec(1, ExecKind::Query, "me".into(), None),
+            )
+            .await;
+            assert_eq!(result.unwrap(), json!([1, "oscar"]));
+        }
+
+        // Unauthenticated but allowed
+        for header in [None, Some("Bearer wrong")] {
+            let result = with_headers(
+                authorization(header),
+                optional.exec(1, ExecKind::Query, "me".into(), None),
+            )
+            .await;
+            assert_eq!(result.unwrap(), json!([1, null]));
+        }
+
+        // Unauthenticated and rejected
+        for header in [None, Some("Bearer wrong")] {
+            let err = with_headers(
+                authorization(header),
+                required.exec(1, ExecKind::Query, "me".into(), None),
+            )
+            .await
+            .unwrap_err();
+            assert!(matches!(err, ExecError::Unauthorized));
+            assert_eq!(crate::Error::from(err).code, ErrorCode::Unauthorized);
+        }
+    }
+}
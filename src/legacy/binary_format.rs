@@ -0,0 +1,182 @@
+use std::error::Error;
+
+use serde_json::Value;
+
+use crate::internal::jsonrpc::Response;
+
+/// A binary encoding (Eg. MessagePack or CBOR) of the frames of a WebSocket connection, which is sent as binary frames (opcode `0x2`) instead of JSON text frames.
+///
+/// A client opts in by offering [`BinaryFormat::protocol`] in the `Sec-WebSocket-Protocol` header of it's upgrade request, every other connection keeps using JSON. Once negotiated the whole JSON-RPC frame (not only the result) is encoded with it, in both directions.
+/// Register it with [`Config::binary_format`](crate::Config::binary_format).
+///
+/// Note: A [`RawStream`](crate::RawStream) result is still a base64 `string` within the frame, as it's converted before the frame is encoded.
+///
+/// ```rust,no_run
+/// use rspc::{internal::jsonrpc::Response, BinaryFormat, Config};
+/// use serde_json::Value;
+///
+/// struct MessagePack;
+///
+/// impl BinaryFormat for MessagePack {
+///     fn protocol(&self) -> &'static str {
+///         "rspc.msgpack"
+///     }
+///
+///     fn encode(&self, frame: &Response) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+///         // Eg. `Ok(rmp_serde::to_vec_named(frame)?)`
+///         # unimplemented!()
+///     }
+///
+///     fn decode(&self, bytes: &[u8]) -> Result<Value, Box<dyn std::error::Error + Send + Sync>> {
+///         // Eg. `Ok(rmp_serde::from_slice(bytes)?)`
+///         # unimplemented!()
+///     }
+/// }
+///
+/// let router = <rspc::Router>::new()
+///     .config(Config::new().binary_format(MessagePack))
+///     .build();
+/// ```
+pub trait BinaryFormat: Send + Sync + 'static {
+    /// The WebSocket subprotocol clients request this format with.
+    fn protocol(&self) -> &'static str;
+
+    /// Encode a frame sent to the client.
+    fn encode(&self, frame: &Response) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>>;
+
+    /// Decode a binary message received from the client into a request (or batch of requests).
+    fn decode(&self, bytes: &[u8]) -> Result<Value, Box<dyn Error + Send + Sync>>;
+}
+
+// The tests use the default runtime
+#[cfg(all(test, feature = "runtime-tokio"))]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use std::{collections::HashMap, error::Error, sync::Arc};
+
+    use futures::stream;
+    use serde_json::{json, Value};
+    use tokio::sync::{mpsc, Mutex};
+
+    use crate::{
+        internal::jsonrpc::{
+            self, decode_frame, encode_frame, handle_json_rpc, negotiate_format, Frame, Headers,
+            RequestId, Response, Sender, SubscriptionMap,
+        },
+        BinaryFormat, Config, ExecError, Router,
+    };
+
+    const MAGIC: u8 = 0xb1;
+
+    // JSON behind a marker byte, so frames encoded with it can be told apart
+    struct Tagged;
+
+    impl BinaryFormat for Tagged {
+        fn protocol(&self) -> &'static str {
+            "rspc.tagged"
+        }
+
+        fn encode(&self, frame: &Response) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+            let mut bytes = vec![MAGIC];
+            serde_json::to_writer(&mut bytes, frame)?;
+            Ok(bytes)
+        }
+
+        fn decode(&self, bytes: &[u8]) -> Result<Value, Box<dyn Error + Send + Sync>> {
+            match bytes.split_first() {
+                Some((&MAGIC, json)) => Ok(serde_json::from_slice(json)?),
+                _ => Err("missing marker".into()),
+            }
+        }
+    }
+
+    fn protocols(value: &str) -> Headers {
+        [("sec-websocket-protocol", value)].into_iter().collect()
+    }
+
+    #[tokio::test]
+    async fn test_binary_frames() {
+        let router = Arc::new(
+            <Router>::new()
+                .config(Config::new().binary_format(Tagged))
+                .subscription("bytes", |t| {
+                    t(|_, _: ()| stream::iter([vec![1u8, 2, 3], vec![4]]))
+                })
+                .build(),
+        );
+
+        // Only clients offering the protocol get binary frames
+        assert!(negotiate_format(&router, &Headers::default()).is_none());
+        assert!(negotiate_format(&router, &protocols("rspc.other")).is_none());
+        assert!(negotiate_format(&<Router>::new().build(), &protocols("rspc.tagged")).is_none());
+        let format = negotiate_format(&router, &protocols("rspc.other, rspc.tagged")).unwrap();
+        let format = Some(&*format);
+
+        // The request is decoded with the format
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "subscription",
+            "params": { "path": "bytes", "input": [1, null] }
+        });
+        let mut encoded = vec![MAGIC];
+        encoded.extend(serde_json::to_vec(&request).unwrap());
+        let request: jsonrpc::Request =
+            serde_json::from_value(decode_frame(format, &Frame::Binary(encoded)).unwrap()).unwrap();
+        assert!(matches!(
+            decode_frame(format, &Frame::Binary(b"{}".to_vec())),
+            Err(ExecError::InvalidRequest(_))
+        ));
+        // Text messages are still JSON
+        assert_eq!(
+            decode_frame(format, &Frame::Text("{}".into())).unwrap(),
+            json!({})
+        );
+
+        let subscriptions = Mutex::new(HashMap::new());
+        let (mut tx, mut rx) = mpsc::unbounded_channel();
+        handle_json_rpc(
+            (),
+            request,
+            &router,
+            &mut Sender::ResponseChannel(&mut tx),
+            &mut SubscriptionMap::Mutex(&subscriptions),
+        )
+        .await;
+
+        // Every frame is sent as a binary frame holding the whole envelope
+        let mut frames = Vec::new();
+        while let Some(resp) = rx.recv().await {
+            let complete = matches!(resp.result, jsonrpc::ResponseInner::Complete);
+            let frame = encode_frame(format, &resp).unwrap();
+            assert!(matches!(&frame, Frame::Binary(bytes) if bytes[0] == MAGIC));
+            if let Frame::Binary(bytes) = frame {
+                frames.push(Tagged.decode(&bytes).unwrap());
+            }
+            if complete {
+                break;
+            }
+        }
+        assert_eq!(
+            frames.pop().unwrap()["result"],
+            json!({ "type": "complete" })
+        );
+        assert_eq!(frames.len(), 2);
+        for (frame, data) in frames.iter().zip([json!([1, 2, 3]), json!([4])]) {
+            assert_eq!(frame["jsonrpc"], "2.0");
+            assert_eq!(frame["id"], 1);
+            assert_eq!(frame["result"], json!({ "type": "event", "data": data }));
+        }
+
+        // Without a format frames are JSON text
+        let resp = Response {
+            jsonrpc: "2.0",
+            id: RequestId::Number(1),
+            result: jsonrpc::ResponseInner::Response(json!("ok")),
+        };
+        assert!(matches!(
+            encode_frame(None, &resp).unwrap(),
+            Frame::Text(text) if text == r#"{"jsonrpc":"2.0","id":1,"result":{"type":"response","data":"ok"}}"#
+        ));
+    }
+}
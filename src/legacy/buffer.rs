@@ -0,0 +1,181 @@
+use std::{
+    future::Future,
+    mem,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use futures::Stream;
+use serde_json::Value;
+
+use crate::{ExecError, Runtime};
+
+type Items = Pin<Box<dyn Stream<Item = Result<Value, ExecError>> + Send>>;
+
+/// Groups the items of a subscription into arrays. See [`BuiltProcedureBuilder::buffer`](crate::internal::BuiltProcedureBuilder::buffer).
+pub(crate) struct Buffer {
+    stream: Items,
+    count: usize,
+    max_delay: Duration,
+    runtime: Option<Arc<dyn Runtime>>,
+    items: Vec<Value>,
+    // Started by the first item of each batch
+    timer: Option<Pin<Box<dyn Future<Output = ()> + Send>>>,
+    // An error from the stream which is sent after the items before it are flushed
+    error: Option<ExecError>,
+    done: bool,
+}
+
+impl Buffer {
+    pub fn new(
+        stream: Items,
+        count: usize,
+        max_delay: Duration,
+        runtime: Option<Arc<dyn Runtime>>,
+    ) -> Self {
+        Self {
+            stream,
+            count,
+            max_delay,
+            runtime,
+            items: Vec::with_capacity(count),
+            timer: None,
+            error: None,
+            done: false,
+        }
+    }
+
+    fn flush(&mut self) -> Poll<Option<Result<Value, ExecError>>> {
+        self.timer = None;
+        Poll::Ready(Some(Ok(Value::Array(mem::replace(
+            &mut self.items,
+            Vec::with_capacity(self.count),
+        )))))
+    }
+}
+
+impl Stream for Buffer {
+    type Item = Result<Value, ExecError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        if let Some(err) = this.error.take() {
+            return Poll::Ready(Some(Err(err)));
+        }
+        if this.done {
+            return Poll::Ready(None);
+        }
+
+        loop {
+            match this.stream.as_mut().poll_next(cx) {
+                Poll::Ready(Some(Ok(item))) => {
+                    if this.items.is_empty() {
+                        let Some(runtime) = &this.runtime else {
+                            this.done = true;
+                            return Poll::Ready(Some(Err(ExecError::NoRuntime)));
+                        };
+                        this.timer = Some(runtime.sleep(this.max_delay));
+                    }
+                    this.items.push(item);
+                    if this.items.len() >= this.count {
+                        return this.flush();
+                    }
+                }
+                Poll::Ready(Some(Err(err))) => {
+                    if this.items.is_empty() {
+                        return Poll::Ready(Some(Err(err)));
+                    }
+                    this.error = Some(err);
+                    return this.flush();
+                }
+                // A partial batch is flushed when the stream completes
+                Poll::Ready(None) => {
+                    this.done = true;
+                    return match this.items.is_empty() {
+                        true => Poll::Ready(None),
+                        false => this.flush(),
+                    };
+                }
+                Poll::Pending => break,
+            }
+        }
+
+        let elapsed = this
+            .timer
+            .as_mut()
+            .is_some_and(|timer| timer.as_mut().poll(cx).is_ready());
+        match elapsed {
+            true => this.flush(),
+            false => Poll::Pending,
+        }
+    }
+}
+
+// The tests use the default runtime
+#[cfg(all(test, feature = "runtime-tokio"))]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use std::{
+        sync::Mutex,
+        time::{Duration, Instant},
+    };
+
+    use futures::{channel::mpsc, stream, StreamExt};
+    use serde_json::json;
+
+    use crate::Router;
+
+    #[tokio::test]
+    async fn test_buffer() {
+        let (tx, rx) = mpsc::unbounded::<u32>();
+        let rx = Mutex::new(Some(rx));
+        let router = <Router>::new()
+            .subscription("numbers", move |t| {
+                let rx = Mutex::new(rx.lock().unwrap().take());
+                t(move |_, _: ()| rx.lock().unwrap().take().unwrap())
+                    .buffer(3, Duration::from_millis(50))
+            })
+            .subscription("finite", |t| {
+                t(|_, _: ()| stream::iter([1, 2, 3, 4])).buffer(3, Duration::from_secs(60))
+            })
+            .build();
+
+        let bindings = router.ts_bindings().unwrap();
+        assert!(bindings.contains(r#"{ key: "numbers", input: never, result: number[] }"#));
+
+        let mut stream = router
+            .exec_subscription((), "numbers".into(), None)
+            .await
+            .unwrap();
+
+        // Flushed when the count is reached
+        for i in 0..4 {
+            tx.unbounded_send(i).unwrap();
+        }
+        assert_eq!(stream.next().await.unwrap().unwrap(), json!([0, 1, 2]));
+
+        // Flushed when the delay since the first item of the batch elapses
+        let start = Instant::now();
+        assert_eq!(stream.next().await.unwrap().unwrap(), json!([3]));
+        assert!(start.elapsed() >= Duration::from_millis(40));
+
+        // A partial batch is flushed when the stream completes, without waiting for the delay
+        tx.unbounded_send(4).unwrap();
+        drop(tx);
+        let start = Instant::now();
+        assert_eq!(stream.next().await.unwrap().unwrap(), json!([4]));
+        assert!(stream.next().await.is_none());
+        assert!(start.elapsed() < Duration::from_millis(40));
+
+        let events = router
+            .exec_subscription((), "finite".into(), None)
+            .await
+            .unwrap()
+            .map(|event| event.unwrap())
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(events, [json!([1, 2, 3]), json!([4])]);
+    }
+}
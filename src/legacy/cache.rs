@@ -0,0 +1,297 @@
+use std::{
+    any::Any,
+    collections::{BTreeMap, HashMap},
+    sync::{Arc, Mutex, PoisonError},
+    time::{Duration, Instant},
+};
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::{
+    internal::{Layer, LayerResult, RequestContext, ValueOrStream},
+    ExecError,
+};
+
+/// A handle to the result caches of every query registered with `.cache(ttl)`.
+///
+/// Get this from [`Router::caches`](crate::Router::caches). It's cheap to clone and is `Send + Sync` so it can be moved into your resolvers for write-through invalidation from mutations.
+///
+/// Invalidation is immediate. Once `invalidate` returns the next request will execute the resolver again.
+/// A request which started executing prior to the invalidation will not write its (possibly stale) result back into the cache.
+#[derive(Clone, Default)]
+pub struct Caches(pub(crate) Arc<BTreeMap<String, Arc<ProcedureCache>>>);
+
+impl Caches {
+    /// Drop every cached result for the procedure with the given key.
+    ///
+    /// Returns `false` if the procedure doesn't exist or doesn't have caching enabled.
+    pub fn invalidate(&self, key: &str) -> bool {
+        match self.0.get(key) {
+            Some(cache) => {
+                cache.clear();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Drop the cached result for a specific input of the procedure with the given key. With [`cache_by`](crate::internal::BuiltProcedureBuilder::cache_by) it's dropped for every context.
+    ///
+    /// The input must serialize to the same JSON the client sent, as results are keyed by the input before `.defaults`, `.deserialize_with` or an input pipeline are applied to it (but after [`Config::rename_fields`](crate::Config::rename_fields) maps it to the Rust field names).
+    /// Eg. an optional field which the client omitted must be omitted here too, not serialized as `null`. When that can't be guaranteed invalidate the whole procedure with [`Caches::invalidate`] instead.
+    pub fn invalidate_input(&self, key: &str, input: impl Serialize) -> Result<bool, ExecError> {
+        let input = serde_json::to_value(input).map_err(ExecError::SerializingResultErr)?;
+        Ok(match self.0.get(key) {
+            Some(cache) => cache.remove(&input.to_string()),
+            None => false,
+        })
+    }
+
+    /// Drop every cached result for every procedure.
+    pub fn invalidate_all(&self) {
+        for cache in self.0.values() {
+            cache.clear();
+        }
+    }
+}
+
+/// The key of the context a result is cached for, registered with `.cache_by`.
+pub(crate) type CacheKeyFn<TCtx> = Arc<dyn Fn(&TCtx) -> String + Send + Sync>;
+
+/// A type erased [`CacheKeyFn`]. It's context type is the one the procedure's resolver receives.
+pub(crate) type AnyCacheKeyFn = Arc<dyn Any + Send + Sync>;
+
+// The serialized input, the key of the context and the requested representation (See `crate::representation`), as the result differs between representations.
+type CacheKey = (String, Option<String>, Option<String>);
+
+// The number of entries at which expired entries are first swept.
+pub(crate) const MIN_SWEEP_AT: usize = 64;
+
+#[derive(Default)]
+struct CacheState {
+    // Incremented on every invalidation so results from requests which were in flight at the time are discarded.
+    generation: u64,
+    entries: HashMap<CacheKey, (Instant, Value)>,
+    // Expired entries are only removed when they're requested again, so they're swept once the cache grows to this many entries.
+    // It's twice the number of entries left after the last sweep, so sweeping is amortised over the inserts.
+    sweep_at: usize,
+}
+
+pub(crate) struct ProcedureCache {
+    ttl: Duration,
+    state: Mutex<CacheState>,
+}
+
+impl ProcedureCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            state: Default::default(),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, CacheState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn get(&self, key: &CacheKey) -> Result<Value, u64> {
+        let mut state = self.lock();
+        match state.entries.get(key) {
+            Some((inserted, value)) if inserted.elapsed() < self.ttl => Ok(value.clone()),
+            Some(_) => {
+                state.entries.remove(key);
+                Err(state.generation)
+            }
+            None => Err(state.generation),
+        }
+    }
+
+    fn insert(&self, generation: u64, key: CacheKey, value: Value) {
+        let mut state = self.lock();
+        if state.generation == generation {
+            if state.entries.len() >= state.sweep_at.max(MIN_SWEEP_AT) {
+                state
+                    .entries
+                    .retain(|_, (inserted, _)| inserted.elapsed() < self.ttl);
+                state.sweep_at = state.entries.len() * 2;
+            }
+            state.entries.insert(key, (Instant::now(), value));
+        }
+    }
+
+    fn remove(&self, input: &str) -> bool {
+        let mut state = self.lock();
+        state.generation += 1;
+        // Every context and representation of the input
+        let len = state.entries.len();
+        state.entries.retain(|(key, _, _), _| key != input);
+        state.entries.len() != len
+    }
+
+    fn clear(&self) {
+        let mut state = self.lock();
+        state.generation += 1;
+        state.entries.clear();
+    }
+}
+
+pub(crate) struct CacheLayer<TLayerCtx: 'static> {
+    pub next: Box<dyn Layer<TLayerCtx>>,
+    pub cache: Arc<ProcedureCache>,
+    pub context_key: Option<CacheKeyFn<TLayerCtx>>,
+}
+
+impl<TLayerCtx: 'static> CacheLayer<TLayerCtx> {
+    pub fn wrap(
+        next: Box<dyn Layer<TLayerCtx>>,
+        cache: Arc<ProcedureCache>,
+        context_key: Option<&AnyCacheKeyFn>,
+    ) -> Box<dyn Layer<TLayerCtx>> {
+        Box::new(Self {
+            next,
+            cache,
+            // This is guaranteed by the bounds on `BuiltProcedureBuilder::cache_by`
+            context_key: context_key.map(|context_key| {
+                context_key
+                    .downcast_ref::<CacheKeyFn<TLayerCtx>>()
+                    .expect("rspc: procedure cache key context type mismatch")
+                    .clone()
+            }),
+        })
+    }
+}
+
+impl<TLayerCtx: 'static> Layer<TLayerCtx> for CacheLayer<TLayerCtx> {
+    fn call(
+        &self,
+        ctx: TLayerCtx,
+        input: Value,
+        req: RequestContext,
+    ) -> Result<LayerResult, ExecError> {
+        let key = (
+            input.to_string(),
+            self.context_key
+                .as_ref()
+                .map(|context_key| context_key(&ctx)),
+            crate::representation(),
+        );
+        let generation = match self.cache.get(&key) {
+            Ok(value) => return Ok(LayerResult::Ready(Ok(value))),
+            Err(generation) => generation,
+        };
+
+        let result = self.next.call(ctx, input, req.clone())?;
+        let cache = self.cache.clone();
+        Ok(LayerResult::Future(Box::pin(async move {
+            match result.into_value_or_stream().await? {
+                ValueOrStream::Value(value) => {
+                    cache.insert(generation, key, value.clone());
+                    Ok(value)
+                }
+                ValueOrStream::Stream(_) => Err(ExecError::UnsupportedMethod(req.path)),
+            }
+        })))
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    use serde_json::json;
+
+    use super::MIN_SWEEP_AT;
+    use crate::{ExecKind, Router};
+
+    #[tokio::test]
+    async fn test_cache_invalidation() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let router = <Router>::new()
+            .query("count", {
+                let calls = calls.clone();
+                move |t| {
+                    let calls = calls.clone();
+                    t(move |_, _: i32| calls.fetch_add(1, Ordering::SeqCst))
+                        .cache(Duration::from_secs(60))
+                }
+            })
+            .build();
+
+        let exec =
+            |input: i32| router.exec((), ExecKind::Query, "count".into(), Some(json!(input)));
+        assert_eq!(exec(1).await.unwrap(), json!(0));
+        assert_eq!(exec(1).await.unwrap(), json!(0));
+        assert_eq!(exec(2).await.unwrap(), json!(1));
+
+        assert!(router.caches().invalidate_input("count", 1).unwrap());
+        assert_eq!(exec(1).await.unwrap(), json!(2));
+        assert_eq!(exec(2).await.unwrap(), json!(1));
+
+        assert!(router.caches().invalidate("count"));
+        assert_eq!(exec(2).await.unwrap(), json!(3));
+        assert!(!router.caches().invalidate("unknown"));
+    }
+
+    #[tokio::test]
+    async fn test_cache_by_context() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let router = <Router<&'static str>>::new()
+            .query("settings", {
+                let calls = calls.clone();
+                move |t| {
+                    let calls = calls.clone();
+                    t(move |tenant: &'static str, _: i32| {
+                        format!("{tenant}:{}", calls.fetch_add(1, Ordering::SeqCst))
+                    })
+                    .cache_by(Duration::from_secs(60), |tenant: &&'static str| {
+                        tenant.to_string()
+                    })
+                }
+            })
+            .build();
+
+        let exec = |tenant| router.exec(tenant, ExecKind::Query, "settings".into(), Some(json!(1)));
+        // Each tenant gets their own result, which is cached for them
+        assert_eq!(exec("a").await.unwrap(), json!("a:0"));
+        assert_eq!(exec("b").await.unwrap(), json!("b:1"));
+        assert_eq!(exec("a").await.unwrap(), json!("a:0"));
+        assert_eq!(exec("b").await.unwrap(), json!("b:1"));
+
+        // Invalidating an input drops it for every tenant
+        assert!(router.caches().invalidate_input("settings", 1).unwrap());
+        assert_eq!(exec("b").await.unwrap(), json!("b:2"));
+        assert_eq!(exec("a").await.unwrap(), json!("a:3"));
+    }
+
+    #[tokio::test]
+    async fn test_cache_expired_entries_swept() {
+        let router = <Router>::new()
+            .query("echo", |t| {
+                t(|_, input: usize| input).cache(Duration::from_millis(20))
+            })
+            .build();
+        let exec =
+            |input: usize| router.exec((), ExecKind::Query, "echo".into(), Some(json!(input)));
+        let entries = || router.caches().0["echo"].lock().entries.len();
+
+        for input in 0..MIN_SWEEP_AT {
+            exec(input).await.unwrap();
+        }
+        assert_eq!(entries(), MIN_SWEEP_AT);
+
+        // Entries for inputs which are never requested again are reclaimed once they expire
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        for input in MIN_SWEEP_AT..MIN_SWEEP_AT * 2 {
+            exec(input).await.unwrap();
+        }
+        assert_eq!(entries(), MIN_SWEEP_AT);
+    }
+}
@@ -0,0 +1,112 @@
+use std::borrow::Cow;
+
+use serde_json::Value;
+
+use crate::{
+    internal::{jsonrpc::set_http_cache_control, Layer, LayerResult, RequestContext},
+    ExecError,
+};
+
+/// Sets the `Cache-Control` header of the HTTP response. The transport only sends it with a successful response.
+pub(crate) struct CacheControlLayer<TLayerCtx: 'static> {
+    pub next: Box<dyn Layer<TLayerCtx>>,
+    pub directive: Cow<'static, str>,
+}
+
+impl<TLayerCtx: 'static> Layer<TLayerCtx> for CacheControlLayer<TLayerCtx> {
+    fn call(
+        &self,
+        ctx: TLayerCtx,
+        input: Value,
+        req: RequestContext,
+    ) -> Result<LayerResult, ExecError> {
+        set_http_cache_control(&self.directive);
+        self.next.call(ctx, input, req)
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use std::sync::Arc;
+
+    use serde_json::json;
+
+    use crate::{
+        internal::jsonrpc::{
+            self, handle_json_rpc, with_http_response, HttpResponse, RequestId, RequestInner,
+            ResponseInner, Sender, SubscriptionMap,
+        },
+        Router,
+    };
+
+    async fn call(
+        router: &Arc<Router>,
+        inner: RequestInner,
+    ) -> (Option<ResponseInner>, HttpResponse) {
+        let mut resp = Sender::Response(None);
+        let (_, http) = with_http_response(handle_json_rpc(
+            (),
+            jsonrpc::Request {
+                jsonrpc: None,
+                id: RequestId::Null,
+                inner,
+            },
+            router,
+            &mut resp,
+            &mut SubscriptionMap::None,
+        ))
+        .await;
+        match resp {
+            Sender::Response(Some(resp)) => (Some(resp.result), http),
+            _ => (None, http),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cache_control() {
+        let router = Arc::new(
+            <Router>::new()
+                .query("version", |t| {
+                    t(|_, _: ()| "1.0.0").cache_control("public, max-age=60")
+                })
+                .query("now", |t| t(|_, _: ()| 0))
+                .mutation("restart", |t| {
+                    t(|_, _: ()| ()).cache_control("public, max-age=60")
+                })
+                .build(),
+        );
+
+        let (result, http) = call(
+            &router,
+            RequestInner::Query {
+                path: "version".into(),
+                input: None,
+            },
+        )
+        .await;
+        assert!(matches!(result, Some(ResponseInner::Response(v)) if v == json!("1.0.0")));
+        assert_eq!(http.cache_control.as_deref(), Some("public, max-age=60"));
+
+        let (_, http) = call(
+            &router,
+            RequestInner::Query {
+                path: "now".into(),
+                input: None,
+            },
+        )
+        .await;
+        assert_eq!(http.cache_control, None);
+
+        // Only queries can be cached
+        let (_, http) = call(
+            &router,
+            RequestInner::Mutation {
+                path: "restart".into(),
+                input: None,
+            },
+        )
+        .await;
+        assert_eq!(http.cache_control, None);
+    }
+}
@@ -0,0 +1,126 @@
+use std::{any::Any, pin::Pin, sync::Arc};
+
+use futures::{stream, FutureExt, Stream, StreamExt};
+use serde::ser::Error as _;
+use serde_json::Value;
+use tokio::sync::oneshot;
+
+use crate::{ExecError, Runtime};
+
+type Items = Pin<Box<dyn Stream<Item = Result<Value, ExecError>> + Send>>;
+
+/// A type erased serializer of a subscription's items. See [`BuiltProcedureBuilder::serialize_concurrently`](crate::internal::BuiltProcedureBuilder::serialize_concurrently).
+pub(crate) type SerializeFn =
+    Arc<dyn Fn(Box<dyn Any + Send>) -> Result<Value, ExecError> + Send + Sync>;
+
+/// Serialize up to `concurrency` items of `stream` at once on tasks of the runtime, yielding them in the order the stream yielded them.
+///
+/// Items which finish early are held in a reorder window (keyed by their position in the stream) until every item before them is done. The stream isn't polled while the window is full, so at most `concurrency` items are ever buffered.
+pub(crate) fn serialize_concurrently(
+    stream: impl Stream<Item = Box<dyn Any + Send>> + Send + 'static,
+    concurrency: usize,
+    serialize: SerializeFn,
+    runtime: Option<Arc<dyn Runtime>>,
+) -> Items {
+    let Some(runtime) = runtime else {
+        return Box::pin(stream::once(async { Err(ExecError::NoRuntime) }));
+    };
+
+    Box::pin(
+        stream
+            .map(move |item| {
+                let (tx, rx) = oneshot::channel();
+                let serialize = serialize.clone();
+                runtime.spawn(Box::pin(async move {
+                    let _ = tx.send(serialize(item));
+                }));
+                rx.map(|value| {
+                    value.unwrap_or_else(|_| {
+                        Err(ExecError::SerializingResultErr(serde_json::Error::custom(
+                            "the task serializing the item was dropped",
+                        )))
+                    })
+                })
+            })
+            .buffered(concurrency),
+    )
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use std::{
+        future::Future,
+        pin::Pin,
+        sync::atomic::{AtomicUsize, Ordering},
+        thread,
+        time::Duration,
+    };
+
+    use futures::{executor::block_on, stream, StreamExt};
+    use serde::{Serialize, Serializer};
+    use serde_json::{json, Value};
+    use specta::Type;
+
+    use crate::{Config, Router, Runtime};
+
+    // Runs every task on it's own thread, so the items are serialized in parallel
+    struct Threads;
+
+    impl Runtime for Threads {
+        fn spawn(&self, fut: Pin<Box<dyn Future<Output = ()> + Send>>) {
+            thread::spawn(move || block_on(fut));
+        }
+
+        fn sleep(&self, duration: Duration) -> Pin<Box<dyn Future<Output = ()> + Send>> {
+            Box::pin(tokio::time::sleep(duration))
+        }
+    }
+
+    static IN_FLIGHT: AtomicUsize = AtomicUsize::new(0);
+    static MAX_IN_FLIGHT: AtomicUsize = AtomicUsize::new(0);
+
+    // An item which is slower to serialize the earlier it is, so it'd be sent out of order if nothing reordered them
+    #[derive(Type)]
+    struct Item(u32);
+
+    impl Serialize for Item {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            let in_flight = IN_FLIGHT.fetch_add(1, Ordering::SeqCst) + 1;
+            MAX_IN_FLIGHT.fetch_max(in_flight, Ordering::SeqCst);
+            thread::sleep(Duration::from_millis(u64::from(10 - self.0 % 10) * 2));
+            IN_FLIGHT.fetch_sub(1, Ordering::SeqCst);
+            serializer.serialize_u32(self.0)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_serialize_concurrently() {
+        let router = <Router>::new()
+            .config(Config::new().runtime(Threads))
+            .subscription("items", |t| {
+                t(|_, _: ()| stream::iter(0..30).map(Item)).serialize_concurrently(3)
+            })
+            .subscription("mapped", |t| {
+                t(|_, _: ()| stream::iter(0..30))
+                    .map_item(Item)
+                    .serialize_concurrently(3)
+            })
+            .build();
+
+        for key in ["items", "mapped"] {
+            let items = router
+                .exec_subscription((), key.into(), None)
+                .await
+                .unwrap()
+                .map(|item| item.unwrap())
+                .collect::<Vec<_>>()
+                .await;
+            // Items are sent in order, even though later ones finish serializing first
+            assert_eq!(items, (0..30).map(|i| json!(i)).collect::<Vec<Value>>());
+        }
+        // They were serialized concurrently, but never more than the window at once
+        let max_in_flight = MAX_IN_FLIGHT.load(Ordering::SeqCst);
+        assert!(max_in_flight > 1 && max_in_flight <= 3, "{max_in_flight}");
+    }
+}
@@ -1,12 +1,163 @@
-use std::path::PathBuf;
+use std::{borrow::Cow, future::Future, path::PathBuf, sync::Arc, time::Duration};
+
+use futures::{future::BoxFuture, FutureExt};
+use serde_json::Value;
+
+use crate::{
+    internal::{
+        jsonrpc::{Headers, Request, Response},
+        ProcedureKind,
+    },
+    BinaryFormat, Error, ExecError, OpenRpcInfo, RenameRule, Runtime, Sampler,
+};
+
+use super::{mutation_hook::MutationHookFn, sla::SlaBreachFn, transform::TransformFn};
+
+/// What to do with a request which arrives while it's connection is already at it's concurrency limit.
+///
+/// See [`Config::max_concurrent_requests`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverloadBehavior {
+    /// Wait for one of the in-flight requests on the connection to finish before executing.
+    Queue,
+    /// Respond immediately with [`ExecError::Overloaded`](crate::ExecError::Overloaded).
+    Reject,
+}
+
+/// What to do with a subscription event which would take it's connection over [`Config::max_buffered_bytes`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BufferOverflow {
+    /// Stop polling the subscription's stream until the transport has flushed enough of the connection's buffered events.
+    Backpressure,
+    /// Drop the event. The subscription keeps running and later events are sent once there is room again.
+    Drop,
+}
+
+/// What happens to a mutation when it's [`Config::after_mutation`] hook fails.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookFailure {
+    /// Respond with the hook's error, even though the mutation itself succeeded.
+    Fail,
+    /// Respond with the mutation's result. The error is logged with the `tracing` feature.
+    Log,
+}
+
+/// How much detail about an error is sent to the client.
+///
+/// See [`Config::error_verbosity`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorVerbosity {
+    /// Send the error's message along with it's full [`source`](std::error::Error::source) chain as `data.sources`. Useful during development.
+    Full,
+    /// Send only a generic message for the error's [`ErrorKind`](crate::ErrorKind) and a `data.correlationId`.
+    /// The full error is passed to [`Config::on_error`] (and logged with the `tracing` feature) along with the same correlation id, so it can be found from a client's report.
+    Minimal,
+}
+
+/// The code and reason of the frame sent when a connection (Eg. a WebSocket) is closed by the server.
+///
+/// See [`Config::close_frame`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CloseFrame {
+    pub code: u16,
+    pub reason: Cow<'static, str>,
+    /// How long the client should wait before reconnecting. See [`Config::retry_after`].
+    pub retry_after: Option<Duration>,
+}
+
+impl CloseFrame {
+    /// `1000`: The connection was closed normally (Eg. it reached [`Config::max_bytes_per_connection`]).
+    pub const NORMAL: Self = Self::new(1000, "");
+    /// `1002`: The client violated the protocol (Eg. it sent a message which isn't a valid request).
+    pub const PROTOCOL_ERROR: Self = Self::new(1002, "");
+    /// `1008`: The client violated a policy of the server (Eg. it's authorization was revoked).
+    pub const POLICY_VIOLATION: Self = Self::new(1008, "");
+
+    pub const fn new(code: u16, reason: &'static str) -> Self {
+        Self {
+            code,
+            reason: Cow::Borrowed(reason),
+            retry_after: None,
+        }
+    }
+
+    /// Set the reason sent with the code. Note: The reason must be at most 123 bytes, longer reasons are truncated by most clients.
+    pub fn reason(mut self, reason: impl Into<Cow<'static, str>>) -> Self {
+        self.reason = reason.into();
+        self
+    }
+
+    /// Ask the client to wait for `retry_after` before reconnecting.
+    pub fn retry_after(mut self, retry_after: Duration) -> Self {
+        self.retry_after = Some(retry_after);
+        self
+    }
+
+    /// The reason to send in the close frame. A [`retry_after`](Self::retry_after) hint is appended to it as `;retry-after=<milliseconds>` (Eg. `server overloaded;retry-after=5000`), as close frames can't carry any other data.
+    pub fn wire_reason(&self) -> Cow<'static, str> {
+        match self.retry_after {
+            Some(retry_after) => {
+                format!("{};retry-after={}", self.reason, retry_after.as_millis()).into()
+            }
+            None => self.reason.clone(),
+        }
+    }
+}
 
 /// TODO
 #[derive(Default)]
 pub struct Config {
     pub(crate) export_bindings_on_build: Option<PathBuf>,
     pub(crate) bindings_header: Option<&'static str>,
+    pub(crate) validate_results: bool,
+    pub(crate) max_concurrent_requests: Option<(usize, OverloadBehavior)>,
+    pub(crate) max_subscriptions_per_connection: Option<usize>,
+    pub(crate) priority_queue: Option<(usize, Duration)>,
+    pub(crate) on_queue_wait: Option<QueueWaitFn>,
+    pub(crate) on_sla_breach: Option<SlaBreachFn>,
+    pub(crate) on_serialize: Option<OnSerializeFn>,
+    pub(crate) trace_sampler: Option<Arc<dyn Sampler>>,
+    pub(crate) max_bytes_per_connection: Option<u64>,
+    pub(crate) max_buffered_bytes: Option<(u64, BufferOverflow)>,
+    pub(crate) rename_fields: Option<RenameRule>,
+    pub(crate) runtime: Option<Arc<dyn Runtime>>,
+    pub(crate) transform_responses: Option<TransformFn>,
+    pub(crate) transform_subscription_events: bool,
+    pub(crate) deprecation_warnings: bool,
+    pub(crate) after_mutation: Option<(MutationHookFn, HookFailure)>,
+    pub(crate) context_timeout: Option<Duration>,
+    pub(crate) unsubscribe_timeout: Option<Duration>,
+    pub(crate) error_verbosity: Option<ErrorVerbosity>,
+    pub(crate) on_error: Option<OnErrorFn>,
+    pub(crate) method_parser: Option<MethodParserFn>,
+    pub(crate) pre_context: Vec<PreContextFn>,
+    pub(crate) close_frame: Option<CloseFrameFn>,
+    pub(crate) binary_format: Option<Arc<dyn BinaryFormat>>,
+    pub(crate) retry_after: Option<RetryAfterFn>,
+    pub(crate) rewrite_frames: Option<RewriteFrameFn>,
+    pub(crate) introspection: Option<(&'static str, OpenRpcInfo)>,
 }
 
+const DEFAULT_UNSUBSCRIBE_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub(crate) type QueueWaitFn = Arc<dyn Fn(ProcedureKind, &str, Duration) + Send + Sync>;
+
+pub(crate) type OnSerializeFn = Arc<dyn Fn(ProcedureKind, &str, Duration) + Send + Sync>;
+
+pub(crate) type MethodParserFn = Arc<dyn Fn(&str) -> Option<(ProcedureKind, String)> + Send + Sync>;
+
+pub(crate) type CloseFrameFn = Arc<dyn Fn(&ExecError) -> Option<CloseFrame> + Send + Sync>;
+
+pub(crate) type OnErrorFn = Arc<dyn Fn(&ExecError, Option<&str>) + Send + Sync>;
+
+pub(crate) type RetryAfterFn = Arc<dyn Fn(&ExecError) -> Option<Duration> + Send + Sync>;
+
+pub(crate) type RewriteFrameFn = Arc<dyn Fn(&Headers, Response) -> Response + Send + Sync>;
+
+/// A hook registered with [`Config::pre_context`].
+pub(crate) type PreContextFn =
+    Arc<dyn Fn(&Request) -> BoxFuture<'static, Result<(), Error>> + Send + Sync>;
+
 impl Config {
     pub fn new() -> Self {
         Default::default()
@@ -28,4 +179,292 @@ impl Config {
         self.bindings_header = Some(custom);
         self
     }
+
+    /// will check every procedure result (and subscription event) against it's declared Specta type, returning an internal server error if it doesn't match.
+    /// This is useful for catching resolvers which serialize differently to their exported type (Eg. a custom `Serialize` implementation).
+    /// Note: This is opt-in and only runs when `debug_assertions` are enabled as it walks every result, so the cost grows with the size of your responses.
+    pub fn validate_results(mut self) -> Self {
+        self.validate_results = true;
+        self
+    }
+
+    /// limits the number of requests which can be executing at once on a single connection (Eg. a WebSocket).
+    /// Active subscriptions count against the limit until they end or are stopped by the client.
+    /// Note: This doesn't apply to plain HTTP requests as every request is it's own connection.
+    pub fn max_concurrent_requests(mut self, limit: usize, behavior: OverloadBehavior) -> Self {
+        self.max_concurrent_requests = Some((limit, behavior));
+        self
+    }
+
+    /// admits the requests waiting for a slot under [`Config::max_concurrent_requests`] (with [`OverloadBehavior::Queue`]) by the [`Priority`](crate::Priority) of their procedure instead of in arrival order, so interactive requests are served ahead of background work when the connection is saturated.
+    /// Requests of the same priority are admitted in arrival order. At most `max_queued` requests can wait at once, beyond that requests fail immediately with [`ExecError::Overloaded`](crate::ExecError::Overloaded).
+    ///
+    /// ## Starvation
+    ///
+    /// A steady stream of high priority requests would keep low priority requests waiting forever, so waiting requests age: every `aging` a request has waited raises it's priority by one level.
+    /// Eg. with an `aging` of 1 second a [`Priority::Low`](crate::Priority::Low) request which has waited 2 seconds is admitted ahead of a [`Priority::High`](crate::Priority::High) request which just arrived.
+    /// An `aging` of zero disables aging.
+    pub fn priority_queue(mut self, max_queued: usize, aging: Duration) -> Self {
+        self.priority_queue = Some((max_queued, aging));
+        self
+    }
+
+    /// calls `record` with the kind, key and queue wait time of every request admitted under [`Config::max_concurrent_requests`], Eg. to record it in a per-procedure histogram of your metrics library.
+    /// The wait is the time between the request trying to acquire a slot (and it's place in the [`Config::priority_queue`]) and being admitted, so it doesn't include the time the resolver takes. A request admitted immediately is recorded with a wait close to zero.
+    /// Note: Requests which are rejected with [`ExecError::Overloaded`](crate::ExecError::Overloaded) or whose connection has no limit aren't recorded.
+    pub fn on_queue_wait(
+        mut self,
+        record: impl Fn(ProcedureKind, &str, Duration) + Send + Sync + 'static,
+    ) -> Self {
+        self.on_queue_wait = Some(Arc::new(record));
+        self
+    }
+
+    /// calls `hook` with the key, SLA and actual latency of every request to a procedure which took longer than it's [`sla`](crate::internal::BuiltProcedureBuilder::sla), Eg. to alert on it or count breaches in your metrics library.
+    /// The latency is measured from when the procedure is called (after the context has been built) until it's result is ready, including requests which fail.
+    /// Note: This is only for observability, the request still completes as usual. Procedures without an SLA aren't timed.
+    pub fn on_sla_breach(
+        mut self,
+        hook: impl Fn(&str, Duration, Duration) + Send + Sync + 'static,
+    ) -> Self {
+        self.on_sla_breach = Some(Arc::new(hook));
+        self
+    }
+
+    /// calls `record` with the kind, key and serialization time of every result and subscription event, Eg. to record it in a per-procedure histogram of your metrics library and tell the procedures which are slow to serialize apart from the ones which are slow to resolve.
+    /// The time is only spent converting the resolver's return value into JSON, so it doesn't include the resolver or the middleware. A subscription records each event separately, including events which are serialized on another task with [`serialize_concurrently`](crate::internal::BuiltProcedureBuilder::serialize_concurrently).
+    /// Note: A result which [`Router::exec_into`](crate::Router::exec_into) serializes directly into it's serializer isn't recorded, as it's written to the output as it's serialized.
+    pub fn on_serialize(
+        mut self,
+        record: impl Fn(ProcedureKind, &str, Duration) + Send + Sync + 'static,
+    ) -> Self {
+        self.on_serialize = Some(Arc::new(record));
+        self
+    }
+
+    /// decides whether the trace started by a request is sampled (kept) or dropped, Eg. [`RandomSampler`](crate::RandomSampler) to keep a fraction of them.
+    /// The decision is made once per request and is sent to downstream services as the sampled flag of [`traceparent`](crate::traceparent), so every service in the trace agrees on it. With the `tracing` feature a request which isn't sampled has no `rspc.request` span.
+    /// Note: This is only asked about requests which start a new trace, a request with a valid `traceparent` header keeps the decision of the service which sent it. By default every request is sampled.
+    pub fn trace_sampler(mut self, sampler: impl Sampler) -> Self {
+        self.trace_sampler = Some(Arc::new(sampler));
+        self
+    }
+
+    /// limits the number of subscriptions which can be active at once on a single connection (Eg. a WebSocket).
+    /// Subscribing beyond the limit fails with [`ExecError::TooManySubscriptions`](crate::ExecError::TooManySubscriptions) without running the resolver.
+    /// A subscription stops counting against the limit once it's stream ends, it's stopped by the client or the connection is closed.
+    pub fn max_subscriptions_per_connection(mut self, limit: usize) -> Self {
+        self.max_subscriptions_per_connection = Some(limit);
+        self
+    }
+
+    /// limits the total number of bytes which can be sent to the client over a single connection (Eg. a WebSocket), to protect against abuse.
+    /// Once a frame takes the connection over the limit the transport finishes sending it and then cleanly closes the connection. See [`Connection::record_sent`](crate::internal::jsonrpc::Connection::record_sent).
+    /// Note: This doesn't apply to plain HTTP requests as every request is it's own connection.
+    pub fn max_bytes_per_connection(mut self, limit: u64) -> Self {
+        self.max_bytes_per_connection = Some(limit);
+        self
+    }
+
+    /// limits the total size of the (serialized) subscription events which have been queued for a single connection (Eg. a WebSocket) but not yet flushed to the client, so a slow client can't make the server buffer an unbounded amount of memory.
+    /// Once an event would take the connection over the limit it's handled according to `behavior`. A single event larger than the limit is still sent once nothing else is buffered.
+    /// The current size is available from [`Connection::buffered_bytes`](crate::internal::jsonrpc::Connection::buffered_bytes).
+    /// Note: The transport must report flushed events with [`Connection::record_flushed`](crate::internal::jsonrpc::Connection::record_flushed) (passing their [`Connection::reserved_len`](crate::internal::jsonrpc::Connection::reserved_len)), otherwise the connection's subscriptions stall once the limit is reached.
+    pub fn max_buffered_bytes(mut self, limit: u64, behavior: BufferOverflow) -> Self {
+        self.max_buffered_bytes = Some((limit, behavior));
+        self
+    }
+
+    /// serves the [OpenRPC document](crate::Router::openrpc) describing the router's procedures and types as the query `key`, so dynamic clients can fetch the schema at runtime instead of using generated bindings.
+    /// Procedures hidden from the requesting context with `.visible_when` are omitted from the document, as are routers mounted with a [`RouterGroup`](crate::RouterGroup).
+    /// Note: This is disabled by default as it exposes your entire API to anyone who can make a request. A procedure registered at `key` takes precedence.
+    pub fn introspection(mut self, key: &'static str, info: OpenRpcInfo) -> Self {
+        self.introspection = Some((key, info));
+        self
+    }
+
+    /// sends the frames of WebSocket connections whose client negotiated `format` (Eg. MessagePack) as binary frames encoded with it, instead of JSON text frames.
+    /// The client negotiates it by offering the format's [`protocol`](BinaryFormat::protocol) in the `Sec-WebSocket-Protocol` header of it's upgrade request, every other connection keeps using JSON. See [`BinaryFormat`].
+    /// Note: This only applies to WebSocket connections, HTTP responses are always JSON.
+    pub fn binary_format(mut self, format: impl BinaryFormat) -> Self {
+        self.binary_format = Some(Arc::new(format));
+        self
+    }
+
+    /// maps the errors which should terminate a connection (Eg. a WebSocket) to the code and reason of it's close frame, so clients can tell why they were disconnected.
+    /// It's called by the transport integration with the errors it encounters outside of a procedure: messages which aren't valid requests ([`ExecError::InvalidRequest`]) and failures to build the context of a request (Eg. because the client's authorization was revoked).
+    /// Returning `Some` closes the connection with the frame, returning `None` keeps it open and handles the error as usual (which is also the behavior when this isn't set).
+    /// Note: Connections closed by the server for any other reason (Eg. [`Config::max_bytes_per_connection`]) use [`CloseFrame::NORMAL`].
+    pub fn close_frame(
+        mut self,
+        map: impl Fn(&ExecError) -> Option<CloseFrame> + Send + Sync + 'static,
+    ) -> Self {
+        self.close_frame = Some(Arc::new(map));
+        self
+    }
+
+    /// computes how long a client should wait before retrying after `err`, so clients rejected while the server is shedding load (Eg. [`ExecError::Overloaded`]) back off instead of all retrying at once.
+    /// It's called with every error sent to the client as an error frame and every error which closes the connection (see [`Config::close_frame`]). Returning `Some` adds the hint:
+    ///  - error frames get a `retryAfter` field (in milliseconds) in their `data`, Eg. `{ "retryAfter": 5000 }`.
+    ///  - close frames get their [`CloseFrame::retry_after`] set, unless [`Config::close_frame`] already set it.
+    ///
+    /// Note: Returning a jittered duration spreads out the reconnects of many clients.
+    pub fn retry_after(
+        mut self,
+        hint: impl Fn(&ExecError) -> Option<Duration> + Send + Sync + 'static,
+    ) -> Self {
+        self.retry_after = Some(Arc::new(hint));
+        self
+    }
+
+    /// rewrites every outgoing JSON-RPC frame just before the transport serializes it, Eg. to keep supporting an old client alongside a new one by downgrading frames to the protocol version it negotiated.
+    /// The rewriter receives the headers of the request (or of the connection's upgrade request for WebSockets), so it can pick the version per connection, and the frame which would be sent.
+    /// It runs for every kind of frame: results and errors, subscription events, logs and lifecycle frames (Eg. `cancelled`), including the errors of requests rejected by [`Config::pre_context`].
+    /// Note: It's applied by the transport integrations with [`rewrite_frame`](crate::internal::jsonrpc::rewrite_frame), so it isn't run for [`Router::exec`](crate::Router::exec). It runs after every other response hook (Eg. [`Config::transform_responses`]).
+    pub fn rewrite_frames(
+        mut self,
+        rewrite: impl Fn(&Headers, Response) -> Response + Send + Sync + 'static,
+    ) -> Self {
+        self.rewrite_frames = Some(Arc::new(rewrite));
+        self
+    }
+
+    /// applies a naming convention (Eg. `camelCase`) to the fields of every type used by the router, without `#[serde(rename_all = "...")]` on each of them.
+    /// Results (including subscription events and logs) are renamed before they're sent, inputs are renamed back before they're deserialized and the exported types use the new names, so the wire and the bindings always agree.
+    /// Note: Only the names of struct fields and enum variant fields are changed. Fields which serialize differently to their Specta type (see [`Config::validate_results`]) may not be renamed.
+    pub fn rename_fields(mut self, rule: RenameRule) -> Self {
+        self.rename_fields = Some(rule);
+        self
+    }
+
+    /// passes the result of every query through `transform` after it's serialized, Eg. to add hypermedia links derived from the procedure and input to every response without editing each resolver.
+    /// The transformer receives the procedure's key, the input sent by the client and the serialized result, and returns the value which is sent instead.
+    /// Note: The exported types aren't changed, so any fields added by the transformer won't appear in the bindings. It runs after [`Config::rename_fields`] so added fields are sent as-is.
+    pub fn transform_responses(
+        mut self,
+        transform: impl Fn(&str, &Value, Value) -> Value + Send + Sync + 'static,
+    ) -> Self {
+        self.transform_responses = Some(Arc::new(transform));
+        self
+    }
+
+    /// also passes every subscription event through the transformer set with [`Config::transform_responses`]. The input is the one the subscription was started with.
+    pub fn transform_subscription_events(mut self) -> Self {
+        self.transform_subscription_events = true;
+        self
+    }
+
+    /// adds a warning to every response of a procedure marked with [`.deprecated(message)`](crate::internal::BuiltProcedureBuilder::deprecated), so monitoring can count the usage of deprecated procedures.
+    /// The warning is appended to a `_warnings` array on the result: `{ "kind": "deprecated", "procedure": "<key>", "message": "<message>" }`.
+    /// Note: This is opt-in as it changes the shape of the result and the exported types aren't changed. It only applies to queries and mutations whose result is an object, other results are sent as-is. It runs after [`Config::transform_responses`].
+    pub fn deprecation_warnings(mut self) -> Self {
+        self.deprecation_warnings = true;
+        self
+    }
+
+    /// runs `hook` after every mutation which succeeds, Eg. to publish an event to a message bus for downstream consumers without every resolver doing it.
+    /// The hook receives the mutation's key, the input sent by the client and the serialized result. The response is only sent once it completes and if it fails the request is handled according to `on_failure`.
+    /// Note: It doesn't run for queries, subscriptions or mutations which return an error. It receives the result before [`Config::deprecation_warnings`] are added.
+    pub fn after_mutation<F, Fut>(mut self, hook: F, on_failure: HookFailure) -> Self
+    where
+        F: Fn(&str, &Value, &Value) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), Error>> + Send + 'static,
+    {
+        self.after_mutation = Some((
+            Arc::new(move |key, input, result| hook(key, input, result).boxed()),
+            on_failure,
+        ));
+        self
+    }
+
+    /// limits how long building the context of a request can take (Eg. loading the session from a slow store). Requests which take longer fail with [`ExecError::ContextTimeout`](crate::ExecError::ContextTimeout) without running the procedure.
+    /// Note: This is applied by the transport integration using [`build_context`](crate::internal::jsonrpc::build_context) and requires a [`Runtime`].
+    pub fn context_timeout(mut self, timeout: Duration) -> Self {
+        self.context_timeout = Some(timeout);
+        self
+    }
+
+    /// limits how long the `on_unsubscribe` hook of a subscription (See [`BuiltProcedureBuilder::on_unsubscribe`](crate::internal::BuiltProcedureBuilder::on_unsubscribe)) can take to clean up. A hook which takes longer is dropped and a warning is logged, so a stuck cleanup doesn't hold up the subscription's teardown.
+    /// Note: This defaults to 10 seconds and requires a [`Runtime`].
+    pub fn unsubscribe_timeout(mut self, timeout: Duration) -> Self {
+        self.unsubscribe_timeout = Some(timeout);
+        self
+    }
+
+    /// controls how much detail about errors is sent to clients (Eg. [`ErrorVerbosity::Full`] in development and [`ErrorVerbosity::Minimal`] in production).
+    /// When this isn't set, errors are sent with their message but without their source chain.
+    /// Note: [`ErrorVerbosity::Minimal`] also replaces the messages of errors returned by your resolvers and the field errors of invalid inputs, as they may contain internal details. Use [`Config::on_error`] to record the full errors.
+    pub fn error_verbosity(mut self, verbosity: ErrorVerbosity) -> Self {
+        self.error_verbosity = Some(verbosity);
+        self
+    }
+
+    /// calls `hook` with every error sent to a client, Eg. to report it to your error tracker. This doesn't need the `tracing` feature.
+    /// With [`ErrorVerbosity::Minimal`] it also receives the correlation id which was sent to the client in place of the error, so the full error can be found from a client's report. Otherwise the correlation id is `None`.
+    /// Note: The hook receives the error before it's rendered, so it's unaffected by [`Config::error_verbosity`].
+    pub fn on_error(
+        mut self,
+        hook: impl Fn(&ExecError, Option<&str>) + Send + Sync + 'static,
+    ) -> Self {
+        self.on_error = Some(Arc::new(hook));
+        self
+    }
+
+    /// maps the methods of JSON-RPC requests which don't use rspc's scheme to a procedure's kind and key, Eg. to support legacy clients which send `{ "method": "query:users.get", "params": <input> }`.
+    /// rspc's own methods (`query`, `mutation`, `subscription` and `subscriptionStop` with the key in `params.path`) are always handled as usual and `parse` is only called for other methods. Their `params` are used as the procedure's input and for subscriptions the request's `id` is used as the subscription's id.
+    /// When this isn't set the methods of the [OpenRPC document](crate::Router::openrpc) (Eg. `{ "method": "query:users.get", "params": { "input": <input> } }`) are supported.
+    /// Note: Requests for which `parse` returns `None` fail with [`ExecError::UnsupportedMethod`](crate::ExecError::UnsupportedMethod).
+    pub fn method_parser(
+        mut self,
+        parse: impl Fn(&str) -> Option<(ProcedureKind, String)> + Send + Sync + 'static,
+    ) -> Self {
+        self.method_parser = Some(Arc::new(parse));
+        self
+    }
+
+    /// adds a middleware which runs before the context of a request is built, so requests it rejects don't pay the cost of building the context (Eg. rate limiting or checking for an API key).
+    ///
+    /// Requests go through two phases of middleware:
+    ///  - pre-context middleware (added with this) run first, in the order they are added. They receive the raw [`Request`] (it's method, procedure key and input) as the context doesn't exist yet.
+    ///    Returning an error rejects the request and it is sent to the client without building the context or running any later middleware.
+    ///  - post-context middleware (added with [`RouterBuilder::middleware`](crate::RouterBuilder::middleware)) run once the context has been built and receive it along with the procedure's input.
+    ///
+    /// ```text
+    /// pre_context(a) -> pre_context(b) -> context -> middleware -> resolver
+    /// ```
+    ///
+    /// Pre-context middleware are run by the transport integrations with [`pre_context`](crate::internal::jsonrpc::pre_context), so they aren't run for [`Router::exec`](crate::Router::exec) which is given an existing context.
+    pub fn pre_context<F, Fut>(mut self, middleware: F) -> Self
+    where
+        F: Fn(&Request) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), Error>> + Send + 'static,
+    {
+        self.pre_context
+            .push(Arc::new(move |req| middleware(req).boxed()));
+        self
+    }
+
+    /// sets the async runtime used to run subscriptions started over a connection (Eg. a WebSocket).
+    /// Defaults to [`TokioRuntime`](crate::TokioRuntime) when the `runtime-tokio` feature is enabled. See [`Runtime`] for what requires a runtime.
+    pub fn runtime(mut self, runtime: impl Runtime) -> Self {
+        self.runtime = Some(Arc::new(runtime));
+        self
+    }
+
+    pub(crate) fn unsubscribe_timeout_or_default(&self) -> Duration {
+        self.unsubscribe_timeout
+            .unwrap_or(DEFAULT_UNSUBSCRIBE_TIMEOUT)
+    }
+
+    pub(crate) fn runtime_or_default(&self) -> Option<Arc<dyn Runtime>> {
+        #[cfg(feature = "runtime-tokio")]
+        return Some(
+            self.runtime
+                .clone()
+                .unwrap_or_else(|| Arc::new(crate::TokioRuntime)),
+        );
+
+        #[cfg(not(feature = "runtime-tokio"))]
+        return self.runtime.clone();
+    }
 }
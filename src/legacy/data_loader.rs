@@ -0,0 +1,231 @@
+use std::{
+    collections::HashMap,
+    future::Future,
+    hash::Hash,
+    sync::{Arc, Mutex, PoisonError, Weak},
+};
+
+use futures::{
+    future::{self, BoxFuture, Shared},
+    FutureExt,
+};
+
+use crate::{is_connected, Error, ErrorCode};
+
+type BatchFn<K, V> =
+    Arc<dyn Fn(Vec<K>) -> BoxFuture<'static, Result<HashMap<K, V>, Error>> + Send + Sync>;
+
+type Batch<K, V> = Shared<BoxFuture<'static, Result<Arc<HashMap<K, V>>, Error>>>;
+
+/// Batches and caches the lookups made while executing a request, to avoid the N+1 problem when many resolvers (Eg. the items of a list) load the same kind of value.
+///
+/// Every [`load`](DataLoader::load) made concurrently (Eg. within a `join_all`) is coalesced into a single call to the batch function, and it's results are cached so each key is only looked up once.
+/// Construct a new loader in your context function so the cache lives for a single request, and never leaks between requests or users.
+///
+/// The batch isn't run if the client stopped waiting for the request (see [`is_connected`](crate::is_connected)), in which case the loads fail with [`ErrorCode::ClientClosedRequest`].
+///
+/// ```rust
+/// use std::collections::HashMap;
+///
+/// use futures::future::try_join_all;
+/// use rspc::{DataLoader, Router};
+///
+/// #[derive(Clone)]
+/// struct Ctx {
+///     users: DataLoader<u32, String>,
+/// }
+///
+/// let router = Router::<Ctx>::new()
+///     .query("users.names", |t| {
+///         t(|ctx, ids: Vec<u32>| async move {
+///             try_join_all(ids.into_iter().map(|id| ctx.users.load(id))).await
+///         })
+///     })
+///     .build();
+///
+/// // A new loader for each request
+/// let ctx = || Ctx {
+///     users: DataLoader::new(|ids: Vec<u32>| async move {
+///         Ok(ids.into_iter().map(|id| (id, format!("user {id}"))).collect::<HashMap<_, _>>())
+///     }),
+/// };
+/// ```
+pub struct DataLoader<K, V> {
+    inner: Arc<Inner<K, V>>,
+}
+
+struct Inner<K, V> {
+    batch_fn: BatchFn<K, V>,
+    state: Mutex<State<K, V>>,
+}
+
+struct State<K, V> {
+    // `None` when the key was loaded but the batch function didn't return a value for it
+    cache: HashMap<K, Option<V>>,
+    // The keys waiting for the next batch, which runs once any of it's loads is polled after yielding
+    pending: Option<(Vec<K>, Batch<K, V>)>,
+}
+
+impl<K, V> Clone for DataLoader<K, V> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<K, V> DataLoader<K, V>
+where
+    K: Eq + Hash + Clone + Send + Sync + 'static,
+    V: Clone + Send + Sync + 'static,
+{
+    /// Construct a loader which looks up a batch of keys with `batch`. Keys which are missing from the returned map are loaded as `None`.
+    pub fn new<TFut>(batch: impl Fn(Vec<K>) -> TFut + Send + Sync + 'static) -> Self
+    where
+        TFut: Future<Output = Result<HashMap<K, V>, Error>> + Send + 'static,
+    {
+        Self {
+            inner: Arc::new(Inner {
+                batch_fn: Arc::new(move |keys| batch(keys).boxed()),
+                state: Mutex::new(State {
+                    cache: HashMap::new(),
+                    pending: None,
+                }),
+            }),
+        }
+    }
+
+    /// Load the value of `key`, joining the batch of any other loads made concurrently with it.
+    pub async fn load(&self, key: K) -> Result<Option<V>, Error> {
+        let batch = {
+            let mut state = self.state();
+            if let Some(value) = state.cache.get(&key) {
+                return Ok(value.clone());
+            }
+
+            match &mut state.pending {
+                Some((keys, batch)) => {
+                    if !keys.contains(&key) {
+                        keys.push(key.clone());
+                    }
+                    batch.clone()
+                }
+                None => {
+                    let batch = self.batch();
+                    state.pending = Some((vec![key.clone()], batch.clone()));
+                    batch
+                }
+            }
+        };
+
+        // Give the other loads made concurrently with this one a chance to join the batch before it runs
+        tokio::task::yield_now().await;
+        Ok(batch.await?.get(&key).cloned())
+    }
+
+    /// Load the values of `keys` in a single batch. The values are returned in the same order as the keys.
+    pub async fn load_many(
+        &self,
+        keys: impl IntoIterator<Item = K>,
+    ) -> Result<Vec<Option<V>>, Error> {
+        future::try_join_all(keys.into_iter().map(|key| self.load(key))).await
+    }
+
+    /// Remove every cached value, Eg. after a mutation changed them.
+    pub fn clear(&self) {
+        self.state().cache.clear();
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, State<K, V>> {
+        self.inner
+            .state
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn batch(&self) -> Batch<K, V> {
+        let inner: Weak<Inner<K, V>> = Arc::downgrade(&self.inner);
+        async move {
+            let inner = inner.upgrade().ok_or_else(|| {
+                Error::new(
+                    ErrorCode::InternalServerError,
+                    "the data loader was dropped".into(),
+                )
+            })?;
+            let keys = inner
+                .state
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .pending
+                .take()
+                .map(|(keys, _)| keys)
+                .unwrap_or_default();
+
+            if !is_connected() {
+                return Err(Error::new(
+                    ErrorCode::ClientClosedRequest,
+                    "the client stopped waiting for the request".into(),
+                ));
+            }
+
+            let values = (inner.batch_fn)(keys.clone()).await?;
+            let mut state = inner.state.lock().unwrap_or_else(PoisonError::into_inner);
+            for key in keys {
+                let value = values.get(&key).cloned();
+                state.cache.insert(key, value);
+            }
+            Ok(Arc::new(values))
+        }
+        .boxed()
+        .shared()
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        sync::{Arc, Mutex},
+    };
+
+    use futures::future::join_all;
+
+    use crate::DataLoader;
+
+    #[tokio::test]
+    async fn test_data_loader_batches() {
+        let batches = Arc::new(Mutex::new(Vec::new()));
+        let loader = DataLoader::new({
+            let batches = batches.clone();
+            move |ids: Vec<u32>| {
+                batches.lock().unwrap().push(ids.clone());
+                async move {
+                    Ok(ids
+                        .into_iter()
+                        .filter(|id| *id != 404)
+                        .map(|id| (id, id * 10))
+                        .collect::<HashMap<_, _>>())
+                }
+            }
+        });
+
+        let values = join_all([1, 2, 1, 3, 404].map(|id| loader.load(id))).await;
+        assert_eq!(
+            values.into_iter().map(Result::unwrap).collect::<Vec<_>>(),
+            [Some(10), Some(20), Some(10), Some(30), None]
+        );
+        assert_eq!(*batches.lock().unwrap(), [vec![1, 2, 3, 404]]);
+
+        // Cached keys aren't loaded again
+        assert_eq!(
+            loader.load_many([3, 4, 404]).await.unwrap(),
+            [Some(30), Some(40), None]
+        );
+        assert_eq!(batches.lock().unwrap().last().unwrap(), &[4]);
+
+        loader.clear();
+        assert_eq!(loader.load(1).await.unwrap(), Some(10));
+        assert_eq!(batches.lock().unwrap().len(), 3);
+    }
+}
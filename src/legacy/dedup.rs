@@ -0,0 +1,279 @@
+use std::{
+    collections::HashMap,
+    hash::Hash,
+    marker::PhantomData,
+    sync::{Arc, Mutex, PoisonError},
+    time::{Duration, Instant},
+};
+
+use futures::{
+    future::{BoxFuture, Shared},
+    FutureExt, TryFutureExt,
+};
+use serde_json::Value;
+
+use super::cache::MIN_SWEEP_AT;
+use crate::{
+    internal::{Layer, LayerResult, ProcedureKind, RequestContext, ValueOrStream},
+    ExecError, MiddlewareLike,
+};
+
+// The error is shared so every caller can receive a copy of it, as `ExecError` isn't `Clone`
+type SharedResult = Shared<BoxFuture<'static, Result<Value, Arc<ExecError>>>>;
+
+// The caller's key, the procedure's path, the serialized input and the requested representation (See `crate::representation`).
+type FlightKey<TKey> = (TKey, String, String, Option<String>);
+
+struct Flight {
+    started: Instant,
+    result: SharedResult,
+}
+
+struct Flights<TKey> {
+    flights: HashMap<FlightKey<TKey>, Flight>,
+    // Flights are only replaced when they're requested again after the window, so expired ones are swept once there are this many.
+    // It's twice the number of flights left after the last sweep, so sweeping is amortised over the requests.
+    sweep_at: usize,
+}
+
+impl<TKey> Default for Flights<TKey> {
+    fn default() -> Self {
+        Self {
+            flights: HashMap::new(),
+            sweep_at: 0,
+        }
+    }
+}
+
+/// Middleware which collapses identical requests made within a short window into a single execution.
+///
+/// Requests are considered identical when they target the same procedure, with the same input, the same key derived from the context and the same [`representation`](crate::representation).
+/// The first request executes the procedure and every matching request which arrives while it's in flight, or within `window` of it starting, receives a clone of its result (or error).
+///
+/// This is useful for guarding against duplicate mutations caused by things like double-clicks. Unlike an idempotency key the client doesn't have to do anything.
+///
+/// Subscriptions are passed through untouched.
+///
+/// ```rust
+/// use std::time::Duration;
+///
+/// struct Ctx { user_id: u32 }
+///
+/// let router = rspc::Router::<Ctx>::new()
+///     .middleware(|_| rspc::Dedup::new(Duration::from_millis(500), |ctx: &Ctx| ctx.user_id))
+///     .mutation("createPost", |t| t(|_, title: String| title))
+///     .build();
+/// ```
+pub struct Dedup<TCtx, TKey> {
+    window: Duration,
+    key: Arc<dyn Fn(&TCtx) -> TKey + Send + Sync>,
+    flights: Arc<Mutex<Flights<TKey>>>,
+    phantom: PhantomData<fn() -> TKey>,
+}
+
+impl<TCtx, TKey> Dedup<TCtx, TKey>
+where
+    TKey: Hash + Eq,
+{
+    /// Construct a new deduplication middleware.
+    ///
+    /// The `key` function is used to identify the caller (Eg. the user or session) so requests from different callers are never merged.
+    pub fn new(window: Duration, key: impl Fn(&TCtx) -> TKey + Send + Sync + 'static) -> Self {
+        Self {
+            window,
+            key: Arc::new(key),
+            flights: Default::default(),
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<TCtx, TKey> Clone for Dedup<TCtx, TKey> {
+    fn clone(&self) -> Self {
+        Self {
+            window: self.window,
+            key: self.key.clone(),
+            flights: self.flights.clone(),
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<TCtx, TKey> MiddlewareLike<TCtx> for Dedup<TCtx, TKey>
+where
+    TCtx: Send + 'static,
+    TKey: Hash + Eq + 'static,
+{
+    type State = ();
+    type NewCtx = TCtx;
+
+    fn handle<TMiddleware: Layer<Self::NewCtx> + 'static>(
+        &self,
+        ctx: TCtx,
+        input: Value,
+        req: RequestContext,
+        next: Arc<TMiddleware>,
+    ) -> Result<LayerResult, ExecError> {
+        if matches!(req.kind, ProcedureKind::Subscription) {
+            return next.call(ctx, input, req);
+        }
+
+        let key = (
+            (self.key)(&ctx),
+            req.path.clone(),
+            input.to_string(),
+            crate::representation(),
+        );
+
+        let mut flights = self.flights.lock().unwrap_or_else(PoisonError::into_inner);
+        let Flights { flights, sweep_at } = &mut *flights;
+        let result = match flights.get(&key) {
+            Some(flight) if flight.started.elapsed() < self.window => flight.result.clone(),
+            _ => {
+                let layer_result = next.call(ctx, input, req.clone())?;
+                let result = async move {
+                    match layer_result.into_value_or_stream().await? {
+                        ValueOrStream::Value(v) => Ok(v),
+                        ValueOrStream::Stream(_) => Err(ExecError::UnsupportedMethod(req.path)),
+                    }
+                }
+                .map_err(Arc::new)
+                .boxed()
+                .shared();
+
+                if flights.len() >= (*sweep_at).max(MIN_SWEEP_AT) {
+                    flights.retain(|_, flight| flight.started.elapsed() < self.window);
+                    *sweep_at = flights.len() * 2;
+                }
+                flights.insert(
+                    key,
+                    Flight {
+                        started: Instant::now(),
+                        result: result.clone(),
+                    },
+                );
+                result
+            }
+        };
+
+        Ok(LayerResult::Future(Box::pin(
+            result.map_err(|err| err.duplicate()),
+        )))
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    use serde::{ser::Error as _, Serialize, Serializer};
+    use specta::Type;
+
+    use super::MIN_SWEEP_AT;
+    use crate::{Dedup, ExecError, ExecKind, Router};
+
+    #[tokio::test]
+    async fn test_dedup_simultaneous_mutations() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let router = Router::<u32>::new()
+            .middleware(|_| Dedup::new(Duration::from_secs(60), |ctx: &u32| *ctx))
+            .mutation("increment", {
+                let calls = calls.clone();
+                move |t| {
+                    let calls = calls.clone();
+                    t(move |_, amount: i32| {
+                        let calls = calls.clone();
+                        async move {
+                            tokio::task::yield_now().await;
+                            calls.fetch_add(1, Ordering::SeqCst) as i32 + amount
+                        }
+                    })
+                }
+            })
+            .build();
+
+        let input = Some(serde_json::json!(5));
+        let (a, b) = tokio::join!(
+            router.exec(1, ExecKind::Mutation, "increment".into(), input.clone()),
+            router.exec(1, ExecKind::Mutation, "increment".into(), input.clone()),
+        );
+        assert_eq!(a.unwrap(), serde_json::json!(5));
+        assert_eq!(b.unwrap(), serde_json::json!(5));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        // A different caller must not share the result
+        let c = router
+            .exec(2, ExecKind::Mutation, "increment".into(), input)
+            .await;
+        assert_eq!(c.unwrap(), serde_json::json!(6));
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[derive(Type)]
+    struct Unserializable;
+
+    impl Serialize for Unserializable {
+        fn serialize<S: Serializer>(&self, _: S) -> Result<S::Ok, S::Error> {
+            Err(S::Error::custom("unserializable"))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dedup_shared_error() {
+        let router = Router::<u32>::new()
+            .middleware(|_| Dedup::new(Duration::from_secs(60), |ctx: &u32| *ctx))
+            .mutation("fail", |t| {
+                t(|_, _: ()| async {
+                    tokio::task::yield_now().await;
+                    Unserializable
+                })
+            })
+            .build();
+
+        // Every caller receives the original error, not a resolver error wrapping it
+        let (a, b) = tokio::join!(
+            router.exec(1, ExecKind::Mutation, "fail".into(), None),
+            router.exec(1, ExecKind::Mutation, "fail".into(), None),
+        );
+        for result in [a, b] {
+            assert!(matches!(
+                result,
+                Err(ExecError::SerializingResultErr(err)) if err.to_string() == "unserializable"
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dedup_expired_flights_swept() {
+        let dedup = Dedup::new(Duration::from_millis(20), |ctx: &u32| *ctx);
+        let router = Router::<u32>::new()
+            .middleware({
+                let dedup = dedup.clone();
+                move |_| dedup.clone()
+            })
+            .mutation("echo", |t| t(|_, input: usize| input))
+            .build();
+        let exec =
+            |input: usize| router.exec(1, ExecKind::Mutation, "echo".into(), Some(input.into()));
+        let flights = || dedup.flights.lock().unwrap().flights.len();
+
+        for input in 0..MIN_SWEEP_AT {
+            exec(input).await.unwrap();
+        }
+        assert_eq!(flights(), MIN_SWEEP_AT);
+
+        // Flights for inputs which are never requested again are reclaimed once their window has passed
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        for input in MIN_SWEEP_AT..MIN_SWEEP_AT * 2 {
+            exec(input).await.unwrap();
+        }
+        assert_eq!(flights(), MIN_SWEEP_AT);
+    }
+}
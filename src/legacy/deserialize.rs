@@ -0,0 +1,380 @@
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use serde_path_to_error::Segment;
+use specta::Type;
+
+use crate::ExecError;
+
+use super::input_pipeline::PipelineFn;
+
+/// A problem with a specific field of a procedure's input.
+///
+/// These are sent to the client in the `data` of the error frame as `{ errors: FieldError[] }` when the input fails to deserialize, so the frontend can show them next to the corresponding form field.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Type)]
+pub struct FieldError {
+    /// A [JSON pointer](https://datatracker.ietf.org/doc/html/rfc6901) to the offending field. This is empty when the input itself is invalid.
+    pub path: String,
+    /// A human readable description of the problem.
+    pub message: String,
+    /// A description of the type which was expected at `path` (Eg. `u32` or `a string`), if it's known.
+    pub expected: Option<String>,
+}
+
+impl FieldError {
+    fn new(path: &serde_path_to_error::Path, err: &serde_json::Error) -> Self {
+        let mut pointer = String::new();
+        for segment in path.iter() {
+            match segment {
+                Segment::Seq { index } => pointer.push_str(&format!("/{index}")),
+                Segment::Map { key } | Segment::Enum { variant: key } => {
+                    pointer.push('/');
+                    pointer.push_str(&key.replace('~', "~0").replace('/', "~1"));
+                }
+                Segment::Unknown => pointer.push_str("/?"),
+            }
+        }
+
+        // serde reports missing fields on their parent object so we add the field to the path
+        let message = err.to_string();
+        let message = message
+            .split_once(" at line ")
+            .map_or(message.as_str(), |(message, _)| message);
+        if let Some(field) = message
+            .strip_prefix("missing field `")
+            .and_then(|field| field.strip_suffix('`'))
+        {
+            pointer.push('/');
+            pointer.push_str(&field.replace('~', "~0").replace('/', "~1"));
+        }
+
+        Self {
+            path: pointer,
+            message: message.to_string(),
+            expected: message
+                .rsplit_once(", expected ")
+                .map(|(_, expected)| expected.to_string()),
+        }
+    }
+}
+
+// Transforms a procedure's raw input before it's deserialized. See `BuiltProcedureBuilder::deserialize_with`.
+pub(crate) type DeserializeWithFn = fn(Value) -> Result<Value, ExecError>;
+
+// Apply a procedure's `DeserializeWithFn`, then it's defaults and then it's input pipeline, if it has them.
+fn transform_input(
+    input: Value,
+    deserialize_with: Option<DeserializeWithFn>,
+    defaults: Option<&Value>,
+    pipeline: Option<&PipelineFn>,
+) -> Result<Value, ExecError> {
+    let mut input = match deserialize_with {
+        Some(deserialize_with) => deserialize_with(input)?,
+        None => input,
+    };
+    if let Some(Value::Object(defaults)) = defaults {
+        if input.is_null() {
+            input = Value::Object(Default::default());
+        }
+        if let Value::Object(input) = &mut input {
+            for (key, value) in defaults {
+                input.entry(key).or_insert_with(|| value.clone());
+            }
+        }
+    }
+    match pipeline {
+        Some(pipeline) => pipeline(input),
+        None => Ok(input),
+    }
+}
+
+/// A limit on the size of a field of a procedure's input. Declare them with [`BuiltProcedureBuilder::constrain`](crate::internal::BuiltProcedureBuilder::constrain).
+///
+/// Lengths are the number of characters of a string or the number of items of an array. A constraint is ignored if the field is missing or isn't a string or an array, as deserialization reports those problems.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Constraint {
+    MaxLength(usize),
+    MinLength(usize),
+}
+
+impl Constraint {
+    // The message of the `FieldError` when `value` violates this constraint
+    fn check(&self, value: &Value) -> Option<String> {
+        let (len, unit) = match value {
+            Value::String(s) => (s.chars().count(), "characters"),
+            Value::Array(items) => (items.len(), "items"),
+            _ => return None,
+        };
+        match *self {
+            Constraint::MaxLength(max) if len > max => {
+                Some(format!("must be at most {max} {unit} long but is {len}"))
+            }
+            Constraint::MinLength(min) if len < min => {
+                Some(format!("must be at least {min} {unit} long but is {len}"))
+            }
+            _ => None,
+        }
+    }
+}
+
+// Check a procedure's input against it's constraints, reporting every field which violates one.
+fn check_constraints(
+    input: Value,
+    constraints: &[(&'static str, Constraint)],
+) -> Result<Value, ExecError> {
+    let errors = constraints
+        .iter()
+        .filter_map(|(path, constraint)| {
+            let message = constraint.check(input.pointer(path)?)?;
+            Some(FieldError {
+                path: path.to_string(),
+                message,
+                expected: None,
+            })
+        })
+        .collect::<Vec<_>>();
+    match errors.is_empty() {
+        true => Ok(input),
+        false => Err(ExecError::InputValidation { errors }),
+    }
+}
+
+// The options of a procedure which are applied to it's raw input before it's deserialized.
+#[derive(Default)]
+pub(crate) struct InputOptions {
+    pub deserialize_with: Option<DeserializeWithFn>,
+    pub defaults: Option<Value>,
+    pub pipeline: Option<PipelineFn>,
+    pub constraints: Vec<(&'static str, Constraint)>,
+}
+
+impl InputOptions {
+    // Transform the raw input and check it against the constraints, ready to be deserialized
+    pub fn apply(&self, input: Value) -> Result<Value, ExecError> {
+        check_constraints(
+            transform_input(
+                input,
+                self.deserialize_with,
+                self.defaults.as_ref(),
+                self.pipeline.as_ref(),
+            )?,
+            &self.constraints,
+        )
+    }
+}
+
+/// Deserialize a procedure's input, tracking the path to the field which failed.
+///
+/// serde stops at the first error so only a single [`FieldError`] is ever reported.
+pub(crate) fn deserialize_input<T: DeserializeOwned>(input: Value) -> Result<T, ExecError> {
+    serde_path_to_error::deserialize(input).map_err(|err| ExecError::InputValidation {
+        errors: vec![FieldError::new(err.path(), err.inner())],
+    })
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use serde::Deserialize;
+    use serde_json::json;
+    use specta::Type;
+
+    use crate::{
+        internal::jsonrpc::JsonRPCError, Constraint, Error, ErrorCode, ErrorKind, ExecError,
+        ExecKind, FieldError, Router,
+    };
+
+    #[derive(Deserialize, Type)]
+    struct Address {
+        #[allow(dead_code)]
+        zip: u32,
+    }
+
+    #[derive(Deserialize, Type)]
+    struct Signup {
+        #[allow(dead_code)]
+        name: String,
+        #[allow(dead_code)]
+        addresses: Vec<Address>,
+    }
+
+    #[tokio::test]
+    async fn test_input_validation() {
+        let router = <Router>::new()
+            .mutation("signup", |t| t(|_, _: Signup| ()))
+            .build();
+        let exec = |input| router.exec((), ExecKind::Mutation, "signup".into(), Some(input));
+
+        let err = exec(json!({ "name": "Monty", "addresses": [{ "zip": 1 }, { "zip": "abc" }] }))
+            .await
+            .unwrap_err();
+        let errors = match &err {
+            ExecError::InputValidation { errors } => errors.clone(),
+            _ => Vec::new(),
+        };
+        assert_eq!(
+            errors,
+            [FieldError {
+                path: "/addresses/1/zip".into(),
+                message: "invalid type: string \"abc\", expected u32".into(),
+                expected: Some("u32".into()),
+            }]
+        );
+        assert_eq!(err.kind(), ErrorKind::Validation);
+
+        let err = JsonRPCError::from(exec(json!({ "addresses": [] })).await.unwrap_err());
+        assert_eq!(
+            err.data,
+            Some(
+                json!({ "errors": [{ "path": "/name", "message": "missing field `name`", "expected": null }] })
+            )
+        );
+    }
+
+    #[derive(Deserialize, Type)]
+    struct Event {
+        date: String,
+    }
+
+    // Rewrite the legacy `DD/MM/YYYY` date to `YYYY-MM-DD`
+    fn legacy_date(mut input: serde_json::Value) -> Result<serde_json::Value, ExecError> {
+        if let Some(date) = input.get_mut("date") {
+            let parts = date
+                .as_str()
+                .map(|date| date.split('/').collect::<Vec<_>>())
+                .unwrap_or_default();
+            if let [day, month, year] = parts[..] {
+                *date = json!(format!("{year}-{month}-{day}"));
+            }
+        }
+        Ok(input)
+    }
+
+    #[tokio::test]
+    async fn test_deserialize_with() {
+        let router = <Router>::new()
+            .mutation("create", |t| {
+                t(|_, event: Event| event.date).deserialize_with(legacy_date)
+            })
+            .mutation("reject", |t| {
+                t(|_, event: Event| event.date).deserialize_with(|_| {
+                    Err(Error::new(ErrorCode::BadRequest, "unsupported format".into()).into())
+                })
+            })
+            .build();
+
+        let result = router
+            .exec(
+                (),
+                ExecKind::Mutation,
+                "create".into(),
+                Some(json!({ "date": "15/10/2026" })),
+            )
+            .await;
+        assert_eq!(result.unwrap(), json!("2026-10-15"));
+        let result = router
+            .exec(
+                (),
+                ExecKind::Mutation,
+                "create".into(),
+                Some(json!({ "date": "2026-10-15" })),
+            )
+            .await;
+        assert_eq!(result.unwrap(), json!("2026-10-15"));
+
+        let result = router
+            .exec(
+                (),
+                ExecKind::Mutation,
+                "reject".into(),
+                Some(json!({ "date": "" })),
+            )
+            .await;
+        assert!(matches!(result, Err(ExecError::ErrResolverError(_))));
+    }
+
+    #[derive(Deserialize, Type)]
+    struct Search {
+        query: String,
+        limit: u32,
+        cursor: Option<String>,
+    }
+
+    #[tokio::test]
+    async fn test_defaults() {
+        let router = <Router>::new()
+            .query("search", |t| {
+                t(|_, search: Search| (search.query, search.limit, search.cursor))
+                    .defaults(json!({ "limit": 10, "cursor": "start" }))
+            })
+            .build();
+        let exec = |input| router.exec((), ExecKind::Query, "search".into(), input);
+
+        // Omitted fields are defaulted but explicit values (including `null`) are kept
+        let result = exec(Some(json!({ "query": "a" }))).await;
+        assert_eq!(result.unwrap(), json!(["a", 10, "start"]));
+        let result = exec(Some(json!({ "query": "a", "limit": 5, "cursor": null }))).await;
+        assert_eq!(result.unwrap(), json!(["a", 5, null]));
+
+        // Fields without a default are still required
+        let result = exec(None).await;
+        assert!(
+            matches!(result, Err(ExecError::InputValidation { errors }) if errors[0].path == "/query")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_constraints() {
+        let router = <Router>::new()
+            .mutation("signup", |t| {
+                t(|_, _: Signup| ())
+                    .constrain("/name", Constraint::MaxLength(5))
+                    .constrain("/name", Constraint::MinLength(2))
+                    .constrain("/addresses", Constraint::MaxLength(1))
+            })
+            .build();
+        let exec = |input| router.exec((), ExecKind::Mutation, "signup".into(), Some(input));
+
+        assert!(
+            exec(json!({ "name": "Monty", "addresses": [{ "zip": 1 }] }))
+                .await
+                .is_ok()
+        );
+
+        // Every violation is reported, not just the first
+        let err = exec(json!({ "name": "Montgomery", "addresses": [{ "zip": 1 }, { "zip": 2 }] }))
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(&err, ExecError::InputValidation { errors } if *errors == [
+                FieldError {
+                    path: "/name".into(),
+                    message: "must be at most 5 characters long but is 10".into(),
+                    expected: None,
+                },
+                FieldError {
+                    path: "/addresses".into(),
+                    message: "must be at most 1 items long but is 2".into(),
+                    expected: None,
+                },
+            ])
+        );
+        assert_eq!(JsonRPCError::from(err).kind, ErrorKind::Validation);
+
+        // Lengths are counted in characters, not bytes
+        assert!(exec(json!({ "name": "ééééé", "addresses": [] }))
+            .await
+            .is_ok());
+        let err = exec(json!({ "name": "M", "addresses": [] }))
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(&err, ExecError::InputValidation { errors } if errors[0].message == "must be at least 2 characters long but is 1")
+        );
+
+        // Missing fields are left to deserialization to report
+        let err = exec(json!({ "addresses": [] })).await.unwrap_err();
+        assert!(
+            matches!(&err, ExecError::InputValidation { errors } if errors[0].message == "missing field `name`")
+        );
+    }
+}
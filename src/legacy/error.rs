@@ -1,9 +1,21 @@
-use std::{error, fmt, sync::Arc};
+use std::{
+    error, fmt,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{SystemTime, UNIX_EPOCH},
+};
 
 use serde::Serialize;
 use specta::Type;
 
-use crate::internal::jsonrpc::JsonRPCError;
+use crate::{
+    internal::{jsonrpc::JsonRPCError, ProcedureKind},
+    ErrorVerbosity,
+};
+
+use super::config::OnErrorFn;
 
 #[derive(thiserror::Error, Debug)]
 pub enum ExecError {
@@ -17,6 +29,8 @@ pub enum ExecError {
     AxumExtractorError,
     #[error("invalid JSON-RPC version")]
     InvalidJsonRpcVersion,
+    #[error("error deserializing request: {0}")]
+    InvalidRequest(serde_json::Error),
     #[error("method '{0}' is not supported by this endpoint.")] // TODO: Better error message
     UnsupportedMethod(String),
     #[error("resolver threw error")]
@@ -25,51 +39,180 @@ pub enum ExecError {
     ErrSubscriptionWithNullId,
     #[error("error creating subscription with duplicate id")]
     ErrSubscriptionDuplicateId,
+    #[error("procedure result doesn't match it's declared type: {0}")]
+    InvalidResult(crate::ValidationError),
+    #[error("too many concurrent requests on this connection")]
+    Overloaded,
+    #[error("too many active subscriptions on this connection")]
+    TooManySubscriptions,
+    #[error("no runtime is configured")]
+    NoRuntime,
+    #[error("building the request context timed out")]
+    ContextTimeout,
+    #[error("procedure expects schema version {expected} but the request declared {received:?}")]
+    VersionMismatch {
+        expected: u32,
+        received: Option<u32>,
+    },
+    #[error("procedure input is invalid: {errors:?}")]
+    InputValidation { errors: Vec<crate::FieldError> },
+    #[error("input stage '{stage}' failed: {error}")]
+    InputStage { stage: &'static str, error: Error },
+    #[error("the request isn't authenticated")]
+    Unauthorized,
+}
+
+impl ExecError {
+    /// The [`ErrorKind`] sent to the client for this error.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            ExecError::OperationNotFound(_) => ErrorKind::NotFound,
+            ExecError::DeserializingArgErr(_)
+            | ExecError::VersionMismatch { .. }
+            | ExecError::InputValidation { .. } => ErrorKind::Validation,
+            ExecError::InvalidJsonRpcVersion
+            | ExecError::InvalidRequest(_)
+            | ExecError::UnsupportedMethod(_)
+            | ExecError::ErrSubscriptionWithNullId
+            | ExecError::ErrSubscriptionDuplicateId => ErrorKind::BadRequest,
+            ExecError::ErrResolverError(err) | ExecError::InputStage { error: err, .. } => err.kind,
+            ExecError::SerializingResultErr(_)
+            | ExecError::AxumExtractorError
+            | ExecError::InvalidResult(_)
+            | ExecError::NoRuntime => ErrorKind::Internal,
+            ExecError::Overloaded | ExecError::TooManySubscriptions => ErrorKind::RateLimited,
+            ExecError::ContextTimeout => ErrorKind::Timeout,
+            ExecError::Unauthorized => ErrorKind::Unauthorized,
+        }
+    }
 }
 
 impl From<ExecError> for Error {
     fn from(v: ExecError) -> Error {
+        let kind = v.kind();
         match v {
             ExecError::OperationNotFound(_) => Error {
+                kind,
                 code: ErrorCode::NotFound,
                 message: "the requested operation is not supported by this server".to_string(),
                 cause: None,
+                data: None,
             },
             ExecError::DeserializingArgErr(err) => Error {
+                kind,
                 code: ErrorCode::BadRequest,
                 message: "error deserializing procedure arguments".to_string(),
                 cause: Some(Arc::new(err)),
+                data: None,
             },
             ExecError::SerializingResultErr(err) => Error {
+                kind,
                 code: ErrorCode::InternalServerError,
                 message: "error serializing procedure result".to_string(),
                 cause: Some(Arc::new(err)),
+                data: None,
             },
             ExecError::AxumExtractorError => Error {
+                kind,
                 code: ErrorCode::BadRequest,
                 message: "Error running Axum extractors on the HTTP request".into(),
                 cause: None,
+                data: None,
             },
             ExecError::InvalidJsonRpcVersion => Error {
+                kind,
                 code: ErrorCode::BadRequest,
                 message: "invalid JSON-RPC version".into(),
                 cause: None,
+                data: None,
             },
-            ExecError::ErrResolverError(err) => err,
+            ExecError::InvalidRequest(err) => Error {
+                kind,
+                code: ErrorCode::BadRequest,
+                message: "error deserializing request".into(),
+                cause: Some(Arc::new(err)),
+                data: None,
+            },
+            ExecError::ErrResolverError(err) | ExecError::InputStage { error: err, .. } => err,
             ExecError::UnsupportedMethod(_) => Error {
+                kind,
                 code: ErrorCode::BadRequest,
                 message: "unsupported metho".into(),
                 cause: None,
+                data: None,
             },
             ExecError::ErrSubscriptionWithNullId => Error {
+                kind,
                 code: ErrorCode::BadRequest,
                 message: "error creating subscription with null request id".into(),
                 cause: None,
+                data: None,
             },
             ExecError::ErrSubscriptionDuplicateId => Error {
+                kind,
                 code: ErrorCode::BadRequest,
                 message: "error creating subscription with duplicate id".into(),
                 cause: None,
+                data: None,
+            },
+            ExecError::InvalidResult(err) => Error {
+                kind,
+                code: ErrorCode::InternalServerError,
+                message: "procedure result doesn't match it's declared type".into(),
+                cause: Some(Arc::new(err)),
+                data: None,
+            },
+            ExecError::Overloaded => Error {
+                kind,
+                code: ErrorCode::TooManyRequests,
+                message: "too many concurrent requests on this connection".into(),
+                cause: None,
+                data: None,
+            },
+            ExecError::TooManySubscriptions => Error {
+                kind,
+                code: ErrorCode::TooManyRequests,
+                message: "too many active subscriptions on this connection".into(),
+                cause: None,
+                data: None,
+            },
+            ExecError::NoRuntime => Error {
+                kind,
+                code: ErrorCode::InternalServerError,
+                message: "no runtime is configured".into(),
+                cause: None,
+                data: None,
+            },
+            ExecError::ContextTimeout => Error {
+                kind,
+                code: ErrorCode::Timeout,
+                message: "building the request context timed out".into(),
+                cause: None,
+                data: None,
+            },
+            ExecError::VersionMismatch { expected, received } => Error {
+                kind,
+                code: ErrorCode::BadRequest,
+                message: match received {
+                    Some(received) => format!("this procedure expects schema version {expected} but the request used version {received}. Please upgrade your client."),
+                    None => format!("this procedure expects schema version {expected} but the request didn't declare a `schema_version`. Please upgrade your client."),
+                },
+                cause: None,
+                data: None,
+            },
+            ExecError::InputValidation { .. } => Error {
+                kind,
+                code: ErrorCode::BadRequest,
+                message: "error deserializing procedure arguments".to_string(),
+                cause: None,
+                data: None,
+            },
+            ExecError::Unauthorized => Error {
+                kind,
+                code: ErrorCode::Unauthorized,
+                message: "authentication is required".into(),
+                cause: None,
+                data: None,
             },
         }
     }
@@ -77,32 +220,197 @@ impl From<ExecError> for Error {
 
 impl From<ExecError> for JsonRPCError {
     fn from(err: ExecError) -> Self {
-        let x: Error = err.into();
-        x.into()
+        let data = match &err {
+            ExecError::InputValidation { errors } => Some(serde_json::json!({ "errors": errors })),
+            ExecError::InputStage { stage, .. } => Some(serde_json::json!({ "stage": stage })),
+            _ => None,
+        };
+        let x: JsonRPCError = Error::from(err).into();
+        JsonRPCError {
+            data: data.or(x.data),
+            ..x
+        }
     }
 }
 
+impl ExecError {
+    /// A copy of this error, Eg. for every caller sharing a single execution. It's sent to the client as the same error frame.
+    ///
+    /// Note: `serde_json` errors can't be cloned, so they are recreated from their message.
+    pub(crate) fn duplicate(&self) -> Self {
+        let json = |err: &serde_json::Error| <serde_json::Error as serde::de::Error>::custom(err);
+        match self {
+            ExecError::OperationNotFound(path) => ExecError::OperationNotFound(path.clone()),
+            ExecError::DeserializingArgErr(err) => ExecError::DeserializingArgErr(json(err)),
+            ExecError::SerializingResultErr(err) => ExecError::SerializingResultErr(json(err)),
+            ExecError::AxumExtractorError => ExecError::AxumExtractorError,
+            ExecError::InvalidJsonRpcVersion => ExecError::InvalidJsonRpcVersion,
+            ExecError::InvalidRequest(err) => ExecError::InvalidRequest(json(err)),
+            ExecError::UnsupportedMethod(path) => ExecError::UnsupportedMethod(path.clone()),
+            ExecError::ErrResolverError(err) => ExecError::ErrResolverError(err.clone()),
+            ExecError::ErrSubscriptionWithNullId => ExecError::ErrSubscriptionWithNullId,
+            ExecError::ErrSubscriptionDuplicateId => ExecError::ErrSubscriptionDuplicateId,
+            ExecError::InvalidResult(err) => ExecError::InvalidResult(err.clone()),
+            ExecError::Overloaded => ExecError::Overloaded,
+            ExecError::TooManySubscriptions => ExecError::TooManySubscriptions,
+            ExecError::NoRuntime => ExecError::NoRuntime,
+            ExecError::ContextTimeout => ExecError::ContextTimeout,
+            ExecError::VersionMismatch { expected, received } => ExecError::VersionMismatch {
+                expected: *expected,
+                received: *received,
+            },
+            ExecError::InputValidation { errors } => ExecError::InputValidation {
+                errors: errors.clone(),
+            },
+            ExecError::InputStage { stage, error } => ExecError::InputStage {
+                stage,
+                error: error.clone(),
+            },
+            ExecError::Unauthorized => ExecError::Unauthorized,
+        }
+    }
+
+    /// Convert this error into the error frame sent to the client with the given [`ErrorVerbosity`], passing it to the [`Config::on_error`](crate::Config::on_error) hook.
+    pub(crate) fn render(
+        self,
+        verbosity: Option<ErrorVerbosity>,
+        on_error: Option<&OnErrorFn>,
+    ) -> JsonRPCError {
+        if !matches!(verbosity, Some(ErrorVerbosity::Minimal)) {
+            if let Some(on_error) = on_error {
+                on_error(&self, None);
+            }
+        }
+
+        match verbosity {
+            None => self.into(),
+            Some(ErrorVerbosity::Full) => {
+                let mut sources = vec![self.to_string()];
+                let mut source = error::Error::source(&self);
+                while let Some(err) = source {
+                    sources.push(err.to_string());
+                    source = err.source();
+                }
+
+                let mut err = JsonRPCError::from(self);
+                match &mut err.data {
+                    Some(serde_json::Value::Object(data)) => {
+                        data.insert("sources".into(), sources.into());
+                    }
+                    data => *data = Some(serde_json::json!({ "sources": sources })),
+                }
+                err
+            }
+            Some(ErrorVerbosity::Minimal) => {
+                let correlation_id = correlation_id();
+                if let Some(on_error) = on_error {
+                    on_error(&self, Some(&correlation_id));
+                }
+                #[cfg(feature = "tracing")]
+                tracing::error!(correlation_id, "Error executing operation: {:?}", self);
+
+                let kind = self.kind();
+                let code = Error::from(self).code;
+                JsonRPCError {
+                    kind,
+                    code: code.to_status_code() as i32,
+                    message: match kind {
+                        ErrorKind::Validation => "the input is invalid",
+                        ErrorKind::BadRequest => "the request is invalid",
+                        ErrorKind::NotFound => "not found",
+                        ErrorKind::Timeout => "the request timed out",
+                        ErrorKind::RateLimited => "too many requests",
+                        ErrorKind::Unauthorized => "unauthorized",
+                        ErrorKind::Resolver => "the request failed",
+                        ErrorKind::Internal => "internal server error",
+                    }
+                    .into(),
+                    data: Some(serde_json::json!({ "correlationId": correlation_id })),
+                }
+            }
+        }
+    }
+}
+
+// A unique id for an error, sent to the client and logged so they can be matched up.
+fn correlation_id() -> String {
+    static NEXT: AtomicU64 = AtomicU64::new(0);
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_nanos() as u64);
+    format!("{nanos:016x}-{:x}", NEXT.fetch_add(1, Ordering::Relaxed))
+}
+
+/// An error from [`Router::exec_into`](crate::Router::exec_into).
+#[derive(thiserror::Error, Debug)]
+pub enum ExecIntoError<E> {
+    #[error(transparent)]
+    Exec(#[from] ExecError),
+    #[error("error serializing procedure result: {0}")]
+    Serialize(E),
+}
+
 #[derive(thiserror::Error, Debug)]
 pub enum ExportError {
     #[error("IO error exporting bindings: {0}")]
     IOErr(#[from] std::io::Error),
+    #[error("the bindings at '{0}' are out of date")]
+    Outdated(std::path::PathBuf),
+}
+
+/// A problem with a router found by [`Router::validate`](crate::Router::validate).
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum BuildError {
+    #[error("'{key}' is registered as more than one kind of procedure: {kinds:?}")]
+    KeyCollision {
+        key: String,
+        kinds: Vec<ProcedureKind>,
+    },
+    #[error("{kind:?} '{key}' uses `.{option}` which doesn't apply to it's kind so it's ignored")]
+    KindMismatch {
+        kind: ProcedureKind,
+        key: String,
+        option: &'static str,
+    },
+    #[error("the types of {kind:?} '{key}' can't be exported: {error}")]
+    ProcedureExport {
+        kind: ProcedureKind,
+        key: String,
+        error: String,
+    },
+    #[error("type '{name}' can't be exported: {error}")]
+    TypeExport { name: String, error: String },
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum NotifyError {
+    #[error("error serializing notification: {0}")]
+    SerializingErr(serde_json::Error),
+    #[error("the connection has been closed")]
+    Closed,
 }
 
 #[derive(Debug, Clone, Serialize, Type)]
 #[allow(dead_code)]
 pub struct Error {
+    #[serde(skip)]
+    pub(crate) kind: ErrorKind,
     pub(crate) code: ErrorCode,
     pub(crate) message: String,
     #[serde(skip)]
     pub(crate) cause: Option<Arc<dyn std::error::Error + Send + Sync>>, // We are using `Arc` instead of `Box` so we can clone the error cause `Clone` isn't dyn safe.
+    #[serde(skip)]
+    pub(crate) data: Option<serde_json::Value>,
 }
 
 impl From<Error> for JsonRPCError {
     fn from(err: Error) -> Self {
         JsonRPCError {
+            kind: err.kind,
             code: err.code.to_status_code() as i32,
             message: err.message,
-            data: None,
+            data: err.data,
         }
     }
 }
@@ -119,16 +427,20 @@ impl fmt::Display for Error {
 
 impl error::Error for Error {
     fn source(&self) -> Option<&(dyn error::Error + 'static)> {
-        None
+        self.cause
+            .as_deref()
+            .map(|cause| cause as &(dyn error::Error + 'static))
     }
 }
 
 impl Error {
     pub const fn new(code: ErrorCode, message: String) -> Self {
         Error {
+            kind: ErrorKind::from_code(&code),
             code,
             message,
             cause: None,
+            data: None,
         }
     }
 
@@ -137,9 +449,65 @@ impl Error {
         TErr: std::error::Error + Send + Sync + 'static,
     {
         Self {
+            kind: ErrorKind::from_code(&code),
             code,
             message,
             cause: Some(Arc::new(cause)),
+            data: None,
+        }
+    }
+
+    /// Create an error which sends `data` to the client as the `data` of it's error frame, Eg. a variant of a domain specific error enum.
+    ///
+    /// Declare it's type with [`BuiltProcedureBuilder::error_type`](crate::internal::BuiltProcedureBuilder::error_type) so it's exported into your bindings. If `data` fails to serialize the frame has no `data`.
+    pub fn with_data<TData: Serialize>(code: ErrorCode, message: String, data: TData) -> Self {
+        Self {
+            data: serde_json::to_value(data).ok(),
+            ..Self::new(code, message)
+        }
+    }
+
+    pub fn code(&self) -> &ErrorCode {
+        &self.code
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+}
+
+/// A coarse, client-facing classification of an error.
+///
+/// This is sent as the `kind` field of every error frame and is exported into your bindings so the frontend can handle each failure mode without parsing messages.
+/// Errors which are the fault of the server (Eg. failing to serialize a result) are all reported as [`ErrorKind::Internal`] so their details aren't leaked.
+#[derive(Debug, Clone, Copy, Serialize, Type, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub enum ErrorKind {
+    /// The procedure's input was invalid.
+    Validation,
+    /// The request was malformed (Eg. invalid JSON-RPC).
+    BadRequest,
+    /// The requested procedure doesn't exist.
+    NotFound,
+    /// The request took too long.
+    Timeout,
+    /// The caller has been rate limited.
+    RateLimited,
+    /// The request isn't authenticated.
+    Unauthorized,
+    /// An error returned by the resolver.
+    Resolver,
+    /// An internal server error.
+    Internal,
+}
+
+impl ErrorKind {
+    /// The kind assigned to an [`Error`] constructed with the given code.
+    pub const fn from_code(code: &ErrorCode) -> Self {
+        match code {
+            ErrorCode::Timeout => ErrorKind::Timeout,
+            ErrorCode::TooManyRequests => ErrorKind::RateLimited,
+            _ => ErrorKind::Resolver,
         }
     }
 }
@@ -156,6 +524,7 @@ pub enum ErrorCode {
     PreconditionFailed,
     PayloadTooLarge,
     MethodNotSupported,
+    TooManyRequests,
     ClientClosedRequest,
     InternalServerError,
 }
@@ -172,6 +541,7 @@ impl ErrorCode {
             ErrorCode::PreconditionFailed => 412,
             ErrorCode::PayloadTooLarge => 413,
             ErrorCode::MethodNotSupported => 405,
+            ErrorCode::TooManyRequests => 429,
             ErrorCode::ClientClosedRequest => 499,
             ErrorCode::InternalServerError => 500,
         }
@@ -188,9 +558,180 @@ impl ErrorCode {
             412 => Some(ErrorCode::PreconditionFailed),
             413 => Some(ErrorCode::PayloadTooLarge),
             405 => Some(ErrorCode::MethodNotSupported),
+            429 => Some(ErrorCode::TooManyRequests),
             499 => Some(ErrorCode::ClientClosedRequest),
             500 => Some(ErrorCode::InternalServerError),
             _ => None,
         }
     }
 }
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::internal::jsonrpc;
+
+    #[test]
+    fn test_error_kind() {
+        let kind = |err: ExecError| JsonRPCError::from(err).kind;
+        assert_eq!(
+            kind(ExecError::OperationNotFound("a".into())),
+            ErrorKind::NotFound
+        );
+        assert_eq!(kind(ExecError::AxumExtractorError), ErrorKind::Internal);
+        assert_eq!(
+            kind(ExecError::ErrResolverError(Error::new(
+                ErrorCode::Timeout,
+                "slow".into()
+            ))),
+            ErrorKind::Timeout
+        );
+        assert_eq!(
+            kind(ExecError::ErrResolverError(Error::new(
+                ErrorCode::Conflict,
+                "taken".into()
+            ))),
+            ErrorKind::Resolver
+        );
+    }
+
+    #[tokio::test]
+    async fn test_error_verbosity() {
+        let exec = |config| async move {
+            let router = std::sync::Arc::new(
+                <crate::Router>::new()
+                    .config(config)
+                    .mutation("save", |t| {
+                        t(|_, _: ()| -> Result<(), Error> {
+                            Err(Error::with_cause(
+                                ErrorCode::InternalServerError,
+                                "failed to save".into(),
+                                std::io::Error::other("connection refused by 10.0.0.1"),
+                            ))
+                        })
+                    })
+                    .build(),
+            );
+            let mut resp = jsonrpc::Sender::Response(None);
+            jsonrpc::handle_json_rpc(
+                (),
+                jsonrpc::Request {
+                    jsonrpc: None,
+                    id: jsonrpc::RequestId::Null,
+                    inner: jsonrpc::RequestInner::Mutation {
+                        path: "save".into(),
+                        input: None,
+                    },
+                },
+                &router,
+                &mut resp,
+                &mut jsonrpc::SubscriptionMap::None,
+            )
+            .await;
+            match resp {
+                jsonrpc::Sender::Response(Some(resp)) => serde_json::to_value(resp.result).unwrap(),
+                _ => serde_json::Value::Null,
+            }
+        };
+
+        let full = exec(crate::Config::new().error_verbosity(ErrorVerbosity::Full)).await;
+        assert_eq!(full["data"]["message"], "failed to save");
+        assert_eq!(
+            full["data"]["data"]["sources"][2],
+            "connection refused by 10.0.0.1"
+        );
+
+        let logged = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let minimal = exec(
+            crate::Config::new()
+                .error_verbosity(ErrorVerbosity::Minimal)
+                .on_error({
+                    let logged = logged.clone();
+                    move |err, correlation_id| {
+                        let correlation_id = correlation_id.map(str::to_string);
+                        logged
+                            .lock()
+                            .unwrap()
+                            .push((format!("{err:?}"), correlation_id));
+                    }
+                }),
+        )
+        .await;
+        assert_eq!(minimal["data"]["message"], "the request failed");
+        let correlation_id = minimal["data"]["data"]["correlationId"].as_str().unwrap();
+        let minimal = minimal.to_string();
+        assert!(!minimal.contains("failed to save") && !minimal.contains("10.0.0.1"));
+
+        // The full error is still available on the server, with the id the client received
+        assert!(matches!(
+            &logged.lock().unwrap()[..],
+            [(err, Some(id))] if err.contains("10.0.0.1") && id == correlation_id
+        ));
+    }
+
+    #[derive(Serialize, Type)]
+    #[serde(tag = "type")]
+    enum UserError {
+        NotFound,
+        Suspended { reason: String },
+    }
+
+    #[tokio::test]
+    async fn test_error_type() {
+        let router = <crate::Router>::new()
+            .query("users.get", |t| {
+                t(|_, id: u32| match id {
+                    0 => Err(Error::with_data(
+                        ErrorCode::NotFound,
+                        "no such user".into(),
+                        UserError::NotFound,
+                    )),
+                    _ => Ok(format!("user {id}")),
+                })
+                .error_type::<UserError>()
+            })
+            .mutation("users.suspend", |t| {
+                t(|_, _: u32| -> Result<(), Error> {
+                    Err(Error::with_data(
+                        ErrorCode::Conflict,
+                        "already suspended".into(),
+                        UserError::Suspended {
+                            reason: "spam".into(),
+                        },
+                    ))
+                })
+                .error_type::<UserError>()
+            })
+            .build();
+
+        let err = router
+            .exec(
+                (),
+                crate::ExecKind::Mutation,
+                "users.suspend".into(),
+                Some(serde_json::json!(1)),
+            )
+            .await
+            .unwrap_err();
+        assert_eq!(
+            JsonRPCError::from(err).data,
+            Some(serde_json::json!({ "type": "Suspended", "reason": "spam" }))
+        );
+
+        // The shared error type is defined once and referenced by both procedures
+        let bindings = router.ts_bindings().unwrap();
+        assert_eq!(bindings.matches("export type UserError =").count(), 1);
+        assert!(bindings
+            .contains(r#"{ key: "users.get", input: number, result: string, error: UserError }"#));
+        assert!(bindings.contains(
+            r#"{ key: "users.suspend", input: number, result: null, error: UserError }"#
+        ));
+        assert!(bindings.contains(
+            "export type UsersGetQueryResult = { success: string } | { error: UserError };"
+        ));
+        assert!(bindings.contains(
+            "export type UsersSuspendMutationResult = { success: null } | { error: UserError };"
+        ));
+    }
+}
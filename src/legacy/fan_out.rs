@@ -0,0 +1,363 @@
+use std::{
+    fmt,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, PoisonError,
+    },
+    task::{ready, Context, Poll},
+    time::Duration,
+};
+
+use futures::{future::join_all, Stream};
+use tokio::sync::mpsc::{self, error::TrySendError};
+
+use crate::{
+    internal::jsonrpc::{frame_sink, ResponseInner},
+    Runtime,
+};
+
+/// What a [`FanOut`] does with a subscriber which can't keep up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SlowSubscriber {
+    /// Wait for every subscriber to accept each value, so the slowest subscriber sets the pace for all of them.
+    Block,
+    /// Wait up to the duration for a subscriber whose buffer is full to accept the value, then drop it.
+    /// It receives the values already in it's buffer, then it's subscription ends with a `lagged` frame instead of `complete`.
+    DropAfter(Duration),
+}
+
+struct Subscriber<T> {
+    tx: mpsc::Sender<T>,
+    lagged: Arc<AtomicBool>,
+}
+
+// The outcome of sending a value to a single subscriber
+enum Delivery {
+    Sent,
+    // The subscription was stopped
+    Closed,
+    Lagged,
+}
+
+/// Sends every value to many subscriptions (Eg. every client watching a live feed), each with it's own bounded buffer.
+///
+/// [`FanOut::send`] delivers to every subscriber concurrently. What happens when a subscriber's buffer is full (Eg. because it's client is on a slow connection) depends on the [`SlowSubscriber`] policy:
+/// with [`SlowSubscriber::Block`] the send waits for it, holding up every other subscriber, while with [`SlowSubscriber::DropAfter`] only that subscriber is dropped once the timeout passes, so the others keep receiving values.
+///
+/// Values are delivered in the order they're sent when they're sent from a single task.
+///
+/// Note: [`SlowSubscriber::DropAfter`] needs a [`Runtime`] for it's timer, which defaults to [`TokioRuntime`](crate::TokioRuntime) with the `runtime-tokio` feature. Without one a subscriber is dropped as soon as it's buffer is full.
+///
+/// ```rust
+/// use std::time::Duration;
+///
+/// use rspc::{FanOut, SlowSubscriber};
+///
+/// let prices = FanOut::<f64>::new(16, SlowSubscriber::DropAfter(Duration::from_secs(1)));
+///
+/// let router = <rspc::Router>::new()
+///     .subscription("prices", {
+///         let prices = prices.clone();
+///         move |t| {
+///             let prices = prices.clone();
+///             t(move |_, _: ()| prices.subscribe())
+///         }
+///     })
+///     .build();
+///
+/// // Elsewhere: `prices.send(42.0).await;`
+/// ```
+pub struct FanOut<T> {
+    subscribers: Arc<Mutex<Vec<Subscriber<T>>>>,
+    capacity: usize,
+    policy: SlowSubscriber,
+    runtime: Option<Arc<dyn Runtime>>,
+}
+
+impl<T> Clone for FanOut<T> {
+    fn clone(&self) -> Self {
+        Self {
+            subscribers: self.subscribers.clone(),
+            capacity: self.capacity,
+            policy: self.policy,
+            runtime: self.runtime.clone(),
+        }
+    }
+}
+
+impl<T> fmt::Debug for FanOut<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FanOut")
+            .field("capacity", &self.capacity)
+            .field("policy", &self.policy)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T: Clone + Send + 'static> FanOut<T> {
+    /// Construct a fan-out whose subscribers each buffer up to `capacity` values (at least one).
+    pub fn new(capacity: usize, policy: SlowSubscriber) -> Self {
+        Self {
+            subscribers: Default::default(),
+            capacity: capacity.max(1),
+            policy,
+            #[cfg(feature = "runtime-tokio")]
+            runtime: Some(Arc::new(crate::TokioRuntime)),
+            #[cfg(not(feature = "runtime-tokio"))]
+            runtime: None,
+        }
+    }
+
+    /// Set the runtime providing the timer of [`SlowSubscriber::DropAfter`].
+    pub fn runtime(mut self, runtime: impl Runtime) -> Self {
+        self.runtime = Some(Arc::new(runtime));
+        self
+    }
+
+    /// Subscribe to every value sent from now on. Return it from a subscription's resolver.
+    pub fn subscribe(&self) -> FanOutStream<T> {
+        let (tx, rx) = mpsc::channel(self.capacity);
+        let lagged = Arc::new(AtomicBool::new(false));
+        self.subscribers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(Subscriber {
+                tx,
+                lagged: lagged.clone(),
+            });
+        FanOutStream {
+            rx,
+            lagged,
+            reported: false,
+        }
+    }
+
+    /// The number of active subscribers.
+    pub fn subscribers(&self) -> usize {
+        let mut subscribers = self
+            .subscribers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        subscribers.retain(|subscriber| !subscriber.tx.is_closed());
+        subscribers.len()
+    }
+
+    /// Send `value` to every subscriber, resolving once each of them has accepted it or been dropped.
+    pub async fn send(&self, value: T) {
+        let senders = self
+            .subscribers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+            .map(|subscriber| subscriber.tx.clone())
+            .collect::<Vec<_>>();
+        let deliveries = join_all(senders.iter().map(|tx| self.deliver(tx, value.clone()))).await;
+
+        let mut subscribers = self
+            .subscribers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        for (tx, delivery) in senders.iter().zip(deliveries) {
+            if matches!(delivery, Delivery::Sent) {
+                continue;
+            }
+            subscribers.retain(|subscriber| {
+                if !subscriber.tx.same_channel(tx) {
+                    return true;
+                }
+                if matches!(delivery, Delivery::Lagged) {
+                    subscriber.lagged.store(true, Ordering::Relaxed);
+                }
+                false
+            });
+        }
+    }
+
+    async fn deliver(&self, tx: &mpsc::Sender<T>, value: T) -> Delivery {
+        let timeout = match self.policy {
+            SlowSubscriber::Block => {
+                return match tx.send(value).await {
+                    Ok(()) => Delivery::Sent,
+                    Err(_) => Delivery::Closed,
+                };
+            }
+            SlowSubscriber::DropAfter(timeout) => timeout,
+        };
+
+        let value = match tx.try_send(value) {
+            Ok(()) => return Delivery::Sent,
+            Err(TrySendError::Closed(_)) => return Delivery::Closed,
+            Err(TrySendError::Full(value)) => value,
+        };
+        let Some(runtime) = &self.runtime else {
+            return Delivery::Lagged;
+        };
+        tokio::select! {
+            result = tx.send(value) => match result {
+                Ok(()) => Delivery::Sent,
+                Err(_) => Delivery::Closed,
+            },
+            _ = runtime.sleep(timeout) => Delivery::Lagged,
+        }
+    }
+}
+
+/// A subscriber of a [`FanOut`]. It ends when the fan-out is dropped, or when it's dropped for falling behind. See [`FanOutStream::is_lagged`].
+pub struct FanOutStream<T> {
+    rx: mpsc::Receiver<T>,
+    lagged: Arc<AtomicBool>,
+    // Whether the `lagged` frame has been sent
+    reported: bool,
+}
+
+impl<T> FanOutStream<T> {
+    /// Whether this subscriber was dropped by a [`SlowSubscriber::DropAfter`] policy for falling behind.
+    pub fn is_lagged(&self) -> bool {
+        self.lagged.load(Ordering::Relaxed)
+    }
+}
+
+impl<T> fmt::Debug for FanOutStream<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FanOutStream")
+            .field("lagged", &self.is_lagged())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T> Stream for FanOutStream<T> {
+    type Item = T;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        if let Some(value) = ready!(self.rx.poll_recv(cx)) {
+            return Poll::Ready(Some(value));
+        }
+
+        // The subscription ends with a `lagged` frame over transports which support it
+        if self.is_lagged() && !std::mem::replace(&mut self.reported, true) {
+            if let Some(sink) = frame_sink() {
+                let _ = sink.send(ResponseInner::Lagged);
+            }
+        }
+        Poll::Ready(None)
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+// Some of the tests use the default runtime, so their helpers are unused without it
+#[cfg_attr(not(feature = "runtime-tokio"), allow(unused))]
+mod tests {
+    use std::{collections::HashMap, sync::Arc, time::Duration};
+
+    use futures::StreamExt;
+    use tokio::{
+        sync::{mpsc, Mutex},
+        time::timeout,
+    };
+
+    use crate::{
+        internal::jsonrpc::{
+            self, handle_json_rpc, RequestId, ResponseInner, Sender, SubscriptionMap,
+        },
+        FanOut, Router, SlowSubscriber,
+    };
+
+    fn subscribe(id: u32) -> jsonrpc::Request {
+        jsonrpc::Request {
+            jsonrpc: None,
+            id: RequestId::Number(id),
+            inner: jsonrpc::RequestInner::Subscription {
+                path: "events".into(),
+                input: (RequestId::Number(id), None),
+            },
+        }
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "runtime-tokio")]
+    async fn test_fan_out_drops_slow_subscriber() {
+        let events = FanOut::<u32>::new(2, SlowSubscriber::DropAfter(Duration::from_millis(20)));
+        let router = Arc::new(
+            <Router>::new()
+                .subscription("events", {
+                    let events = events.clone();
+                    move |t| {
+                        let events = events.clone();
+                        t(move |_, _: ()| events.subscribe())
+                    }
+                })
+                .build(),
+        );
+
+        let subscriptions = Mutex::new(HashMap::new());
+        let (mut fast_tx, mut fast_rx) = mpsc::unbounded_channel();
+        handle_json_rpc(
+            (),
+            subscribe(1),
+            &router,
+            &mut Sender::ResponseChannel(&mut fast_tx),
+            &mut SubscriptionMap::Mutex(&subscriptions),
+        )
+        .await;
+        // The connection of this subscriber is never read from, so it stalls once it's full
+        let (mut stalled_tx, mut stalled_rx) = mpsc::channel(1);
+        handle_json_rpc(
+            (),
+            subscribe(2),
+            &router,
+            &mut Sender::Channel(&mut stalled_tx),
+            &mut SubscriptionMap::Mutex(&subscriptions),
+        )
+        .await;
+        drop(stalled_tx);
+        assert_eq!(events.subscribers(), 2);
+
+        // The stalled subscriber doesn't hold up the others
+        for i in 0..10 {
+            timeout(Duration::from_secs(1), events.send(i))
+                .await
+                .unwrap();
+        }
+        for i in 0..10 {
+            let resp = fast_rx.recv().await.unwrap();
+            assert!(matches!(resp.result, ResponseInner::Event(v) if v == i));
+        }
+        assert_eq!(events.subscribers(), 1);
+
+        // The stalled subscriber gets what it buffered, then ends with a `lagged` frame
+        let mut frames = Vec::new();
+        while let Some(resp) = stalled_rx.recv().await {
+            frames.push(resp.result);
+        }
+        assert!(matches!(frames.pop(), Some(ResponseInner::Lagged)));
+        assert!(!frames.is_empty() && frames.len() < 10);
+        for (i, frame) in frames.into_iter().enumerate() {
+            assert!(matches!(frame, ResponseInner::Event(v) if v == i));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fan_out_blocks() {
+        let events = FanOut::<u32>::new(1, SlowSubscriber::Block);
+        let mut stalled = events.subscribe();
+        let mut other = events.subscribe();
+
+        events.send(1).await;
+        // Every subscriber waits for the slowest one
+        assert!(timeout(Duration::from_millis(20), events.send(2))
+            .await
+            .is_err());
+        assert_eq!(other.next().await, Some(1));
+        assert_eq!(stalled.next().await, Some(1));
+        assert!(!stalled.is_lagged());
+        assert_eq!(events.subscribers(), 2);
+
+        // A stopped subscription is removed
+        drop(stalled);
+        events.send(3).await;
+        assert_eq!(events.subscribers(), 1);
+        assert_eq!(other.next().await, Some(3));
+    }
+}
@@ -0,0 +1,198 @@
+use std::{
+    collections::HashMap,
+    ops::Deref,
+    sync::{Arc, Mutex, PoisonError},
+};
+
+use futures::future::BoxFuture;
+use serde_json::Value;
+use tokio::sync::OnceCell;
+
+use crate::{
+    internal::{Layer, LayerResult, RequestContext},
+    ExecError, MiddlewareLike,
+};
+
+/// Decides whether a feature flag is enabled for a request.
+///
+/// Evaluation is async so it can call out to a remote flag service. It's only invoked the first time a flag is checked within a request, see [`WithFeatures::feature`].
+pub trait FeatureFlagProvider<TCtx>: Send + Sync + 'static {
+    fn is_enabled<'a>(&'a self, ctx: &'a TCtx, flag: &'a str) -> BoxFuture<'a, bool>;
+}
+
+/// Middleware which wraps the context in [`WithFeatures`] so resolvers can check feature flags resolved by a [`FeatureFlagProvider`].
+///
+/// ```rust
+/// use futures::{future::BoxFuture, FutureExt};
+/// use rspc::{FeatureFlagProvider, FeatureFlags};
+///
+/// struct Ctx { beta_tester: bool }
+///
+/// struct BetaFlags;
+///
+/// impl FeatureFlagProvider<Ctx> for BetaFlags {
+///     fn is_enabled<'a>(&'a self, ctx: &'a Ctx, flag: &'a str) -> BoxFuture<'a, bool> {
+///         async move { flag == "new_search" && ctx.beta_tester }.boxed()
+///     }
+/// }
+///
+/// let router = rspc::Router::<Ctx>::new()
+///     .middleware(|_| FeatureFlags::new(BetaFlags))
+///     .query("search", |t| {
+///         t(|ctx, _: ()| async move { ctx.feature("new_search").await })
+///     })
+///     .build();
+/// ```
+pub struct FeatureFlags<TCtx> {
+    provider: Arc<dyn FeatureFlagProvider<TCtx>>,
+}
+
+impl<TCtx: 'static> FeatureFlags<TCtx> {
+    pub fn new(provider: impl FeatureFlagProvider<TCtx>) -> Self {
+        Self {
+            provider: Arc::new(provider),
+        }
+    }
+}
+
+impl<TCtx> Clone for FeatureFlags<TCtx> {
+    fn clone(&self) -> Self {
+        Self {
+            provider: self.provider.clone(),
+        }
+    }
+}
+
+impl<TCtx> MiddlewareLike<TCtx> for FeatureFlags<TCtx>
+where
+    TCtx: Send + 'static,
+{
+    type State = ();
+    type NewCtx = WithFeatures<TCtx>;
+
+    fn handle<TMiddleware: Layer<Self::NewCtx> + 'static>(
+        &self,
+        ctx: TCtx,
+        input: Value,
+        req: RequestContext,
+        next: Arc<TMiddleware>,
+    ) -> Result<LayerResult, ExecError> {
+        next.call(
+            WithFeatures {
+                ctx,
+                provider: self.provider.clone(),
+                evaluated: Default::default(),
+            },
+            input,
+            req,
+        )
+    }
+}
+
+/// The context of a request which passed through the [`FeatureFlags`] middleware. This derefs to the original context.
+pub struct WithFeatures<TCtx> {
+    ctx: TCtx,
+    provider: Arc<dyn FeatureFlagProvider<TCtx>>,
+    evaluated: Mutex<HashMap<String, Arc<OnceCell<bool>>>>,
+}
+
+impl<TCtx: 'static> WithFeatures<TCtx> {
+    /// Check whether the feature flag `flag` is enabled for this request.
+    ///
+    /// The result is memoized, so the provider is called at most once per flag per request (or per subscription) even when it's checked concurrently, and every check within the request sees the same value.
+    pub async fn feature(&self, flag: &str) -> bool {
+        let cell = self
+            .evaluated
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .entry(flag.to_string())
+            .or_default()
+            .clone();
+
+        *cell
+            .get_or_init(|| self.provider.is_enabled(&self.ctx, flag))
+            .await
+    }
+
+    /// Unwrap the original context.
+    pub fn into_inner(self) -> TCtx {
+        self.ctx
+    }
+}
+
+impl<TCtx> Deref for WithFeatures<TCtx> {
+    type Target = TCtx;
+
+    fn deref(&self) -> &Self::Target {
+        &self.ctx
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use futures::{future::BoxFuture, FutureExt};
+    use serde_json::json;
+
+    use crate::{ExecKind, FeatureFlagProvider, FeatureFlags, Router};
+
+    struct StubProvider {
+        calls: Arc<AtomicUsize>,
+    }
+
+    impl FeatureFlagProvider<u32> for StubProvider {
+        fn is_enabled<'a>(&'a self, ctx: &'a u32, flag: &'a str) -> BoxFuture<'a, bool> {
+            async move {
+                self.calls.fetch_add(1, Ordering::SeqCst);
+                tokio::task::yield_now().await;
+                flag == "new_search" && *ctx == 1
+            }
+            .boxed()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_feature_flags() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let router = Router::<u32>::new()
+            .middleware({
+                let calls = calls.clone();
+                move |_| {
+                    FeatureFlags::new(StubProvider {
+                        calls: calls.clone(),
+                    })
+                }
+            })
+            .query("search", |t| {
+                t(|ctx, _: ()| async move {
+                    let (a, b) = tokio::join!(ctx.feature("new_search"), ctx.feature("new_search"));
+                    assert_eq!(a, b);
+                    [
+                        ctx.feature("new_search").await,
+                        ctx.feature("other").await,
+                        *ctx == 1,
+                    ]
+                })
+            })
+            .build();
+
+        let result = router
+            .exec(1, ExecKind::Query, "search".into(), None)
+            .await
+            .unwrap();
+        assert_eq!(result, json!([true, false, true]));
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        let result = router
+            .exec(2, ExecKind::Query, "search".into(), None)
+            .await
+            .unwrap();
+        assert_eq!(result, json!([false, false, false]));
+        assert_eq!(calls.load(Ordering::SeqCst), 4);
+    }
+}
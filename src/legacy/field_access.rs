@@ -0,0 +1,174 @@
+use std::{any::Any, sync::Arc};
+
+use futures::StreamExt;
+use serde_json::Value;
+
+use crate::{
+    internal::{Layer, LayerResult, RequestContext, ValueOrStream},
+    ExecError,
+};
+
+/// The capabilities (Eg. `admin` or `billing.read`) of a request's context, used to strip the fields restricted with [`BuiltProcedureBuilder::restrict_field`](crate::internal::BuiltProcedureBuilder::restrict_field) from results.
+///
+/// ```rust
+/// struct Ctx {
+///     roles: Vec<&'static str>,
+/// }
+///
+/// impl rspc::Capabilities for Ctx {
+///     fn has_capability(&self, capability: &str) -> bool {
+///         self.roles.contains(&capability)
+///     }
+/// }
+/// ```
+pub trait Capabilities {
+    fn has_capability(&self, capability: &str) -> bool;
+}
+
+/// `Capabilities::has_capability` of the procedure's context.
+pub(crate) type CapabilityFn<TCtx> = fn(&TCtx, &str) -> bool;
+
+/// A type erased [`CapabilityFn`]. It's context type is the one the procedure's resolver receives.
+pub(crate) type AnyCapabilityFn = Arc<dyn Any + Send + Sync>;
+
+/// The fields restricted with `.restrict_field`, along with how to check the context's capabilities.
+#[derive(Clone)]
+pub(crate) struct FieldAccess {
+    pub has_capability: AnyCapabilityFn,
+    pub fields: Vec<(&'static str, &'static str)>,
+}
+
+/// Removes the restricted fields the context lacks the capability for from every result of a procedure.
+pub(crate) struct FieldAccessLayer<TLayerCtx: 'static> {
+    next: Box<dyn Layer<TLayerCtx>>,
+    has_capability: CapabilityFn<TLayerCtx>,
+    fields: Arc<[(&'static str, &'static str)]>,
+}
+
+impl<TLayerCtx: 'static> FieldAccessLayer<TLayerCtx> {
+    /// Wrap `next` if the procedure has restricted fields.
+    pub fn wrap(
+        next: Box<dyn Layer<TLayerCtx>>,
+        access: Option<FieldAccess>,
+    ) -> Box<dyn Layer<TLayerCtx>> {
+        match access {
+            Some(access) => Box::new(Self {
+                next,
+                // This is guaranteed by the bounds on `BuiltProcedureBuilder::restrict_field`
+                has_capability: *access
+                    .has_capability
+                    .downcast_ref::<CapabilityFn<TLayerCtx>>()
+                    .expect("rspc: procedure capabilities context type mismatch"),
+                fields: access.fields.into(),
+            }),
+            None => next,
+        }
+    }
+}
+
+impl<TLayerCtx: 'static> Layer<TLayerCtx> for FieldAccessLayer<TLayerCtx> {
+    fn call(
+        &self,
+        ctx: TLayerCtx,
+        input: Value,
+        req: RequestContext,
+    ) -> Result<LayerResult, ExecError> {
+        let denied = self
+            .fields
+            .iter()
+            .filter(|(_, capability)| !(self.has_capability)(&ctx, capability))
+            .map(|(field, _)| *field)
+            .collect::<Arc<[_]>>();
+        let result = self.next.call(ctx, input, req)?;
+        if denied.is_empty() {
+            return Ok(result);
+        }
+
+        Ok(LayerResult::FutureValueOrStream(Box::pin(async move {
+            Ok(match result.into_value_or_stream().await? {
+                ValueOrStream::Value(mut value) => {
+                    strip_fields(&mut value, &denied);
+                    ValueOrStream::Value(value)
+                }
+                ValueOrStream::Stream(stream) => {
+                    ValueOrStream::Stream(Box::pin(stream.map(move |item| {
+                        item.map(|mut value| {
+                            strip_fields(&mut value, &denied);
+                            value
+                        })
+                    })))
+                }
+            })
+        })))
+    }
+}
+
+// Remove `fields` from every object in `value`, at any depth.
+fn strip_fields(value: &mut Value, fields: &[&str]) {
+    match value {
+        Value::Object(object) => {
+            object.retain(|key, _| !fields.contains(&key.as_str()));
+            for value in object.values_mut() {
+                strip_fields(value, fields);
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                strip_fields(item, fields);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use serde::Serialize;
+    use serde_json::json;
+    use specta::Type;
+
+    use crate::{Capabilities, ExecKind, Router};
+
+    struct Ctx {
+        admin: bool,
+    }
+
+    impl Capabilities for Ctx {
+        fn has_capability(&self, capability: &str) -> bool {
+            capability == "admin" && self.admin
+        }
+    }
+
+    #[derive(Serialize, Type)]
+    struct User {
+        name: String,
+        email: String,
+    }
+
+    #[tokio::test]
+    async fn test_restrict_field() {
+        let router = Router::<Ctx>::new()
+            .query("users.list", |t| {
+                t(|_, _: ()| {
+                    vec![User {
+                        name: "Oscar".into(),
+                        email: "oscar@example.com".into(),
+                    }]
+                })
+                .restrict_field("email", "admin")
+            })
+            .build();
+
+        let exec = |admin| router.exec(Ctx { admin }, ExecKind::Query, "users.list".into(), None);
+        assert_eq!(
+            exec(true).await.unwrap(),
+            json!([{ "name": "Oscar", "email": "oscar@example.com" }])
+        );
+        assert_eq!(exec(false).await.unwrap(), json!([{ "name": "Oscar" }]));
+
+        // The exported type is unchanged
+        let bindings = router.ts_bindings().unwrap();
+        assert!(bindings.contains("export type User = { name: string; email: string }"));
+    }
+}
@@ -0,0 +1,210 @@
+use std::{fmt, sync::Arc};
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::{Error, ExecError};
+
+// A type erased pipeline which produces the input passed on to deserialization. See `BuiltProcedureBuilder::pipeline`.
+pub(crate) type PipelineFn = Arc<dyn Fn(Value) -> Result<Value, ExecError> + Send + Sync>;
+
+type StagesFn<T> = Arc<dyn Fn(Value) -> Result<T, ExecError> + Send + Sync>;
+
+/// An ordered list of named stages which transform a procedure's raw input, Eg. `normalize -> default -> validate -> coerce`. Attach it to a procedure with [`BuiltProcedureBuilder::pipeline`](crate::internal::BuiltProcedureBuilder::pipeline).
+///
+/// The first stage receives the raw input as a [`Value`] and every later stage receives the output of the stage before it, so each stage can change the type (Eg. parse into a struct, then coerce it's fields).
+/// The output of the last stage is serialized and deserialized into the resolver's argument.
+///
+/// If a stage returns an error the remaining stages and the resolver aren't run. The client receives the stage's error with the name of the stage in it's `data` as `{ stage: "<name>" }`.
+///
+/// ## Ordering
+///
+/// ```text
+/// middleware -> schema_version -> deserialize_with -> defaults -> pipeline stages (in order) -> constraints -> deserialization -> resolver
+/// ```
+///
+/// ```rust
+/// use rspc::{Error, ErrorCode, InputPipeline};
+/// use serde_json::Value;
+///
+/// let router = <rspc::Router>::new()
+///     .mutation("users.rename", |t| {
+///         t(|_, name: String| name).pipeline(
+///             InputPipeline::new()
+///                 .stage("normalize", |input: Value| {
+///                     Ok(input.as_str().unwrap_or_default().trim().to_lowercase())
+///                 })
+///                 .stage("validate", |name: String| match name.is_empty() {
+///                     true => Err(Error::new(ErrorCode::BadRequest, "the name is empty".into())),
+///                     false => Ok(name),
+///                 }),
+///         )
+///     })
+///     .build();
+/// ```
+pub struct InputPipeline<T = Value> {
+    run: StagesFn<T>,
+    stages: Vec<&'static str>,
+}
+
+impl<T> Clone for InputPipeline<T> {
+    fn clone(&self) -> Self {
+        Self {
+            run: self.run.clone(),
+            stages: self.stages.clone(),
+        }
+    }
+}
+
+impl<T> fmt::Debug for InputPipeline<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("InputPipeline")
+            .field("stages", &self.stages)
+            .finish()
+    }
+}
+
+impl Default for InputPipeline {
+    fn default() -> Self {
+        Self {
+            run: Arc::new(Ok),
+            stages: Vec::new(),
+        }
+    }
+}
+
+impl InputPipeline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<T: 'static> InputPipeline<T> {
+    /// Add a stage to the end of the pipeline, which transforms the output of the previous stage (or the raw input for the first stage).
+    ///
+    /// `name` identifies the stage in the error sent to the client when it fails.
+    pub fn stage<U>(
+        self,
+        name: &'static str,
+        stage: impl Fn(T) -> Result<U, Error> + Send + Sync + 'static,
+    ) -> InputPipeline<U> {
+        let Self { run, mut stages } = self;
+        stages.push(name);
+        InputPipeline {
+            run: Arc::new(move |input| {
+                stage(run(input)?).map_err(|error| ExecError::InputStage { stage: name, error })
+            }),
+            stages,
+        }
+    }
+
+    /// The names of the pipeline's stages in the order they run.
+    pub fn stages(&self) -> &[&'static str] {
+        &self.stages
+    }
+}
+
+impl<T: Serialize + 'static> InputPipeline<T> {
+    pub(crate) fn into_fn(self) -> PipelineFn {
+        let run = self.run;
+        Arc::new(move |input| {
+            serde_json::to_value(run(input)?).map_err(ExecError::DeserializingArgErr)
+        })
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+    use serde_json::{json, Value};
+    use specta::Type;
+
+    use crate::{Error, ErrorCode, ExecError, ExecKind, InputPipeline, Router};
+
+    #[derive(Serialize, Deserialize, Type)]
+    struct Search {
+        query: String,
+        limit: u32,
+    }
+
+    #[tokio::test]
+    async fn test_input_pipeline() {
+        let pipeline = InputPipeline::new()
+            .stage("normalize", |mut input: Value| {
+                if let Some(query) = input.get_mut("query") {
+                    *query = query.as_str().unwrap_or_default().trim().into();
+                }
+                Ok(input)
+            })
+            .stage("default", |mut input: Value| {
+                if input.get("limit").is_none() {
+                    input["limit"] = json!(10);
+                }
+                Ok(input)
+            })
+            .stage("validate", |input: Value| {
+                serde_json::from_value::<Search>(input)
+                    .map_err(|err| Error::new(ErrorCode::BadRequest, err.to_string()))
+                    .and_then(|search| match search.query.is_empty() {
+                        true => Err(Error::new(ErrorCode::BadRequest, "empty query".into())),
+                        false => Ok(search),
+                    })
+            })
+            .stage("coerce", |search: Search| {
+                Ok(Search {
+                    limit: search.limit.min(50),
+                    ..search
+                })
+            });
+        assert_eq!(
+            pipeline.stages(),
+            ["normalize", "default", "validate", "coerce"]
+        );
+
+        let router = <Router>::new()
+            .query("search", |t| {
+                t(|_, search: Search| format!("{} {}", search.query, search.limit))
+                    .pipeline(pipeline.clone())
+            })
+            .build();
+
+        let result = router
+            .exec(
+                (),
+                ExecKind::Query,
+                "search".into(),
+                Some(json!({ "query": "  rspc " })),
+            )
+            .await;
+        assert_eq!(result.unwrap(), json!("rspc 10"));
+
+        let result = router
+            .exec(
+                (),
+                ExecKind::Query,
+                "search".into(),
+                Some(json!({ "query": "rspc", "limit": 500 })),
+            )
+            .await;
+        assert_eq!(result.unwrap(), json!("rspc 50"));
+
+        // The failing stage is reported and the later stages don't run
+        let err = router
+            .exec(
+                (),
+                ExecKind::Query,
+                "search".into(),
+                Some(json!({ "query": " " })),
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            &err,
+            ExecError::InputStage { stage: "validate", error } if error.message == "empty query"
+        ));
+        let frame = crate::internal::jsonrpc::JsonRPCError::from(err);
+        assert_eq!(frame.message, "empty query");
+        assert_eq!(frame.data, Some(json!({ "stage": "validate" })));
+    }
+}
@@ -2,6 +2,9 @@ use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use specta::Type;
 
+use crate::ErrorKind;
+
+pub use super::jsonrpc_batch::*;
 pub use super::jsonrpc_exec::*;
 
 #[derive(Debug, Clone, Deserialize, Serialize, Type, PartialEq, Eq, Hash)]
@@ -38,6 +41,12 @@ pub enum RequestInner {
     SubscriptionStop {
         input: RequestId,
     },
+    /// A request using a method which isn't one of the above (Eg. `query:users.get`). It's mapped to a procedure with [`Config::method_parser`](crate::Config::method_parser).
+    #[serde(untagged)]
+    Method {
+        method: String,
+        params: Option<Value>,
+    },
 }
 
 #[derive(Debug, Clone, Serialize)] // TODO: Add `specta::Type` when supported
@@ -51,12 +60,38 @@ pub struct Response {
 #[serde(tag = "type", content = "data", rename_all = "camelCase")]
 pub enum ResponseInner {
     Event(Value),
+    /// A log line sent by a [`WithLogs`](crate::WithLogs) result before the terminal `Response` or `Error`.
+    Log(Value),
+    /// A fragment of the result sent by a [`Partial`](crate::Partial) result before the terminal `Response` or `Error`.
+    /// `value` should be merged into the result at the JSON Pointer `path` using JSON Merge Patch.
+    Patch {
+        path: String,
+        value: Value,
+    },
+    /// A message pushed by the server with a [`Notifier`](crate::Notifier) which isn't a response to any request. It's sent with a `null` id.
+    Notification {
+        method: String,
+        params: Value,
+    },
+    /// The final frame of a subscription produced by it's [`on_complete`](crate::internal::BuiltProcedureBuilder::on_complete) hook. It's only sent when the subscription completes by itself without an error.
+    Trailer(Value),
+    /// Sent once by a [`Replay`](crate::Replay) or [`snapshot_then_stream`](crate::internal::UnbuiltProcedureBuilder::snapshot_then_stream) subscription when it has finished sending history (or the snapshot) and switches to live events.
+    CaughtUp,
+    /// Acknowledges a `subscriptionStop` request. It's sent with the subscription's id once the subscription's `on_unsubscribe` hook has run, after which no more frames are sent for it.
+    Cancelled,
+    /// Sent with the subscription's id once it has completed by itself, after it's last event (and trailer). No more frames are sent for it.
+    Complete,
+    /// Sent with the subscription's id instead of `complete` when it was dropped by a [`FanOut`](crate::FanOut) for falling behind, after the events it had buffered. No more frames are sent for it.
+    Lagged,
+    /// The successful result of a procedure returning [`NoContent`](crate::NoContent). It has no data.
+    NoContent,
     Response(Value),
     Error(JsonRPCError),
 }
 
 #[derive(Debug, Clone, Serialize, Type)]
 pub struct JsonRPCError {
+    pub kind: ErrorKind,
     pub code: i32,
     pub message: String,
     pub data: Option<Value>,
@@ -0,0 +1,585 @@
+use std::{
+    mem,
+    pin::pin,
+    sync::Arc,
+    task::{ready, Poll},
+};
+
+use futures::{
+    future::FusedFuture,
+    stream::{self, FuturesUnordered},
+    FutureExt, Stream, StreamExt,
+};
+use serde::de::Error as _;
+use tokio::sync::mpsc;
+
+use crate::{ExecError, Router};
+
+use super::jsonrpc::{
+    self, handle_json_rpc_with_connection, pre_context, render_error, Connection, RequestId,
+    ResponseInner, Sender, SubscriptionMap,
+};
+
+/// An error which stopped a batch from being read. See [`handle_json_rpc_batch`].
+#[derive(thiserror::Error, Debug)]
+pub enum BatchError<E> {
+    #[error("error reading the request body: {0}")]
+    Body(E),
+    #[error("invalid batch request: {0}")]
+    Json(serde_json::Error),
+}
+
+/// Execute a batch of requests (a JSON array of [`jsonrpc::Request`]s) read incrementally from `body`, sending each response to `tx`.
+///
+/// The array is parsed as it's received and every request is dispatched as soon as it's element is complete, so only the request currently being parsed is buffered instead of the whole body.
+/// A context is created with `ctx_fn` for each request after it passes the router's [pre-context middleware](crate::Config::pre_context).
+///
+/// ## Ordering
+///
+/// Requests are executed concurrently (bounded by [`Config::max_concurrent_requests`](crate::Config::max_concurrent_requests) of `connection`) and each response is sent as soon as it's request completes.
+/// This means responses are sent in completion order, not request order, so the client must match them to requests using their `id`. Requests are started in the order they appear in the batch.
+///
+/// Subscriptions aren't supported within a batch and respond with an error.
+///
+/// Each element is deserialized independently, so an element which isn't a valid request only responds with an [`ExecError::InvalidRequest`] error (using it's `id` if one can be read, otherwise `null`) and the rest of the batch still executes.
+/// If the body fails to read or isn't a valid JSON array, no more requests are dispatched but the requests which were already dispatched run to completion before the error is returned.
+pub async fn handle_json_rpc_batch<TCtx, TMeta, B, E>(
+    ctx_fn: impl Fn() -> TCtx,
+    body: impl Stream<Item = Result<B, E>>,
+    router: &Arc<Router<TCtx, TMeta>>,
+    tx: mpsc::Sender<jsonrpc::Response>,
+    connection: &Connection,
+) -> Result<(), BatchError<E>>
+where
+    TCtx: 'static,
+    B: AsRef<[u8]>,
+{
+    let mut body = pin!(body);
+    let mut splitter = ArraySplitter::default();
+    let mut in_flight = FuturesUnordered::new();
+
+    let result = loop {
+        tokio::select! {
+            Some(()) = in_flight.next(), if !in_flight.is_empty() => {}
+            chunk = body.next() => {
+                let elements = match chunk {
+                    Some(Ok(chunk)) => splitter.push(chunk.as_ref()),
+                    Some(Err(err)) => break Err(BatchError::Body(err)),
+                    None => break splitter.finish().map_err(BatchError::Json),
+                };
+
+                match elements {
+                    Ok(elements) => {
+                        for element in elements {
+                            let (ctx_fn, router, mut tx) = (&ctx_fn, router.clone(), tx.clone());
+                            in_flight.push(async move {
+                                let resp = match serde_json::from_slice::<jsonrpc::Request>(&element) {
+                                    Ok(req) => match pre_context(&router, &req).await {
+                                        Ok(()) => {
+                                            return handle_json_rpc_with_connection(
+                                                ctx_fn(),
+                                                req,
+                                                &router,
+                                                &mut Sender::Channel(&mut tx),
+                                                &mut SubscriptionMap::None,
+                                                connection,
+                                            )
+                                            .await;
+                                        }
+                                        Err(resp) => resp,
+                                    },
+                                    Err(err) => jsonrpc::Response {
+                                        jsonrpc: "2.0",
+                                        id: element_id(&element),
+                                        result: ResponseInner::Error(
+                                            render_error(&router, ExecError::InvalidRequest(err)),
+                                        ),
+                                    },
+                                };
+                                let _ = tx.send(resp).await;
+                            });
+                        }
+                    }
+                    Err(err) => break Err(BatchError::Json(err)),
+                }
+            }
+        }
+    };
+
+    while in_flight.next().await.is_some() {}
+    result
+}
+
+/// Execute a batch like [`handle_json_rpc_batch`], but return the responses as a stream of [NDJSON](https://github.com/ndjson/ndjson-spec) lines instead of sending them to a channel.
+///
+/// Each item is a single serialized [`jsonrpc::Response`] followed by a `\n`, yielded as soon as it's request completes so the client can process early responses while the rest of the batch is still running.
+/// Responses are in completion order, so the client must match them to requests using their `id`.
+///
+/// This requires a streaming transport which writes each item to the client as it's yielded (Eg. a chunked HTTP response with `Content-Type: application/x-ndjson`). A transport which buffers the body gains nothing over [`handle_json_rpc_batch`].
+///
+/// If the body fails to read or isn't a valid JSON array, the error is yielded after the responses of the requests which were already dispatched and the stream ends.
+pub fn stream_json_rpc_batch<'a, TCtx, TMeta, B, E>(
+    ctx_fn: impl Fn() -> TCtx + 'a,
+    body: impl Stream<Item = Result<B, E>> + 'a,
+    router: &'a Arc<Router<TCtx, TMeta>>,
+    connection: &'a Connection,
+) -> impl Stream<Item = Result<Vec<u8>, BatchError<E>>> + 'a
+where
+    TCtx: 'static,
+    TMeta: 'a,
+    B: AsRef<[u8]> + 'a,
+    E: 'a,
+{
+    let (tx, mut rx) = mpsc::channel(16);
+    let mut batch = Box::pin(handle_json_rpc_batch(ctx_fn, body, router, tx, connection).fuse());
+    let mut result = None;
+
+    stream::poll_fn(move |cx| {
+        if !batch.is_terminated() {
+            if let Poll::Ready(r) = batch.poll_unpin(cx) {
+                result = Some(r);
+            }
+        }
+
+        // The channel only closes once the batch has completed, so it's error is yielded last
+        Poll::Ready(match ready!(rx.poll_recv(cx)) {
+            Some(resp) => {
+                let mut line =
+                    serde_json::to_vec(&resp).expect("rspc: failed to serialize a batch response");
+                line.push(b'\n');
+                Some(Ok(line))
+            }
+            None => result.take().and_then(Result::err).map(Err),
+        })
+    })
+}
+
+/// The `id` of a batch element which isn't a valid request, if it has a valid one.
+fn element_id(element: &[u8]) -> RequestId {
+    serde_json::from_slice::<serde_json::Value>(element)
+        .ok()
+        .and_then(|mut element| serde_json::from_value(element.get_mut("id")?.take()).ok())
+        .unwrap_or(RequestId::Null)
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum State {
+    // Before the opening `[`
+    #[default]
+    Start,
+    // After the `[`, so the array may be empty
+    First,
+    // After a `,`
+    Next,
+    Element {
+        depth: usize,
+        in_string: bool,
+        escaped: bool,
+    },
+    // After the closing `]`
+    End,
+}
+
+/// Splits a top-level JSON array into the raw bytes of it's elements as it's received.
+///
+/// This only tracks strings and nesting to find the boundaries of elements. The elements themselves are validated when they are deserialized.
+#[derive(Default)]
+struct ArraySplitter {
+    state: State,
+    element: Vec<u8>,
+}
+
+impl ArraySplitter {
+    /// Push the next chunk of the body, returning every element which was completed by it.
+    fn push(&mut self, chunk: &[u8]) -> Result<Vec<Vec<u8>>, serde_json::Error> {
+        let mut elements = Vec::new();
+        for &byte in chunk {
+            if byte.is_ascii_whitespace() && !matches!(self.state, State::Element { .. }) {
+                continue;
+            }
+            match self.state {
+                State::Start if byte == b'[' => self.state = State::First,
+                State::First if byte == b']' => self.state = State::End,
+                State::First | State::Next if byte != b']' && byte != b',' => {
+                    self.state = State::Element {
+                        depth: 0,
+                        in_string: false,
+                        escaped: false,
+                    };
+                    self.push_element_byte(byte, &mut elements);
+                }
+                State::Element { .. } => self.push_element_byte(byte, &mut elements),
+                State::Start | State::First | State::Next | State::End => {
+                    return Err(serde_json::Error::custom(format!(
+                        "unexpected character '{}' in batch",
+                        byte as char
+                    )));
+                }
+            }
+        }
+        Ok(elements)
+    }
+
+    fn push_element_byte(&mut self, byte: u8, elements: &mut Vec<Vec<u8>>) {
+        let State::Element {
+            depth,
+            in_string,
+            escaped,
+        } = &mut self.state
+        else {
+            unreachable!();
+        };
+
+        match byte {
+            _ if *escaped => *escaped = false,
+            b'\\' if *in_string => *escaped = true,
+            b'"' => *in_string = !*in_string,
+            _ if *in_string => {}
+            b'{' | b'[' => *depth += 1,
+            b'}' | b']' if *depth > 0 => *depth -= 1,
+            b']' | b',' if *depth == 0 => {
+                self.state = match byte {
+                    b']' => State::End,
+                    _ => State::Next,
+                };
+                elements.push(mem::take(&mut self.element));
+                return;
+            }
+            _ => {}
+        }
+        self.element.push(byte);
+    }
+
+    /// Check the body contained a complete array once it has ended.
+    fn finish(&self) -> Result<(), serde_json::Error> {
+        match self.state {
+            State::End => Ok(()),
+            _ => Err(serde_json::Error::custom("unexpected end of batch")),
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use std::{convert::Infallible, sync::Arc};
+
+    use futures::stream;
+    use serde_json::json;
+    use tokio::sync::{mpsc, Semaphore};
+
+    use super::*;
+    use crate::internal::jsonrpc::{RequestId, ResponseInner};
+
+    #[test]
+    fn test_array_splitter() {
+        let body = br#" [ {"a": "],\"["}, [1, {"b": 2}] ,3, "x" ] "#;
+        // Byte at a time, so every boundary falls within a chunk
+        let mut splitter = ArraySplitter::default();
+        let mut elements = Vec::new();
+        for byte in body {
+            elements.extend(splitter.push(&[*byte]).unwrap());
+        }
+        splitter.finish().unwrap();
+        let elements = elements
+            .iter()
+            .map(|e| serde_json::from_slice::<serde_json::Value>(e).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            elements,
+            [
+                json!({ "a": "],\"[" }),
+                json!([1, { "b": 2 }]),
+                json!(3),
+                json!("x")
+            ]
+        );
+
+        let mut splitter = ArraySplitter::default();
+        assert!(splitter.push(b"[]").unwrap().is_empty());
+        assert!(splitter.finish().is_ok());
+        assert!(ArraySplitter::default().push(b"{}").is_err());
+        assert!(ArraySplitter::default().push(b"[1,,2]").is_err());
+        let mut splitter = ArraySplitter::default();
+        splitter.push(b"[1, 2").unwrap();
+        assert!(splitter.finish().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_batch() {
+        let gate = Arc::new(Semaphore::new(0));
+        let router = Arc::new(
+            <crate::Router>::new()
+                .query("slow", {
+                    let gate = gate.clone();
+                    move |t| {
+                        let gate = gate.clone();
+                        t(move |_, _: ()| {
+                            let gate = gate.clone();
+                            async move { gate.acquire().await.unwrap().forget() }
+                        })
+                    }
+                })
+                .query("echo", |t| t(|_, input: String| input))
+                .build(),
+        );
+
+        let body = r#"[
+            {"id": 1, "method": "query", "params": {"path": "slow"}},
+            {"id": 2, "method": "query", "params": {"path": "echo", "input": "a]\"b"}}
+        ]"#;
+        let chunks = body
+            .as_bytes()
+            .chunks(7)
+            .map(|chunk| Ok::<_, Infallible>(chunk.to_vec()))
+            .collect::<Vec<_>>();
+
+        let (tx, mut rx) = mpsc::channel(4);
+        let connection = Connection::default();
+        let batch = handle_json_rpc_batch(|| (), stream::iter(chunks), &router, tx, &connection);
+        let (result, ()) = tokio::join!(batch, async {
+            // The second request responds while the first is still running
+            let resp = rx.recv().await.unwrap();
+            assert_eq!(resp.id, RequestId::Number(2));
+            assert!(matches!(resp.result, ResponseInner::Response(v) if v == json!("a]\"b")));
+
+            gate.add_permits(1);
+            let resp = rx.recv().await.unwrap();
+            assert_eq!(resp.id, RequestId::Number(1));
+        });
+        assert!(result.is_ok());
+
+        // Requests before an invalid element are still executed
+        let (tx, mut rx) = mpsc::channel(4);
+        let body = r#"[{"id": 1, "method": "query", "params": {"path": "echo", "input": "a"}}, {"#;
+        let result = handle_json_rpc_batch(
+            || (),
+            stream::iter([Ok::<_, Infallible>(body)]),
+            &router,
+            tx,
+            &Connection::default(),
+        )
+        .await;
+        assert!(matches!(result, Err(BatchError::Json(_))));
+        assert_eq!(rx.recv().await.unwrap().id, RequestId::Number(1));
+    }
+
+    #[tokio::test]
+    async fn test_stream_batch() {
+        let router = Arc::new(
+            <crate::Router>::new()
+                .query("sleep", |t| {
+                    t(|_, ms: u64| async move {
+                        tokio::time::sleep(std::time::Duration::from_millis(ms)).await;
+                        ms
+                    })
+                })
+                .build(),
+        );
+
+        let body = r#"[
+            {"id": 1, "method": "query", "params": {"path": "sleep", "input": 60}},
+            {"id": 2, "method": "query", "params": {"path": "sleep", "input": 0}},
+            {"id": 3, "method": "query", "params": {"path": "sleep", "input": 30}}
+        ]"#;
+        let connection = Connection::default();
+        let lines = stream_json_rpc_batch(
+            || (),
+            stream::iter([Ok::<_, Infallible>(body)]),
+            &router,
+            &connection,
+        )
+        .map(|line| line.unwrap())
+        .collect::<Vec<_>>()
+        .await;
+
+        // Each response is it's own line, in the order the requests completed
+        assert!(lines.iter().all(|line| line.ends_with(b"\n")));
+        let responses = lines
+            .iter()
+            .map(|line| serde_json::from_slice::<serde_json::Value>(line).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            responses,
+            [
+                json!({ "jsonrpc": "2.0", "id": 2, "result": { "type": "response", "data": 0 } }),
+                json!({ "jsonrpc": "2.0", "id": 3, "result": { "type": "response", "data": 30 } }),
+                json!({ "jsonrpc": "2.0", "id": 1, "result": { "type": "response", "data": 60 } }),
+            ]
+        );
+
+        // The error is yielded after the responses of the requests which were already dispatched
+        let body = r#"[{"id": 1, "method": "query", "params": {"path": "sleep", "input": 10}}, {"#;
+        let items = stream_json_rpc_batch(
+            || (),
+            stream::iter([Ok::<_, Infallible>(body)]),
+            &router,
+            &connection,
+        )
+        .collect::<Vec<_>>()
+        .await;
+        assert!(matches!(&items[..], [Ok(_), Err(BatchError::Json(_))]));
+    }
+
+    #[tokio::test]
+    async fn test_batch_invalid_element() {
+        let router = Arc::new(
+            <crate::Router>::new()
+                .query("echo", |t| t(|_, input: String| input))
+                .build(),
+        );
+
+        let body = r#"[
+            {"id": 1, "method": "query", "params": {"path": "echo", "input": "a"}},
+            {"id": 2, "method": "query", "params": {"input": "missing path"}},
+            "not a request",
+            {"id": 3, "method": "query", "params": {"path": "echo", "input": "b"}}
+        ]"#;
+        let (tx, mut rx) = mpsc::channel(4);
+        let result = handle_json_rpc_batch(
+            || (),
+            stream::iter([Ok::<_, Infallible>(body)]),
+            &router,
+            tx,
+            &Connection::default(),
+        )
+        .await;
+        assert!(result.is_ok());
+
+        let mut responses = Vec::new();
+        while let Some(resp) = rx.recv().await {
+            responses.push(resp);
+        }
+        responses.sort_by_key(|resp| format!("{:?}", resp.id));
+        assert_eq!(responses.len(), 4);
+        assert!(matches!(
+            &responses[0],
+            jsonrpc::Response { id: RequestId::Null, result: ResponseInner::Error(err), .. } if err.kind == crate::ErrorKind::BadRequest
+        ));
+        assert!(matches!(
+            &responses[1],
+            jsonrpc::Response { id: RequestId::Number(1), result: ResponseInner::Response(v), .. } if v == "a"
+        ));
+        assert!(matches!(
+            &responses[2],
+            jsonrpc::Response {
+                id: RequestId::Number(2),
+                result: ResponseInner::Error(_),
+                ..
+            }
+        ));
+        assert!(matches!(
+            &responses[3],
+            jsonrpc::Response { id: RequestId::Number(3), result: ResponseInner::Response(v), .. } if v == "b"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_batch_subscription() {
+        let router = Arc::new(
+            <crate::Router>::new()
+                .query("echo", |t| t(|_, input: String| input))
+                .subscription("s", |t| t(|_, _: ()| stream::iter([1, 2])))
+                .build(),
+        );
+
+        let body = r#"[
+            {"id": 1, "method": "subscription", "params": {"path": "s", "input": [1, null]}},
+            {"id": 2, "method": "query", "params": {"path": "echo", "input": "a"}}
+        ]"#;
+        let (tx, mut rx) = mpsc::channel(4);
+        let result = handle_json_rpc_batch(
+            || (),
+            stream::iter([Ok::<_, Infallible>(body)]),
+            &router,
+            tx,
+            &Connection::default(),
+        )
+        .await;
+        assert!(result.is_ok());
+
+        // The subscription is rejected without affecting the rest of the batch
+        let mut responses = Vec::new();
+        while let Some(resp) = rx.recv().await {
+            responses.push(resp);
+        }
+        responses.sort_by_key(|resp| format!("{:?}", resp.id));
+        assert_eq!(responses.len(), 2);
+        assert!(matches!(
+            &responses[0],
+            jsonrpc::Response {
+                id: RequestId::Number(1),
+                result: ResponseInner::Error(_),
+                ..
+            }
+        ));
+        assert!(matches!(
+            &responses[1],
+            jsonrpc::Response { id: RequestId::Number(2), result: ResponseInner::Response(v), .. } if v == "a"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_pre_context() {
+        let calls = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let middleware = |name: &'static str| {
+            let calls = calls.clone();
+            move |req: &jsonrpc::Request| {
+                calls.lock().unwrap().push(name);
+                let rejected = matches!(&req.inner, jsonrpc::RequestInner::Query { path, .. } if path == "secret");
+                async move {
+                    match rejected {
+                        true => Err(crate::Error::new(
+                            crate::ErrorCode::Unauthorized,
+                            "no api key".into(),
+                        )),
+                        false => Ok(()),
+                    }
+                }
+            }
+        };
+        let router = Arc::new(
+            <crate::Router>::new()
+                .config(
+                    crate::Config::new()
+                        .pre_context(middleware("a"))
+                        .pre_context(middleware("b")),
+                )
+                .query("echo", |t| t(|_, input: String| input))
+                .query("secret", |t| t(|_, _: ()| "secret"))
+                .build(),
+        );
+
+        let body = r#"[
+            {"id": 1, "method": "query", "params": {"path": "echo", "input": "a"}},
+            {"id": 2, "method": "query", "params": {"path": "secret"}}
+        ]"#;
+        let contexts = std::sync::Mutex::new(0);
+        let (tx, mut rx) = mpsc::channel(4);
+        let result = handle_json_rpc_batch(
+            || *contexts.lock().unwrap() += 1,
+            stream::iter([Ok::<_, Infallible>(body)]),
+            &router,
+            tx,
+            &Connection::default(),
+        )
+        .await;
+        assert!(result.is_ok());
+
+        let mut responses = Vec::new();
+        while let Some(resp) = rx.recv().await {
+            responses.push(resp);
+        }
+        responses.sort_by_key(|resp| format!("{:?}", resp.id));
+        assert!(matches!(&responses[0].result, ResponseInner::Response(v) if v == "a"));
+        assert!(
+            matches!(&responses[1], jsonrpc::Response { id: RequestId::Number(2), result: ResponseInner::Error(err), .. } if err.message == "no api key")
+        );
+        // The context is only built for the request which wasn't rejected
+        assert_eq!(*contexts.lock().unwrap(), 1);
+        let mut calls = calls.lock().unwrap().clone();
+        calls.sort();
+        // Both middleware ran for the first request but `b` didn't run after `a` rejected the second
+        assert_eq!(calls, ["a", "a", "b"]);
+    }
+}
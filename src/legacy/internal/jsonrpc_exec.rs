@@ -1,16 +1,476 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, HashMap},
+    future::Future,
+    io,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, PoisonError,
+    },
+    task::{Context, Poll},
+    time::Instant,
+};
 
-use futures::StreamExt;
+use futures::{task::noop_waker_ref, Stream, StreamExt};
+use serde::Serialize;
 use serde_json::Value;
-use tokio::sync::{broadcast, mpsc, oneshot, Mutex};
+use tokio::sync::{broadcast, mpsc, oneshot, Mutex, Notify, OwnedSemaphorePermit, Semaphore};
 
-use crate::{internal::jsonrpc, ExecError, Router};
+use crate::{
+    internal::jsonrpc,
+    legacy::{
+        config::{OnErrorFn, RetryAfterFn},
+        openrpc,
+        priority::{PriorityQueue, QueuePermit},
+        subscription_hooks::unsubscribe,
+    },
+    BinaryFormat, BufferOverflow, CloseFrame, ConnectionId, ErrorVerbosity, ExecError, NotifyError,
+    OverloadBehavior, Priority, RawStream, Router,
+};
 
 use super::{
     jsonrpc::{RequestId, RequestInner, ResponseInner},
-    ProcedureKind, RequestContext, ValueOrStream,
+    ProcedureKind, ValueOrStream,
 };
 
+/// Response metadata which is only meaningful to HTTP transports.
+#[derive(Debug, Default)]
+pub struct HttpResponse {
+    /// Set when the resolver returned a [`Redirect`](crate::Redirect).
+    pub redirect: Option<String>,
+    /// Set when the resolver returned a [`RawStream`]. It should be sent as the response body instead of the JSON-RPC response.
+    pub body: Option<RawStream>,
+    /// Set when the query was built with [`cache_control`](crate::internal::BuiltProcedureBuilder::cache_control). It should only be sent with a successful response.
+    pub cache_control: Option<String>,
+}
+
+tokio::task_local! {
+    static HTTP_RESPONSE: RefCell<HttpResponse>;
+    static TRANSPORT: Transport;
+    static FRAMES: mpsc::UnboundedSender<ResponseInner>;
+    static NOTIFIER: Notifier;
+    static CONNECTION_ID: ConnectionId;
+    static STATUS: ConnectionStatus;
+    static HEADERS: Headers;
+}
+
+/// The kind of transport a request was received over.
+///
+/// Get this from within a resolver (or middleware) using [`transport`](crate::transport).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Transport {
+    /// A regular HTTP request. Subscriptions aren't supported.
+    Http,
+    /// A request made over a WebSocket connection.
+    WebSocket,
+    /// A request made by a Tauri webview.
+    Tauri,
+    /// The procedure was called directly from Rust (Eg. [`Router::exec`]).
+    /// This is also the value for any custom integration which doesn't call [`with_transport`].
+    InProcess,
+}
+
+/// Returns the [`Transport`] the current request was received over.
+///
+/// This is only meaningful while a procedure is executing. Outside of a request [`Transport::InProcess`] is returned.
+pub fn transport() -> Transport {
+    TRANSPORT.try_with(|t| *t).unwrap_or(Transport::InProcess)
+}
+
+/// Run `fut` (a call to [`handle_json_rpc`]) marking every procedure it executes as coming from `transport`.
+///
+/// This should be called by every transport integration. Subscriptions started within `fut` keep the transport for their entire lifetime.
+pub async fn with_transport<F: Future>(transport: Transport, fut: F) -> F::Output {
+    TRANSPORT.scope(transport, fut).await
+}
+
+/// The headers of the request currently being executed, normalized across transports.
+///
+/// Header names are case-insensitive: they're stored lowercased and looked up ignoring case, so `headers().get("Accept-Language")` and `headers().get("accept-language")` are equivalent.
+/// For WebSocket requests these are the headers of the request which opened the connection. Requests made in process (Eg. [`Router::exec`]) have no headers.
+///
+/// Get them from within a resolver (or middleware) using [`headers`](crate::headers).
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Headers(Arc<BTreeMap<String, Vec<String>>>);
+
+impl Headers {
+    /// Returns the first value of the header `name`.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.get_all(name).next()
+    }
+
+    /// Returns every value of the header `name` in the order they were received.
+    pub fn get_all(&self, name: &str) -> impl Iterator<Item = &str> {
+        self.0
+            .get(&name.to_ascii_lowercase())
+            .into_iter()
+            .flatten()
+            .map(String::as_str)
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.0.contains_key(&name.to_ascii_lowercase())
+    }
+
+    /// Iterate over every header as lowercased `(name, value)` pairs.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.0.iter().flat_map(|(name, values)| {
+            values
+                .iter()
+                .map(move |value| (name.as_str(), value.as_str()))
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl<K: AsRef<str>, V: Into<String>> FromIterator<(K, V)> for Headers {
+    fn from_iter<T: IntoIterator<Item = (K, V)>>(iter: T) -> Self {
+        let mut headers = BTreeMap::<_, Vec<_>>::new();
+        for (name, value) in iter {
+            headers
+                .entry(name.as_ref().to_ascii_lowercase())
+                .or_default()
+                .push(value.into());
+        }
+        Self(Arc::new(headers))
+    }
+}
+
+/// Returns the [`Headers`] of the current request. They are empty outside of a request or when the transport didn't provide any.
+pub fn headers() -> Headers {
+    HEADERS.try_with(Clone::clone).unwrap_or_default()
+}
+
+/// Run `fut` (a call to [`handle_json_rpc`]) making `headers` available to every procedure it executes with [`headers`](crate::headers).
+///
+/// This should be called by every transport integration which receives headers. Subscriptions started within `fut` keep the headers for their entire lifetime.
+pub async fn with_headers<F: Future>(headers: Headers, fut: F) -> F::Output {
+    HEADERS.scope(headers, fut).await
+}
+
+/// Run the router's [pre-context middleware](crate::Config::pre_context) for `req`, stopping at the first one which rejects it.
+///
+/// This should be called by every transport integration before building the context of a request. If it fails the returned error response should be sent instead of executing the request.
+pub async fn pre_context<TCtx, TMeta>(
+    router: &Router<TCtx, TMeta>,
+    req: &jsonrpc::Request,
+) -> Result<(), jsonrpc::Response> {
+    for middleware in &router.config.pre_context {
+        if let Err(err) = middleware(req).await {
+            return Err(jsonrpc::Response {
+                jsonrpc: "2.0",
+                id: req.id.clone(),
+                result: ResponseInner::Error(render_error(router, ExecError::from(err))),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// The frame to close the connection with for `err`, or `None` if the connection should stay open. See [`Config::close_frame`](crate::Config::close_frame).
+///
+/// This should be called by every transport integration with persistent connections for the errors it encounters outside of a procedure.
+pub fn close_frame<TCtx, TMeta>(
+    router: &Router<TCtx, TMeta>,
+    err: &ExecError,
+) -> Option<CloseFrame> {
+    let mut frame = router
+        .config
+        .close_frame
+        .as_ref()
+        .and_then(|map| map(err))?;
+    if frame.retry_after.is_none() {
+        frame.retry_after = router
+            .config
+            .retry_after
+            .as_ref()
+            .and_then(|hint| hint(err));
+    }
+    Some(frame)
+}
+
+// Render `err` into the error frame sent to the client, adding the router's `Config::retry_after` hint.
+pub(crate) fn render_error<TCtx, TMeta>(
+    router: &Router<TCtx, TMeta>,
+    err: ExecError,
+) -> jsonrpc::JsonRPCError {
+    render_with_hint(
+        err,
+        router.config.error_verbosity,
+        router.config.on_error.as_ref(),
+        router.config.retry_after.as_ref(),
+    )
+}
+
+fn render_with_hint(
+    err: ExecError,
+    verbosity: Option<ErrorVerbosity>,
+    on_error: Option<&OnErrorFn>,
+    retry_after: Option<&RetryAfterFn>,
+) -> jsonrpc::JsonRPCError {
+    let retry_after = retry_after.and_then(|hint| hint(&err));
+    let mut err = err.render(verbosity, on_error);
+    if let Some(retry_after) = retry_after {
+        let retry_after = Value::from(retry_after.as_millis() as u64);
+        match &mut err.data {
+            Some(Value::Object(data)) => {
+                data.insert("retryAfter".into(), retry_after);
+            }
+            data => *data = Some(serde_json::json!({ "retryAfter": retry_after })),
+        }
+    }
+    err
+}
+
+/// Rewrite `resp` with the router's [`Config::rewrite_frames`](crate::Config::rewrite_frames), or return it unchanged if it isn't set.
+///
+/// This should be called by every transport integration on every frame it sends, just before serializing it. `headers` are the same headers given to [`with_headers`].
+pub fn rewrite_frame<TCtx, TMeta>(
+    router: &Router<TCtx, TMeta>,
+    headers: &Headers,
+    resp: jsonrpc::Response,
+) -> jsonrpc::Response {
+    match &router.config.rewrite_frames {
+        Some(rewrite) => rewrite(headers, resp),
+        None => resp,
+    }
+}
+
+/// A message of a WebSocket connection, encoded with [`encode_frame`] or to be decoded with [`decode_frame`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Frame {
+    /// A text frame (opcode `0x1`) holding JSON.
+    Text(String),
+    /// A binary frame (opcode `0x2`), holding the connection's [`BinaryFormat`] if one was negotiated.
+    Binary(Vec<u8>),
+}
+
+/// The [`BinaryFormat`] of a WebSocket connection opened with `headers`, or `None` if it uses JSON text frames. See [`Config::binary_format`](crate::Config::binary_format).
+///
+/// A format is only used when the client offered it's protocol in the `Sec-WebSocket-Protocol` header, the transport integration must then accept that protocol in it's handshake response.
+pub fn negotiate_format<TCtx, TMeta>(
+    router: &Router<TCtx, TMeta>,
+    headers: &Headers,
+) -> Option<Arc<dyn BinaryFormat>> {
+    let format = router.config.binary_format.as_ref()?;
+    headers
+        .get_all("sec-websocket-protocol")
+        .flat_map(|protocols| protocols.split(','))
+        .any(|protocol| protocol.trim() == format.protocol())
+        .then(|| format.clone())
+}
+
+/// Encode `resp` into the frame sent to the client, using the connection's negotiated `format`.
+///
+/// This should be called by every transport integration with persistent connections on every frame it sends, after [`rewrite_frame`].
+pub fn encode_frame(
+    format: Option<&dyn BinaryFormat>,
+    resp: &jsonrpc::Response,
+) -> Result<Frame, Box<dyn std::error::Error + Send + Sync>> {
+    Ok(match format {
+        Some(format) => Frame::Binary(format.encode(resp)?),
+        None => Frame::Text(serde_json::to_string(resp)?),
+    })
+}
+
+/// Decode a message received from the client into a request (or batch of requests), using the connection's negotiated `format`.
+///
+/// Text messages are always JSON, as are binary messages if no format was negotiated.
+pub fn decode_frame(format: Option<&dyn BinaryFormat>, frame: &Frame) -> Result<Value, ExecError> {
+    match (frame, format) {
+        (Frame::Text(text), _) => serde_json::from_str(text),
+        (Frame::Binary(bytes), Some(format)) => format
+            .decode(bytes)
+            .map_err(<serde_json::Error as serde::de::Error>::custom),
+        (Frame::Binary(bytes), None) => serde_json::from_slice(bytes),
+    }
+    .map_err(ExecError::InvalidRequest)
+}
+
+/// Await the future building the context of a request, applying the router's [`Config::context_timeout`](crate::Config::context_timeout).
+///
+/// This should be called by every transport integration which builds the context asynchronously.
+pub async fn build_context<TCtx, TMeta, F>(
+    router: &Router<TCtx, TMeta>,
+    ctx: F,
+) -> Result<TCtx, ExecError>
+where
+    F: Future<Output = Result<TCtx, ExecError>>,
+{
+    let Some(timeout) = router.config.context_timeout else {
+        return ctx.await;
+    };
+    let runtime = router
+        .config
+        .runtime_or_default()
+        .ok_or(ExecError::NoRuntime)?;
+
+    tokio::select! {
+        biased;
+        ctx = ctx => ctx,
+        _ = runtime.sleep(timeout) => Err(ExecError::ContextTimeout),
+    }
+}
+
+/// Run `fut` (a call to [`handle_json_rpc`]) collecting the [`HttpResponse`] metadata produced by the procedure.
+///
+/// This should only be used by HTTP transports. Outside of it HTTP specific results fallback to their regular JSON representation.
+pub async fn with_http_response<F: Future>(fut: F) -> (F::Output, HttpResponse) {
+    HTTP_RESPONSE
+        .scope(Default::default(), async move {
+            let output = fut.await;
+            (output, HTTP_RESPONSE.with(|resp| resp.take()))
+        })
+        .await
+}
+
+pub(crate) fn set_http_redirect(url: &str) {
+    let _ = HTTP_RESPONSE.try_with(|resp| resp.borrow_mut().redirect = Some(url.to_string()));
+}
+
+pub(crate) fn set_http_cache_control(directive: &str) {
+    let _ = HTTP_RESPONSE
+        .try_with(|resp| resp.borrow_mut().cache_control = Some(directive.to_string()));
+}
+
+/// Whether the current request came from an HTTP transport, so a body given to [`set_http_body`] is streamed to the client.
+pub(crate) fn accepts_http_body() -> bool {
+    HTTP_RESPONSE.try_with(|_| ()).is_ok()
+}
+
+/// Hand the body to the HTTP transport. The stream is given back if the request didn't come from one.
+pub(crate) fn set_http_body(body: RawStream) -> Result<(), RawStream> {
+    let mut body = Some(body);
+    let _ = HTTP_RESPONSE.try_with(|resp| resp.borrow_mut().body = body.take());
+    match body {
+        Some(body) => Err(body),
+        None => Ok(()),
+    }
+}
+
+/// Get the channel the intermediate frames (Eg. log lines) of the current request should be sent to, if the transport supports them.
+pub(crate) fn frame_sink() -> Option<mpsc::UnboundedSender<ResponseInner>> {
+    FRAMES.try_with(|tx| tx.clone()).ok()
+}
+
+/// Send the `caughtUp` frame of a subscription. Returns whether it was sent, in which case `cx` is woken and the stream should return `Poll::Pending` so the frame is sent before it's next event.
+pub(crate) fn send_caught_up(cx: &mut std::task::Context<'_>) -> bool {
+    let sent = frame_sink().is_some_and(|sink| sink.send(ResponseInner::CaughtUp).is_ok());
+    if sent {
+        cx.waker().wake_by_ref();
+    }
+    sent
+}
+
+/// Run `fut` passing any log lines produced by a [`WithLogs`](crate::WithLogs) result through `map` before they are sent.
+pub(crate) async fn map_logs<F: Future>(fut: F, map: impl Fn(Value) -> Value) -> F::Output {
+    let Some(sink) = frame_sink() else {
+        return fut.await;
+    };
+    let map = |frame| match frame {
+        ResponseInner::Log(log) => ResponseInner::Log(map(log)),
+        frame => frame,
+    };
+
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let fut = FRAMES.scope(tx, fut);
+    tokio::pin!(fut);
+
+    let output = loop {
+        tokio::select! {
+            biased;
+            output = &mut fut => break output,
+            Some(frame) = rx.recv() => {
+                let _ = sink.send(map(frame));
+            }
+        }
+    };
+
+    while let Ok(frame) = rx.try_recv() {
+        let _ = sink.send(map(frame));
+    }
+    output
+}
+
+/// Pass any trailer sent while polling a subscription's `stream` through `map` before it's sent.
+pub(crate) fn map_trailer<S>(
+    stream: S,
+    map: impl Fn(Value) -> Value + Send + 'static,
+) -> impl Stream<Item = S::Item> + Send
+where
+    S: Stream + Send + Unpin,
+{
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let mut stream = stream;
+    futures::stream::poll_fn(move |cx| {
+        let Some(sink) = frame_sink() else {
+            return stream.poll_next_unpin(cx);
+        };
+
+        let poll = FRAMES.sync_scope(tx.clone(), || stream.poll_next_unpin(cx));
+        while let Ok(frame) = rx.try_recv() {
+            let _ = sink.send(match frame {
+                ResponseInner::Trailer(trailer) => ResponseInner::Trailer(map(trailer)),
+                frame => frame,
+            });
+        }
+        poll
+    })
+}
+
+/// Run `fut` sending any intermediate frames produced by the procedure (Eg. the log lines of a [`WithLogs`](crate::WithLogs) result) with the given id.
+async fn forward_frames<F: Future>(fut: F, id: &RequestId, sender: &mut Sender<'_>) -> F::Output {
+    // There is only room for a single response
+    if matches!(sender, Sender::Response(_)) {
+        return fut.await;
+    }
+
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let fut = FRAMES.scope(tx, fut);
+    tokio::pin!(fut);
+
+    let output = loop {
+        tokio::select! {
+            biased;
+            output = &mut fut => break output,
+            Some(frame) = rx.recv() => {
+                let _ = sender
+                    .send(jsonrpc::Response {
+                        jsonrpc: "2.0",
+                        id: id.clone(),
+                        result: frame,
+                    })
+                    .await
+                    .map_err(|_err| {
+                        #[cfg(feature = "tracing")]
+                        tracing::error!("Failed to send response: {:?}", _err);
+                    });
+            }
+        }
+    };
+
+    // Frames which were sent while the result was resolving
+    while let Ok(frame) = rx.try_recv() {
+        let _ = sender
+            .send(jsonrpc::Response {
+                jsonrpc: "2.0",
+                id: id.clone(),
+                result: frame,
+            })
+            .await
+            .map_err(|_err| {
+                #[cfg(feature = "tracing")]
+                tracing::error!("Failed to send response: {:?}", _err);
+            });
+    }
+
+    output
+}
+
 // TODO: Deduplicate this function with the httpz integration
 
 pub enum SubscriptionMap<'a> {
@@ -44,14 +504,15 @@ impl<'a> SubscriptionMap<'a> {
         }
     }
 
-    pub async fn remove(&mut self, id: &RequestId) {
+    /// Remove the subscription `id`, returning the sender which stops it.
+    ///
+    /// Dropping the sender also stops the subscription, but only sending on it acknowledges the stop with a [`ResponseInner::Cancelled`] frame.
+    pub async fn remove(&mut self, id: &RequestId) -> Option<oneshot::Sender<()>> {
         match self {
-            SubscriptionMap::Ref(map) => {
-                map.remove(id);
-            }
+            SubscriptionMap::Ref(map) => map.remove(id),
             SubscriptionMap::Mutex(map) => {
                 let mut map = map.lock().await;
-                map.remove(id);
+                map.remove(id)
             }
             SubscriptionMap::None => unreachable!(),
         }
@@ -64,6 +525,7 @@ pub enum Sender<'a> {
     Response(Option<jsonrpc::Response>),
 }
 
+#[derive(Clone)]
 pub enum Sender2 {
     Channel(mpsc::Sender<jsonrpc::Response>),
     ResponseChannel(mpsc::UnboundedSender<jsonrpc::Response>),
@@ -122,171 +584,2183 @@ impl<'a> Sender<'a> {
     }
 }
 
-pub async fn handle_json_rpc<TCtx, TMeta>(
-    ctx: TCtx,
-    req: jsonrpc::Request,
-    router: &Arc<Router<TCtx, TMeta>>,
-    sender: &mut Sender<'_>,
-    subscriptions: &mut SubscriptionMap<'_>,
-) where
-    TCtx: 'static,
-{
-    if req.jsonrpc.is_some() && req.jsonrpc.as_deref() != Some("2.0") {
-        let _ = sender
+/// A handle for pushing notifications to a single connection which aren't tied to any request or subscription (Eg. "you have a new message").
+///
+/// Get the notifier of the current connection from within a resolver (or middleware) using [`notifier`](crate::notifier). It can be cloned and stored (Eg. in a map of online users) to notify the client later.
+///
+/// ## Wire protocol
+///
+/// Notifications are sent as `{ jsonrpc: "2.0", id: null, result: { type: "notification", data: { method, params } } }`.
+#[derive(Clone)]
+pub struct Notifier {
+    sender: Sender2,
+}
+
+impl Notifier {
+    /// Construct a notifier which sends to the same channel as the connection's responses.
+    ///
+    /// This should be called by transport integrations when the connection is opened and attached to it's [`Connection`] with [`Connection::with_notifier`].
+    pub fn new(sender: Sender2) -> Self {
+        Self { sender }
+    }
+
+    /// Send a `method` notification with `params` to the client.
+    ///
+    /// Notifications share the connection's channel with responses so they respect it's backpressure: when the channel is bounded (Eg. [`Sender2::Channel`]) this waits until there is room.
+    pub async fn notify(&self, method: &str, params: impl Serialize) -> Result<(), NotifyError> {
+        let params = serde_json::to_value(params).map_err(NotifyError::SerializingErr)?;
+        self.sender
+            .clone()
             .send(jsonrpc::Response {
                 jsonrpc: "2.0",
-                id: req.id.clone(),
-                result: ResponseInner::Error(ExecError::InvalidJsonRpcVersion.into()),
+                id: RequestId::Null,
+                result: ResponseInner::Notification {
+                    method: method.into(),
+                    params,
+                },
             })
             .await
-            .map_err(|_err| {
-                #[cfg(feature = "tracing")]
-                tracing::error!("Failed to send response: {}", _err);
-            });
+            .map_err(|_| NotifyError::Closed)
     }
+}
 
-    let (path, input, procedures, sub_id) = match req.inner {
-        RequestInner::Query { path, input } => (path, input, router.queries(), None),
-        RequestInner::Mutation { path, input } => (path, input, router.mutations(), None),
-        RequestInner::Subscription { path, input } => {
-            (path, input.1, router.subscriptions(), Some(input.0))
-        }
-        RequestInner::SubscriptionStop { input } => {
-            subscriptions.remove(&input).await;
-            return;
-        }
-    };
+impl std::fmt::Debug for Notifier {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Notifier").finish_non_exhaustive()
+    }
+}
 
-    let result = match procedures
-        .get(&path)
-        .ok_or_else(|| ExecError::OperationNotFound(path.clone()))
-        .and_then(|v| {
-            v.exec.call(
-                ctx,
-                input.unwrap_or(Value::Null),
-                RequestContext {
-                    kind: ProcedureKind::Query,
-                    path,
-                },
-            )
-        }) {
-        Ok(op) => match op.into_value_or_stream().await {
-            Ok(ValueOrStream::Value(v)) => ResponseInner::Response(v),
-            Ok(ValueOrStream::Stream(mut stream)) => {
-                if matches!(sender, Sender::Response(_))
-                    || matches!(subscriptions, SubscriptionMap::None)
-                {
-                    let _ = sender
-                        .send(jsonrpc::Response {
-                            jsonrpc: "2.0",
-                            id: req.id.clone(),
-                            result: ResponseInner::Error(
-                                ExecError::UnsupportedMethod("Subscription".to_string()).into(),
-                            ),
-                        })
-                        .await
-                        .map_err(|_err| {
-                            #[cfg(feature = "tracing")]
-                            tracing::error!("Failed to send response: {}", _err);
-                        });
-                }
+/// Returns the [`Notifier`] of the connection the current request was received over.
+///
+/// This is `None` outside of a request or when the transport doesn't support notifications (Eg. HTTP).
+pub fn notifier() -> Option<Notifier> {
+    NOTIFIER.try_with(|n| n.clone()).ok()
+}
 
-                if let Some(id) = sub_id {
-                    if matches!(id, RequestId::Null) {
-                        let _ = sender
-                            .send(jsonrpc::Response {
-                                jsonrpc: "2.0",
-                                id: req.id.clone(),
-                                result: ResponseInner::Error(
-                                    ExecError::ErrSubscriptionWithNullId.into(),
-                                ),
-                            })
-                            .await
-                            .map_err(|_err| {
-                                #[cfg(feature = "tracing")]
-                                tracing::error!("Failed to send response: {}", _err);
-                            });
-                    } else if subscriptions.has_subscription(&id).await {
-                        let _ = sender
-                            .send(jsonrpc::Response {
-                                jsonrpc: "2.0",
-                                id: req.id.clone(),
-                                result: ResponseInner::Error(
-                                    ExecError::ErrSubscriptionDuplicateId.into(),
-                                ),
-                            })
-                            .await
-                            .map_err(|_err| {
-                                #[cfg(feature = "tracing")]
-                                tracing::error!("Failed to send response: {}", _err);
-                            });
-                    }
+/// Returns the [`ConnectionId`] of the connection the current request was received over.
+///
+/// This is `None` outside of a request made through [`handle_json_rpc`] or [`handle_json_rpc_with_connection`].
+pub fn connection_id() -> Option<ConnectionId> {
+    CONNECTION_ID.try_with(|id| *id).ok()
+}
 
-                    let (shutdown_tx, mut shutdown_rx) = oneshot::channel();
-                    subscriptions.insert(id.clone(), shutdown_tx).await;
-                    let mut sender2 = sender.sender2();
-                    tokio::spawn(async move {
-                        loop {
-                            tokio::select! {
-                                biased; // Note: Order matters
-                                _ = &mut shutdown_rx => {
-                                    #[cfg(feature = "tracing")]
-                                    tracing::debug!("Removing subscription with id '{:?}'", id);
-                                    break;
-                                }
-                                v = stream.next() => {
-                                    match v {
-                                        Some(Ok(v)) => {
-                                            let _ = sender2.send(jsonrpc::Response {
-                                                jsonrpc: "2.0",
-                                                id: id.clone(),
-                                                result: ResponseInner::Event(v),
-                                            })
-                                            .await
-                                            .map_err(|_err| {
-                                                #[cfg(feature = "tracing")]
-                                                tracing::error!("Failed to send response: {:?}", _err);
-                                            });
-                                        }
-                                        Some(Err(_err)) => {
-                                           #[cfg(feature = "tracing")]
-                                            tracing::error!("Subscription error: {:?}", _err);
-                                        }
-                                        None => {
-                                            break;
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    });
-                }
+/// A cheap handle for checking whether the client is still waiting for the result of the current request, so expensive work can be skipped once nobody will receive it.
+///
+/// Get it from within a resolver (or middleware) using [`connection_status`](crate::connection_status), or check it directly with [`is_connected`](crate::is_connected). It can be cloned and moved into background work (Eg. [`spawn_blocking`](tokio::task::spawn_blocking)).
+///
+/// ## Best-effort
+///
+/// This only reports what the transport has noticed. The client is considered gone once the request is dropped by the transport (Eg. the HTTP client disconnected), a subscription is stopped or the connection is [closed](Connection::close).
+/// A client which disappeared without the transport noticing yet (Eg. a network partition) is still reported as connected, so this should only be used to skip work and never to decide whether a mutation was applied.
+#[derive(Clone, Default)]
+pub struct ConnectionStatus {
+    request_ended: Arc<AtomicBool>,
+    connection_closed: Arc<AtomicBool>,
+}
 
-                return;
-            }
-            Err(err) => {
-                #[cfg(feature = "tracing")]
-                tracing::error!("Error executing operation: {:?}", err);
+impl ConnectionStatus {
+    // A status for a new request on `connection`. The request ends when the returned guard is dropped.
+    fn new(connection: &Connection) -> (Self, RequestGuard) {
+        let request_ended = Arc::new(AtomicBool::new(false));
+        (
+            Self {
+                request_ended: request_ended.clone(),
+                connection_closed: connection.closed.clone(),
+            },
+            RequestGuard(request_ended),
+        )
+    }
 
-                ResponseInner::Error(err.into())
-            }
-        },
-        Err(err) => {
-            #[cfg(feature = "tracing")]
-            tracing::error!("Error executing operation: {:?}", err);
-            ResponseInner::Error(err.into())
-        }
-    };
+    /// Returns `false` once the client is known to no longer be waiting for the result.
+    pub fn is_connected(&self) -> bool {
+        !self.request_ended.load(Ordering::Relaxed)
+            && !self.connection_closed.load(Ordering::Relaxed)
+    }
+}
 
-    let _ = sender
-        .send(jsonrpc::Response {
-            jsonrpc: "2.0",
-            id: req.id,
-            result,
-        })
-        .await
-        .map_err(|_err| {
-            #[cfg(feature = "tracing")]
+impl std::fmt::Debug for ConnectionStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ConnectionStatus")
+            .field("is_connected", &self.is_connected())
+            .finish()
+    }
+}
+
+// Marks the request as ended when it completes or is dropped.
+struct RequestGuard(Arc<AtomicBool>);
+
+impl Drop for RequestGuard {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Returns the [`ConnectionStatus`] of the current request.
+///
+/// This is `None` outside of a request made through [`handle_json_rpc`] or [`handle_json_rpc_with_connection`] (Eg. [`Router::exec`](crate::Router::exec)).
+pub fn connection_status() -> Option<ConnectionStatus> {
+    STATUS.try_with(|status| status.clone()).ok()
+}
+
+/// Returns `false` once the client is known to no longer be waiting for the result of the current request. See [`ConnectionStatus`] for when this is noticed.
+///
+/// Call this before doing expensive work in a resolver to bail out early. Outside of a request this always returns `true`.
+pub fn is_connected() -> bool {
+    STATUS
+        .try_with(|status| status.is_connected())
+        .unwrap_or(true)
+}
+
+/// State which is shared by every request made over a single long-lived connection (Eg. a WebSocket).
+///
+/// Transports should construct one of these when the connection is opened and pass it to every call to [`handle_json_rpc_with_connection`].
+#[derive(Clone)]
+pub struct Connection {
+    id: ConnectionId,
+    closed: Arc<AtomicBool>,
+    bytes_sent: Arc<AtomicU64>,
+    max_bytes_sent: Option<u64>,
+    buffered: Arc<Buffered>,
+    limit: Option<(Arc<Semaphore>, OverloadBehavior)>,
+    // Replaces `limit` when requests are queued by priority
+    queue: Option<Arc<PriorityQueue>>,
+    subscriptions: Option<Arc<Semaphore>>,
+    notifier: Option<Notifier>,
+    // The subscriptions queued by a `RequestQueue` which haven't been registered yet, and whether they were stopped in the meantime
+    starting: Arc<std::sync::Mutex<HashMap<RequestId, bool>>>,
+}
+
+impl Default for Connection {
+    fn default() -> Self {
+        Self {
+            id: ConnectionId::next(),
+            closed: Default::default(),
+            bytes_sent: Default::default(),
+            max_bytes_sent: None,
+            buffered: Default::default(),
+            limit: None,
+            queue: None,
+            subscriptions: None,
+            notifier: None,
+            starting: Default::default(),
+        }
+    }
+}
+
+// The subscription events which have been queued for the transport but not yet flushed to the client.
+#[derive(Default)]
+struct Buffered {
+    bytes: AtomicU64,
+    flushed: Notify,
+    limit: Option<(u64, BufferOverflow)>,
+}
+
+impl Buffered {
+    // Account for an event of `bytes` being queued, returning `false` if it should be dropped instead.
+    // Waiting for room stops once the connection is `closed`, as nothing will be flushed anymore.
+    async fn reserve(&self, bytes: u64, closed: &AtomicBool) -> bool {
+        loop {
+            // Created before checking so a flush (or close) in between isn't missed
+            let flushed = self.flushed.notified();
+            if closed.load(Ordering::Relaxed) {
+                return false;
+            }
+            let reserved = self.bytes.fetch_update(
+                Ordering::Relaxed,
+                Ordering::Relaxed,
+                |buffered| match self.limit {
+                    Some((limit, _)) if buffered > 0 && buffered + bytes > limit => None,
+                    _ => Some(buffered + bytes),
+                },
+            );
+            match (reserved, self.limit) {
+                (Ok(_), _) => return true,
+                (Err(_), Some((_, BufferOverflow::Backpressure))) => flushed.await,
+                (Err(_), _) => return false,
+            }
+        }
+    }
+}
+
+// The length of `value` serialized as JSON, without allocating it.
+fn serialized_len(value: &impl Serialize) -> u64 {
+    struct Counter(u64);
+
+    impl io::Write for Counter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0 += buf.len() as u64;
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let mut counter = Counter(0);
+    let _ = serde_json::to_writer(&mut counter, value);
+    counter.0
+}
+
+// Released when dropped. For subscriptions they are held until the stream ends.
+struct Permits {
+    _request: Option<OwnedSemaphorePermit>,
+    _queued: Option<QueuePermit>,
+    _subscription: Option<OwnedSemaphorePermit>,
+}
+
+impl Permits {
+    // Whether the request was admitted under `Config::max_concurrent_requests`
+    fn limited(&self) -> bool {
+        self._request.is_some()
+    }
+}
+
+// A subscription which hasn't been registered yet. See `RequestQueue`.
+struct Starting<'a>(&'a Connection, RequestId);
+
+impl Starting<'_> {
+    // Mark the subscription as registered, returning whether the client stopped it before it was
+    fn started(self) -> bool {
+        self.0
+            .starting
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(&self.1)
+            .unwrap_or(false)
+    }
+}
+
+impl Drop for Starting<'_> {
+    fn drop(&mut self) {
+        self.0
+            .starting
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(&self.1);
+    }
+}
+
+impl Connection {
+    pub fn new<TCtx, TMeta>(router: &Router<TCtx, TMeta>) -> Self {
+        let (limit, queue) = match (
+            router.config.max_concurrent_requests,
+            router.config.priority_queue,
+        ) {
+            (Some((limit, OverloadBehavior::Queue)), Some((max_queued, aging))) => {
+                (None, Some(PriorityQueue::new(limit, max_queued, aging)))
+            }
+            (limit, _) => (
+                limit.map(|(limit, behavior)| (Arc::new(Semaphore::new(limit)), behavior)),
+                None,
+            ),
+        };
+        Self {
+            id: ConnectionId::next(),
+            closed: Default::default(),
+            bytes_sent: Default::default(),
+            max_bytes_sent: router.config.max_bytes_per_connection,
+            buffered: Arc::new(Buffered {
+                limit: router.config.max_buffered_bytes,
+                ..Default::default()
+            }),
+            limit,
+            queue,
+            subscriptions: router
+                .config
+                .max_subscriptions_per_connection
+                .map(|limit| Arc::new(Semaphore::new(limit))),
+            notifier: None,
+            starting: Default::default(),
+        }
+    }
+
+    /// The unique id of this connection. It's used to cancel the connection's subscriptions with [`ActiveSubscriptions`](crate::ActiveSubscriptions).
+    pub fn id(&self) -> ConnectionId {
+        self.id
+    }
+
+    /// Mark the connection as closed, so [`is_connected`](crate::is_connected) returns `false` for every request still running on it.
+    ///
+    /// Transports should call this when the underlying connection is closed.
+    pub fn close(&self) {
+        self.closed.store(true, Ordering::Relaxed);
+        // Wake the subscriptions waiting for room in the buffer, as it won't be flushed anymore
+        self.buffered.flushed.notify_waiters();
+    }
+
+    /// Record that a frame of `bytes` was sent to the client, returning `false` once the connection has sent more than [`Config::max_bytes_per_connection`](crate::Config::max_bytes_per_connection).
+    ///
+    /// Transports should call this with the size of every serialized frame they send. When it returns `false` the transport should finish sending the current frame and then cleanly close the connection.
+    /// The connection is [closed](Connection::close) when the limit is exceeded, so requests still running on it see [`is_connected`](crate::is_connected) return `false`.
+    pub fn record_sent(&self, bytes: usize) -> bool {
+        let total = self.bytes_sent.fetch_add(bytes as u64, Ordering::Relaxed) + bytes as u64;
+        match self.max_bytes_sent {
+            Some(max) if total > max => {
+                self.close();
+                false
+            }
+            _ => true,
+        }
+    }
+
+    /// The total number of bytes sent to the client over this connection, as recorded by the transport with [`Connection::record_sent`]. Eg. for billing.
+    pub fn bytes_sent(&self) -> u64 {
+        self.bytes_sent.load(Ordering::Relaxed)
+    }
+
+    /// The number of bytes reserved under [`Config::max_buffered_bytes`](crate::Config::max_buffered_bytes) for `frame` when it was queued, which is `0` for frames other than [`ResponseInner::Event`].
+    ///
+    /// Transports should take this as the frame is taken off the queue, before it's rewritten or encoded (which changes it's size), and pass it to [`Connection::record_flushed`] once the frame has been sent or dropped.
+    pub fn reserved_len(frame: &jsonrpc::Response) -> usize {
+        match frame.result {
+            ResponseInner::Event(_) => serialized_len(frame) as usize,
+            _ => 0,
+        }
+    }
+
+    /// Record that a subscription event which had `bytes` [reserved](Connection::reserved_len) has been flushed to the client (or dropped), making room for more events under [`Config::max_buffered_bytes`](crate::Config::max_buffered_bytes).
+    ///
+    /// Transports should call this with the [`Connection::reserved_len`] of every frame they take off the queue, whether or not it was sent, so the buffer doesn't drift.
+    pub fn record_flushed(&self, bytes: usize) {
+        let _ =
+            self.buffered
+                .bytes
+                .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |buffered| {
+                    Some(buffered.saturating_sub(bytes as u64))
+                });
+        self.buffered.flushed.notify_waiters();
+    }
+
+    /// The total size of the subscription events which have been queued for this connection but not yet [flushed](Connection::record_flushed) to the client. Eg. for monitoring slow clients.
+    pub fn buffered_bytes(&self) -> u64 {
+        self.buffered.bytes.load(Ordering::Relaxed)
+    }
+
+    /// Make `notifier` available to the procedures executed on this connection. See [`notifier`](crate::notifier).
+    pub fn with_notifier(mut self, notifier: Notifier) -> Self {
+        self.notifier = Some(notifier);
+        self
+    }
+
+    /// Run `fut` with the connection's id and notifier (if any) in scope.
+    async fn scope<F: Future>(&self, fut: F) -> F::Output {
+        let fut = CONNECTION_ID.scope(self.id, fut);
+        match &self.notifier {
+            Some(notifier) => NOTIFIER.scope(notifier.clone(), fut).await,
+            None => fut.await,
+        }
+    }
+
+    async fn acquire(
+        &self,
+        kind: &ProcedureKind,
+        priority: Priority,
+    ) -> Result<Permits, ExecError> {
+        let subscription = match (kind, &self.subscriptions) {
+            (ProcedureKind::Subscription, Some(semaphore)) => Some(
+                semaphore
+                    .clone()
+                    .try_acquire_owned()
+                    .map_err(|_| ExecError::TooManySubscriptions)?,
+            ),
+            _ => None,
+        };
+
+        let request = match &self.limit {
+            Some((semaphore, OverloadBehavior::Queue)) => Some(
+                semaphore
+                    .clone()
+                    .acquire_owned()
+                    .await
+                    .map_err(|_| ExecError::Overloaded)?,
+            ),
+            Some((semaphore, OverloadBehavior::Reject)) => Some(
+                semaphore
+                    .clone()
+                    .try_acquire_owned()
+                    .map_err(|_| ExecError::Overloaded)?,
+            ),
+            None => None,
+        };
+        let queued = match &self.queue {
+            Some(queue) => Some(queue.acquire(priority).await?),
+            None => None,
+        };
+
+        Ok(Permits {
+            _request: request,
+            _queued: queued,
+            _subscription: subscription,
+        })
+    }
+}
+
+pub async fn handle_json_rpc<TCtx, TMeta>(
+    ctx: TCtx,
+    req: jsonrpc::Request,
+    router: &Arc<Router<TCtx, TMeta>>,
+    sender: &mut Sender<'_>,
+    subscriptions: &mut SubscriptionMap<'_>,
+) where
+    TCtx: 'static,
+{
+    handle_json_rpc_with_connection(
+        ctx,
+        req,
+        router,
+        sender,
+        subscriptions,
+        &Connection::default(),
+    )
+    .await
+}
+
+/// Like [`handle_json_rpc`] but the request counts against the limits of the given [`Connection`] and can use it's [`Notifier`].
+pub async fn handle_json_rpc_with_connection<TCtx, TMeta>(
+    ctx: TCtx,
+    req: jsonrpc::Request,
+    router: &Arc<Router<TCtx, TMeta>>,
+    sender: &mut Sender<'_>,
+    subscriptions: &mut SubscriptionMap<'_>,
+    connection: &Connection,
+) where
+    TCtx: 'static,
+{
+    let (status, guard) = ConnectionStatus::new(connection);
+    connection
+        .scope(STATUS.scope(
+            status.clone(),
+            handle_request(
+                ctx,
+                req,
+                router,
+                sender,
+                subscriptions,
+                connection,
+                (status, guard),
+            ),
+        ))
+        .await
+}
+
+/// Executes the requests received over a long-lived connection (Eg. a WebSocket) in the order they were received.
+///
+/// Queries are executed concurrently, but every other request waits for the ones before it to start: a mutation waits for the previous request and is completed before the next one starts, and a subscription is registered before the next request starts, so a `subscriptionStop` sent right after it always finds it.
+/// A `subscriptionStop` is applied as soon as it's pushed, even while earlier requests are waiting for room under [`Config::max_concurrent_requests`](crate::Config::max_concurrent_requests), so a client can always free up it's connection. A queued subscription which is stopped before it's registered is acknowledged with a `cancelled` frame once it is.
+pub struct RequestQueue<TCtx> {
+    requests: mpsc::UnboundedSender<(TCtx, jsonrpc::Request)>,
+    subscriptions: Arc<Mutex<HashMap<RequestId, oneshot::Sender<()>>>>,
+    connection: Connection,
+}
+
+impl<TCtx: Send + 'static> RequestQueue<TCtx> {
+    /// Construct a queue for the requests of `connection`, whose responses are sent to `sender`.
+    ///
+    /// The returned future executes the requests until the queue is dropped. It should be spawned within the connection's [`with_transport`] and [`with_headers`] scopes, which the queries it executes concurrently inherit.
+    pub fn new<TMeta: Send + Sync + 'static>(
+        router: Arc<Router<TCtx, TMeta>>,
+        connection: Connection,
+        subscriptions: Arc<Mutex<HashMap<RequestId, oneshot::Sender<()>>>>,
+        sender: mpsc::Sender<jsonrpc::Response>,
+    ) -> (Self, impl Future<Output = ()> + Send + 'static)
+    where
+        TCtx: Sync,
+    {
+        let (requests, mut rx) = mpsc::unbounded_channel::<(TCtx, jsonrpc::Request)>();
+        let queue = Self {
+            requests,
+            subscriptions: subscriptions.clone(),
+            connection: connection.clone(),
+        };
+
+        let execute = async move {
+            let runtime = router.config.runtime_or_default();
+            while let Some((ctx, req)) = rx.recv().await {
+                let concurrent = matches!(req.inner, RequestInner::Query { .. });
+                let (router, subscriptions, connection, mut sender) = (
+                    router.clone(),
+                    subscriptions.clone(),
+                    connection.clone(),
+                    sender.clone(),
+                );
+                let fut = async move {
+                    handle_json_rpc_with_connection(
+                        ctx,
+                        req,
+                        &router,
+                        &mut Sender::Channel(&mut sender),
+                        &mut SubscriptionMap::Mutex(&subscriptions),
+                        &connection,
+                    )
+                    .await
+                };
+                match &runtime {
+                    Some(runtime) if concurrent => runtime.spawn(Box::pin(with_transport(
+                        transport(),
+                        with_headers(headers(), fut),
+                    ))),
+                    _ => fut.await,
+                }
+            }
+        };
+        (queue, execute)
+    }
+
+    /// Queue `req` to be executed with `ctx`.
+    pub async fn push(&self, ctx: TCtx, req: jsonrpc::Request) {
+        match &req.inner {
+            RequestInner::SubscriptionStop { input } => {
+                // A subscription which is still queued is stopped once it's registered.
+                // It's only removed from `starting` after it's registered, so if it's not there it can be stopped now.
+                if let Some(stopped) = self
+                    .connection
+                    .starting
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .get_mut(input)
+                {
+                    *stopped = true;
+                    return;
+                }
+                if let Some(shutdown_tx) = self.subscriptions.lock().await.remove(input) {
+                    let _ = shutdown_tx.send(());
+                }
+                return;
+            }
+            RequestInner::Subscription { input: (id, _), .. } => {
+                self.connection
+                    .starting
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .entry(id.clone())
+                    .or_insert(false);
+            }
+            _ => {}
+        }
+        let _ = self.requests.send((ctx, req));
+    }
+}
+
+async fn handle_request<TCtx, TMeta>(
+    ctx: TCtx,
+    req: jsonrpc::Request,
+    router: &Arc<Router<TCtx, TMeta>>,
+    sender: &mut Sender<'_>,
+    subscriptions: &mut SubscriptionMap<'_>,
+    connection: &Connection,
+    // A subscription takes over the guard, so it only ends when the subscription does
+    (status, guard): (ConnectionStatus, RequestGuard),
+) where
+    TCtx: 'static,
+{
+    if req.jsonrpc.is_some() && req.jsonrpc.as_deref() != Some("2.0") {
+        let _ = sender
+            .send(jsonrpc::Response {
+                jsonrpc: "2.0",
+                id: req.id.clone(),
+                result: ResponseInner::Error(render_error(
+                    router,
+                    ExecError::InvalidJsonRpcVersion,
+                )),
+            })
+            .await
+            .map_err(|_err| {
+                #[cfg(feature = "tracing")]
+                tracing::error!("Failed to send response: {}", _err);
+            });
+    }
+
+    let (path, input, kind, sub_id) = match req.inner {
+        RequestInner::Query { path, input } => (path, input, ProcedureKind::Query, None),
+        RequestInner::Mutation { path, input } => (path, input, ProcedureKind::Mutation, None),
+        RequestInner::Subscription { path, input } => {
+            (path, input.1, ProcedureKind::Subscription, Some(input.0))
+        }
+        RequestInner::SubscriptionStop { input } => {
+            if let Some(shutdown_tx) = subscriptions.remove(&input).await {
+                let _ = shutdown_tx.send(());
+            }
+            return;
+        }
+        RequestInner::Method { method, params } => {
+            let parsed = match &router.config.method_parser {
+                Some(parse) => parse(&method).map(|(kind, path)| (kind, path, params)),
+                None => openrpc::parse_method(&method)
+                    .map(|(kind, path)| (kind, path, openrpc::method_input(params))),
+            };
+            match parsed {
+                // The request's id is used as the subscription's id as there's nowhere else to put it
+                Some((ProcedureKind::Subscription, path, params)) => (
+                    path,
+                    params,
+                    ProcedureKind::Subscription,
+                    Some(req.id.clone()),
+                ),
+                Some((kind, path, params)) => (path, params, kind, None),
+                None => {
+                    let _ = sender
+                        .send(jsonrpc::Response {
+                            jsonrpc: "2.0",
+                            id: req.id,
+                            result: ResponseInner::Error(render_error(
+                                router,
+                                ExecError::UnsupportedMethod(method),
+                            )),
+                        })
+                        .await
+                        .map_err(|_err| {
+                            #[cfg(feature = "tracing")]
+                            tracing::error!("Failed to send response: {}", _err);
+                        });
+                    return;
+                }
+            }
+        }
+    };
+
+    let mut starting = sub_id.clone().map(|id| Starting(connection, id));
+
+    // Held until the request completes, or for subscriptions until the stream ends.
+    let priority = router.priorities.get(&path).copied().unwrap_or_default();
+    let started = Instant::now();
+    let permits = match connection.acquire(&kind, priority).await {
+        Ok(permits) => permits,
+        Err(err) => {
+            let _ = sender
+                .send(jsonrpc::Response {
+                    jsonrpc: "2.0",
+                    id: req.id,
+                    result: ResponseInner::Error(render_error(router, err)),
+                })
+                .await
+                .map_err(|_err| {
+                    #[cfg(feature = "tracing")]
+                    tracing::error!("Failed to send response: {}", _err);
+                });
+            return;
+        }
+    };
+
+    if let Some(on_queue_wait) = router
+        .config
+        .on_queue_wait
+        .as_ref()
+        .filter(|_| permits.limited())
+    {
+        on_queue_wait(kind, &path, started.elapsed());
+    }
+
+    let no_content = router.no_content(kind, &path);
+    let result = match router.call(ctx, kind, path, input.unwrap_or(Value::Null)) {
+        Ok(op) => match forward_frames(op.into_value_or_stream(), &req.id, sender).await {
+            Ok(ValueOrStream::Value(_)) if no_content => ResponseInner::NoContent,
+            Ok(ValueOrStream::Value(v)) => ResponseInner::Response(v),
+            Ok(ValueOrStream::Stream(mut stream)) => {
+                if matches!(sender, Sender::Response(_))
+                    || matches!(subscriptions, SubscriptionMap::None)
+                {
+                    let _ = sender
+                        .send(jsonrpc::Response {
+                            jsonrpc: "2.0",
+                            id: req.id.clone(),
+                            result: ResponseInner::Error(render_error(
+                                router,
+                                ExecError::UnsupportedMethod("Subscription".to_string()),
+                            )),
+                        })
+                        .await
+                        .map_err(|_err| {
+                            #[cfg(feature = "tracing")]
+                            tracing::error!("Failed to send response: {}", _err);
+                        });
+                    return;
+                }
+
+                if let Some(id) = sub_id {
+                    if matches!(id, RequestId::Null) {
+                        let _ = sender
+                            .send(jsonrpc::Response {
+                                jsonrpc: "2.0",
+                                id: req.id.clone(),
+                                result: ResponseInner::Error(render_error(
+                                    router,
+                                    ExecError::ErrSubscriptionWithNullId,
+                                )),
+                            })
+                            .await
+                            .map_err(|_err| {
+                                #[cfg(feature = "tracing")]
+                                tracing::error!("Failed to send response: {}", _err);
+                            });
+                    } else if subscriptions.has_subscription(&id).await {
+                        let _ = sender
+                            .send(jsonrpc::Response {
+                                jsonrpc: "2.0",
+                                id: req.id.clone(),
+                                result: ResponseInner::Error(render_error(
+                                    router,
+                                    ExecError::ErrSubscriptionDuplicateId,
+                                )),
+                            })
+                            .await
+                            .map_err(|_err| {
+                                #[cfg(feature = "tracing")]
+                                tracing::error!("Failed to send response: {}", _err);
+                            });
+                    }
+
+                    // An already completed stream is completed immediately instead of being driven by a task.
+                    // Only a stream which won't yield any items is polled here, as `stream::pending` reports the same size hint.
+                    if stream.size_hint() == (0, Some(0))
+                        && matches!(
+                            stream
+                                .as_mut()
+                                .poll_next(&mut Context::from_waker(noop_waker_ref())),
+                            Poll::Ready(None)
+                        )
+                    {
+                        unsubscribe(stream).await;
+                        let _ = sender
+                            .send(jsonrpc::Response {
+                                jsonrpc: "2.0",
+                                id,
+                                result: ResponseInner::Complete,
+                            })
+                            .await
+                            .map_err(|_err| {
+                                #[cfg(feature = "tracing")]
+                                tracing::error!("Failed to send response: {}", _err);
+                            });
+                        return;
+                    }
+
+                    let Some(runtime) = router.config.runtime_or_default() else {
+                        let _ = sender
+                            .send(jsonrpc::Response {
+                                jsonrpc: "2.0",
+                                id: req.id.clone(),
+                                result: ResponseInner::Error(render_error(
+                                    router,
+                                    ExecError::NoRuntime,
+                                )),
+                            })
+                            .await
+                            .map_err(|_err| {
+                                #[cfg(feature = "tracing")]
+                                tracing::error!("Failed to send response: {}", _err);
+                            });
+                        return;
+                    };
+
+                    let (shutdown_tx, mut shutdown_rx) = oneshot::channel();
+                    subscriptions.insert(id.clone(), shutdown_tx).await;
+                    // The client stopped it while it was queued
+                    if starting.take().is_some_and(Starting::started) {
+                        if let Some(shutdown_tx) = subscriptions.remove(&id).await {
+                            let _ = shutdown_tx.send(());
+                        }
+                    }
+                    let (registration, mut cancel_rx) = router
+                        .active_subscriptions
+                        .register(connection.id, id.clone());
+                    let mut sender2 = sender.sender2();
+                    let connection = connection.clone();
+                    let buffered = connection.buffered.clone();
+                    let closed = connection.closed.clone();
+                    let (verbosity, on_error, retry_after) = (
+                        router.config.error_verbosity,
+                        router.config.on_error.clone(),
+                        router.config.retry_after.clone(),
+                    );
+                    runtime.spawn(Box::pin(with_transport(transport(), with_headers(headers(), STATUS.scope(status, async move {
+                        connection.scope(async move {
+                        let _permits = permits;
+                        let _guard = guard;
+                        let _registration = registration;
+                        // Frames sent while polling the stream (Eg. it's trailer)
+                        let (frames_tx, mut frames_rx) = mpsc::unbounded_channel();
+                        FRAMES.scope(frames_tx, async move {
+                        // Set when the client stopped the subscription, as opposed to it being dropped with the connection
+                        let mut stopped = false;
+                        // Set when the stream completed by itself
+                        let mut completed = false;
+                        // An event waiting for room in the connection's buffer, and it's size
+                        let mut pending: Option<(jsonrpc::Response, u64)> = None;
+                        loop {
+                            let pending_len = pending.as_ref().map_or(0, |(_, len)| *len);
+                            tokio::select! {
+                                biased; // Note: Order matters
+                                result = &mut shutdown_rx => {
+                                    #[cfg(feature = "tracing")]
+                                    tracing::debug!("Removing subscription with id '{:?}'", id);
+                                    stopped = result.is_ok();
+                                    break;
+                                }
+                                Ok(err) = &mut cancel_rx => {
+                                    #[cfg(feature = "tracing")]
+                                    tracing::debug!("Cancelling subscription with id '{:?}'", id);
+                                    let _ = sender2.send(jsonrpc::Response {
+                                        jsonrpc: "2.0",
+                                        id: id.clone(),
+                                        result: ResponseInner::Error(render_with_hint(ExecError::from(err), verbosity, on_error.as_ref(), retry_after.as_ref())),
+                                    })
+                                    .await
+                                    .map_err(|_err| {
+                                        #[cfg(feature = "tracing")]
+                                        tracing::error!("Failed to send response: {:?}", _err);
+                                    });
+                                    break;
+                                }
+                                Some(frame) = frames_rx.recv() => {
+                                    let _ = sender2.send(jsonrpc::Response {
+                                        jsonrpc: "2.0",
+                                        id: id.clone(),
+                                        result: frame,
+                                    })
+                                    .await
+                                    .map_err(|_err| {
+                                        #[cfg(feature = "tracing")]
+                                        tracing::error!("Failed to send response: {:?}", _err);
+                                    });
+                                }
+                                // Waited for in the loop so a stop or cancellation isn't held up by backpressure
+                                reserved = buffered.reserve(pending_len, &closed), if pending.is_some() => {
+                                    let Some((response, _)) = pending.take() else { continue };
+                                    if !reserved {
+                                        #[cfg(feature = "tracing")]
+                                        tracing::debug!("Dropping event of subscription with id '{:?}' as it's connection's buffer is full", id);
+                                        continue;
+                                    }
+
+                                    let _ = sender2.send(response)
+                                    .await
+                                    .map_err(|_err| {
+                                        #[cfg(feature = "tracing")]
+                                        tracing::error!("Failed to send response: {:?}", _err);
+                                    });
+                                }
+                                v = stream.next(), if pending.is_none() => {
+                                    match v {
+                                        Some(Ok(v)) => {
+                                            let response = jsonrpc::Response {
+                                                jsonrpc: "2.0",
+                                                id: id.clone(),
+                                                result: ResponseInner::Event(v),
+                                            };
+                                            let len = serialized_len(&response);
+                                            pending = Some((response, len));
+                                        }
+                                        Some(Err(_err)) => {
+                                           #[cfg(feature = "tracing")]
+                                            tracing::error!("Subscription error: {:?}", _err);
+                                        }
+                                        None => {
+                                            completed = true;
+                                            break;
+                                        }
+                                    }
+                                }
+                            }
+                        }
+
+                        // The frames sent as the stream completed. A `lagged` frame ends the subscription instead of `complete`.
+                        while let Ok(frame) = frames_rx.try_recv() {
+                            completed &= !matches!(frame, ResponseInner::Lagged);
+                            let _ = sender2.send(jsonrpc::Response {
+                                jsonrpc: "2.0",
+                                id: id.clone(),
+                                result: frame,
+                            })
+                            .await
+                            .map_err(|_err| {
+                                #[cfg(feature = "tracing")]
+                                tracing::error!("Failed to send response: {:?}", _err);
+                            });
+                        }
+
+                        // Acknowledge the stop once the subscription has been cleaned up, which runs it's `on_unsubscribe` hook
+                        unsubscribe(stream).await;
+                        if stopped || completed {
+                            let _ = sender2.send(jsonrpc::Response {
+                                jsonrpc: "2.0",
+                                id: id.clone(),
+                                result: match stopped {
+                                    true => ResponseInner::Cancelled,
+                                    false => ResponseInner::Complete,
+                                },
+                            })
+                            .await
+                            .map_err(|_err| {
+                                #[cfg(feature = "tracing")]
+                                tracing::error!("Failed to send response: {:?}", _err);
+                            });
+                        }
+                        }).await
+                        }).await
+                    })))));
+                }
+
+                return;
+            }
+            Err(err) => {
+                #[cfg(feature = "tracing")]
+                tracing::error!("Error executing operation: {:?}", err);
+
+                ResponseInner::Error(render_error(router, err))
+            }
+        },
+        Err(err) => {
+            #[cfg(feature = "tracing")]
+            tracing::error!("Error executing operation: {:?}", err);
+            ResponseInner::Error(render_error(router, err))
+        }
+    };
+
+    let _ = sender
+        .send(jsonrpc::Response {
+            jsonrpc: "2.0",
+            id: req.id,
+            result,
+        })
+        .await
+        .map_err(|_err| {
+            #[cfg(feature = "tracing")]
             tracing::error!("Failed to send response: {:?}", _err);
         });
 }
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+// Some of the tests use the default runtime, so their helpers are unused without it
+#[cfg_attr(not(feature = "runtime-tokio"), allow(unused))]
+mod tests {
+    use std::{sync::Arc, time::Duration};
+
+    use futures::future::{join_all, Either};
+    use tokio::sync::{mpsc, Mutex, Semaphore};
+
+    use super::*;
+    use crate::{
+        internal::jsonrpc::{JsonRPCError, Request, RequestId, RequestInner, ResponseInner},
+        BufferOverflow, Config, ErrorKind, OverloadBehavior, Router,
+    };
+
+    fn query(id: u32) -> Request {
+        Request {
+            jsonrpc: None,
+            id: RequestId::Number(id),
+            inner: RequestInner::Query {
+                path: "wait".into(),
+                input: None,
+            },
+        }
+    }
+
+    #[tokio::test]
+    async fn test_queue_wait() {
+        let gate = Arc::new(Semaphore::new(0));
+        let waits = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let router = Arc::new(
+            <Router>::new()
+                .config(
+                    Config::new()
+                        .max_concurrent_requests(1, OverloadBehavior::Queue)
+                        .on_queue_wait({
+                            let waits = waits.clone();
+                            move |kind, key, wait| {
+                                waits.lock().unwrap().push((kind, key.to_string(), wait))
+                            }
+                        }),
+                )
+                .query("wait", {
+                    let gate = gate.clone();
+                    move |t| {
+                        let gate = gate.clone();
+                        t(move |_, _: ()| {
+                            let gate = gate.clone();
+                            async move { gate.acquire().await.unwrap().forget() }
+                        })
+                    }
+                })
+                .build(),
+        );
+
+        let connection = Connection::new(&router);
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let requests = join_all((0..2).map(|id| {
+            let (router, connection, mut tx) = (router.clone(), connection.clone(), tx.clone());
+            async move {
+                handle_json_rpc_with_connection(
+                    (),
+                    query(id),
+                    &router,
+                    &mut Sender::ResponseChannel(&mut tx),
+                    &mut SubscriptionMap::None,
+                    &connection,
+                )
+                .await
+            }
+        }));
+        // The first request holds the only slot for 50ms, the second waits for it
+        tokio::join!(requests, async {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            gate.add_permits(2);
+        });
+
+        let mut waits = waits.lock().unwrap().clone();
+        waits.sort_by_key(|(_, _, wait)| *wait);
+        assert!(matches!(
+            &waits[..],
+            [(ProcedureKind::Query, a, first), (ProcedureKind::Query, b, second)]
+                if a == "wait" && b == "wait"
+                    && *first < Duration::from_millis(50)
+                    && *second >= Duration::from_millis(50)
+        ));
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "runtime-tokio")]
+    async fn test_request_queue_order() {
+        let gate = Arc::new(Semaphore::new(0));
+        let applied = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let router = Arc::new(
+            <Router>::new()
+                .config(Config::new().max_concurrent_requests(1, OverloadBehavior::Queue))
+                .query("wait", {
+                    let gate = gate.clone();
+                    move |t| {
+                        let gate = gate.clone();
+                        t(move |_, _: ()| {
+                            let gate = gate.clone();
+                            async move { gate.acquire().await.unwrap().forget() }
+                        })
+                    }
+                })
+                .mutation("apply", {
+                    let applied = applied.clone();
+                    move |t| {
+                        let applied = applied.clone();
+                        t(move |_, n: u32| {
+                            let applied = applied.clone();
+                            async move {
+                                // The first mutation is slower, so it'd finish last if they ran concurrently
+                                for _ in 0..(10 - n) {
+                                    tokio::task::yield_now().await;
+                                }
+                                applied.lock().unwrap().push(n);
+                            }
+                        })
+                    }
+                })
+                .subscription("events", |t| {
+                    t(|_, _: ()| futures::stream::pending::<u32>())
+                })
+                .build(),
+        );
+
+        let connection = Connection::new(&router);
+        let (tx, mut rx) = mpsc::channel(100);
+        let (queue, execute) =
+            RequestQueue::new(router.clone(), connection, Default::default(), tx);
+        tokio::spawn(execute);
+
+        // The query holds the only slot, so the subscription is stopped while it's still queued
+        queue.push((), query(0)).await;
+        queue
+            .push(
+                (),
+                Request {
+                    jsonrpc: None,
+                    id: RequestId::Null,
+                    inner: RequestInner::Subscription {
+                        path: "events".into(),
+                        input: (RequestId::Number(1), None),
+                    },
+                },
+            )
+            .await;
+        queue
+            .push(
+                (),
+                Request {
+                    jsonrpc: None,
+                    id: RequestId::Null,
+                    inner: RequestInner::SubscriptionStop {
+                        input: RequestId::Number(1),
+                    },
+                },
+            )
+            .await;
+        for n in 0..2 {
+            queue
+                .push(
+                    (),
+                    Request {
+                        jsonrpc: None,
+                        id: RequestId::Number(2 + n),
+                        inner: RequestInner::Mutation {
+                            path: "apply".into(),
+                            input: Some(n.into()),
+                        },
+                    },
+                )
+                .await;
+        }
+        gate.add_permits(1);
+
+        let mut responses = Vec::new();
+        while responses.len() < 4 {
+            let resp = tokio::time::timeout(Duration::from_secs(1), rx.recv())
+                .await
+                .unwrap()
+                .unwrap();
+            responses.push((resp.id, resp.result));
+        }
+        // Queries aren't ordered, but the mutations are
+        let position = |id| {
+            responses
+                .iter()
+                .position(|(resp, _)| *resp == RequestId::Number(id))
+        };
+        assert!(position(2) < position(3));
+        responses.sort_by_key(|(id, _)| format!("{id:?}"));
+        assert!(matches!(
+            &responses[..],
+            [
+                (RequestId::Number(0), ResponseInner::Response(_)),
+                (RequestId::Number(1), ResponseInner::Cancelled),
+                (RequestId::Number(2), ResponseInner::Response(_)),
+                (RequestId::Number(3), ResponseInner::Response(_)),
+            ]
+        ));
+        assert_eq!(*applied.lock().unwrap(), [0, 1]);
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "runtime-tokio")]
+    async fn test_connection_concurrency_limit() {
+        let gate = Arc::new(Semaphore::new(0));
+        let router = Arc::new(
+            <Router>::new()
+                .config(Config::new().max_concurrent_requests(2, OverloadBehavior::Reject))
+                .query("wait", {
+                    let gate = gate.clone();
+                    move |t| {
+                        let gate = gate.clone();
+                        t(move |_, _: ()| {
+                            let gate = gate.clone();
+                            async move { gate.acquire().await.unwrap().forget() }
+                        })
+                    }
+                })
+                .subscription("pending", |t| {
+                    t(|_, _: ()| futures::stream::pending::<i32>())
+                })
+                .build(),
+        );
+
+        let connection = Connection::new(&router);
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let requests = join_all((0..10).map(|id| {
+            let (router, connection, mut tx) = (router.clone(), connection.clone(), tx.clone());
+            async move {
+                handle_json_rpc_with_connection(
+                    (),
+                    query(id),
+                    &router,
+                    &mut Sender::ResponseChannel(&mut tx),
+                    &mut SubscriptionMap::None,
+                    &connection,
+                )
+                .await
+            }
+        }));
+
+        // Everything past the limit is rejected while the first two are blocked
+        let mut rejected = 0;
+        tokio::join!(requests, async {
+            while rejected < 8 {
+                assert!(matches!(
+                    rx.recv().await.unwrap().result,
+                    ResponseInner::Error(JsonRPCError {
+                        kind: ErrorKind::RateLimited,
+                        ..
+                    })
+                ));
+                rejected += 1;
+            }
+            gate.add_permits(2);
+        });
+        for _ in 0..2 {
+            assert!(matches!(
+                rx.recv().await.unwrap().result,
+                ResponseInner::Response(_)
+            ));
+        }
+
+        // An active subscription holds a permit until it's stopped
+        let subscriptions = Mutex::new(Default::default());
+        let handle = |inner| {
+            let (router, connection, mut tx) = (router.clone(), connection.clone(), tx.clone());
+            let subscriptions = &subscriptions;
+            async move {
+                handle_json_rpc_with_connection(
+                    (),
+                    Request {
+                        jsonrpc: None,
+                        id: RequestId::Null,
+                        inner,
+                    },
+                    &router,
+                    &mut Sender::ResponseChannel(&mut tx),
+                    &mut SubscriptionMap::Mutex(subscriptions),
+                    &connection,
+                )
+                .await
+            }
+        };
+        for id in 0..2 {
+            handle(RequestInner::Subscription {
+                path: "pending".into(),
+                input: (RequestId::Number(id), None),
+            })
+            .await;
+        }
+        handle(query(0).inner).await;
+        assert!(matches!(
+            rx.recv().await.unwrap().result,
+            ResponseInner::Error(_)
+        ));
+
+        handle(RequestInner::SubscriptionStop {
+            input: RequestId::Number(0),
+        })
+        .await;
+        assert!(matches!(
+            rx.recv().await.unwrap().result,
+            ResponseInner::Cancelled
+        ));
+        tokio::task::yield_now().await;
+        gate.add_permits(1);
+        handle(query(0).inner).await;
+        assert!(matches!(
+            rx.recv().await.unwrap().result,
+            ResponseInner::Response(_)
+        ));
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "runtime-tokio")]
+    async fn test_subscription_stop_acknowledged() {
+        let unsubscribed = Arc::new(std::sync::Mutex::new(false));
+        let router =
+            Arc::new(
+                <Router>::new()
+                    .subscription("numbers", {
+                        let unsubscribed = unsubscribed.clone();
+                        move |t| {
+                            let unsubscribed = unsubscribed.clone();
+                            t(|_, _: ()| {
+                                futures::stream::iter(0..2).chain(futures::stream::pending())
+                            })
+                            .on_unsubscribe(move |_, _| {
+                                *unsubscribed.lock().unwrap() = true;
+                                async {}
+                            })
+                        }
+                    })
+                    .build(),
+            );
+
+        let connection = Connection::new(&router);
+        let subscriptions = Mutex::new(Default::default());
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let handle = |inner| {
+            let (router, connection, mut tx) = (router.clone(), connection.clone(), tx.clone());
+            let subscriptions = &subscriptions;
+            async move {
+                handle_json_rpc_with_connection(
+                    (),
+                    Request {
+                        jsonrpc: None,
+                        id: RequestId::Null,
+                        inner,
+                    },
+                    &router,
+                    &mut Sender::ResponseChannel(&mut tx),
+                    &mut SubscriptionMap::Mutex(subscriptions),
+                    &connection,
+                )
+                .await
+            }
+        };
+
+        handle(RequestInner::Subscription {
+            path: "numbers".into(),
+            input: (RequestId::Number(1), None),
+        })
+        .await;
+        for i in 0..2 {
+            assert!(matches!(rx.recv().await.unwrap().result, ResponseInner::Event(v) if v == i));
+        }
+
+        handle(RequestInner::SubscriptionStop {
+            input: RequestId::Number(1),
+        })
+        .await;
+        let response = rx.recv().await.unwrap();
+        assert_eq!(response.id, RequestId::Number(1));
+        assert!(matches!(response.result, ResponseInner::Cancelled));
+        // The subscription was cleaned up before it was acknowledged
+        assert!(*unsubscribed.lock().unwrap());
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "runtime-tokio")]
+    async fn test_subscription_completes_immediately() {
+        let unsubscribed = Arc::new(std::sync::Mutex::new(0));
+        let router = Arc::new(
+            Router::<bool>::new()
+                .subscription("visible", {
+                    let unsubscribed = unsubscribed.clone();
+                    move |t| {
+                        let unsubscribed = unsubscribed.clone();
+                        t(|can_see, _: ()| match can_see {
+                            true => Either::Left(futures::stream::iter([1])),
+                            false => Either::Right(futures::stream::empty()),
+                        })
+                        .on_unsubscribe(move |_, _| {
+                            *unsubscribed.lock().unwrap() += 1;
+                            async {}
+                        })
+                    }
+                })
+                .build(),
+        );
+
+        let connection = Connection::new(&router);
+        let subscriptions = Mutex::new(Default::default());
+        let (mut tx, mut rx) = mpsc::unbounded_channel();
+        handle_json_rpc_with_connection(
+            false,
+            Request {
+                jsonrpc: None,
+                id: RequestId::Null,
+                inner: RequestInner::Subscription {
+                    path: "visible".into(),
+                    input: (RequestId::Number(1), None),
+                },
+            },
+            &router,
+            &mut Sender::ResponseChannel(&mut tx),
+            &mut SubscriptionMap::Mutex(&subscriptions),
+            &connection,
+        )
+        .await;
+
+        // Completed before the request returned, without registering the subscription
+        let response = rx.try_recv().unwrap();
+        assert_eq!(response.id, RequestId::Number(1));
+        assert!(matches!(response.result, ResponseInner::Complete));
+        assert!(subscriptions.lock().await.is_empty());
+        assert_eq!(*unsubscribed.lock().unwrap(), 1);
+
+        // A subscription with events completes after them
+        handle_json_rpc_with_connection(
+            true,
+            Request {
+                jsonrpc: None,
+                id: RequestId::Null,
+                inner: RequestInner::Subscription {
+                    path: "visible".into(),
+                    input: (RequestId::Number(2), None),
+                },
+            },
+            &router,
+            &mut Sender::ResponseChannel(&mut tx),
+            &mut SubscriptionMap::Mutex(&subscriptions),
+            &connection,
+        )
+        .await;
+        assert!(rx.try_recv().is_err());
+        assert!(matches!(rx.recv().await.unwrap().result, ResponseInner::Event(v) if v == 1));
+        assert!(matches!(
+            rx.recv().await.unwrap().result,
+            ResponseInner::Complete
+        ));
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "runtime-tokio")]
+    async fn test_subscriptions_per_connection_limit() {
+        let router = Arc::new(
+            <Router>::new()
+                .config(Config::new().max_subscriptions_per_connection(2))
+                .subscription("pending", |t| {
+                    t(|_, _: ()| futures::stream::pending::<i32>())
+                })
+                .subscription("empty", |t| t(|_, _: ()| futures::stream::empty::<i32>()))
+                .build(),
+        );
+
+        let connection = Connection::new(&router);
+        let subscriptions = Mutex::new(Default::default());
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let handle = |inner| {
+            let (router, connection, mut tx) = (router.clone(), connection.clone(), tx.clone());
+            let subscriptions = &subscriptions;
+            async move {
+                handle_json_rpc_with_connection(
+                    (),
+                    Request {
+                        jsonrpc: None,
+                        id: RequestId::Null,
+                        inner,
+                    },
+                    &router,
+                    &mut Sender::ResponseChannel(&mut tx),
+                    &mut SubscriptionMap::Mutex(subscriptions),
+                    &connection,
+                )
+                .await
+            }
+        };
+        let subscribe = |path: &str, id| RequestInner::Subscription {
+            path: path.into(),
+            input: (RequestId::Number(id), None),
+        };
+
+        // Subscriptions which end by themselves don't count
+        handle(subscribe("empty", 0)).await;
+        tokio::task::yield_now().await;
+        assert!(matches!(
+            rx.try_recv().unwrap().result,
+            ResponseInner::Complete
+        ));
+
+        handle(subscribe("pending", 1)).await;
+        handle(subscribe("pending", 2)).await;
+        assert!(rx.try_recv().is_err());
+        handle(subscribe("pending", 3)).await;
+        assert!(matches!(
+            rx.recv().await.unwrap().result,
+            ResponseInner::Error(JsonRPCError {
+                kind: ErrorKind::RateLimited,
+                ..
+            })
+        ));
+
+        // Stopping a subscription frees it's slot
+        handle(RequestInner::SubscriptionStop {
+            input: RequestId::Number(1),
+        })
+        .await;
+        assert!(matches!(
+            rx.recv().await.unwrap().result,
+            ResponseInner::Cancelled
+        ));
+        tokio::task::yield_now().await;
+        handle(subscribe("pending", 3)).await;
+        assert!(rx.try_recv().is_err());
+
+        // As does the connection closing
+        subscriptions.lock().await.clear();
+        tokio::task::yield_now().await;
+        handle(subscribe("pending", 4)).await;
+        handle(subscribe("pending", 5)).await;
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_transport() {
+        let router = Arc::new(
+            <Router>::new()
+                .query("transport", |t| {
+                    t(|_, _: ()| format!("{:?}", crate::transport()))
+                })
+                .build(),
+        );
+
+        assert_eq!(
+            router
+                .exec((), crate::ExecKind::Query, "transport".into(), None)
+                .await
+                .unwrap(),
+            "InProcess"
+        );
+
+        let mut resp = Sender::Response(None);
+        with_transport(
+            Transport::Http,
+            handle_json_rpc(
+                (),
+                Request {
+                    jsonrpc: None,
+                    id: RequestId::Null,
+                    inner: RequestInner::Query {
+                        path: "transport".into(),
+                        input: None,
+                    },
+                },
+                &router,
+                &mut resp,
+                &mut SubscriptionMap::None,
+            ),
+        )
+        .await;
+        assert!(matches!(
+            resp,
+            Sender::Response(Some(jsonrpc::Response {
+                result: ResponseInner::Response(serde_json::Value::String(v)),
+                ..
+            })) if v == "Http"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_headers() {
+        let router = Arc::new(
+            <Router>::new()
+                .query("language", |t| {
+                    t(|_, _: ()| crate::headers().get("Accept-Language").map(String::from))
+                })
+                .build(),
+        );
+
+        // Requests made in process have no headers
+        assert_eq!(
+            router
+                .exec((), crate::ExecKind::Query, "language".into(), None)
+                .await
+                .unwrap(),
+            serde_json::Value::Null
+        );
+
+        let headers = Headers::from_iter([
+            ("accept-language", "en-AU"),
+            ("X-Custom", "a"),
+            ("x-custom", "b"),
+        ]);
+        assert_eq!(headers.get_all("X-CUSTOM").collect::<Vec<_>>(), ["a", "b"]);
+
+        let mut resp = Sender::Response(None);
+        with_headers(
+            headers,
+            handle_json_rpc(
+                (),
+                Request {
+                    jsonrpc: None,
+                    id: RequestId::Null,
+                    inner: RequestInner::Query {
+                        path: "language".into(),
+                        input: None,
+                    },
+                },
+                &router,
+                &mut resp,
+                &mut SubscriptionMap::None,
+            ),
+        )
+        .await;
+        assert!(matches!(
+            resp,
+            Sender::Response(Some(jsonrpc::Response {
+                result: ResponseInner::Response(serde_json::Value::String(v)),
+                ..
+            })) if v == "en-AU"
+        ));
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "runtime-tokio")]
+    async fn test_max_buffered_bytes() {
+        let event = |data: i32| jsonrpc::Response {
+            jsonrpc: "2.0",
+            id: RequestId::Number(1),
+            result: ResponseInner::Event(data.into()),
+        };
+        let size = serialized_len(&event(0));
+        let next_event = |rx: &mut mpsc::UnboundedReceiver<jsonrpc::Response>, data: i32| matches!(rx.try_recv(), Ok(jsonrpc::Response { result: ResponseInner::Event(v), .. }) if v == data);
+
+        for behavior in [BufferOverflow::Drop, BufferOverflow::Backpressure] {
+            let router = Arc::new(
+                <Router>::new()
+                    .config(Config::new().max_buffered_bytes(size * 2, behavior))
+                    .subscription("count", |t| t(|_, _: ()| futures::stream::iter(0..5)))
+                    .build(),
+            );
+            let connection = Connection::new(&router);
+            let subscriptions = Mutex::new(Default::default());
+            let (mut tx, mut rx) = mpsc::unbounded_channel();
+            handle_json_rpc_with_connection(
+                (),
+                Request {
+                    jsonrpc: None,
+                    id: RequestId::Null,
+                    inner: RequestInner::Subscription {
+                        path: "count".into(),
+                        input: (RequestId::Number(1), None),
+                    },
+                },
+                &router,
+                &mut Sender::ResponseChannel(&mut tx),
+                &mut SubscriptionMap::Mutex(&subscriptions),
+                &connection,
+            )
+            .await;
+
+            // The consumer is stalled so only the events which fit are queued
+            for _ in 0..10 {
+                tokio::task::yield_now().await;
+            }
+            assert_eq!(connection.buffered_bytes(), size * 2);
+            assert!(next_event(&mut rx, 0));
+            assert!(next_event(&mut rx, 1));
+            if behavior == BufferOverflow::Drop {
+                // The stream ended while the buffer was full
+                assert!(matches!(
+                    rx.try_recv().unwrap().result,
+                    ResponseInner::Complete
+                ));
+            }
+            assert!(rx.try_recv().is_err());
+
+            connection.record_flushed(size as usize * 2);
+            assert_eq!(connection.buffered_bytes(), 0);
+            for _ in 0..10 {
+                tokio::task::yield_now().await;
+            }
+            if behavior == BufferOverflow::Backpressure {
+                assert!(next_event(&mut rx, 2));
+                assert!(next_event(&mut rx, 3));
+            }
+            assert!(rx.try_recv().is_err());
+        }
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "runtime-tokio")]
+    async fn test_reserved_len() {
+        let router = Arc::new(
+            <Router>::new()
+                .config(Config::new().max_buffered_bytes(1024, BufferOverflow::Backpressure))
+                .subscription("sizes", |t| {
+                    t(|_, _: ()| futures::stream::iter([1, 10, 100].map(|n| "x".repeat(n))))
+                })
+                .build(),
+        );
+        let connection = Connection::new(&router);
+        let subscriptions = Mutex::new(Default::default());
+        let (mut tx, mut rx) = mpsc::unbounded_channel();
+        handle_json_rpc_with_connection(
+            (),
+            Request {
+                jsonrpc: None,
+                id: RequestId::Null,
+                inner: RequestInner::Subscription {
+                    path: "sizes".into(),
+                    input: (RequestId::Number(1), None),
+                },
+            },
+            &router,
+            &mut Sender::ResponseChannel(&mut tx),
+            &mut SubscriptionMap::Mutex(&subscriptions),
+            &connection,
+        )
+        .await;
+        for _ in 0..10 {
+            tokio::task::yield_now().await;
+        }
+        assert!(connection.buffered_bytes() > 0);
+
+        // Releasing what each queued frame reserved empties the buffer exactly, however the frames are encoded
+        let mut frames = 0;
+        while let Ok(frame) = rx.try_recv() {
+            connection.record_flushed(Connection::reserved_len(&frame));
+            frames += 1;
+        }
+        assert_eq!(frames, 4);
+        assert_eq!(connection.buffered_bytes(), 0);
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "runtime-tokio")]
+    async fn test_backpressure_released_on_close() {
+        let event = jsonrpc::Response {
+            jsonrpc: "2.0",
+            id: RequestId::Number(1),
+            result: ResponseInner::Event(0.into()),
+        };
+        let size = serialized_len(&event);
+        let router = Arc::new(
+            <Router>::new()
+                .config(Config::new().max_buffered_bytes(size, BufferOverflow::Backpressure))
+                .subscription("count", |t| t(|_, _: ()| futures::stream::iter(0..5)))
+                .build(),
+        );
+        let subscribe = |id: u32| Request {
+            jsonrpc: None,
+            id: RequestId::Null,
+            inner: RequestInner::Subscription {
+                path: "count".into(),
+                input: (RequestId::Number(id), None),
+            },
+        };
+
+        // A subscription blocked on a full buffer can still be stopped
+        let connection = Connection::new(&router);
+        let subscriptions = Mutex::new(Default::default());
+        let (mut tx, mut rx) = mpsc::unbounded_channel();
+        for req in [
+            subscribe(1),
+            Request {
+                jsonrpc: None,
+                id: RequestId::Null,
+                inner: RequestInner::SubscriptionStop {
+                    input: RequestId::Number(1),
+                },
+            },
+        ] {
+            handle_json_rpc_with_connection(
+                (),
+                req,
+                &router,
+                &mut Sender::ResponseChannel(&mut tx),
+                &mut SubscriptionMap::Mutex(&subscriptions),
+                &connection,
+            )
+            .await;
+            for _ in 0..10 {
+                tokio::task::yield_now().await;
+            }
+        }
+        assert!(matches!(
+            rx.try_recv().unwrap().result,
+            ResponseInner::Event(_)
+        ));
+        assert!(matches!(
+            rx.try_recv().unwrap().result,
+            ResponseInner::Cancelled
+        ));
+
+        // and closing the connection releases it, instead of it waiting for a flush which never comes
+        let connection = Connection::new(&router);
+        let subscriptions = Mutex::new(Default::default());
+        let (mut tx, mut rx) = mpsc::unbounded_channel();
+        handle_json_rpc_with_connection(
+            (),
+            subscribe(2),
+            &router,
+            &mut Sender::ResponseChannel(&mut tx),
+            &mut SubscriptionMap::Mutex(&subscriptions),
+            &connection,
+        )
+        .await;
+        for _ in 0..10 {
+            tokio::task::yield_now().await;
+        }
+        assert!(matches!(
+            rx.try_recv().unwrap().result,
+            ResponseInner::Event(_)
+        ));
+        assert!(rx.try_recv().is_err());
+
+        connection.close();
+        let resp = tokio::time::timeout(std::time::Duration::from_secs(1), rx.recv())
+            .await
+            .unwrap();
+        // The remaining events are dropped and the stream runs to completion
+        assert!(matches!(resp.unwrap().result, ResponseInner::Complete));
+    }
+
+    #[tokio::test]
+    async fn test_notifier() {
+        let router = Arc::new(
+            <Router>::new()
+                .mutation("sendMessage", |t| {
+                    t(|_, to: String| async move {
+                        // In a real app this would be the recipient's connection
+                        crate::notifier()
+                            .unwrap()
+                            .notify("newMessage", serde_json::json!({ "to": to }))
+                            .await
+                            .unwrap();
+                    })
+                })
+                .build(),
+        );
+
+        // A bounded channel, so the notification must wait for the client to read
+        let (mut tx, mut rx) = mpsc::channel(1);
+        let connection =
+            Connection::new(&router).with_notifier(Notifier::new(Sender2::Channel(tx.clone())));
+        let (_, frames) = tokio::join!(
+            async {
+                handle_json_rpc_with_connection(
+                    (),
+                    Request {
+                        jsonrpc: None,
+                        id: RequestId::Number(1),
+                        inner: RequestInner::Mutation {
+                            path: "sendMessage".into(),
+                            input: Some(serde_json::json!("oscar")),
+                        },
+                    },
+                    &router,
+                    &mut Sender::Channel(&mut tx),
+                    &mut SubscriptionMap::None,
+                    &connection,
+                )
+                .await
+            },
+            async {
+                let mut frames = Vec::new();
+                for _ in 0..2 {
+                    frames.push(serde_json::to_value(rx.recv().await.unwrap()).unwrap());
+                }
+                frames
+            }
+        );
+        assert_eq!(
+            frames,
+            [
+                serde_json::json!({ "jsonrpc": "2.0", "id": null, "result": { "type": "notification", "data": { "method": "newMessage", "params": { "to": "oscar" } } } }),
+                serde_json::json!({ "jsonrpc": "2.0", "id": 1, "result": { "type": "response", "data": null } }),
+            ]
+        );
+
+        // Without a request there is no connection to notify
+        assert!(crate::notifier().is_none());
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "runtime-tokio")]
+    async fn test_context_timeout() {
+        let router = <Router<u32>>::new()
+            .config(Config::new().context_timeout(std::time::Duration::from_millis(10)))
+            .build();
+
+        let result = build_context(&router, async {
+            tokio::time::sleep(std::time::Duration::from_secs(60)).await;
+            Ok(1)
+        })
+        .await;
+        assert!(matches!(result, Err(ExecError::ContextTimeout)));
+
+        let result = build_context(&router, async { Ok(1) }).await;
+        assert!(matches!(result, Ok(1)));
+    }
+
+    #[tokio::test]
+    async fn test_is_connected() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let router = Arc::new(
+            <Router>::new()
+                .query("expensive", move |t| {
+                    let tx = tx.clone();
+                    t(move |_, _: ()| {
+                        let tx = tx.clone();
+                        async move {
+                            tx.send((crate::is_connected(), crate::connection_status().unwrap()))
+                                .unwrap();
+                            std::future::pending::<()>().await
+                        }
+                    })
+                })
+                .build(),
+        );
+        let request = |connection| {
+            let router = router.clone();
+            async move {
+                handle_json_rpc_with_connection(
+                    (),
+                    Request {
+                        jsonrpc: None,
+                        id: RequestId::Number(1),
+                        inner: RequestInner::Query {
+                            path: "expensive".into(),
+                            input: None,
+                        },
+                    },
+                    &router,
+                    &mut Sender::Response(None),
+                    &mut SubscriptionMap::None,
+                    &connection,
+                )
+                .await
+            }
+        };
+
+        // The transport dropping the request
+        let status = tokio::select! {
+            _ = request(Connection::default()) => None,
+            status = rx.recv() => status,
+        };
+        let (connected, status) = status.unwrap();
+        assert!(connected);
+        assert!(!status.is_connected());
+
+        // The connection closing while the request is running
+        let connection = Connection::new(&router);
+        let handle = tokio::spawn(request(connection.clone()));
+        let (connected, status) = rx.recv().await.unwrap();
+        assert!(connected && status.is_connected());
+        connection.close();
+        assert!(!status.is_connected());
+        handle.abort();
+
+        assert!(crate::is_connected());
+    }
+
+    #[test]
+    fn test_max_bytes_per_connection() {
+        let router = <Router>::new()
+            .config(Config::new().max_bytes_per_connection(100))
+            .build();
+
+        let connection = Connection::new(&router);
+        assert!(connection.record_sent(60));
+        assert!(connection.record_sent(40));
+        assert!(!connection.closed.load(Ordering::Relaxed));
+
+        // The frame which takes the connection over the limit is still sent, then the connection is closed
+        assert!(!connection.record_sent(1));
+        assert!(connection.closed.load(Ordering::Relaxed));
+        assert_eq!(connection.bytes_sent(), 101);
+
+        // Bytes are still counted without a limit
+        let connection = Connection::default();
+        assert!(connection.record_sent(1_000_000));
+        assert_eq!(connection.bytes_sent(), 1_000_000);
+    }
+
+    #[test]
+    fn test_close_frame() {
+        let err = || ExecError::InvalidRequest(serde_json::from_str::<Request>("{").unwrap_err());
+
+        // Connections stay open by default
+        let router = <Router>::new().build();
+        assert_eq!(close_frame(&router, &err()), None);
+
+        let router = <Router>::new()
+            .config(Config::new().close_frame(|err| match err {
+                ExecError::InvalidRequest(_) => {
+                    Some(CloseFrame::PROTOCOL_ERROR.reason("invalid request"))
+                }
+                _ => None,
+            }))
+            .build();
+        let frame = close_frame(&router, &err()).unwrap();
+        assert_eq!(frame.code, 1002);
+        assert_eq!(frame.reason, "invalid request");
+        assert_eq!(close_frame(&router, &ExecError::ContextTimeout), None);
+    }
+
+    #[tokio::test]
+    async fn test_retry_after() {
+        let gate = Arc::new(Semaphore::new(0));
+        let router = Arc::new(
+            <Router>::new()
+                .config(
+                    Config::new()
+                        .max_concurrent_requests(1, OverloadBehavior::Reject)
+                        .retry_after(|err| match err {
+                            ExecError::Overloaded | ExecError::InvalidRequest(_) => {
+                                Some(Duration::from_secs(5))
+                            }
+                            _ => None,
+                        })
+                        .close_frame(|err| match err {
+                            ExecError::InvalidRequest(_) => Some(CloseFrame::PROTOCOL_ERROR),
+                            _ => Some(
+                                CloseFrame::POLICY_VIOLATION.retry_after(Duration::from_secs(1)),
+                            ),
+                        }),
+                )
+                .query("wait", {
+                    let gate = gate.clone();
+                    move |t| {
+                        let gate = gate.clone();
+                        t(move |_, _: ()| {
+                            let gate = gate.clone();
+                            async move { gate.acquire().await.unwrap().forget() }
+                        })
+                    }
+                })
+                .build(),
+        );
+
+        // Simulate overload by blocking the only slot of the connection
+        let connection = Connection::new(&router);
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let requests = join_all((0..2).map(|id| {
+            let (router, connection, mut tx) = (router.clone(), connection.clone(), tx.clone());
+            async move {
+                handle_json_rpc_with_connection(
+                    (),
+                    query(id),
+                    &router,
+                    &mut Sender::ResponseChannel(&mut tx),
+                    &mut SubscriptionMap::None,
+                    &connection,
+                )
+                .await
+            }
+        }));
+        tokio::join!(requests, async {
+            let resp = rx.recv().await.unwrap();
+            assert!(matches!(
+                resp.result,
+                ResponseInner::Error(JsonRPCError {
+                    kind: ErrorKind::RateLimited,
+                    data: Some(data),
+                    ..
+                }) if data == serde_json::json!({ "retryAfter": 5000 })
+            ));
+            gate.add_permits(1);
+        });
+        assert!(matches!(
+            rx.recv().await.unwrap().result,
+            ResponseInner::Response(_)
+        ));
+
+        // The hook fills in close frames, unless they already have a hint
+        let err = ExecError::InvalidRequest(serde_json::from_str::<Request>("{").unwrap_err());
+        let frame = close_frame(&router, &err).unwrap();
+        assert_eq!(frame.retry_after, Some(Duration::from_secs(5)));
+        assert_eq!(frame.wire_reason(), ";retry-after=5000");
+        let frame = close_frame(&router, &ExecError::ContextTimeout).unwrap();
+        assert_eq!(frame.retry_after, Some(Duration::from_secs(1)));
+        assert_eq!(
+            CloseFrame::NORMAL.reason("overloaded").wire_reason(),
+            "overloaded"
+        );
+    }
+
+    #[test]
+    fn test_rewrite_frames() {
+        // Version 1 clients don't understand the `data` of errors or `cancelled` frames
+        let router = <Router>::new()
+            .config(Config::new().rewrite_frames(|headers, mut resp| {
+                if headers.get("x-protocol-version") == Some("1") {
+                    resp.result = match resp.result {
+                        ResponseInner::Error(err) => {
+                            ResponseInner::Error(JsonRPCError { data: None, ..err })
+                        }
+                        ResponseInner::Cancelled => ResponseInner::Response(Value::Null),
+                        result => result,
+                    };
+                }
+                resp
+            }))
+            .build();
+        let frame = |result| jsonrpc::Response {
+            jsonrpc: "2.0",
+            id: RequestId::Number(1),
+            result,
+        };
+        let error = || {
+            ResponseInner::Error(JsonRPCError {
+                kind: ErrorKind::NotFound,
+                code: 404,
+                message: "not found".into(),
+                data: Some(serde_json::json!({ "path": "users.get" })),
+            })
+        };
+
+        let v1 = Headers::from_iter([("X-Protocol-Version", "1")]);
+        assert_eq!(
+            serde_json::to_value(rewrite_frame(&router, &v1, frame(error()))).unwrap(),
+            serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "result": {
+                    "type": "error",
+                    "data": { "kind": "notFound", "code": 404, "message": "not found", "data": null }
+                }
+            })
+        );
+        assert!(matches!(
+            rewrite_frame(&router, &v1, frame(ResponseInner::Cancelled)).result,
+            ResponseInner::Response(Value::Null)
+        ));
+
+        // Version 2 clients get the frame as-is
+        let v2 = Headers::from_iter([("X-Protocol-Version", "2")]);
+        assert!(matches!(
+            rewrite_frame(&router, &v2, frame(error())).result,
+            ResponseInner::Error(JsonRPCError {
+                kind: ErrorKind::NotFound,
+                data: Some(_),
+                ..
+            })
+        ));
+        assert!(matches!(
+            rewrite_frame(
+                &<Router>::new().build(),
+                &v1,
+                frame(ResponseInner::Cancelled)
+            )
+            .result,
+            ResponseInner::Cancelled
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_method_parser() {
+        let router = Arc::new(
+            <Router>::new()
+                .config(Config::new().method_parser(|method| {
+                    let (kind, key) = method.split_once(':')?;
+                    let kind = match kind {
+                        "query" => ProcedureKind::Query,
+                        "mutation" => ProcedureKind::Mutation,
+                        _ => return None,
+                    };
+                    Some((kind, key.to_string()))
+                }))
+                .query("users.get", |t| t(|_, id: u32| format!("user {id}")))
+                .build(),
+        );
+
+        let exec = |req: serde_json::Value| {
+            let router = router.clone();
+            async move {
+                let mut resp = Sender::Response(None);
+                handle_json_rpc(
+                    (),
+                    serde_json::from_value(req).unwrap(),
+                    &router,
+                    &mut resp,
+                    &mut SubscriptionMap::None,
+                )
+                .await;
+                match resp {
+                    Sender::Response(Some(resp)) => resp.result,
+                    _ => unreachable!(),
+                }
+            }
+        };
+
+        let result =
+            exec(serde_json::json!({ "id": 1, "method": "query:users.get", "params": 1 })).await;
+        assert!(matches!(result, ResponseInner::Response(v) if v == "user 1"));
+
+        // rspc's own methods are still supported
+        let result = exec(serde_json::json!({
+            "id": 1,
+            "method": "query",
+            "params": { "path": "users.get", "input": 2 }
+        }))
+        .await;
+        assert!(matches!(result, ResponseInner::Response(v) if v == "user 2"));
+
+        for method in ["unknown:users.get", "users.get"] {
+            let result = exec(serde_json::json!({ "id": 1, "method": method, "params": 1 })).await;
+            assert!(matches!(result, ResponseInner::Error(_)));
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use std::{future::Future, marker::PhantomData, pin::Pin, sync::Arc};
+use std::{any::Any, future::Future, marker::PhantomData, pin::Pin, sync::Arc, time::Duration};
 
 use futures::Stream;
 use serde_json::Value;
@@ -11,6 +11,52 @@ pub trait MiddlewareBuilderLike<TCtx> {
     fn build<T>(&self, next: T) -> Box<dyn Layer<TCtx>>
     where
         T: Layer<Self::LayerContext>;
+
+    /// Is there a middleware with the given name in this stack.
+    fn contains_layer(&self, name: &str) -> bool;
+
+    /// Whether this stack has no middleware, so the results of the procedures it's built with reach the caller unchanged. See [`Router::exec_into`](crate::Router::exec_into).
+    fn is_empty(&self) -> bool {
+        false
+    }
+
+    /// Insert a layer next to the middleware with the given name. `layer` must be a [`DynLayer`] of the context at the insertion point.
+    fn insert_layer(
+        &mut self,
+        name: &str,
+        position: LayerPosition,
+        layer: &dyn Any,
+    ) -> InsertLayerResult;
+}
+
+/// A type erased middleware which doesn't change the context. Used for layers inserted by name.
+pub type DynLayer<TCtx> = Arc<dyn Fn(Box<dyn Layer<TCtx>>) -> Box<dyn Layer<TCtx>> + Send + Sync>;
+
+pub fn dyn_layer<TCtx, TMiddleware>(mw: TMiddleware) -> DynLayer<TCtx>
+where
+    TCtx: Send + Sync + 'static,
+    TMiddleware: MiddlewareLike<TCtx, NewCtx = TCtx> + Send + Sync + 'static,
+{
+    Arc::new(move |next| {
+        Box::new(MiddlewareLayer {
+            next: Arc::new(next),
+            mw: mw.clone(),
+            phantom: PhantomData,
+        })
+    })
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum LayerPosition {
+    Before,
+    After,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InsertLayerResult {
+    Inserted,
+    NotFound,
+    ContextMismatch,
 }
 
 pub struct MiddlewareMerger<TCtx, TLayerCtx, TNewLayerCtx, TMiddleware, TIncomingMiddleware>
@@ -40,6 +86,26 @@ where
     {
         self.middleware.build(self.middleware2.build(next))
     }
+
+    fn contains_layer(&self, name: &str) -> bool {
+        self.middleware.contains_layer(name) || self.middleware2.contains_layer(name)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.middleware.is_empty() && self.middleware2.is_empty()
+    }
+
+    fn insert_layer(
+        &mut self,
+        name: &str,
+        position: LayerPosition,
+        layer: &dyn Any,
+    ) -> InsertLayerResult {
+        match self.middleware2.insert_layer(name, position, layer) {
+            InsertLayerResult::NotFound => self.middleware.insert_layer(name, position, layer),
+            result => result,
+        }
+    }
 }
 
 pub struct MiddlewareLayerBuilder<TCtx, TLayerCtx, TNewLayerCtx, TMiddleware, TNewMiddleware>
@@ -52,6 +118,10 @@ where
 {
     pub middleware: TMiddleware,
     pub mw: TNewMiddleware,
+    pub name: Option<&'static str>,
+    // Layers inserted by name. `before` are outer to `mw` and `after` are inner to it. The most recently inserted is always closest to `mw`.
+    pub before: Vec<DynLayer<TLayerCtx>>,
+    pub after: Vec<DynLayer<TNewLayerCtx>>,
     pub phantom: PhantomData<(TCtx, TLayerCtx, TNewLayerCtx)>,
 }
 
@@ -70,11 +140,56 @@ where
     where
         T: Layer<Self::LayerContext> + Sync,
     {
-        self.middleware.build(MiddlewareLayer {
+        if self.before.is_empty() && self.after.is_empty() {
+            return self.middleware.build(MiddlewareLayer {
+                next: Arc::new(next),
+                mw: self.mw.clone(),
+                phantom: PhantomData,
+            });
+        }
+
+        let mut next: Box<dyn Layer<TNewLayerCtx>> = Box::new(next);
+        for layer in &self.after {
+            next = layer(next);
+        }
+
+        let mut layer: Box<dyn Layer<TLayerCtx>> = Box::new(MiddlewareLayer {
             next: Arc::new(next),
             mw: self.mw.clone(),
             phantom: PhantomData,
-        })
+        });
+        for before in self.before.iter().rev() {
+            layer = before(layer);
+        }
+
+        self.middleware.build(layer)
+    }
+
+    fn contains_layer(&self, name: &str) -> bool {
+        self.name == Some(name) || self.middleware.contains_layer(name)
+    }
+
+    fn insert_layer(
+        &mut self,
+        name: &str,
+        position: LayerPosition,
+        layer: &dyn Any,
+    ) -> InsertLayerResult {
+        if self.name != Some(name) {
+            return self.middleware.insert_layer(name, position, layer);
+        }
+
+        match position {
+            LayerPosition::Before => match layer.downcast_ref::<DynLayer<TLayerCtx>>() {
+                Some(layer) => self.before.push(layer.clone()),
+                None => return InsertLayerResult::ContextMismatch,
+            },
+            LayerPosition::After => match layer.downcast_ref::<DynLayer<TNewLayerCtx>>() {
+                Some(layer) => self.after.push(layer.clone()),
+                None => return InsertLayerResult::ContextMismatch,
+            },
+        }
+        InsertLayerResult::Inserted
     }
 }
 
@@ -133,6 +248,23 @@ where
     {
         Box::new(next)
     }
+
+    fn contains_layer(&self, _name: &str) -> bool {
+        false
+    }
+
+    fn is_empty(&self) -> bool {
+        true
+    }
+
+    fn insert_layer(
+        &mut self,
+        _name: &str,
+        _position: LayerPosition,
+        _layer: &dyn Any,
+    ) -> InsertLayerResult {
+        InsertLayerResult::NotFound
+    }
 }
 
 // TODO: Rename this so it doesn't conflict with the middleware builder struct
@@ -174,9 +306,18 @@ where
     }
 }
 
+impl<TLayerCtx> Layer<TLayerCtx> for Arc<dyn Layer<TLayerCtx> + 'static>
+where
+    TLayerCtx: 'static,
+{
+    fn call(&self, a: TLayerCtx, b: Value, c: RequestContext) -> Result<LayerResult, ExecError> {
+        (**self).call(a, b, c)
+    }
+}
+
 // TODO: Is this a duplicate of any type?
 // TODO: Move into public API cause it might be used in middleware
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ProcedureKind {
     Query,
     Mutation,
@@ -198,6 +339,25 @@ impl ProcedureKind {
 pub struct RequestContext {
     pub kind: ProcedureKind,
     pub path: String, // TODO: String slice??
+    pub(crate) params: Arc<Value>,
+    // The router's runtime, for procedures which need a timer
+    pub(crate) runtime: Option<Arc<dyn crate::Runtime>>,
+    // See `Config::unsubscribe_timeout`
+    pub(crate) unsubscribe_timeout: Duration,
+}
+
+impl RequestContext {
+    /// The raw `params` of the request, exactly as the client sent them.
+    ///
+    /// This is read-only and isn't affected by middleware which rewrite the input. To change the input passed to the next layer modify the `input` given to the middleware instead.
+    pub fn params(&self) -> &Value {
+        &self.params
+    }
+
+    /// Look up a field of the raw `params` by JSON Pointer (Eg. `/filter/owner_id`) without deserializing the input. See [`RequestContext::params`].
+    pub fn param(&self, pointer: &str) -> Option<&Value> {
+        self.params.pointer(pointer)
+    }
 }
 
 pub enum ValueOrStream {
@@ -234,3 +394,74 @@ impl LayerResult {
         }
     }
 }
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use serde::Deserialize;
+    use serde_json::{json, Value};
+    use specta::Type;
+
+    use super::{Layer, LayerResult, RequestContext};
+    use crate::{ExecError, ExecKind, MiddlewareLike, Router};
+
+    #[derive(Deserialize, Type)]
+    struct Search {
+        filter: Filter,
+    }
+
+    #[derive(Deserialize, Type)]
+    struct Filter {
+        owner_id: u32,
+    }
+
+    // Builds a cache key from a single field of the input
+    #[derive(Clone)]
+    struct CacheKey(Arc<Mutex<Vec<String>>>);
+
+    impl MiddlewareLike<()> for CacheKey {
+        type State = ();
+        type NewCtx = ();
+
+        fn handle<TMiddleware: Layer<()> + 'static>(
+            &self,
+            ctx: (),
+            mut input: Value,
+            req: RequestContext,
+            next: Arc<TMiddleware>,
+        ) -> Result<LayerResult, ExecError> {
+            // Rewriting the input doesn't change the raw params
+            input["filter"]["owner_id"] = json!(0);
+
+            let owner = req.param("/filter/owner_id").unwrap();
+            self.0.lock().unwrap().push(format!("{}:{owner}", req.path));
+            next.call(ctx, input, req)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_request_params() {
+        let keys = Arc::new(Mutex::new(Vec::new()));
+        let router = <Router>::new()
+            .middleware({
+                let keys = keys.clone();
+                move |_| CacheKey(keys.clone())
+            })
+            .query("search", |t| t(|_, search: Search| search.filter.owner_id))
+            .build();
+
+        let result = router
+            .exec(
+                (),
+                ExecKind::Query,
+                "search".into(),
+                Some(json!({ "query": "rspc", "filter": { "owner_id": 42 } })),
+            )
+            .await
+            .unwrap();
+        assert_eq!(result, json!(0));
+        assert_eq!(*keys.lock().unwrap(), ["search:42"]);
+    }
+}
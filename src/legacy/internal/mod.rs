@@ -1,5 +1,6 @@
 //! Internal types which power rspc. The module provides no guarantee of compatibility between updates, so you should be careful rely on types from it.
 
+mod jsonrpc_batch;
 mod jsonrpc_exec;
 mod middleware;
 mod procedure_builder;
@@ -1,4 +1,26 @@
-use std::{marker::PhantomData, ops::Deref};
+use std::{
+    any::Any, borrow::Cow, future::Future, marker::PhantomData, ops::Deref, sync::Arc,
+    time::Duration,
+};
+
+use futures::{FutureExt, Stream};
+use serde::Serialize;
+use serde_json::Value;
+use specta::{DataType, Type, TypeMap};
+
+use crate::{
+    legacy::{
+        cache::{AnyCacheKeyFn, CacheKeyFn},
+        deserialize::InputOptions,
+        field_access::{CapabilityFn, FieldAccess},
+        serialize_timer,
+        snapshot::{snapshot_then_stream, SnapshotResolver},
+        subscription_hooks::{AnyHookFn, OnComplete, OnCompleteFn, OnSubscribeFn, OnUnsubscribeFn},
+        visibility::{AnyVisibleFn, VisibleFn},
+        warmup::{AnyWarmupFn, WarmupFn},
+    },
+    Capabilities, Constraint, Error, ExecError, InputPipeline, Priority,
+};
 
 pub struct UnbuiltProcedureBuilder<TLayerCtx, TResolver> {
     deref_handler: fn(TResolver) -> BuiltProcedureBuilder<TResolver>,
@@ -8,7 +30,10 @@ pub struct UnbuiltProcedureBuilder<TLayerCtx, TResolver> {
 impl<TLayerCtx, TResolver> Default for UnbuiltProcedureBuilder<TLayerCtx, TResolver> {
     fn default() -> Self {
         Self {
-            deref_handler: |resolver| BuiltProcedureBuilder { resolver },
+            deref_handler: |resolver| BuiltProcedureBuilder {
+                resolver,
+                options: Default::default(),
+            },
             phantom: PhantomData,
         }
     }
@@ -18,6 +43,45 @@ impl<TLayerCtx, TResolver> UnbuiltProcedureBuilder<TLayerCtx, TResolver> {
     pub fn resolver(self, resolver: TResolver) -> BuiltProcedureBuilder<TResolver> {
         (self.deref_handler)(resolver)
     }
+
+    /// Build a subscription which returns the current state immediately, then streams changes to it.
+    ///
+    /// The first event is the result of `snapshot` and every following event is an item of the stream returned by `updates`, wrapped in a [`SnapshotEvent`](crate::SnapshotEvent) so the client can tell them apart.
+    /// The snapshot and the updates can be different types (Eg. a full list and the individual changes to it). Both are reflected in the exported type of the subscription as `SnapshotEvent<TSnapshot, TUpdate>`.
+    ///
+    /// `updates` is called before `snapshot`, so a change made while the snapshot is taken isn't missed (as long as the stream starts listening when it's created, Eg. a broadcast receiver). Such a change may also be included in the snapshot, so updates should be idempotent.
+    ///
+    /// ```rust
+    /// use futures::stream;
+    ///
+    /// let router = <rspc::Router>::new()
+    ///     .subscription("todos", |t| {
+    ///         t.snapshot_then_stream(
+    ///             |_, _: ()| vec!["buy milk".to_string()],
+    ///             |_, _: ()| stream::iter(["walk the dog".to_string()]),
+    ///         )
+    ///     })
+    ///     .build();
+    /// ```
+    pub fn snapshot_then_stream<TArg, TSnapshot, TStream, TUpdate>(
+        self,
+        snapshot: impl Fn(TLayerCtx, TArg) -> TSnapshot + Send + Sync + 'static,
+        updates: impl Fn(TLayerCtx, TArg) -> TStream + Send + Sync + 'static,
+    ) -> BuiltProcedureBuilder<SnapshotResolver<TLayerCtx, TArg, TSnapshot, TUpdate>>
+    where
+        TLayerCtx: Clone,
+        TArg: Clone,
+        TSnapshot: Send + Sync + 'static,
+        TStream: Stream<Item = TUpdate> + Send + Sync + 'static,
+        TUpdate: Send + Sync + 'static,
+    {
+        UnbuiltProcedureBuilder::<TLayerCtx, _>::default().resolver(Box::new(
+            move |ctx: TLayerCtx, arg: TArg| {
+                let updates = updates(ctx.clone(), arg.clone());
+                snapshot_then_stream(snapshot(ctx, arg), updates)
+            },
+        ))
+    }
 }
 
 impl<TLayerCtx, TResolver> Deref for UnbuiltProcedureBuilder<TLayerCtx, TResolver> {
@@ -30,4 +94,547 @@ impl<TLayerCtx, TResolver> Deref for UnbuiltProcedureBuilder<TLayerCtx, TResolve
 
 pub struct BuiltProcedureBuilder<TResolver> {
     pub resolver: TResolver,
+    pub(crate) options: ProcedureOptions,
+}
+
+// The options set with the methods of `BuiltProcedureBuilder`. Every kind of procedure has the same options, the router records the ones which don't apply to it's kind for `Router::validate`.
+#[derive(Default)]
+pub(crate) struct ProcedureOptions {
+    // The TTL and the key of the context it's cached for, See `BuiltProcedureBuilder::cache_by`
+    pub cache: Option<(Duration, Option<AnyCacheKeyFn>)>,
+    pub cache_control: Option<Cow<'static, str>>,
+    pub sla: Option<Duration>,
+    pub map_item: Option<MapItem>,
+    pub buffer: Option<(usize, Duration)>,
+    pub spawn: bool,
+    pub serialize_concurrently: Option<SerializeConcurrently>,
+    pub visible: Option<AnyVisibleFn>,
+    pub field_access: Option<FieldAccess>,
+    pub schema_version: Option<u32>,
+    pub virtual_fields: Vec<&'static str>,
+    pub priority: Option<Priority>,
+    pub on_subscribe: Option<AnyHookFn>,
+    pub on_unsubscribe: Option<AnyHookFn>,
+    pub on_complete: Option<OnComplete>,
+    pub warmup: Option<AnyWarmupFn>,
+    pub description: Option<Cow<'static, str>>,
+    pub deprecated: Option<Cow<'static, str>>,
+    pub tags: Vec<&'static str>,
+    pub error_ty: Option<fn(&mut TypeMap) -> DataType>,
+    pub aliases: Vec<&'static str>,
+    pub input: InputOptions,
+}
+
+// A type erased subscription item mapper. The item type is checked by `BuiltProcedureBuilder::map_item` so the downcast can't fail.
+type MapItemFn = Arc<dyn Fn(Box<dyn Any>) -> Result<Value, ExecError> + Send + Sync>;
+
+#[derive(Clone)]
+pub(crate) struct MapItem {
+    pub map: MapItemFn,
+    pub typedef: fn(&mut TypeMap) -> DataType,
+}
+
+pub(crate) struct SerializeConcurrently {
+    pub concurrency: usize,
+    // Marks the items as `Send` so they can be moved to the serializing tasks. The item type is checked by `BuiltProcedureBuilder::serialize_concurrently` so the downcast can't fail.
+    pub into_send: fn(Box<dyn Any>) -> Box<dyn Any + Send>,
+}
+
+impl<TResolver> BuiltProcedureBuilder<TResolver> {
+    /// Cache the result of this query for `ttl`, keyed by it's input.
+    ///
+    /// The cache is shared between all callers so the resolver should not depend on the context, use [`BuiltProcedureBuilder::cache_by`] if it does. Middleware still run on every request.
+    /// Results are cached separately for each [`representation`](crate::representation), but not for anything else the resolver reads from the request's headers.
+    /// Cached results can be invalidated at runtime using [`Router::caches`](crate::Router::caches).
+    ///
+    /// This only applies to queries and is ignored for mutations and subscriptions.
+    pub fn cache(mut self, ttl: Duration) -> Self {
+        self.options.cache = Some((ttl, None));
+        self
+    }
+
+    /// Cache the result of this query for `ttl`, keyed by it's input and the key `key` returns for the context (Eg. the tenant or user id), for resolvers whose result depends on the context.
+    ///
+    /// Requests whose contexts have different keys never share a result, so one tenant can't be served another's data. Everything else works like [`BuiltProcedureBuilder::cache`], and invalidating an input drops it's result for every key.
+    ///
+    /// ```rust
+    /// # use std::time::Duration;
+    /// struct Ctx {
+    ///     tenant: u32,
+    /// }
+    ///
+    /// let router = rspc::Router::<Ctx>::new()
+    ///     .query("settings", |t| {
+    ///         t(|ctx: Ctx, _: ()| format!("settings of {}", ctx.tenant))
+    ///             .cache_by(Duration::from_secs(60), |ctx: &Ctx| ctx.tenant.to_string())
+    ///     })
+    ///     .build();
+    /// ```
+    ///
+    /// This only applies to queries and is ignored for mutations and subscriptions.
+    pub fn cache_by<TCtx, TArg, TResult>(
+        mut self,
+        ttl: Duration,
+        key: impl Fn(&TCtx) -> String + Send + Sync + 'static,
+    ) -> Self
+    where
+        TResolver: Fn(TCtx, TArg) -> TResult,
+        TCtx: 'static,
+    {
+        let key: CacheKeyFn<TCtx> = Arc::new(key);
+        self.options.cache = Some((ttl, Some(Arc::new(key))));
+        self
+    }
+
+    /// Set the `Cache-Control` header of this query's successful HTTP responses, Eg. `public, max-age=60` to let a CDN cache them.
+    ///
+    /// Unlike [`BuiltProcedureBuilder::cache`] this doesn't cache anything on the server, it instructs the client and any intermediaries.
+    /// Error responses never have the header. It's ignored for WebSocket and in-process requests, and for mutations and subscriptions.
+    pub fn cache_control(mut self, directive: impl Into<Cow<'static, str>>) -> Self {
+        self.options.cache_control = Some(directive.into());
+        self
+    }
+
+    /// Set the latency target of this procedure (Eg. 200ms). Requests which take longer are reported to the [`Config::on_sla_breach`](crate::Config::on_sla_breach) hook, they aren't cancelled.
+    ///
+    /// This only applies to queries and mutations and is ignored for subscriptions.
+    pub fn sla(mut self, latency: Duration) -> Self {
+        self.options.sla = Some(latency);
+        self
+    }
+
+    /// Set how urgently this procedure's requests are admitted when their connection is at it's concurrency limit. See [`Config::priority_queue`](crate::Config::priority_queue).
+    pub fn priority(mut self, priority: Priority) -> Self {
+        self.options.priority = Some(priority);
+        self
+    }
+
+    /// Document this procedure. The description is exported as a JSDoc comment on the procedure in the TypeScript bindings and as the method's description in the OpenRPC document.
+    ///
+    /// Multi-line descriptions are supported. Calling this again replaces the previous description.
+    pub fn description(mut self, description: impl Into<Cow<'static, str>>) -> Self {
+        self.options.description = Some(description.into());
+        self
+    }
+
+    /// Mark this procedure as deprecated, with a `message` explaining what to use instead. It's exported as a `@deprecated` JSDoc tag on the procedure in the TypeScript bindings and marks the method as deprecated in the OpenRPC document.
+    ///
+    /// With [`Config::deprecation_warnings`](crate::Config::deprecation_warnings) the message is also sent to clients in the procedure's responses.
+    pub fn deprecated(mut self, message: impl Into<Cow<'static, str>>) -> Self {
+        self.options.deprecated = Some(message.into());
+        self
+    }
+
+    /// Tag this procedure (Eg. `public` or `internal`), so separate bindings can be exported for each tag with [`Router::export_ts_filtered`](crate::Router::export_ts_filtered).
+    ///
+    /// This can be called multiple times to add more tags.
+    pub fn tag(mut self, tag: &'static str) -> Self {
+        self.options.tags.push(tag);
+        self
+    }
+
+    /// Declare the type of the `data` this procedure's errors are sent with (See [`Error::with_data`]), Eg. an enum of the failures of a domain shared by several procedures.
+    ///
+    /// It's exported as the `error` of the procedure in the TypeScript bindings, along with a `<Key><Kind>Result` type which is `{ success: <result> } | { error: <error> }`. A type shared by several procedures is only defined once.
+    pub fn error_type<TError: Type>(mut self) -> Self {
+        self.options.error_ty = Some(|defs| TError::reference(defs, &[]).inner);
+        self
+    }
+
+    /// Also register this procedure under `key`, Eg. to keep an old name working while clients migrate to a new one.
+    ///
+    /// Every name dispatches to the same resolver (and middleware) and is exported with the same types, which are only defined once in the bindings. A [`cache`](Self::cache) is shared between every name.
+    /// This can be called multiple times to add more names.
+    pub fn also_named(mut self, key: &'static str) -> Self {
+        self.options.aliases.push(key);
+        self
+    }
+
+    /// Transform the raw input of this procedure with `deserialize_with` before it's deserialized into the resolver's argument, Eg. to accept a legacy date format which a client can't stop sending.
+    ///
+    /// Returning an error rejects the request without calling the resolver. The exported type of the input is still the resolver's argument, so it reflects the shape *after* the transform and not what the legacy client sends.
+    /// Calling this again replaces the previous function.
+    pub fn deserialize_with(
+        mut self,
+        deserialize_with: fn(Value) -> Result<Value, ExecError>,
+    ) -> Self {
+        self.options.input.deserialize_with = Some(deserialize_with);
+        self
+    }
+
+    /// Fill in the fields of the input object which the client omitted from `defaults` before it's deserialized, so older clients keep working when a field is added without needing `#[serde(default)]` on the type.
+    ///
+    /// `defaults` must serialize to an object. Only top-level fields which are missing are filled in, a field the client sent as `null` is kept. A missing or `null` input is treated as an empty object. Defaults are applied after [`deserialize_with`](Self::deserialize_with).
+    ///
+    /// The defaults are merged into the input before serde sees it, so a field which is only in `defaults` is rejected by a type with `#[serde(deny_unknown_fields)]`. The exported type of the input doesn't know about the defaults, so the fields are still required in the bindings unless they are also optional on the type.
+    ///
+    /// Calling this again replaces the previous defaults.
+    #[allow(clippy::panic)]
+    pub fn defaults(mut self, defaults: impl Serialize) -> Self {
+        match serde_json::to_value(defaults) {
+            Ok(defaults @ Value::Object(_)) => self.options.input.defaults = Some(defaults),
+            _ => panic!("rspc error: procedure input defaults must serialize to an object"),
+        }
+        self
+    }
+
+    /// Transform the raw input of this procedure with the stages of `pipeline` in order, before it's deserialized into the resolver's argument. See [`InputPipeline`] for how the stages are run and reported when they fail.
+    ///
+    /// The pipeline runs after [`defaults`](Self::defaults) and before [`constrain`](Self::constrain)'s checks. The exported type of the input is still the resolver's argument.
+    /// Calling this again replaces the previous pipeline.
+    pub fn pipeline<T: Serialize + 'static>(mut self, pipeline: InputPipeline<T>) -> Self {
+        self.options.input.pipeline = Some(pipeline.into_fn());
+        self
+    }
+
+    /// Limit the size of the field of the input at `path` (a [JSON pointer](https://datatracker.ietf.org/doc/html/rfc6901), Eg. `/username`), Eg. to stop a client sending a huge string.
+    ///
+    /// Constraints are checked on the raw input before it's deserialized (after [`defaults`](Self::defaults)), so an oversized input is rejected before the resolver's argument is built.
+    /// Every violated constraint is reported as a [`FieldError`](crate::FieldError) of an [`ExecError::InputValidation`] error, so the client can show them next to the corresponding form fields.
+    ///
+    /// Constraints are separate from the input's Specta type as it has no way to describe them, so they aren't included in the exported bindings.
+    /// This can be called multiple times to add more constraints, including several on the same field.
+    pub fn constrain(mut self, path: &'static str, constraint: Constraint) -> Self {
+        self.options.input.constraints.push((path, constraint));
+        self
+    }
+
+    /// Require requests to this procedure to declare they were built against schema `version` of it's input.
+    ///
+    /// The client declares it by sending a `schema_version` field in the input object. Requests with a missing or different version are rejected with [`ExecError::VersionMismatch`] before the input is deserialized, so outdated clients get a clear message telling them to upgrade instead of a deserialization error.
+    /// The field is left in the input, so you can (optionally) declare it on your input type to have it appear in the bindings.
+    ///
+    /// ## Forward compatibility
+    ///
+    /// Only bump the version for breaking changes (Eg. removing or renaming a field). New fields which are `Option` (or `#[serde(default)]`) can be added without a bump as older clients can omit them and serde ignores unknown fields sent by newer clients.
+    pub fn schema_version(mut self, version: u32) -> Self {
+        self.options.schema_version = Some(version);
+        self
+    }
+
+    /// Declare computed fields of this procedure's result which are only resolved when the client requests them, as they're expensive (Eg. an aggregate query).
+    ///
+    /// The client selects them by sending a `$virtuals` array of field names in the input object, which is removed before the input is deserialized (an input which only holds it is treated as no input). Selecting an undeclared field is rejected with [`ExecError::InputValidation`].
+    /// The resolver checks the selection with [`virtual_requested`](crate::virtual_requested) or [`resolve_virtual`](crate::resolve_virtual), and leaves the fields which weren't requested as `None`.
+    ///
+    /// Declare them on your result type as `Option` with `#[serde(skip_serializing_if = "Option::is_none")]` and `#[specta(optional)]` so they're exported as optional (`field?: T`), as they're omitted unless requested.
+    /// This can be called multiple times to declare more fields.
+    ///
+    /// Note: Subscriptions don't support virtual fields.
+    pub fn virtual_fields(mut self, fields: impl IntoIterator<Item = &'static str>) -> Self {
+        self.options.virtual_fields.extend(fields);
+        self
+    }
+
+    /// Only allow this procedure to be called when `visible` returns `true` for the context.
+    ///
+    /// When it returns `false` the request fails with the same error as calling a procedure that doesn't exist, so it's existence isn't leaked.
+    /// The predicate receives the same context as the resolver. If that's the router's context it runs before any middleware, so a middleware rejecting the request can't reveal that the procedure exists.
+    /// If a middleware constructs your context (Eg. authentication) the predicate can use it, but it then runs after all middleware, immediately before the resolver.
+    ///
+    /// Hidden procedures can also be omitted from the exported bindings using [`Router::export_ts_for`](crate::Router::export_ts_for).
+    pub fn visible_when<TCtx, TArg, TResult>(
+        mut self,
+        visible: impl Fn(&TCtx) -> bool + Send + Sync + 'static,
+    ) -> Self
+    where
+        TResolver: Fn(TCtx, TArg) -> TResult,
+        TCtx: 'static,
+    {
+        let visible: VisibleFn<TCtx> = Arc::new(visible);
+        self.options.visible = Some(Arc::new(visible));
+        self
+    }
+
+    /// Remove `field` from this procedure's results when the context doesn't have `capability` (See [`Capabilities`]), Eg. so only admins see a user's `email`.
+    ///
+    /// The field is removed from every object in the result, at any depth (Eg. each user of a list), after it has been serialized. Use the field's serialized name, before [`Config::rename_fields`](crate::Config::rename_fields) is applied.
+    /// For subscriptions it's removed from every event.
+    /// This can be called multiple times to restrict more fields.
+    ///
+    /// The exported type still has the field, so clients must handle it being absent (Eg. declare it as an `Option` with `#[serde(skip_serializing_if = "Option::is_none")]` or `#[specta(optional)]`).
+    pub fn restrict_field<TCtx, TArg, TResult>(
+        mut self,
+        field: &'static str,
+        capability: &'static str,
+    ) -> Self
+    where
+        TResolver: Fn(TCtx, TArg) -> TResult,
+        TCtx: Capabilities + 'static,
+    {
+        let access = self.options.field_access.get_or_insert_with(|| {
+            let has_capability: CapabilityFn<TCtx> = TCtx::has_capability;
+            FieldAccess {
+                has_capability: Arc::new(has_capability),
+                fields: Vec::new(),
+            }
+        });
+        access.fields.push((field, capability));
+        self
+    }
+
+    /// Run `hook` when [`Router::warmup`](crate::Router::warmup) is called to pre-initialize lazy state of this procedure (Eg. connection pools, caches or compiled templates) before serving traffic.
+    ///
+    /// The hook receives the router's context, so it can't be used on a procedure after a middleware which changes the context. This is checked when the router is built.
+    pub fn warmup<TCtx, TArg, TResult, TFut>(
+        mut self,
+        hook: impl Fn(TCtx) -> TFut + Send + Sync + 'static,
+    ) -> Self
+    where
+        TResolver: Fn(TCtx, TArg) -> TResult,
+        TCtx: 'static,
+        TFut: Future<Output = Result<(), Error>> + Send + 'static,
+    {
+        let hook: WarmupFn<TCtx> = Arc::new(move |ctx| hook(ctx).boxed());
+        self.options.warmup = Some(Arc::new(hook));
+        self
+    }
+
+    /// Run `hook` with the context and input every time this subscription is started.
+    ///
+    /// The hook runs before the resolver and isn't called if the input fails to deserialize or a middleware rejects the request.
+    ///
+    /// This only applies to subscriptions and is ignored for queries and mutations.
+    pub fn on_subscribe<TCtx, TArg, TStream>(
+        mut self,
+        hook: impl Fn(&TCtx, &TArg) + Send + Sync + 'static,
+    ) -> Self
+    where
+        TResolver: Fn(TCtx, TArg) -> TStream,
+        TStream: Stream,
+        TCtx: 'static,
+        TArg: 'static,
+    {
+        let hook: OnSubscribeFn<TCtx, TArg> = Arc::new(hook);
+        self.options.on_subscribe = Some(Arc::new(hook));
+        self
+    }
+
+    /// Run `hook` with the context and input every time this subscription ends.
+    ///
+    /// This is guaranteed to run exactly once for every subscription which was started (See [`BuiltProcedureBuilder::on_subscribe`]), whether the stream completes, the client stops the subscription or the client disconnects.
+    /// The context and input are cloned when the subscription starts so they can be passed to the hook.
+    ///
+    /// The hook is async so it can do I/O (Eg. releasing a lock). It's bounded by [`Config::unsubscribe_timeout`](crate::Config::unsubscribe_timeout) and a stopped subscription is only acknowledged once it has finished or timed out.
+    /// The timeout needs a runtime, so building the router panics if neither the `runtime-tokio` feature nor [`Config::runtime`](crate::Config::runtime) is enabled.
+    ///
+    /// This only applies to subscriptions and is ignored for queries and mutations.
+    pub fn on_unsubscribe<TCtx, TArg, TStream, TFut>(
+        mut self,
+        hook: impl Fn(TCtx, TArg) -> TFut + Send + Sync + 'static,
+    ) -> Self
+    where
+        TResolver: Fn(TCtx, TArg) -> TStream,
+        TStream: Stream,
+        TCtx: Clone + Send + 'static,
+        TArg: Clone + Send + 'static,
+        TFut: Future<Output = ()> + Send + 'static,
+    {
+        let hook = Arc::new(hook);
+        let hook: OnUnsubscribeFn<TCtx, TArg> = Arc::new(move |ctx: &TCtx, arg: &TArg| {
+            let (hook, ctx, arg) = (hook.clone(), ctx.clone(), arg.clone());
+            Box::new(move || Box::pin(hook(ctx, arg)))
+        });
+        self.options.on_unsubscribe = Some(Arc::new(hook));
+        self
+    }
+
+    /// Run `hook` with the context when this subscription completes to send a final trailer frame (Eg. a summary with the item count or the final cursor). Returning `None` sends no trailer.
+    ///
+    /// The hook only runs when the stream ends by itself and didn't yield an error, not when the client stops the subscription or disconnects. The trailer is sent as a `trailer` frame after the last event and it's type is exported as the `trailer` of the subscription.
+    /// The context is cloned when the subscription starts so it can be passed to the hook.
+    ///
+    /// This only applies to subscriptions and is ignored for queries and mutations.
+    pub fn on_complete<TCtx, TArg, TStream, TTrailer>(
+        mut self,
+        hook: impl Fn(TCtx) -> Option<TTrailer> + Send + Sync + 'static,
+    ) -> Self
+    where
+        TResolver: Fn(TCtx, TArg) -> TStream,
+        TStream: Stream,
+        TCtx: Clone + Send + 'static,
+        TTrailer: Serialize + Type,
+    {
+        let hook = Arc::new(hook);
+        let hook: OnCompleteFn<TCtx> = Arc::new(move |ctx: &TCtx| {
+            let (hook, ctx) = (hook.clone(), ctx.clone());
+            Box::new(move || {
+                hook(ctx).map(|trailer| {
+                    serde_json::to_value(trailer).map_err(ExecError::SerializingResultErr)
+                })
+            })
+        });
+        self.options.on_complete = Some(OnComplete {
+            hook: Arc::new(hook),
+            typedef: |defs| TTrailer::reference(defs, &[]).inner,
+        });
+        self
+    }
+
+    /// Group the items yielded by this subscription into arrays, so the client can process them in batches. The exported type of the subscription becomes an array of it's items.
+    ///
+    /// A batch is sent as soon as it has `count` items, or `max_delay` after it's first item was yielded, whichever comes first. A partial batch is sent immediately when the stream completes or yields an error.
+    /// The timer uses the router's [`Runtime`](crate::Runtime). Items are grouped after [`map_item`](Self::map_item) is applied.
+    ///
+    /// This only applies to subscriptions and is ignored for queries and mutations.
+    #[allow(clippy::panic)]
+    pub fn buffer(mut self, count: usize, max_delay: Duration) -> Self {
+        if count == 0 {
+            panic!("rspc error: a subscription's buffer must hold at least one item");
+        }
+        self.options.buffer = Some((count, max_delay));
+        self
+    }
+
+    /// Poll this subscription's stream on it's own task instead of the task driving the subscription, so a CPU-heavy stream can't starve the transport or the other requests on the connection.
+    ///
+    /// The task is spawned with the router's [`Runtime`](crate::Runtime) and sends the stream's items to the transport through a bounded channel of 16 items.
+    /// Once the channel is full the task stops polling the stream until the transport catches up, so a slow client applies backpressure to the stream instead of it's items building up in memory.
+    /// The task stops as soon as the subscription is stopped or the connection is closed, even while the stream is idle.
+    ///
+    /// The stream and it's items are moved to the spawned task, so they must be `Send` (which is already required of every subscription). Items are serialized and [mapped](Self::map_item) on the spawned task.
+    ///
+    /// This only applies to subscriptions and is ignored for queries and mutations.
+    pub fn spawn(mut self) -> Self {
+        self.options.spawn = true;
+        self
+    }
+
+    /// Serialize up to `concurrency` items of this subscription at once on tasks of the router's [`Runtime`](crate::Runtime), for streams whose items are CPU-heavy to serialize (Eg. large documents).
+    ///
+    /// Items are still sent in the order the stream yields them. Ones which finish serializing early are held in a reorder window until every earlier item is done, and the stream isn't polled while the window is full, so at most `concurrency` items are buffered.
+    /// This trades latency for throughput: an item can't be sent until every item before it is serialized, so one slow item holds up those behind it, and each item pays for a task being spawned. Only use it when serialization is the bottleneck, as for cheap items it's slower than serializing them inline.
+    ///
+    /// Items are [mapped](Self::map_item) on the serializing tasks. They're serialized with blocking calls, so a large `concurrency` can occupy every worker of the runtime.
+    ///
+    /// This only applies to subscriptions and is ignored for queries and mutations.
+    #[allow(clippy::panic)]
+    pub fn serialize_concurrently<TCtx, TArg, TStream, TItem>(mut self, concurrency: usize) -> Self
+    where
+        TResolver: Fn(TCtx, TArg) -> TStream,
+        TStream: Stream<Item = TItem>,
+        TItem: Send + 'static,
+    {
+        if concurrency == 0 {
+            panic!("rspc error: a subscription must serialize at least one item at once");
+        }
+        self.options.serialize_concurrently = Some(SerializeConcurrently {
+            concurrency,
+            into_send: |item| {
+                item.downcast::<TItem>()
+                    .expect("rspc: subscription item type mismatch")
+            },
+        });
+        self
+    }
+
+    /// Transform each item yielded by this subscription before it's serialized.
+    ///
+    /// The item is passed to `mapper` as it's original type and the exported type of the subscription becomes the mapper's return type.
+    /// Calling this again replaces the previous mapper.
+    ///
+    /// This only applies to subscriptions and is ignored for queries and mutations.
+    pub fn map_item<TCtx, TArg, TStream, TItem, TNewItem>(
+        mut self,
+        mapper: impl Fn(TItem) -> TNewItem + Send + Sync + 'static,
+    ) -> Self
+    where
+        TResolver: Fn(TCtx, TArg) -> TStream,
+        TStream: Stream<Item = TItem>,
+        TItem: 'static,
+        TNewItem: Serialize + Type,
+    {
+        self.options.map_item = Some(MapItem {
+            map: Arc::new(move |item| {
+                let item = item
+                    .downcast::<TItem>()
+                    .expect("rspc: subscription item type mismatch");
+                serialize_timer::to_value(&mapper(*item))
+            }),
+            typedef: |defs| TNewItem::reference(defs, &[]).inner,
+        });
+        self
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use futures::{stream, StreamExt};
+    use serde::Serialize;
+    use serde_json::json;
+    use specta::Type;
+
+    use crate::Router;
+
+    #[derive(Serialize, Type)]
+    struct Enriched {
+        value: i32,
+        doubled: i32,
+    }
+
+    #[tokio::test]
+    async fn test_map_item() {
+        let router = <Router>::new()
+            .subscription("numbers", |t| {
+                t(|_, max: i32| stream::iter(1..=max)).map_item(|value| Enriched {
+                    value,
+                    doubled: value * 2,
+                })
+            })
+            .build();
+
+        let items = router
+            .exec_subscription((), "numbers".into(), Some(json!(2)))
+            .await
+            .unwrap()
+            .map(|item| item.unwrap())
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(
+            items,
+            [
+                json!({ "value": 1, "doubled": 2 }),
+                json!({ "value": 2, "doubled": 4 })
+            ]
+        );
+
+        let path = std::env::temp_dir().join("rspc_test_map_item.ts");
+        router.export_ts(&path).unwrap();
+        let bindings = std::fs::read_to_string(&path).unwrap();
+        assert!(bindings.contains(r#"{ key: "numbers", input: number, result: Enriched }"#));
+    }
+
+    #[derive(Serialize, Type)]
+    struct User {
+        id: i32,
+    }
+
+    #[tokio::test]
+    async fn test_also_named() {
+        let router = <Router>::new()
+            .query("users.get", |t| {
+                t(|_, id: i32| User { id })
+                    .also_named("getUser")
+                    .also_named("user")
+            })
+            .build();
+
+        for key in ["users.get", "getUser", "user"] {
+            let result = router
+                .exec((), crate::ExecKind::Query, key.into(), Some(json!(1)))
+                .await;
+            assert_eq!(result.unwrap(), json!({ "id": 1 }));
+        }
+
+        let path = std::env::temp_dir().join("rspc_test_also_named.ts");
+        router.export_ts(&path).unwrap();
+        let bindings = std::fs::read_to_string(&path).unwrap();
+        for key in ["users.get", "getUser", "user"] {
+            assert!(bindings.contains(&format!(
+                r#"{{ key: "{key}", input: number, result: User }}"#
+            )));
+        }
+        assert_eq!(bindings.matches("export type User =").count(), 1);
+    }
 }
@@ -1,20 +1,42 @@
-use std::collections::BTreeMap;
+use std::{borrow::Cow, collections::BTreeMap, sync::Arc, time::Duration};
 
 use specta::DataType;
 
 use super::Layer;
+use crate::legacy::visibility::AnyVisibleFn;
 
 // TODO: Make private
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct ProcedureDataType {
     pub arg_ty: DataType,
     pub result_ty: DataType,
+    /// The type of the log lines streamed before the result by a [`WithLogs`](crate::WithLogs) result.
+    pub logs_ty: Option<DataType>,
+    /// The type of the trailer sent when a subscription completes, set with [`BuiltProcedureBuilder::on_complete`](crate::internal::BuiltProcedureBuilder::on_complete).
+    pub trailer_ty: Option<DataType>,
+    /// Set when the resolver returns [`NoContent`](crate::NoContent). The result type is exported as `void` and the success frame has no data.
+    pub no_content: bool,
+    /// The description set with [`BuiltProcedureBuilder::description`](crate::internal::BuiltProcedureBuilder::description).
+    pub description: Option<Cow<'static, str>>,
+    /// The message set with [`BuiltProcedureBuilder::deprecated`](crate::internal::BuiltProcedureBuilder::deprecated).
+    pub deprecated: Option<Cow<'static, str>>,
+    /// The tags added with [`BuiltProcedureBuilder::tag`](crate::internal::BuiltProcedureBuilder::tag).
+    pub tags: Vec<&'static str>,
+    /// The type of the `data` of this procedure's errors, set with [`BuiltProcedureBuilder::error_type`](crate::internal::BuiltProcedureBuilder::error_type).
+    pub error_ty: Option<DataType>,
+    /// The `Cache-Control` directive set with [`BuiltProcedureBuilder::cache_control`](crate::internal::BuiltProcedureBuilder::cache_control).
+    pub cache_control: Option<Cow<'static, str>>,
+    /// The latency target set with [`BuiltProcedureBuilder::sla`](crate::internal::BuiltProcedureBuilder::sla).
+    pub sla: Option<Duration>,
 }
 
 // TODO: Make private
 pub struct Procedure<TCtx> {
     pub exec: Box<dyn Layer<TCtx>>,
     pub ty: ProcedureDataType,
+    pub(crate) visible: Option<AnyVisibleFn>,
+    // Whether the resolver's result reaches the caller unchanged, so `Router::exec_into` can serialize it without building a `Value`
+    pub(crate) typed: bool,
 }
 
 pub struct ProcedureStore<TCtx> {
@@ -30,7 +52,14 @@ impl<TCtx> ProcedureStore<TCtx> {
         }
     }
 
-    pub fn append(&mut self, key: String, exec: Box<dyn Layer<TCtx>>, ty: ProcedureDataType) {
+    pub fn append(
+        &mut self,
+        key: String,
+        exec: Box<dyn Layer<TCtx>>,
+        ty: ProcedureDataType,
+        visible: Option<AnyVisibleFn>,
+        typed: bool,
+    ) {
         #[allow(clippy::panic)]
         if key.is_empty() || key == "ws" || key.starts_with("rpc.") || key.starts_with("rspc.") {
             panic!(
@@ -48,6 +77,43 @@ impl<TCtx> ProcedureStore<TCtx> {
             );
         }
 
-        self.store.insert(key, Procedure { exec, ty });
+        self.store.insert(
+            key,
+            Procedure {
+                exec,
+                ty,
+                visible,
+                typed,
+            },
+        );
+    }
+
+    /// Register a procedure under `key` and each of it's `aliases`. The aliases share the same layer so they dispatch identically.
+    pub(crate) fn append_with_aliases(
+        &mut self,
+        key: String,
+        aliases: &[&'static str],
+        exec: Box<dyn Layer<TCtx>>,
+        ty: ProcedureDataType,
+        visible: Option<AnyVisibleFn>,
+        typed: bool,
+    ) where
+        TCtx: 'static,
+    {
+        if aliases.is_empty() {
+            return self.append(key, exec, ty, visible, typed);
+        }
+
+        let exec: Arc<dyn Layer<TCtx>> = exec.into();
+        for alias in aliases {
+            self.append(
+                alias.to_string(),
+                Box::new(exec.clone()),
+                ty.clone(),
+                visible.clone(),
+                typed,
+            );
+        }
+        self.append(key, Box::new(exec), ty, visible, typed);
     }
 }
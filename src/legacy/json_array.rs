@@ -0,0 +1,222 @@
+use std::{fmt, marker::PhantomData};
+
+use futures::stream;
+use serde::{Serialize, Serializer};
+use specta::Type;
+
+use crate::{
+    internal::{
+        jsonrpc::{accepts_http_body, set_http_body},
+        LayerResult,
+    },
+    Error, ExecError, RawStream, RequestLayer,
+};
+
+// The size of the chunks the items are serialized into before they're sent
+const CHUNK_SIZE: usize = 64 * 1024;
+
+// The JSON-RPC response wrapping the array in the body, so it's the same as a regular response to the client
+const PREFIX: &[u8] = br#"{"jsonrpc":"2.0","id":null,"result":{"type":"response","data":["#;
+const SUFFIX: &[u8] = b"]}}";
+
+/// A collection result (Eg. a large `Vec`) which is serialized item by item straight into the HTTP response body, instead of being converted into a [`serde_json::Value`] first.
+///
+/// A regular result builds the whole `Value` tree of the collection in memory before it's sent, which for a million small structs is many times the size of the collection itself.
+/// With this the items are written with [`serde_json::to_writer`] into chunks of 64 KiB which are sent as they fill up, so the memory used while sending is bounded by the chunk size.
+///
+/// ## Transport behavior
+///
+///  - **HTTP**: the body is the same JSON-RPC response as a regular result, streamed as the items are serialized.
+///  - **Everything else** (Eg. WebSocket, [`Router::exec`](crate::Router::exec)): the collection is converted into a `Value` as usual, as it has to be embedded in a frame.
+///
+/// The exported type of the procedure is `T[]`.
+///
+/// Like [`RawStream`] the body bypasses everything which rewrites the result's JSON, so don't use this on a procedure which relies on [`Config::rename_fields`](crate::Config::rename_fields), `restrict_field` or a [`Config::rewrite_frames`](crate::Config::rewrite_frames) hook.
+/// An item which fails to serialize aborts the response, as the status code has already been sent.
+///
+/// ```rust
+/// use rspc::JsonArray;
+///
+/// #[derive(serde::Serialize, specta::Type)]
+/// struct Point { x: u32, y: u32 }
+///
+/// let router = <rspc::Router>::new()
+///     .query("points", |t| {
+///         t(|_, _: ()| JsonArray((0..1_000_000).map(|x| Point { x, y: x * 2 })))
+///     })
+///     .build();
+/// ```
+pub struct JsonArray<I>(pub I);
+
+impl<I> fmt::Debug for JsonArray<I> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("JsonArray").finish_non_exhaustive()
+    }
+}
+
+pub struct JsonArrayMarker(PhantomData<()>);
+impl<I, T> RequestLayer<JsonArrayMarker> for JsonArray<I>
+where
+    I: IntoIterator<Item = T>,
+    I::IntoIter: Send + 'static,
+    T: Serialize + Type,
+{
+    type Result = Vec<T>;
+
+    fn into_layer_result(self) -> Result<LayerResult, ExecError> {
+        if !accepts_http_body() {
+            return Ok(LayerResult::Ready(
+                serde_json::value::Serializer
+                    .collect_seq(self.0)
+                    .map_err(ExecError::SerializingResultErr),
+            ));
+        }
+
+        let chunks = Chunks {
+            items: Some(self.0.into_iter()),
+            first: true,
+        };
+        let body = RawStream::new(stream::iter(chunks)).content_type("application/json");
+        let _ = set_http_body(body);
+        Ok(LayerResult::Ready(Ok(serde_json::Value::Null)))
+    }
+}
+
+impl<I, T> RequestLayer<JsonArrayMarker> for Result<JsonArray<I>, Error>
+where
+    I: IntoIterator<Item = T>,
+    I::IntoIter: Send + 'static,
+    T: Serialize + Type,
+{
+    type Result = Vec<T>;
+
+    fn into_layer_result(self) -> Result<LayerResult, ExecError> {
+        self.map_err(ExecError::ErrResolverError)?
+            .into_layer_result()
+    }
+}
+
+// Serializes the items into chunks of the body as it's polled
+struct Chunks<I> {
+    // `None` once the array has been closed
+    items: Option<I>,
+    first: bool,
+}
+
+impl<I: Iterator<Item = T>, T: Serialize> Iterator for Chunks<I> {
+    type Item = Result<Vec<u8>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let items = self.items.as_mut()?;
+        let mut chunk = Vec::with_capacity(CHUNK_SIZE);
+        if self.first {
+            chunk.extend_from_slice(PREFIX);
+        }
+
+        while chunk.len() < CHUNK_SIZE {
+            let Some(item) = items.next() else {
+                chunk.extend_from_slice(SUFFIX);
+                self.items = None;
+                break;
+            };
+            if !std::mem::take(&mut self.first) {
+                chunk.push(b',');
+            }
+            if let Err(err) = serde_json::to_writer(&mut chunk, &item) {
+                self.items = None;
+                return Some(Err(ExecError::SerializingResultErr(err).into()));
+            }
+        }
+        Some(Ok(chunk))
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use std::sync::Arc;
+
+    use futures::StreamExt;
+    use serde::Serialize;
+    use serde_json::{json, Value};
+    use specta::Type;
+
+    use crate::{
+        internal::jsonrpc::{
+            self, handle_json_rpc, with_http_response, RequestId, Sender, SubscriptionMap,
+        },
+        ExecKind, JsonArray, Router,
+    };
+
+    use super::CHUNK_SIZE;
+
+    #[derive(Serialize, Type)]
+    struct Point {
+        x: u32,
+        y: u32,
+    }
+
+    const COUNT: u32 = 200_000;
+
+    #[tokio::test]
+    async fn test_json_array() {
+        let router = Arc::new(
+            <Router>::new()
+                .query("points", |t| {
+                    t(|_, _: ()| JsonArray((0..COUNT).map(|x| Point { x, y: x * 2 })))
+                })
+                .build(),
+        );
+        let bindings = router.ts_bindings().unwrap();
+        assert!(bindings.contains("result: Point[]"));
+
+        // Non-HTTP transports get a regular value
+        let result = router
+            .exec((), ExecKind::Query, "points".into(), None)
+            .await
+            .unwrap();
+        assert_eq!(result.as_array().unwrap().len(), COUNT as usize);
+        assert_eq!(result[1], json!({ "x": 1, "y": 2 }));
+
+        let mut resp = Sender::Response(None);
+        let (_, http) = with_http_response(handle_json_rpc(
+            (),
+            jsonrpc::Request {
+                jsonrpc: None,
+                id: RequestId::Null,
+                inner: jsonrpc::RequestInner::Query {
+                    path: "points".into(),
+                    input: None,
+                },
+            },
+            &router,
+            &mut resp,
+            &mut SubscriptionMap::None,
+        ))
+        .await;
+        let (content_type, mut body) = http.body.unwrap().into_parts();
+        assert_eq!(content_type, "application/json");
+
+        // The body is sent in bounded chunks rather than all at once
+        let mut bytes = Vec::new();
+        let mut chunks = 0;
+        while let Some(chunk) = body.next().await {
+            let chunk = chunk.unwrap();
+            assert!(chunk.len() < CHUNK_SIZE + 64);
+            bytes.extend(chunk);
+            chunks += 1;
+        }
+        assert!(chunks > 1);
+
+        // It's the same response as a regular result
+        let resp: Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(resp["result"]["type"], "response");
+        let points = resp["result"]["data"].as_array().unwrap();
+        assert_eq!(points.len(), COUNT as usize);
+        assert_eq!(
+            points[COUNT as usize - 1],
+            json!({ "x": COUNT - 1, "y": (COUNT - 1) * 2 })
+        );
+        assert_eq!(*points, result.as_array().unwrap()[..]);
+    }
+}
@@ -0,0 +1,182 @@
+use std::{fmt, future::ready, marker::PhantomData, pin::Pin};
+
+use futures::{Stream, StreamExt};
+use serde::Serialize;
+use specta::{DataType, Type, TypeMap};
+
+use crate::{
+    internal::{
+        jsonrpc::{frame_sink, ResponseInner},
+        LayerResult, ValueOrStream,
+    },
+    ExecError, RequestLayer,
+};
+
+/// A result which streams log lines to the client while it's being computed, Eg. for a long-running deploy job.
+///
+/// Unlike a subscription the procedure has a definite final result, so the client's call resolves (or rejects) once it completes.
+/// The log type is exported as the `logs` field of the procedure in the bindings.
+///
+/// ## Wire protocol
+///
+/// Each log line is sent as a `{ type: "log", data: L }` frame with the id of the request, as soon as it's yielded.
+/// Once the log stream has ended *and* the result has resolved a single terminal `{ type: "response", data: T }` (or `{ type: "error", ... }`) frame is sent. No log frames are sent after it.
+/// The log stream should therefore end when the job finishes (Eg. by dropping the sending half of a channel), otherwise the response is held back.
+///
+/// Log frames are only sent over transports which support multiple frames per request (Eg. WebSocket). Over HTTP, or when calling [`Router::exec`](crate::Router::exec), the logs are discarded and only the result is returned.
+///
+/// ```rust
+/// use rspc::WithLogs;
+///
+/// let router = <rspc::Router>::new()
+///     .mutation("deploy", |t| {
+///         t(|_, _: ()| {
+///             let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+///             WithLogs::new(
+///                 futures::stream::poll_fn(move |cx| rx.poll_recv(cx)),
+///                 async move {
+///                     tx.send("building".to_string()).ok();
+///                     Ok::<_, rspc::Error>("deployed")
+///                 },
+///             )
+///         })
+///     })
+///     .build();
+/// ```
+pub struct WithLogs<L, F> {
+    logs: Pin<Box<dyn Stream<Item = L> + Send>>,
+    result: F,
+}
+
+impl<L, F> WithLogs<L, F> {
+    pub fn new(logs: impl Stream<Item = L> + Send + 'static, result: F) -> Self {
+        Self {
+            logs: Box::pin(logs),
+            result,
+        }
+    }
+}
+
+impl<L, F> fmt::Debug for WithLogs<L, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WithLogs").finish_non_exhaustive()
+    }
+}
+
+pub struct WithLogsMarker<TMarker>(PhantomData<TMarker>);
+impl<L, F, TMarker> RequestLayer<WithLogsMarker<TMarker>> for WithLogs<L, F>
+where
+    L: Serialize + Type + 'static,
+    F: RequestLayer<TMarker>,
+{
+    type Result = F::Result;
+
+    fn into_layer_result(self) -> Result<LayerResult, ExecError> {
+        let result = self.result.into_layer_result()?;
+        let logs = self.logs;
+
+        Ok(LayerResult::Future(Box::pin(async move {
+            let sink = frame_sink();
+            let logs = logs.for_each(|log| {
+                if let Some(sink) = &sink {
+                    match serde_json::to_value(&log) {
+                        Ok(log) => {
+                            let _ = sink.send(ResponseInner::Log(log));
+                        }
+                        Err(_err) => {
+                            #[cfg(feature = "tracing")]
+                            tracing::error!("Failed to serialize log: {:?}", _err);
+                        }
+                    }
+                }
+                ready(())
+            });
+
+            let ((), result) = futures::join!(logs, result.into_value_or_stream());
+            match result? {
+                ValueOrStream::Value(v) => Ok(v),
+                ValueOrStream::Stream(_) => Err(ExecError::UnsupportedMethod(
+                    "Subscription with logs".into(),
+                )),
+            }
+        })))
+    }
+
+    fn logs_type(defs: &mut TypeMap) -> Option<DataType> {
+        Some(L::reference(defs, &[]).inner)
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use std::sync::Arc;
+
+    use futures::stream;
+    use serde_json::json;
+    use tokio::sync::mpsc;
+
+    use crate::{
+        internal::jsonrpc::{self, handle_json_rpc, RequestId, Sender, SubscriptionMap},
+        ExecKind, Router, WithLogs,
+    };
+
+    #[tokio::test]
+    async fn test_with_logs() {
+        let router = Arc::new(
+            <Router>::new()
+                .mutation("deploy", |t| {
+                    t(|_, _: ()| {
+                        WithLogs::new(stream::iter(["building", "pushing"]), async {
+                            tokio::task::yield_now().await;
+                            Ok(42)
+                        })
+                    })
+                })
+                .build(),
+        );
+
+        let (mut tx, mut rx) = mpsc::unbounded_channel();
+        handle_json_rpc(
+            (),
+            jsonrpc::Request {
+                jsonrpc: None,
+                id: RequestId::Number(1),
+                inner: jsonrpc::RequestInner::Mutation {
+                    path: "deploy".into(),
+                    input: None,
+                },
+            },
+            &router,
+            &mut Sender::ResponseChannel(&mut tx),
+            &mut SubscriptionMap::None,
+        )
+        .await;
+        drop(tx);
+
+        let mut frames = Vec::new();
+        while let Some(resp) = rx.recv().await {
+            assert_eq!(resp.id, RequestId::Number(1));
+            frames.push(serde_json::to_value(resp.result).unwrap());
+        }
+        assert_eq!(
+            frames,
+            [
+                json!({ "type": "log", "data": "building" }),
+                json!({ "type": "log", "data": "pushing" }),
+                json!({ "type": "response", "data": 42 }),
+            ]
+        );
+
+        // Logs are discarded when the transport can't send them
+        let result = router
+            .exec((), ExecKind::Mutation, "deploy".into(), None)
+            .await;
+        assert_eq!(result.unwrap(), json!(42));
+
+        let bindings = router.ts_bindings().unwrap();
+        assert!(
+            bindings.contains(r#"{ key: "deploy", input: never, result: number, logs: string }"#)
+        );
+    }
+}
@@ -0,0 +1,203 @@
+use std::{
+    collections::HashMap,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use futures::Stream;
+use tokio::sync::{mpsc, Mutex};
+
+use crate::{
+    internal::jsonrpc::{
+        handle_json_rpc_with_connection, Connection, Request, Response, Sender, Sender2,
+        SubscriptionMap,
+    },
+    ConnectionId, Notifier, Router,
+};
+
+/// An in-process connection to a router, for driving the client side of a long-lived connection (Eg. a WebSocket) from a test without a real socket.
+///
+/// Requests sent with [`LoopbackConnection::send`] are executed by the same executor as the transport integrations, concurrently and with the router's connection level [`Config`](crate::Config) applied.
+/// The connection is a [`Stream`] of every frame the server sends back, including subscription events and [notifications](crate::notifier).
+/// Dropping it closes the connection and stops it's subscriptions.
+///
+/// Note: Requests are executed on the router's [`Runtime`](crate::Runtime) so one is required. They are reported as [`Transport::InProcess`](crate::Transport::InProcess).
+///
+/// ```rust
+/// use std::sync::Arc;
+///
+/// use futures::StreamExt;
+/// use rspc::{internal::jsonrpc::{Request, RequestId, RequestInner, ResponseInner}, LoopbackConnection};
+///
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() {
+/// let router = Arc::new(<rspc::Router>::new().query("ping", |t| t(|_, _: ()| "pong")).build());
+/// let mut connection = LoopbackConnection::new(router, || ());
+///
+/// connection.send(Request {
+///     jsonrpc: None,
+///     id: RequestId::Number(1),
+///     inner: RequestInner::Query { path: "ping".into(), input: None },
+/// });
+/// let response = connection.next().await.unwrap();
+/// assert!(matches!(response.result, ResponseInner::Response(v) if v == "pong"));
+/// # }
+/// ```
+pub struct LoopbackConnection {
+    requests: mpsc::UnboundedSender<Request>,
+    responses: mpsc::Receiver<Response>,
+    id: ConnectionId,
+}
+
+impl LoopbackConnection {
+    /// Open a connection to `router`, building the context of each request with `ctx`.
+    pub fn new<TCtx, TMeta>(
+        router: Arc<Router<TCtx, TMeta>>,
+        ctx: impl Fn() -> TCtx + Send + 'static,
+    ) -> Self
+    where
+        TCtx: Send + 'static,
+        TMeta: Send + Sync + 'static,
+    {
+        #[allow(clippy::panic)]
+        let Some(runtime) = router.config.runtime_or_default() else {
+            panic!("rspc error: attempted to open a `LoopbackConnection`, however the router doesn't have a runtime. See `Config::runtime`.");
+        };
+
+        let (requests_tx, mut requests_rx) = mpsc::unbounded_channel::<Request>();
+        let (tx, responses) = mpsc::channel::<Response>(100);
+        let connection =
+            Connection::new(&router).with_notifier(Notifier::new(Sender2::Channel(tx.clone())));
+        let id = connection.id();
+
+        runtime.clone().spawn(Box::pin(async move {
+            let subscriptions = Arc::new(Mutex::new(HashMap::new()));
+            while let Some(request) = requests_rx.recv().await {
+                let (router, connection, subscriptions, mut tx) = (
+                    router.clone(),
+                    connection.clone(),
+                    subscriptions.clone(),
+                    tx.clone(),
+                );
+                let ctx = ctx();
+                runtime.spawn(Box::pin(async move {
+                    handle_json_rpc_with_connection(
+                        ctx,
+                        request,
+                        &router,
+                        &mut Sender::Channel(&mut tx),
+                        &mut SubscriptionMap::Mutex(&subscriptions),
+                        &connection,
+                    )
+                    .await
+                }));
+            }
+
+            // The client end was dropped
+            connection.close();
+            subscriptions.lock().await.clear();
+        }));
+
+        Self {
+            requests: requests_tx,
+            responses,
+            id,
+        }
+    }
+
+    /// Send a request to the server. It's response (or for subscriptions, it's events) are yielded by the connection's stream.
+    pub fn send(&self, request: Request) {
+        // The server end only stops once this is dropped
+        let _ = self.requests.send(request);
+    }
+
+    /// Get a handle for sending requests to the server, Eg. from another task while this one reads the responses.
+    pub fn sender(&self) -> mpsc::UnboundedSender<Request> {
+        self.requests.clone()
+    }
+
+    /// The id of the connection, Eg. to cancel it's subscriptions with [`ActiveSubscriptions`](crate::ActiveSubscriptions).
+    pub fn id(&self) -> ConnectionId {
+        self.id
+    }
+}
+
+impl Stream for LoopbackConnection {
+    type Item = Response;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.responses.poll_recv(cx)
+    }
+}
+
+// The tests use the default runtime
+#[cfg(all(test, feature = "runtime-tokio"))]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use std::sync::Arc;
+
+    use futures::{stream, StreamExt};
+    use serde_json::json;
+
+    use crate::{
+        internal::jsonrpc::{Request, RequestId, RequestInner, ResponseInner},
+        LoopbackConnection, Router,
+    };
+
+    fn request(id: u32, inner: RequestInner) -> Request {
+        Request {
+            jsonrpc: None,
+            id: RequestId::Number(id),
+            inner,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_loopback_ping_echo() {
+        let router = Arc::new(
+            <Router>::new()
+                .query("ping", |t| t(|_, _: ()| "pong"))
+                .subscription("echo", |t| {
+                    t(|_, words: Vec<String>| stream::iter(words).chain(stream::pending()))
+                })
+                .build(),
+        );
+        let mut connection = LoopbackConnection::new(router, || ());
+
+        connection.send(request(
+            1,
+            RequestInner::Query {
+                path: "ping".into(),
+                input: None,
+            },
+        ));
+        let response = connection.next().await.unwrap();
+        assert_eq!(response.id, RequestId::Number(1));
+        assert!(matches!(response.result, ResponseInner::Response(v) if v == "pong"));
+
+        connection.send(request(
+            2,
+            RequestInner::Subscription {
+                path: "echo".into(),
+                input: (RequestId::Number(2), Some(json!(["hello", "world"]))),
+            },
+        ));
+        for word in ["hello", "world"] {
+            let response = connection.next().await.unwrap();
+            assert_eq!(response.id, RequestId::Number(2));
+            assert!(matches!(response.result, ResponseInner::Event(v) if v == word));
+        }
+
+        // The subscription keeps running until it's stopped, which is acknowledged after it's last event
+        connection.send(request(
+            3,
+            RequestInner::SubscriptionStop {
+                input: RequestId::Number(2),
+            },
+        ));
+        let response = connection.next().await.unwrap();
+        assert_eq!(response.id, RequestId::Number(2));
+        assert!(matches!(response.result, ResponseInner::Cancelled));
+    }
+}
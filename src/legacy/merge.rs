@@ -0,0 +1,236 @@
+use std::{
+    cmp::Ordering,
+    fmt,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures::Stream;
+
+type BoxedSource<T> = Pin<Box<dyn Stream<Item = T> + Send + Sync>>;
+
+type CompareFn<T> = Box<dyn Fn(&T, &T) -> Ordering + Send + Sync>;
+
+/// How [`merge_streams`] orders the items of it's sources.
+pub enum MergeOrder<T> {
+    /// Emit every item as soon as it's ready. This has the highest throughput and doesn't buffer anything.
+    Unordered,
+    /// Emit items sorted by the given comparison, assuming each source is already sorted by it (Eg. by timestamp).
+    ///
+    /// ## Cost
+    ///
+    /// An item can only be emitted once every source which hasn't ended has an item ready, otherwise a quieter source could still produce an earlier item.
+    /// This means one item is buffered per source and a single idle source holds back the items of every other source until it produces an item or ends.
+    /// While held back the other sources aren't polled, so anything buffering in front of them (Eg. a channel) grows in the meantime.
+    OrderedBy(CompareFn<T>),
+}
+
+impl<T> MergeOrder<T> {
+    /// Emit items sorted by `key`. See [`MergeOrder::OrderedBy`].
+    pub fn ordered_by_key<K: Ord>(key: impl Fn(&T) -> K + Send + Sync + 'static) -> Self {
+        Self::OrderedBy(Box::new(move |a, b| key(a).cmp(&key(b))))
+    }
+}
+
+impl<T> fmt::Debug for MergeOrder<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unordered => write!(f, "Unordered"),
+            Self::OrderedBy(_) => write!(f, "OrderedBy(..)"),
+        }
+    }
+}
+
+/// Merge multiple streams into one, for returning from a subscription which combines multiple sources.
+///
+/// The stream ends once every source has ended.
+///
+/// ```rust
+/// use futures::stream;
+/// use rspc::{merge_streams, MergeOrder};
+///
+/// let router = <rspc::Router>::new()
+///     .subscription("events", |t| {
+///         t(|_, _: ()| {
+///             merge_streams(
+///                 MergeOrder::ordered_by_key(|timestamp: &u32| *timestamp),
+///                 [stream::iter([1, 4]), stream::iter([2, 3])],
+///             )
+///         })
+///     })
+///     .build();
+/// ```
+pub fn merge_streams<T, S>(
+    order: MergeOrder<T>,
+    streams: impl IntoIterator<Item = S>,
+) -> MergeStreams<T>
+where
+    S: Stream<Item = T> + Send + Sync + 'static,
+{
+    let sources = streams
+        .into_iter()
+        .map(|stream| Source {
+            stream: Some(Box::pin(stream) as BoxedSource<T>),
+            ready: None,
+        })
+        .collect();
+
+    MergeStreams {
+        order,
+        sources,
+        next: 0,
+    }
+}
+
+struct Source<T> {
+    // `None` once the stream has ended
+    stream: Option<BoxedSource<T>>,
+    // The buffered item in ordered mode
+    ready: Option<T>,
+}
+
+/// The stream returned by [`merge_streams`].
+pub struct MergeStreams<T> {
+    order: MergeOrder<T>,
+    sources: Vec<Source<T>>,
+    // The source to poll first in unordered mode so no source is starved.
+    next: usize,
+}
+
+impl<T> MergeStreams<T> {
+    fn poll_unordered(&mut self, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        let len = self.sources.len();
+        for i in 0..len {
+            let idx = (self.next + i) % len;
+            let Some(stream) = &mut self.sources[idx].stream else {
+                continue;
+            };
+
+            match stream.as_mut().poll_next(cx) {
+                Poll::Ready(Some(item)) => {
+                    self.next = (idx + 1) % len;
+                    return Poll::Ready(Some(item));
+                }
+                Poll::Ready(None) => self.sources[idx].stream = None,
+                Poll::Pending => {}
+            }
+        }
+
+        match self.sources.iter().all(|source| source.stream.is_none()) {
+            true => Poll::Ready(None),
+            false => Poll::Pending,
+        }
+    }
+
+    fn poll_ordered(&mut self, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        let MergeOrder::OrderedBy(compare) = &self.order else {
+            return Poll::Pending;
+        };
+
+        let mut waiting = false;
+        for source in &mut self.sources {
+            if source.ready.is_some() {
+                continue;
+            }
+            let Some(stream) = &mut source.stream else {
+                continue;
+            };
+
+            match stream.as_mut().poll_next(cx) {
+                Poll::Ready(Some(item)) => source.ready = Some(item),
+                Poll::Ready(None) => source.stream = None,
+                Poll::Pending => waiting = true,
+            }
+        }
+        if waiting {
+            return Poll::Pending;
+        }
+
+        let mut earliest: Option<&mut Source<T>> = None;
+        for source in &mut self.sources {
+            if let Some(item) = &source.ready {
+                if earliest
+                    .as_ref()
+                    .and_then(|earliest| earliest.ready.as_ref())
+                    .is_none_or(|earliest| compare(item, earliest) == Ordering::Less)
+                {
+                    earliest = Some(source);
+                }
+            }
+        }
+
+        Poll::Ready(earliest.and_then(|source| source.ready.take()))
+    }
+}
+
+impl<T> Stream for MergeStreams<T> {
+    type Item = T;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        let this = self.get_mut();
+        match this.order {
+            MergeOrder::Unordered => this.poll_unordered(cx),
+            MergeOrder::OrderedBy(_) => this.poll_ordered(cx),
+        }
+    }
+}
+
+// `MergeStreams` never hands out pinned references to it's fields.
+impl<T> Unpin for MergeStreams<T> {}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use futures::{stream, FutureExt, StreamExt};
+    use tokio::sync::mpsc;
+
+    use super::*;
+
+    fn channel() -> (
+        mpsc::UnboundedSender<u32>,
+        impl Stream<Item = u32> + Send + Sync,
+    ) {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        (tx, stream::poll_fn(move |cx| rx.poll_recv(cx)))
+    }
+
+    #[tokio::test]
+    async fn test_merge_streams() {
+        let mut items = merge_streams(
+            MergeOrder::Unordered,
+            [stream::iter([1, 4, 5]), stream::iter([2, 3, 6])],
+        )
+        .collect::<Vec<_>>()
+        .await;
+        items.sort();
+        assert_eq!(items, [1, 2, 3, 4, 5, 6]);
+
+        let items = merge_streams(
+            MergeOrder::ordered_by_key(|v: &u32| *v),
+            [stream::iter([1, 4, 5]), stream::iter([2, 3, 6])],
+        )
+        .collect::<Vec<_>>()
+        .await;
+        assert_eq!(items, [1, 2, 3, 4, 5, 6]);
+
+        // An idle source holds back the others in ordered mode but not in unordered mode
+        let (a_tx, a) = channel();
+        let (b_tx, b) = channel();
+        let mut ordered = merge_streams(MergeOrder::ordered_by_key(|v: &u32| *v), [a, b]);
+        let (c_tx, c) = channel();
+        let (_d_tx, d) = channel();
+        let mut unordered = merge_streams(MergeOrder::Unordered, [c, d]);
+
+        a_tx.send(5).unwrap();
+        c_tx.send(5).unwrap();
+        assert_eq!(ordered.next().now_or_never(), None);
+        assert_eq!(unordered.next().now_or_never(), Some(Some(5)));
+
+        b_tx.send(3).unwrap();
+        assert_eq!(ordered.next().now_or_never(), Some(Some(3)));
+        drop(b_tx);
+        assert_eq!(ordered.next().now_or_never(), Some(Some(5)));
+        drop(a_tx);
+        assert_eq!(ordered.next().now_or_never(), Some(None));
+    }
+}
@@ -55,6 +55,7 @@ where
     TLayerCtx: Send,
     TState: Send,
 {
+    /// Replace the context passed to the next layer. Every procedure registered after this middleware receives a `TNewCtx`, see [`RouterBuilder::middleware`](crate::RouterBuilder::middleware).
     pub fn with_ctx<TNewCtx>(
         self,
         new_ctx: TNewCtx,
@@ -0,0 +1,235 @@
+use std::{marker::PhantomData, sync::Arc};
+
+use serde_json::Value;
+
+use crate::{
+    internal::{Layer, LayerResult, RequestContext},
+    ExecError, MiddlewareBuilder, MiddlewareLike,
+};
+
+/// A reusable list of middleware, built once and applied to any number of routers with [`RouterBuilder::stack`](crate::RouterBuilder::stack).
+///
+/// A stack is applied like a single middleware at the point it's attached, so it only applies to procedures registered after it.
+/// It's middleware run in the order they were added to the stack, outside of any middleware added to the router after the stack and inside of any added before it.
+///
+/// ```text
+/// .middleware(a).stack(&[b, c]).middleware(d).query(..)  =>  a -> b -> c -> d -> resolver
+/// ```
+///
+/// Note: Every middleware in a stack must keep the context unchanged. Middleware which replace the context must be added to the router directly.
+///
+/// ```rust
+/// use rspc::{MiddlewareStack, Router};
+///
+/// let common = MiddlewareStack::<()>::new()
+///     .middleware(|mw| mw.middleware(|mw| async move { Ok(mw) }))
+///     .middleware(|_| rspc::SampledLogger::new(0.01, |log| println!("{log:?}")));
+///
+/// let users = Router::<()>::new()
+///     .stack(&common)
+///     .query("list", |t| t(|_, _: ()| vec!["alice", "bob"]));
+///
+/// let router = Router::<()>::new()
+///     .stack(&common)
+///     .query("version", |t| t(|_, _: ()| env!("CARGO_PKG_VERSION")))
+///     .merge("users.", users)
+///     .build();
+/// ```
+pub struct MiddlewareStack<TCtx> {
+    layers: Arc<Vec<Arc<dyn StackMiddleware<TCtx>>>>,
+}
+
+impl<TCtx> Clone for MiddlewareStack<TCtx> {
+    fn clone(&self) -> Self {
+        Self {
+            layers: self.layers.clone(),
+        }
+    }
+}
+
+impl<TCtx> Default for MiddlewareStack<TCtx> {
+    fn default() -> Self {
+        Self {
+            layers: Arc::new(Vec::new()),
+        }
+    }
+}
+
+impl<TCtx> MiddlewareStack<TCtx>
+where
+    TCtx: Send + Sync + 'static,
+{
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a middleware to the end of the stack, so it runs after every middleware already in it.
+    pub fn middleware<TMiddleware>(
+        mut self,
+        builder: impl Fn(MiddlewareBuilder<TCtx>) -> TMiddleware,
+    ) -> Self
+    where
+        TMiddleware: MiddlewareLike<TCtx, NewCtx = TCtx> + Send + Sync + 'static,
+    {
+        Arc::make_mut(&mut self.layers).push(Arc::new(builder(MiddlewareBuilder(PhantomData))));
+        self
+    }
+}
+
+impl<TCtx> MiddlewareLike<TCtx> for MiddlewareStack<TCtx>
+where
+    TCtx: Send + Sync + 'static,
+{
+    type State = ();
+    type NewCtx = TCtx;
+
+    fn handle<TMiddleware: Layer<Self::NewCtx> + 'static>(
+        &self,
+        ctx: TCtx,
+        input: Value,
+        req: RequestContext,
+        next: Arc<TMiddleware>,
+    ) -> Result<LayerResult, ExecError> {
+        StackLayer {
+            layers: self.layers.clone(),
+            index: 0,
+            next,
+        }
+        .call(ctx, input, req)
+    }
+}
+
+// A type erased middleware which doesn't change the context
+trait StackMiddleware<TCtx: 'static>: Send + Sync {
+    fn handle(
+        &self,
+        ctx: TCtx,
+        input: Value,
+        req: RequestContext,
+        next: Arc<StackLayer<TCtx>>,
+    ) -> Result<LayerResult, ExecError>;
+}
+
+impl<TCtx, TMiddleware> StackMiddleware<TCtx> for TMiddleware
+where
+    TCtx: Send + Sync + 'static,
+    TMiddleware: MiddlewareLike<TCtx, NewCtx = TCtx> + Send + Sync + 'static,
+{
+    fn handle(
+        &self,
+        ctx: TCtx,
+        input: Value,
+        req: RequestContext,
+        next: Arc<StackLayer<TCtx>>,
+    ) -> Result<LayerResult, ExecError> {
+        MiddlewareLike::handle(self, ctx, input, req, next)
+    }
+}
+
+// The rest of the stack from `index`, followed by the layer the stack was attached in front of
+struct StackLayer<TCtx: 'static> {
+    layers: Arc<Vec<Arc<dyn StackMiddleware<TCtx>>>>,
+    index: usize,
+    next: Arc<dyn Layer<TCtx>>,
+}
+
+impl<TCtx: Send + Sync + 'static> Layer<TCtx> for StackLayer<TCtx> {
+    fn call(&self, ctx: TCtx, input: Value, req: RequestContext) -> Result<LayerResult, ExecError> {
+        match self.layers.get(self.index) {
+            Some(layer) => layer.handle(
+                ctx,
+                input,
+                req,
+                Arc::new(StackLayer {
+                    layers: self.layers.clone(),
+                    index: self.index + 1,
+                    next: self.next.clone(),
+                }),
+            ),
+            None => self.next.call(ctx, input, req),
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use serde_json::Value;
+
+    use crate::{
+        internal::{Layer, LayerResult, RequestContext},
+        ExecError, ExecKind, MiddlewareBuilder, MiddlewareLike, MiddlewareStack, Router,
+    };
+
+    type Calls = Arc<Mutex<Vec<&'static str>>>;
+
+    // Records it's name when a request passes through it
+    #[derive(Clone)]
+    struct Record(Calls, &'static str);
+
+    impl MiddlewareLike<()> for Record {
+        type State = ();
+        type NewCtx = ();
+
+        fn handle<TMiddleware: Layer<()> + 'static>(
+            &self,
+            ctx: (),
+            input: Value,
+            req: RequestContext,
+            next: Arc<TMiddleware>,
+        ) -> Result<LayerResult, ExecError> {
+            self.0.lock().unwrap().push(self.1);
+            next.call(ctx, input, req)
+        }
+    }
+
+    fn record(calls: &Calls, name: &'static str) -> impl Fn(MiddlewareBuilder<()>) -> Record {
+        let calls = calls.clone();
+        move |_| Record(calls.clone(), name)
+    }
+
+    #[tokio::test]
+    async fn test_middleware_stack() {
+        let calls = Calls::default();
+        let stack = MiddlewareStack::new()
+            .middleware(record(&calls, "auth"))
+            .middleware(record(&calls, "logging"));
+
+        let users = <Router>::new()
+            .stack(&stack)
+            .query("list", |t| t(|_, _: ()| "users"));
+        let router = <Router>::new()
+            .middleware(record(&calls, "outer"))
+            .stack(&stack)
+            .middleware(record(&calls, "inner"))
+            .query("version", |t| t(|_, _: ()| "1.0.0"))
+            .mutation("restart", |t| t(|_, _: ()| ()))
+            .merge("users.", users)
+            .build();
+
+        for (kind, key, expected) in [
+            (
+                ExecKind::Query,
+                "version",
+                &["outer", "auth", "logging", "inner"][..],
+            ),
+            (
+                ExecKind::Mutation,
+                "restart",
+                &["outer", "auth", "logging", "inner"],
+            ),
+            // Merged routers are wrapped by the middleware of the router they're merged into
+            (
+                ExecKind::Query,
+                "users.list",
+                &["outer", "auth", "logging", "inner", "auth", "logging"],
+            ),
+        ] {
+            calls.lock().unwrap().clear();
+            router.exec((), kind, key.into(), None).await.unwrap();
+            assert_eq!(*calls.lock().unwrap(), expected, "{key}");
+        }
+    }
+}
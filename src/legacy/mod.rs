@@ -1,21 +1,120 @@
+mod active_subscriptions;
+mod auth;
+mod binary_format;
+mod buffer;
+mod cache;
+mod cache_control;
+mod concurrent_serialize;
 mod config;
+mod data_loader;
+mod dedup;
+mod deserialize;
 mod error;
+mod fan_out;
+mod feature_flags;
+mod field_access;
+mod input_pipeline;
+mod json_array;
+mod logs;
+mod loopback;
+mod merge;
 mod middleware;
+mod middleware_stack;
+mod mutation_hook;
+mod no_content;
+mod openrpc;
+mod page;
+mod partial;
+mod priority;
+mod progress;
+mod query_params;
+mod raw_stream;
+mod redirect;
+mod rename;
+mod replay;
+mod representation;
+mod request_span;
 mod resolver;
 mod resolver_result;
 mod router;
 mod router_builder;
+mod router_group;
+mod runtime;
+mod sampled_logger;
+mod schema_version;
 mod selection;
+mod serialize_timer;
+mod sla;
+mod snapshot;
+mod snapshot_merge;
+mod spawn;
+mod subscription_hooks;
+mod trace_context;
+mod transform;
+mod validate;
+mod virtual_fields;
+mod visibility;
+mod warmup;
+mod with_meta;
+mod zod;
 
-pub use config::Config;
-pub use error::{Error, ErrorCode, ExecError, ExportError};
+pub use active_subscriptions::{ActiveSubscriptions, ConnectionId};
+pub use auth::{Authenticate, Authenticator, WithAuth};
+pub use binary_format::BinaryFormat;
+pub use cache::Caches;
+pub use config::{
+    BufferOverflow, CloseFrame, Config, ErrorVerbosity, HookFailure, OverloadBehavior,
+};
+pub use data_loader::DataLoader;
+pub use dedup::Dedup;
+pub use deserialize::{Constraint, FieldError};
+pub use error::{
+    BuildError, Error, ErrorCode, ErrorKind, ExecError, ExecIntoError, ExportError, NotifyError,
+};
+pub use fan_out::{FanOut, FanOutStream, SlowSubscriber};
+pub use feature_flags::{FeatureFlagProvider, FeatureFlags, WithFeatures};
+pub use field_access::Capabilities;
+pub use input_pipeline::InputPipeline;
+pub use json_array::{JsonArray, JsonArrayMarker};
+pub use logs::{WithLogs, WithLogsMarker};
+pub use loopback::LoopbackConnection;
+pub use merge::{merge_streams, MergeOrder, MergeStreams};
 pub use middleware::{
     Middleware, MiddlewareBuilder, MiddlewareContext, MiddlewareLike, MiddlewareWithResponseHandler,
 };
+pub use middleware_stack::MiddlewareStack;
+pub use no_content::{NoContent, NoContentMarker};
+pub use openrpc::OpenRpcInfo;
+pub use page::Page;
+pub use partial::{Partial, PartialMarker, Patch};
+pub use priority::Priority;
+pub use progress::Progress;
+pub use raw_stream::{RawChunk, RawStream, RawStreamMarker};
+pub use redirect::{Redirect, RedirectMarker};
+pub use rename::RenameRule;
+pub use replay::{Replay, ReplayStream};
+pub use representation::representation;
+pub use request_span::record;
 pub use resolver::{typedef, DoubleArgMarker, DoubleArgStreamMarker, Resolver, StreamResolver};
 pub use resolver_result::{FutureMarker, RequestLayer, ResultMarker, SerializeMarker};
 pub use router::{ExecKind, Router};
 pub use router_builder::RouterBuilder;
+pub use router_group::RouterGroup;
+pub use runtime::Runtime;
+#[cfg(feature = "runtime-tokio")]
+pub use runtime::TokioRuntime;
+pub use sampled_logger::{RandomSampler, RequestLog, SampledLogger, Sampler};
+pub use snapshot::SnapshotEvent;
+pub use snapshot_merge::{MergedEvent, SnapshotMerge, SourceError};
+pub use trace_context::traceparent;
+pub use validate::{validate_value, ValidationError};
+pub use virtual_fields::{resolve_virtual, virtual_requested};
+pub use with_meta::{ResultMeta, WithMeta};
+
+pub use internal::jsonrpc::{
+    connection_id, connection_status, headers, is_connected, notifier, transport, ConnectionStatus,
+    Headers, Notifier, Transport,
+};
 
 pub mod internal;
 
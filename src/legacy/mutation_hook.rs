@@ -0,0 +1,161 @@
+use std::sync::Arc;
+
+use futures::future::BoxFuture;
+use serde_json::Value;
+
+use crate::{
+    internal::{Layer, LayerResult, Procedure, ProcedureStore, RequestContext, ValueOrStream},
+    Error, ExecError, HookFailure,
+};
+
+/// A hook registered with [`Config::after_mutation`](crate::Config::after_mutation).
+pub(crate) type MutationHookFn =
+    Arc<dyn Fn(&str, &Value, &Value) -> BoxFuture<'static, Result<(), Error>> + Send + Sync>;
+
+/// Run `hook` after every successful mutation.
+pub(crate) fn after_mutation<TCtx: 'static>(
+    (hook, on_failure): &(MutationHookFn, HookFailure),
+    mut procedures: ProcedureStore<TCtx>,
+) -> ProcedureStore<TCtx> {
+    procedures.store = std::mem::take(&mut procedures.store)
+        .into_iter()
+        .map(|(key, procedure)| {
+            let exec = Box::new(MutationHookLayer {
+                next: procedure.exec,
+                key: key.as_str().into(),
+                hook: hook.clone(),
+                on_failure: *on_failure,
+            });
+            (
+                key,
+                Procedure {
+                    exec,
+                    ty: procedure.ty,
+                    visible: procedure.visible,
+                    typed: false,
+                },
+            )
+        })
+        .collect();
+    procedures
+}
+
+struct MutationHookLayer<TCtx: 'static> {
+    next: Box<dyn Layer<TCtx>>,
+    key: Arc<str>,
+    hook: MutationHookFn,
+    on_failure: HookFailure,
+}
+
+impl<TCtx: 'static> Layer<TCtx> for MutationHookLayer<TCtx> {
+    fn call(&self, ctx: TCtx, input: Value, req: RequestContext) -> Result<LayerResult, ExecError> {
+        let result = self.next.call(ctx, input.clone(), req)?;
+        let (key, hook, on_failure) = (self.key.clone(), self.hook.clone(), self.on_failure);
+
+        Ok(LayerResult::FutureValueOrStream(Box::pin(async move {
+            let value = match result.into_value_or_stream().await? {
+                ValueOrStream::Value(value) => value,
+                stream => return Ok(stream),
+            };
+
+            if let Err(err) = hook(&key, &input, &value).await {
+                match on_failure {
+                    HookFailure::Fail => return Err(ExecError::ErrResolverError(err)),
+                    HookFailure::Log => {
+                        #[cfg(feature = "tracing")]
+                        tracing::error!("Error running mutation hook of '{}': {:?}", key, err);
+                    }
+                }
+            }
+
+            Ok(ValueOrStream::Value(value))
+        })))
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use serde_json::{json, Value};
+
+    use crate::{Config, Error, ErrorCode, ExecError, ExecKind, HookFailure, Router};
+
+    type Bus = Arc<Mutex<Vec<(String, Value, Value)>>>;
+
+    fn router(bus: Bus, on_failure: HookFailure) -> Router {
+        <Router>::new()
+            .config(Config::new().after_mutation(
+                move |key, input, result| {
+                    let bus = bus.clone();
+                    let event = (key.to_string(), input.clone(), result.clone());
+                    async move {
+                        if event.0 == "unpublishable" {
+                            return Err(Error::new(
+                                ErrorCode::InternalServerError,
+                                "bus is down".into(),
+                            ));
+                        }
+                        bus.lock().unwrap().push(event);
+                        Ok(())
+                    }
+                },
+                on_failure,
+            ))
+            .query("get", |t| t(|_, _: ()| 1))
+            .mutation("create", |t| t(|_, name: String| format!("created {name}")))
+            .mutation("fail", |t| {
+                t(|_, _: ()| Err::<(), _>(Error::new(ErrorCode::BadRequest, "nope".into())))
+            })
+            .mutation("unpublishable", |t| t(|_, _: ()| ()))
+            .build()
+    }
+
+    #[tokio::test]
+    async fn test_after_mutation() {
+        let bus = Bus::default();
+        let router = router(bus.clone(), HookFailure::Fail);
+
+        assert_eq!(
+            router
+                .exec((), ExecKind::Mutation, "create".into(), Some(json!("post")))
+                .await
+                .unwrap(),
+            json!("created post")
+        );
+        router
+            .exec((), ExecKind::Query, "get".into(), None)
+            .await
+            .unwrap();
+        assert!(router
+            .exec((), ExecKind::Mutation, "fail".into(), None)
+            .await
+            .is_err());
+
+        // Only the successful mutation is published
+        assert_eq!(
+            *bus.lock().unwrap(),
+            [("create".to_string(), json!("post"), json!("created post"))]
+        );
+
+        assert!(matches!(
+            router
+                .exec((), ExecKind::Mutation, "unpublishable".into(), None)
+                .await,
+            Err(ExecError::ErrResolverError(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_after_mutation_log() {
+        let router = router(Bus::default(), HookFailure::Log);
+        assert_eq!(
+            router
+                .exec((), ExecKind::Mutation, "unpublishable".into(), None)
+                .await
+                .unwrap(),
+            Value::Null
+        );
+    }
+}
@@ -0,0 +1,130 @@
+use std::marker::PhantomData;
+
+use serde_json::Value;
+
+use crate::{internal::LayerResult, Error, ExecError, RequestLayer};
+
+/// A result for procedures which have nothing meaningful to return, Eg. a mutation deleting a record.
+///
+/// The exported type of the procedure's result is `void` instead of `null`.
+///
+/// ## Transport behavior
+///
+///  - **JSON-RPC** (Eg. WebSocket): the success frame is `{ type: "noContent" }`, without any data.
+///  - **HTTP**: the response is a `204 No Content` with no body.
+///  - **[`Router::exec`](crate::Router::exec)**: the result is `null`.
+///
+/// This can be returned from queries and mutations, directly or via `Result<NoContent, rspc::Error>` or a future.
+///
+/// ```rust
+/// use rspc::NoContent;
+///
+/// let router = <rspc::Router>::new()
+///     .mutation("users.delete", |t| t(|_, _id: u32| async move { NoContent }))
+///     .build();
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NoContent;
+
+pub struct NoContentMarker(PhantomData<()>);
+impl RequestLayer<NoContentMarker> for NoContent {
+    type Result = ();
+
+    fn into_layer_result(self) -> Result<LayerResult, ExecError> {
+        Ok(LayerResult::Ready(Ok(Value::Null)))
+    }
+
+    fn no_content() -> bool {
+        true
+    }
+}
+
+impl RequestLayer<NoContentMarker> for Result<NoContent, Error> {
+    type Result = ();
+
+    fn into_layer_result(self) -> Result<LayerResult, ExecError> {
+        self.map_err(ExecError::ErrResolverError)?
+            .into_layer_result()
+    }
+
+    fn no_content() -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use std::sync::Arc;
+
+    use serde_json::{json, Value};
+
+    use crate::{
+        internal::jsonrpc::{self, handle_json_rpc, RequestId, Sender, SubscriptionMap},
+        Error, ErrorCode, ExecKind, NoContent, Router,
+    };
+
+    #[tokio::test]
+    async fn test_no_content() {
+        let router = Arc::new(
+            <Router>::new()
+                .mutation("users.delete", |t| {
+                    t(|_, id: u32| async move {
+                        match id {
+                            0 => Err(Error::new(ErrorCode::NotFound, "no such user".into())),
+                            _ => Ok(NoContent),
+                        }
+                    })
+                })
+                .build(),
+        );
+
+        let call = |id: u32| {
+            let router = router.clone();
+            async move {
+                let mut resp = Sender::Response(None);
+                handle_json_rpc(
+                    (),
+                    jsonrpc::Request {
+                        jsonrpc: None,
+                        id: RequestId::Number(1),
+                        inner: jsonrpc::RequestInner::Mutation {
+                            path: "users.delete".into(),
+                            input: Some(json!(id)),
+                        },
+                    },
+                    &router,
+                    &mut resp,
+                    &mut SubscriptionMap::None,
+                )
+                .await;
+                match resp {
+                    Sender::Response(Some(resp)) => serde_json::to_value(resp).unwrap(),
+                    _ => Value::Null,
+                }
+            }
+        };
+
+        assert_eq!(
+            call(1).await,
+            json!({ "jsonrpc": "2.0", "id": 1, "result": { "type": "noContent" } })
+        );
+        assert_eq!(call(0).await["result"]["type"], "error");
+
+        let result = router
+            .exec(
+                (),
+                ExecKind::Mutation,
+                "users.delete".into(),
+                Some(json!(1)),
+            )
+            .await;
+        assert_eq!(result.unwrap(), Value::Null);
+
+        let bindings = router.ts_bindings().unwrap();
+        assert!(bindings.contains(r#"{ key: "users.delete", input: number, result: void }"#));
+        assert!(router.zod_bindings().contains(
+            r#""users.delete": { input: z.number().int().nonnegative(), result: z.void() },"#
+        ));
+    }
+}
@@ -0,0 +1,557 @@
+use std::{borrow::Cow, collections::BTreeMap};
+
+use serde_json::{json, Map, Value};
+use specta::{
+    datatype::{
+        EnumRepr, EnumType, EnumVariants, Field, LiteralType, PrimitiveType, StructFields,
+        StructType,
+    },
+    DataType, TypeMap,
+};
+
+use crate::internal::{Procedure, ProcedureKind};
+
+/// The service metadata included in the `info` section of a generated OpenRPC document.
+#[derive(Debug, Clone)]
+pub struct OpenRpcInfo {
+    pub title: String,
+    pub version: String,
+    pub description: Option<String>,
+}
+
+impl OpenRpcInfo {
+    pub fn new(title: impl Into<String>, version: impl Into<String>) -> Self {
+        Self {
+            title: title.into(),
+            version: version.into(),
+            description: None,
+        }
+    }
+
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+}
+
+/// Map a method of the generated document (Eg. `query:users.get`) to the procedure's kind and key. This is the default [`Config::method_parser`](crate::Config::method_parser).
+pub(crate) fn parse_method(method: &str) -> Option<(ProcedureKind, String)> {
+    let (kind, key) = method.split_once(':')?;
+    let kind = match kind {
+        "query" => ProcedureKind::Query,
+        "mutation" => ProcedureKind::Mutation,
+        "subscription" => ProcedureKind::Subscription,
+        _ => return None,
+    };
+    Some((kind, key.to_string()))
+}
+
+/// Take the input of a request to a method of the generated document out of it's by-name `input` param.
+pub(crate) fn method_input(params: Option<Value>) -> Option<Value> {
+    match params {
+        Some(Value::Object(mut params)) => params.remove("input"),
+        _ => None,
+    }
+}
+
+pub(crate) fn generate<'a, TCtx: 'static>(
+    info: &OpenRpcInfo,
+    procedures: impl IntoIterator<Item = (ProcedureKind, &'a BTreeMap<String, Procedure<TCtx>>)>,
+    filter: &dyn Fn(&Procedure<TCtx>) -> bool,
+    type_map: &TypeMap,
+) -> Value {
+    let schema = Schema { type_map };
+
+    let methods = procedures
+        .into_iter()
+        .flat_map(|(kind, procedures)| procedures.iter().map(move |(key, p)| (kind, key, p)))
+        .filter(|(_, _, procedure)| filter(procedure))
+        .map(|(kind, key, procedure)| {
+            let mut params = Vec::new();
+            // `()` is exported as `null` so there is nothing for the caller to send.
+            if !is_null(&procedure.ty.arg_ty) {
+                params.push(json!({
+                    "name": "input",
+                    "required": true,
+                    "schema": schema.convert(&procedure.ty.arg_ty, &[]),
+                }));
+            }
+
+            let mut method = json!({
+                "name": format!("{}:{key}", kind.to_str()),
+                "paramStructure": "by-name",
+                "params": params,
+                "result": {
+                    "name": "result",
+                    "schema": schema.convert(&procedure.ty.result_ty, &[]),
+                },
+                "x-rspc-kind": kind.to_str(),
+            });
+            if let Some(description) = &procedure.ty.description {
+                method["description"] = json!(description);
+            }
+            if procedure.ty.deprecated.is_some() {
+                method["deprecated"] = json!(true);
+            }
+            if matches!(kind, ProcedureKind::Subscription) {
+                method["x-rspc-subscription"] = json!({
+                    "description": "The result schema describes each event of the subscription.",
+                });
+            }
+            if let Some(logs_ty) = &procedure.ty.logs_ty {
+                method["x-rspc-logs"] = json!({
+                    "description": "Log lines sent as `log` frames before the result.",
+                    "schema": schema.convert(logs_ty, &[]),
+                });
+            }
+            if let Some(trailer_ty) = &procedure.ty.trailer_ty {
+                method["x-rspc-trailer"] = json!({
+                    "description": "The final frame sent as a `trailer` when the subscription completes.",
+                    "schema": schema.convert(trailer_ty, &[]),
+                });
+            }
+            method
+        })
+        .collect::<Vec<_>>();
+
+    let schemas = type_map
+        .iter()
+        // Generic types are inlined where they're used as JSON Schema has no way to represent them.
+        .filter(|(_, ty)| ty.inner.generics().is_none_or(|g| g.is_empty()))
+        .map(|(_, ty)| {
+            let mut s = schema.convert(&ty.inner, &[]);
+            if !ty.docs().is_empty() {
+                s["description"] = json!(ty.docs().trim());
+            }
+            if ty.deprecated().is_some() {
+                s["deprecated"] = json!(true);
+            }
+            (ty.name().to_string(), s)
+        })
+        .collect::<Map<_, _>>();
+
+    let mut info_obj = json!({
+        "title": info.title,
+        "version": info.version,
+    });
+    if let Some(description) = &info.description {
+        info_obj["description"] = json!(description);
+    }
+
+    json!({
+        "openrpc": "1.2.6",
+        "info": info_obj,
+        "methods": methods,
+        "components": {
+            "schemas": schemas,
+        },
+    })
+}
+
+fn is_null(ty: &DataType) -> bool {
+    match ty {
+        DataType::Tuple(t) => t.elements().is_empty(),
+        DataType::Literal(LiteralType::None) => true,
+        _ => false,
+    }
+}
+
+/// Converts Specta types into JSON Schema (draft-07, as used by OpenRPC).
+struct Schema<'a> {
+    type_map: &'a TypeMap,
+}
+
+type Generics = [(Cow<'static, str>, Value)];
+
+impl Schema<'_> {
+    fn convert(&self, ty: &DataType, generics: &Generics) -> Value {
+        match ty {
+            DataType::Any | DataType::Unknown => json!({}),
+            DataType::Primitive(p) => primitive(p),
+            DataType::Literal(l) => literal(l),
+            DataType::List(l) => {
+                let mut s = json!({ "type": "array", "items": self.convert(l.ty(), generics) });
+                if let Some(len) = l.length() {
+                    s["minItems"] = json!(len);
+                    s["maxItems"] = json!(len);
+                }
+                if l.unique() {
+                    s["uniqueItems"] = json!(true);
+                }
+                s
+            }
+            DataType::Map(m) => json!({
+                "type": "object",
+                "additionalProperties": self.convert(m.value_ty(), generics),
+            }),
+            DataType::Nullable(t) => json!({
+                "anyOf": [self.convert(t, generics), { "type": "null" }],
+            }),
+            DataType::Struct(s) => self.structure(s, generics),
+            DataType::Enum(e) => self.enumeration(e, generics),
+            DataType::Tuple(t) => self.tuple(t.elements().iter(), generics),
+            DataType::Reference(r) if r.generics().is_empty() => {
+                json!({ "$ref": format!("#/components/schemas/{}", r.name()) })
+            }
+            DataType::Reference(r) => match self.type_map.get(r.sid()) {
+                Some(named) => {
+                    let generics = r
+                        .generics()
+                        .iter()
+                        .map(|(name, ty)| {
+                            (Cow::Owned(name.to_string()), self.convert(ty, generics))
+                        })
+                        .collect::<Vec<_>>();
+                    self.convert(&named.inner, &generics)
+                }
+                None => json!({}),
+            },
+            DataType::Generic(g) => generics
+                .iter()
+                .find(|(name, _)| *name == g.to_string())
+                .map(|(_, s)| s.clone())
+                .unwrap_or_else(|| json!({})),
+        }
+    }
+
+    fn tuple<'a>(
+        &self,
+        elements: impl ExactSizeIterator<Item = &'a DataType>,
+        generics: &Generics,
+    ) -> Value {
+        let len = elements.len();
+        match len {
+            0 => json!({ "type": "null" }),
+            _ => json!({
+                "type": "array",
+                "items": elements.map(|ty| self.convert(ty, generics)).collect::<Vec<_>>(),
+                "minItems": len,
+                "maxItems": len,
+            }),
+        }
+    }
+
+    fn unnamed(&self, fields: &[Field], generics: &Generics) -> Value {
+        let fields = fields.iter().filter_map(|f| f.ty()).collect::<Vec<_>>();
+        match fields[..] {
+            // Serde represents newtypes as their inner value
+            [ty] => self.convert(ty, generics),
+            _ => self.tuple(fields.into_iter(), generics),
+        }
+    }
+
+    fn object<'a>(
+        &self,
+        fields: impl Iterator<Item = &'a (Cow<'static, str>, Field)>,
+        generics: &Generics,
+    ) -> Value {
+        let mut properties = Map::new();
+        let mut required = Vec::new();
+        let mut flattened = Vec::new();
+        for (name, field) in fields {
+            let Some(ty) = field.ty() else { continue };
+
+            if field.flatten() {
+                flattened.push(self.convert(ty, generics));
+                continue;
+            }
+
+            let mut s = self.convert(ty, generics);
+            if !field.docs().is_empty() {
+                s["description"] = json!(field.docs().trim());
+            }
+            if field.deprecated().is_some() {
+                s["deprecated"] = json!(true);
+            }
+            if !field.optional() {
+                required.push(name.to_string());
+            }
+            properties.insert(name.to_string(), s);
+        }
+
+        let object = json!({
+            "type": "object",
+            "properties": properties,
+            "required": required,
+        });
+        match flattened.is_empty() {
+            true => object,
+            false => {
+                flattened.insert(0, object);
+                json!({ "allOf": flattened })
+            }
+        }
+    }
+
+    fn structure(&self, s: &StructType, generics: &Generics) -> Value {
+        let mut schema = match s.fields() {
+            StructFields::Unit => json!({ "type": "null" }),
+            StructFields::Unnamed(f) => self.unnamed(f.fields(), generics),
+            StructFields::Named(f) => self.object(f.fields().iter(), generics),
+        };
+        if let (Some(tag), Some(properties)) = (s.tag(), schema.get_mut("properties")) {
+            properties[tag.as_ref()] = json!({ "const": s.name() });
+            if let Some(required) = schema["required"].as_array_mut() {
+                required.push(json!(tag));
+            }
+        }
+        schema
+    }
+
+    fn enumeration(&self, e: &EnumType, generics: &Generics) -> Value {
+        let variants = e
+            .variants()
+            .iter()
+            .filter(|(_, v)| !v.skip())
+            .map(|(name, variant)| {
+                let inner = match variant.inner() {
+                    EnumVariants::Unit => None,
+                    EnumVariants::Named(f) => Some(self.object(f.fields().iter(), generics)),
+                    EnumVariants::Unnamed(f) => Some(self.unnamed(f.fields(), generics)),
+                };
+
+                match (e.repr(), inner) {
+                    (EnumRepr::Untagged, None) => json!({ "type": "null" }),
+                    (EnumRepr::Untagged, Some(inner)) => inner,
+                    (EnumRepr::External, None) => json!({ "const": name }),
+                    (EnumRepr::External, Some(inner)) => json!({
+                        "type": "object",
+                        "properties": { name.as_ref(): inner },
+                        "required": [name],
+                        "additionalProperties": false,
+                    }),
+                    (EnumRepr::Internal { tag }, inner) => {
+                        let tag = json!({
+                            "type": "object",
+                            "properties": { tag.as_ref(): { "const": name } },
+                            "required": [tag],
+                        });
+                        match inner {
+                            Some(inner) => json!({ "allOf": [tag, inner] }),
+                            None => tag,
+                        }
+                    }
+                    (EnumRepr::Adjacent { tag, content }, inner) => {
+                        let mut properties = Map::new();
+                        properties.insert(tag.to_string(), json!({ "const": name }));
+                        let mut required = vec![json!(tag)];
+                        if let Some(inner) = inner {
+                            properties.insert(content.to_string(), inner);
+                            required.push(json!(content));
+                        }
+                        json!({
+                            "type": "object",
+                            "properties": properties,
+                            "required": required,
+                        })
+                    }
+                }
+            })
+            .collect::<Vec<_>>();
+
+        match e.repr() {
+            EnumRepr::Untagged => json!({ "anyOf": variants }),
+            _ => json!({ "oneOf": variants }),
+        }
+    }
+}
+
+fn primitive(p: &PrimitiveType) -> Value {
+    match p {
+        PrimitiveType::i8
+        | PrimitiveType::i16
+        | PrimitiveType::i32
+        | PrimitiveType::i64
+        | PrimitiveType::i128
+        | PrimitiveType::isize => json!({ "type": "integer" }),
+        PrimitiveType::u8
+        | PrimitiveType::u16
+        | PrimitiveType::u32
+        | PrimitiveType::u64
+        | PrimitiveType::u128
+        | PrimitiveType::usize => json!({ "type": "integer", "minimum": 0 }),
+        PrimitiveType::f32 | PrimitiveType::f64 => json!({ "type": "number" }),
+        PrimitiveType::bool => json!({ "type": "boolean" }),
+        PrimitiveType::char => json!({ "type": "string", "minLength": 1, "maxLength": 1 }),
+        PrimitiveType::String => json!({ "type": "string" }),
+    }
+}
+
+fn literal(l: &LiteralType) -> Value {
+    match l {
+        LiteralType::i8(v) => json!({ "const": v }),
+        LiteralType::i16(v) => json!({ "const": v }),
+        LiteralType::i32(v) => json!({ "const": v }),
+        LiteralType::u8(v) => json!({ "const": v }),
+        LiteralType::u16(v) => json!({ "const": v }),
+        LiteralType::u32(v) => json!({ "const": v }),
+        LiteralType::f32(v) => json!({ "const": v }),
+        LiteralType::f64(v) => json!({ "const": v }),
+        LiteralType::bool(v) => json!({ "const": v }),
+        LiteralType::String(v) => json!({ "const": v }),
+        LiteralType::char(v) => json!({ "const": v }),
+        LiteralType::None => json!({ "type": "null" }),
+        _ => json!({}),
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use futures::stream;
+    use serde::Serialize;
+    use serde_json::json;
+    use specta::Type;
+
+    use std::sync::Arc;
+
+    use crate::{
+        internal::jsonrpc::{handle_json_rpc, ResponseInner, Sender, SubscriptionMap},
+        Config, ExecError, ExecKind, OpenRpcInfo, Router,
+    };
+
+    #[derive(Serialize, Type)]
+    struct User {
+        id: u32,
+        name: Option<String>,
+    }
+
+    #[derive(Serialize, Type)]
+    struct Page<T> {
+        items: Vec<T>,
+    }
+
+    #[test]
+    fn test_openrpc() {
+        let router = <Router>::new()
+            .query("users.get", |t| t(|_, id: u32| User { id, name: None }))
+            .query("users.list", |t| {
+                t(|_, _: ()| Page::<User> { items: vec![] })
+            })
+            .subscription("users.changes", |t| t(|_, _: ()| stream::iter(vec![true])))
+            .build();
+
+        let doc = router.openrpc(&OpenRpcInfo::new("Users API", "1.0.0"));
+        assert_eq!(
+            doc["info"],
+            json!({ "title": "Users API", "version": "1.0.0" })
+        );
+
+        let methods = doc["methods"].as_array().unwrap();
+        let method = |name: &str| methods.iter().find(|m| m["name"] == name).unwrap();
+
+        let get = method("query:users.get");
+        assert_eq!(get["x-rspc-kind"], "query");
+        assert_eq!(
+            get["params"],
+            json!([{ "name": "input", "required": true, "schema": { "type": "integer", "minimum": 0 } }])
+        );
+        assert_eq!(
+            get["result"]["schema"],
+            json!({ "$ref": "#/components/schemas/User" })
+        );
+
+        // Generic types are inlined
+        let list = method("query:users.list");
+        assert_eq!(list["params"], json!([]));
+        assert_eq!(
+            list["result"]["schema"]["properties"]["items"]["items"],
+            json!({ "$ref": "#/components/schemas/User" })
+        );
+
+        let changes = method("subscription:users.changes");
+        assert_eq!(changes["x-rspc-kind"], "subscription");
+        assert!(changes.get("x-rspc-subscription").is_some());
+
+        assert_eq!(
+            doc["components"]["schemas"]["User"],
+            json!({
+                "type": "object",
+                "properties": {
+                    "id": { "type": "integer", "minimum": 0 },
+                    "name": { "anyOf": [{ "type": "string" }, { "type": "null" }] },
+                },
+                "required": ["id", "name"],
+            })
+        );
+        assert!(doc["components"]["schemas"].get("Page").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_openrpc_dispatch() {
+        let router = Arc::new(
+            <Router>::new()
+                .query("counter", |t| t(|_, _: ()| 1))
+                .mutation("counter", |t| t(|_, by: u32| by + 1))
+                .build(),
+        );
+
+        // A query and mutation with the same key are separate methods
+        let doc = router.openrpc(&OpenRpcInfo::new("api", "1.0.0"));
+        let names = doc["methods"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|method| method["name"].as_str().unwrap().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["query:counter", "mutation:counter"]);
+
+        // And requests using the generated methods are dispatched to them
+        for (method, params, expected) in [
+            ("query:counter", json!({}), 1),
+            ("mutation:counter", json!({ "input": 41 }), 42),
+        ] {
+            let mut resp = Sender::Response(None);
+            handle_json_rpc(
+                (),
+                serde_json::from_value(json!({ "id": 1, "method": method, "params": params }))
+                    .unwrap(),
+                &router,
+                &mut resp,
+                &mut SubscriptionMap::None,
+            )
+            .await;
+            assert!(matches!(
+                resp,
+                Sender::Response(Some(resp)) if matches!(&resp.result, ResponseInner::Response(v) if *v == expected)
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_introspection() {
+        let router = Router::<bool>::new()
+            .config(Config::new().introspection("rspc.schema", OpenRpcInfo::new("api", "1.0.0")))
+            .query("version", |t| t(|_, _: ()| "1.0.0"))
+            .query("admin.stats", |t| {
+                t(|_, _: ()| 42).visible_when(|admin: &bool| *admin)
+            })
+            .build();
+
+        let methods = |document: serde_json::Value| {
+            document["methods"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|method| method["name"].as_str().unwrap().to_string())
+                .collect::<Vec<_>>()
+        };
+        let schema = |admin| router.exec(admin, ExecKind::Query, "rspc.schema".into(), None);
+        assert_eq!(methods(schema(false).await.unwrap()), ["query:version"]);
+        assert_eq!(
+            methods(schema(true).await.unwrap()),
+            ["query:admin.stats", "query:version"]
+        );
+
+        // It's opt-in
+        let router = <Router>::new()
+            .query("version", |t| t(|_, _: ()| "1.0.0"))
+            .build();
+        assert!(matches!(
+            router
+                .exec((), ExecKind::Query, "rspc.schema".into(), None)
+                .await,
+            Err(ExecError::OperationNotFound(_))
+        ));
+    }
+}
@@ -0,0 +1,92 @@
+use serde::Serialize;
+use specta::Type;
+
+/// A single page of a paginated list.
+///
+/// This serializes as `{ items: T[], next_cursor: string | null, total: number | null }` and is exported as a generic `Page<T>` type into your bindings.
+/// The type is only emitted once and every procedure returning a page references it, so the frontend can share a single pagination implementation.
+///
+/// Like [`WithMeta`](crate::WithMeta) it's a regular [`Serialize`] + [`Type`] value so it can be returned directly, wrapped in a `Result` or from an `async` resolver.
+///
+/// ```rust
+/// use rspc::Page;
+///
+/// let router = <rspc::Router>::new()
+///     .query("users", |t| {
+///         t(|_, cursor: Option<String>| {
+///             Page::new(vec!["Monty".to_string()]).next_cursor("2")
+///         })
+///     })
+///     .build();
+/// ```
+#[derive(Debug, Clone, Serialize, Type)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    /// An opaque cursor the client can send back to fetch the next page. `None` when this is the last page.
+    pub next_cursor: Option<String>,
+    /// The total number of items across every page, if it's known.
+    // Exported as a `number` as rspc doesn't allow bigints in bindings. Totals past `Number.MAX_SAFE_INTEGER` will lose precision.
+    #[specta(type = Option<u32>)]
+    pub total: Option<u64>,
+}
+
+impl<T> Page<T> {
+    pub fn new(items: Vec<T>) -> Self {
+        Self {
+            items,
+            next_cursor: None,
+            total: None,
+        }
+    }
+
+    /// Set the cursor for the next page.
+    pub fn next_cursor(mut self, cursor: impl Into<String>) -> Self {
+        self.next_cursor = Some(cursor.into());
+        self
+    }
+
+    /// Set the total number of items across every page.
+    pub fn total(mut self, total: u64) -> Self {
+        self.total = Some(total);
+        self
+    }
+}
+
+impl<T> From<Vec<T>> for Page<T> {
+    fn from(items: Vec<T>) -> Self {
+        Self::new(items)
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use serde_json::json;
+
+    use crate::{ExecKind, Page, Router};
+
+    #[tokio::test]
+    async fn test_page_bindings() {
+        let router = <Router>::new()
+            .query("users", |t| {
+                t(|_, _: ()| Page::new(vec!["Monty".to_string()]).next_cursor("2"))
+            })
+            .query("ids", |t| t(|_, _: ()| Page::new(vec![1u32]).total(1)))
+            .build();
+
+        assert_eq!(
+            router
+                .exec((), ExecKind::Query, "users".into(), None)
+                .await
+                .unwrap(),
+            json!({ "items": ["Monty"], "next_cursor": "2", "total": null })
+        );
+
+        let path = std::env::temp_dir().join("rspc_test_page_bindings.ts");
+        router.export_ts(&path).unwrap();
+        let bindings = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(bindings.matches("export type Page<").count(), 1);
+        assert!(bindings.contains("Page<string>"));
+        assert!(bindings.contains("Page<number>"));
+    }
+}
@@ -0,0 +1,260 @@
+use std::{fmt, marker::PhantomData, pin::Pin};
+
+use futures::{Stream, StreamExt};
+use serde::Serialize;
+use serde_json::{Map, Value};
+use specta::Type;
+
+use crate::{
+    internal::{
+        jsonrpc::{frame_sink, ResponseInner},
+        LayerResult,
+    },
+    ExecError, RequestLayer,
+};
+
+/// A fragment of a [`Partial`] result.
+#[derive(Debug)]
+pub struct Patch {
+    path: String,
+    value: Result<Value, serde_json::Error>,
+}
+
+impl Patch {
+    /// Merge `value` into the result at `path`.
+    ///
+    /// The `path` is a JSON Pointer (Eg. `/user/name`, or `""` for the root) and the value is merged using JSON Merge Patch, so objects are merged key by key and `null` removes a key.
+    /// Missing objects along the path are created.
+    pub fn new(path: impl Into<String>, value: impl Serialize) -> Self {
+        Self {
+            path: path.into(),
+            value: serde_json::to_value(value),
+        }
+    }
+}
+
+/// A result which is assembled from patches, so the client can render fragments as soon as their source resolves instead of waiting for everything.
+///
+/// The exported result type is `T`, the shape of the result once every patch has been applied.
+///
+/// ## Wire protocol
+///
+/// Each patch is sent as a `{ type: "patch", data: { path, value } }` frame with the id of the request, as soon as it's yielded. The client applies them in order, starting from `null`.
+/// Once the stream ends a single terminal `{ type: "response", data: T }` frame with the complete result is sent (or `{ type: "error", ... }` if a patch failed to serialize), so clients which ignore patches still receive the full result.
+///
+/// Patches are only sent over transports which support multiple frames per request (Eg. WebSocket). Over HTTP, or when calling [`Router::exec`](crate::Router::exec), the patches are applied on the server and only the complete result is returned.
+/// Patches are sent as-is, so they aren't affected by [`Config::rename_fields`](crate::Config::rename_fields).
+///
+/// ```rust
+/// use futures::stream;
+/// use rspc::{Partial, Patch};
+///
+/// #[derive(serde::Serialize, specta::Type)]
+/// struct Dashboard { user: String, stats: Vec<u32> }
+///
+/// let router = <rspc::Router>::new()
+///     .query("dashboard", |t| {
+///         t(|_, _: ()| {
+///             Partial::<Dashboard>::new(stream::iter([
+///                 Patch::new("/user", "Oscar"),
+///                 Patch::new("/stats", [1, 2, 3]),
+///             ]))
+///         })
+///     })
+///     .build();
+/// ```
+pub struct Partial<T> {
+    patches: Pin<Box<dyn Stream<Item = Patch> + Send>>,
+    phantom: PhantomData<fn() -> T>,
+}
+
+impl<T> Partial<T> {
+    pub fn new(patches: impl Stream<Item = Patch> + Send + 'static) -> Self {
+        Self {
+            patches: Box::pin(patches),
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<T> fmt::Debug for Partial<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Partial").finish_non_exhaustive()
+    }
+}
+
+pub struct PartialMarker(PhantomData<()>);
+impl<T> RequestLayer<PartialMarker> for Partial<T>
+where
+    T: Type + 'static,
+{
+    type Result = T;
+
+    fn into_layer_result(self) -> Result<LayerResult, ExecError> {
+        let mut patches = self.patches;
+
+        Ok(LayerResult::Future(Box::pin(async move {
+            let sink = frame_sink();
+            let mut result = Value::Null;
+            while let Some(patch) = patches.next().await {
+                let value = patch.value.map_err(ExecError::SerializingResultErr)?;
+                apply_patch(&mut result, &patch.path, value.clone());
+                if let Some(sink) = &sink {
+                    let _ = sink.send(ResponseInner::Patch {
+                        path: patch.path,
+                        value,
+                    });
+                }
+            }
+            Ok(result)
+        })))
+    }
+}
+
+/// Merge `patch` into `target` at the JSON Pointer `path`.
+fn apply_patch(target: &mut Value, path: &str, patch: Value) {
+    let mut target = target;
+    for token in path.split('/').skip(1) {
+        let token = token.replace("~1", "/").replace("~0", "~");
+        target = match target {
+            Value::Array(items) => match token.parse::<usize>() {
+                Ok(i) if i < items.len() => &mut items[i],
+                _ if token == "-" || token.parse::<usize>() == Ok(items.len()) => {
+                    items.push(Value::Null);
+                    items.last_mut().expect("an item was just pushed")
+                }
+                // An out of bounds index can't be created
+                _ => return,
+            },
+            target => {
+                if !target.is_object() {
+                    *target = Value::Object(Map::new());
+                }
+                match target {
+                    Value::Object(object) => object.entry(token).or_insert(Value::Null),
+                    _ => unreachable!(),
+                }
+            }
+        };
+    }
+    merge_patch(target, patch);
+}
+
+// https://datatracker.ietf.org/doc/html/rfc7386
+fn merge_patch(target: &mut Value, patch: Value) {
+    let Value::Object(patch) = patch else {
+        *target = patch;
+        return;
+    };
+    if !target.is_object() {
+        *target = Value::Object(Map::new());
+    }
+    if let Value::Object(target) = target {
+        for (key, value) in patch {
+            match value {
+                Value::Null => {
+                    target.remove(&key);
+                }
+                value => merge_patch(target.entry(key).or_insert(Value::Null), value),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use std::sync::Arc;
+
+    use futures::stream;
+    use serde::Serialize;
+    use serde_json::json;
+    use specta::Type;
+    use tokio::sync::mpsc;
+
+    use super::apply_patch;
+    use crate::{
+        internal::jsonrpc::{self, handle_json_rpc, RequestId, Sender, SubscriptionMap},
+        ExecKind, Partial, Patch, Router,
+    };
+
+    #[derive(Serialize, Type)]
+    struct Profile {
+        name: String,
+        stats: Stats,
+    }
+
+    #[derive(Serialize, Type)]
+    struct Stats {
+        posts: u32,
+        followers: u32,
+    }
+
+    #[test]
+    fn test_apply_patch() {
+        let mut value = json!(null);
+        apply_patch(&mut value, "", json!({ "a": { "b": 1, "c": 2 } }));
+        apply_patch(&mut value, "/a", json!({ "b": null, "d": [1] }));
+        apply_patch(&mut value, "/a/d/-", json!(2));
+        apply_patch(&mut value, "/e~1f", json!(true));
+        assert_eq!(value, json!({ "a": { "c": 2, "d": [1, 2] }, "e/f": true }));
+    }
+
+    #[tokio::test]
+    async fn test_partial() {
+        let router = Arc::new(
+            <Router>::new()
+                .query("profile", |t| {
+                    t(|_, _: ()| {
+                        Partial::<Profile>::new(stream::iter([
+                            Patch::new("/name", "Oscar"),
+                            Patch::new("/stats", json!({ "posts": 3 })),
+                            Patch::new("/stats/followers", 10),
+                        ]))
+                    })
+                })
+                .build(),
+        );
+        let complete = json!({ "name": "Oscar", "stats": { "posts": 3, "followers": 10 } });
+
+        let (mut tx, mut rx) = mpsc::unbounded_channel();
+        handle_json_rpc(
+            (),
+            jsonrpc::Request {
+                jsonrpc: None,
+                id: RequestId::Number(1),
+                inner: jsonrpc::RequestInner::Query {
+                    path: "profile".into(),
+                    input: None,
+                },
+            },
+            &router,
+            &mut Sender::ResponseChannel(&mut tx),
+            &mut SubscriptionMap::None,
+        )
+        .await;
+        drop(tx);
+
+        let mut frames = Vec::new();
+        while let Some(resp) = rx.recv().await {
+            frames.push(serde_json::to_value(resp.result).unwrap());
+        }
+        assert_eq!(
+            frames,
+            [
+                json!({ "type": "patch", "data": { "path": "/name", "value": "Oscar" } }),
+                json!({ "type": "patch", "data": { "path": "/stats", "value": { "posts": 3 } } }),
+                json!({ "type": "patch", "data": { "path": "/stats/followers", "value": 10 } }),
+                json!({ "type": "response", "data": complete }),
+            ]
+        );
+
+        let result = router
+            .exec((), ExecKind::Query, "profile".into(), None)
+            .await;
+        assert_eq!(result.unwrap(), complete);
+
+        let bindings = router.ts_bindings().unwrap();
+        assert!(bindings.contains(r#"{ key: "profile", input: never, result: Profile }"#));
+    }
+}
@@ -0,0 +1,266 @@
+use std::{
+    cmp::Reverse,
+    sync::{Arc, Mutex, PoisonError},
+    time::{Duration, Instant},
+};
+
+use tokio::sync::oneshot;
+
+use crate::ExecError;
+
+/// How urgently a procedure's requests are admitted when it's connection is at it's concurrency limit. Set it with [`BuiltProcedureBuilder::priority`](crate::internal::BuiltProcedureBuilder::priority).
+///
+/// Priorities only have an effect with [`Config::priority_queue`](crate::Config::priority_queue).
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Priority {
+    /// Background work (Eg. bulk exports) which can wait for everything else.
+    Low,
+    #[default]
+    Normal,
+    /// Requests a user is actively waiting on (Eg. interactive queries).
+    High,
+}
+
+struct Waiter {
+    priority: Priority,
+    since: Instant,
+    // Breaks ties between waiters of the same effective priority in arrival order
+    seq: u64,
+    tx: oneshot::Sender<QueuePermit>,
+}
+
+struct State {
+    available: usize,
+    waiters: Vec<Waiter>,
+    next_seq: u64,
+}
+
+/// A concurrency limit which admits waiting requests in priority order instead of arrival order. See [`Config::priority_queue`](crate::Config::priority_queue).
+pub(crate) struct PriorityQueue {
+    state: Mutex<State>,
+    max_queued: usize,
+    aging: Duration,
+}
+
+/// Releases it's slot to the next waiting request when dropped.
+pub(crate) struct QueuePermit(Option<Arc<PriorityQueue>>);
+
+impl Drop for QueuePermit {
+    fn drop(&mut self) {
+        if let Some(queue) = self.0.take() {
+            queue.release();
+        }
+    }
+}
+
+impl PriorityQueue {
+    pub fn new(permits: usize, max_queued: usize, aging: Duration) -> Arc<Self> {
+        Arc::new(Self {
+            state: Mutex::new(State {
+                available: permits,
+                waiters: Vec::new(),
+                next_seq: 0,
+            }),
+            max_queued,
+            aging,
+        })
+    }
+
+    /// Wait for a slot, failing with [`ExecError::Overloaded`] if `max_queued` requests are already waiting.
+    pub async fn acquire(self: &Arc<Self>, priority: Priority) -> Result<QueuePermit, ExecError> {
+        let rx = {
+            let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+            // Requests which stopped waiting (Eg. the client disconnected) don't take up space in the queue
+            state.waiters.retain(|waiter| !waiter.tx.is_closed());
+            if state.available > 0 {
+                state.available -= 1;
+                return Ok(QueuePermit(Some(self.clone())));
+            }
+            if state.waiters.len() >= self.max_queued {
+                return Err(ExecError::Overloaded);
+            }
+
+            let (tx, rx) = oneshot::channel();
+            let seq = state.next_seq;
+            state.next_seq += 1;
+            state.waiters.push(Waiter {
+                priority,
+                since: Instant::now(),
+                seq,
+                tx,
+            });
+            rx
+        };
+        rx.await.map_err(|_| ExecError::Overloaded)
+    }
+
+    // Hand the slot to the waiter with the highest effective priority, or return it if no one is waiting.
+    fn release(self: &Arc<Self>) {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        let now = Instant::now();
+        loop {
+            let next = state
+                .waiters
+                .iter()
+                .enumerate()
+                .max_by_key(|(_, waiter)| {
+                    (self.effective_priority(waiter, now), Reverse(waiter.seq))
+                })
+                .map(|(i, _)| i);
+            let Some(i) = next else {
+                state.available += 1;
+                return;
+            };
+
+            let waiter = state.waiters.swap_remove(i);
+            match waiter.tx.send(QueuePermit(Some(self.clone()))) {
+                Ok(()) => return,
+                // The request stopped waiting, so the slot goes to the next waiter
+                Err(mut permit) => permit.0 = None,
+            }
+        }
+    }
+
+    // A waiter gains a level of priority for every `aging` it has waited, so low priority requests can't be starved.
+    fn effective_priority(&self, waiter: &Waiter, now: Instant) -> u128 {
+        let waited = now.saturating_duration_since(waiter.since).as_nanos();
+        waiter.priority as u128 + waited.checked_div(self.aging.as_nanos()).unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use std::{
+        sync::{Arc, Mutex as StdMutex},
+        time::Duration,
+    };
+
+    use futures::future::join_all;
+    use tokio::sync::{mpsc, Mutex, Semaphore};
+
+    use super::{Priority, PriorityQueue};
+    use crate::{
+        internal::jsonrpc::{
+            self, handle_json_rpc_with_connection, Connection, RequestId, RequestInner, Sender,
+            SubscriptionMap,
+        },
+        Config, ExecError, OverloadBehavior, Router,
+    };
+
+    #[tokio::test]
+    async fn test_priority_ordering() {
+        let gate = Arc::new(Semaphore::new(0));
+        let order = Arc::new(StdMutex::new(Vec::new()));
+        let procedure = |priority: Priority| {
+            let (gate, order) = (gate.clone(), order.clone());
+            move |t: crate::internal::UnbuiltProcedureBuilder<(), _>| {
+                let (gate, order) = (gate.clone(), order.clone());
+                t(move |_, name: String| {
+                    let (gate, order) = (gate.clone(), order.clone());
+                    async move {
+                        gate.acquire().await.unwrap().forget();
+                        order.lock().unwrap().push(name);
+                    }
+                })
+                .priority(priority)
+            }
+        };
+        let router = Arc::new(
+            <Router>::new()
+                .config(
+                    Config::new()
+                        .max_concurrent_requests(1, OverloadBehavior::Queue)
+                        .priority_queue(3, Duration::from_secs(60)),
+                )
+                .mutation("bulk", procedure(Priority::Low))
+                .query("search", procedure(Priority::High))
+                .query("list", procedure(Priority::Normal))
+                .build(),
+        );
+
+        let connection = Connection::new(&router);
+        let subscriptions = Mutex::new(Default::default());
+        let request = |path: &'static str, name: &'static str| {
+            let (router, connection, subscriptions) =
+                (router.clone(), connection.clone(), &subscriptions);
+            async move {
+                let (mut tx, _rx) = mpsc::unbounded_channel();
+                let input = Some(serde_json::json!(name));
+                handle_json_rpc_with_connection(
+                    (),
+                    jsonrpc::Request {
+                        jsonrpc: None,
+                        id: RequestId::Null,
+                        inner: match path {
+                            "bulk" => RequestInner::Mutation {
+                                path: path.into(),
+                                input,
+                            },
+                            _ => RequestInner::Query {
+                                path: path.into(),
+                                input,
+                            },
+                        },
+                    },
+                    &router,
+                    &mut Sender::ResponseChannel(&mut tx),
+                    &mut SubscriptionMap::Mutex(subscriptions),
+                    &connection,
+                )
+                .await;
+            }
+        };
+
+        // The first request holds the only slot while the rest queue up in arrival order
+        let requests = join_all([
+            request("bulk", "running"),
+            request("bulk", "low"),
+            request("list", "normal"),
+            request("search", "high"),
+            // Rejected as the queue is full
+            request("search", "rejected"),
+        ]);
+        let release = async {
+            for _ in 0..20 {
+                tokio::task::yield_now().await;
+            }
+            gate.add_permits(4);
+        };
+        tokio::join!(requests, release);
+
+        assert_eq!(*order.lock().unwrap(), ["running", "high", "normal", "low"]);
+    }
+
+    #[tokio::test]
+    async fn test_priority_aging() {
+        let queue = PriorityQueue::new(1, 10, Duration::from_millis(20));
+        let running = queue.acquire(Priority::Normal).await.unwrap();
+
+        let low = tokio::spawn({
+            let queue = queue.clone();
+            async move { queue.acquire(Priority::Low).await }
+        });
+        // Once it has waited for two aging periods the low priority request outranks a new high priority one
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let high = tokio::spawn({
+            let queue = queue.clone();
+            async move { queue.acquire(Priority::High).await.map(drop) }
+        });
+        tokio::task::yield_now().await;
+
+        drop(running);
+        let low = low.await.unwrap().unwrap();
+        tokio::task::yield_now().await;
+        assert!(!high.is_finished());
+        drop(low);
+        high.await.unwrap().unwrap();
+
+        // A full queue rejects new requests
+        let queue = PriorityQueue::new(0, 0, Duration::from_secs(60));
+        assert!(matches!(
+            queue.acquire(Priority::High).await,
+            Err(ExecError::Overloaded)
+        ));
+    }
+}
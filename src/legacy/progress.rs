@@ -0,0 +1,129 @@
+use serde::Serialize;
+use specta::Type;
+
+/// The progress of a long-running operation (Eg. an upload), reported to the client before the result.
+///
+/// Stream it from a mutation with [`WithLogs`](crate::WithLogs), which sends each update as a `{ type: "log", data: { percent, message } }` frame followed by the terminal result frame.
+/// The procedure's `logs` type in the bindings is `Progress` so the client can tell progress updates apart from plain log lines.
+///
+/// The percentage is clamped to `0..=100`. It's not required to only increase, Eg. a retried step can move it backwards.
+///
+/// ```rust
+/// use futures::stream;
+/// use rspc::{Progress, WithLogs};
+///
+/// let router = <rspc::Router>::new()
+///     .mutation("upload", |t| {
+///         t(|_, _: ()| {
+///             WithLogs::new(
+///                 stream::iter([Progress::new(0.0), Progress::new(50.0).message("halfway")]),
+///                 async { Ok::<_, rspc::Error>("uploaded") },
+///             )
+///         })
+///     })
+///     .build();
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Type)]
+pub struct Progress {
+    percent: f64,
+    message: Option<String>,
+}
+
+impl Progress {
+    pub fn new(percent: f64) -> Self {
+        Self {
+            // `NaN` isn't valid JSON so it's reported as no progress
+            percent: match percent.is_nan() {
+                true => 0.0,
+                false => percent.clamp(0.0, 100.0),
+            },
+            message: None,
+        }
+    }
+
+    /// Describe the step the operation is currently on.
+    pub fn message(mut self, message: impl Into<String>) -> Self {
+        self.message = Some(message.into());
+        self
+    }
+
+    pub fn percent(&self) -> f64 {
+        self.percent
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use std::sync::Arc;
+
+    use futures::stream;
+    use serde_json::json;
+    use tokio::sync::mpsc;
+
+    use crate::{
+        internal::jsonrpc::{self, handle_json_rpc, RequestId, Sender, SubscriptionMap},
+        Progress, Router, WithLogs,
+    };
+
+    #[tokio::test]
+    async fn test_progress() {
+        let router = Arc::new(
+            <Router>::new()
+                .mutation("upload", |t| {
+                    t(|_, _: ()| {
+                        WithLogs::new(
+                            stream::iter([
+                                Progress::new(-5.0),
+                                Progress::new(60.0).message("hashing"),
+                                Progress::new(40.0),
+                                Progress::new(250.0),
+                            ]),
+                            async { Ok("uploaded") },
+                        )
+                    })
+                })
+                .build(),
+        );
+
+        let (mut tx, mut rx) = mpsc::unbounded_channel();
+        handle_json_rpc(
+            (),
+            jsonrpc::Request {
+                jsonrpc: None,
+                id: RequestId::Number(1),
+                inner: jsonrpc::RequestInner::Mutation {
+                    path: "upload".into(),
+                    input: None,
+                },
+            },
+            &router,
+            &mut Sender::ResponseChannel(&mut tx),
+            &mut SubscriptionMap::None,
+        )
+        .await;
+        drop(tx);
+
+        let mut frames = Vec::new();
+        while let Some(resp) = rx.recv().await {
+            frames.push(serde_json::to_value(resp.result).unwrap());
+        }
+        assert_eq!(
+            frames,
+            [
+                json!({ "type": "log", "data": { "percent": 0.0, "message": null } }),
+                json!({ "type": "log", "data": { "percent": 60.0, "message": "hashing" } }),
+                json!({ "type": "log", "data": { "percent": 40.0, "message": null } }),
+                json!({ "type": "log", "data": { "percent": 100.0, "message": null } }),
+                json!({ "type": "response", "data": "uploaded" }),
+            ]
+        );
+        assert_eq!(Progress::new(f64::NAN).percent(), 0.0);
+
+        let bindings = router.ts_bindings().unwrap();
+        assert!(
+            bindings.contains("export type Progress = { percent: number; message: string | null }")
+        );
+        assert!(bindings.contains("logs: Progress"));
+    }
+}
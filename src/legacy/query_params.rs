@@ -0,0 +1,139 @@
+use serde_json::{Map, Number, Value};
+use specta::{
+    datatype::{PrimitiveType, StructFields},
+    DataType, TypeMap,
+};
+
+/// A field of a procedure's input which can be sent as a query string parameter.
+pub(crate) struct QueryParam<'a> {
+    pub name: &'a str,
+    pub ty: &'a DataType,
+    pub optional: bool,
+}
+
+/// Get the query string parameters of an input, if it's a struct whose fields are all strings, numbers or booleans (or optional versions of them).
+///
+/// Nested inputs (Eg. a field containing an array or another struct) can't be encoded as a query string, so `None` is returned for them.
+pub(crate) fn query_params<'a>(
+    ty: &'a DataType,
+    type_map: &'a TypeMap,
+) -> Option<Vec<QueryParam<'a>>> {
+    let ty = match ty {
+        DataType::Reference(r) if r.generics().is_empty() => &type_map.get(r.sid())?.inner,
+        ty => ty,
+    };
+    let DataType::Struct(s) = ty else {
+        return None;
+    };
+    let StructFields::Named(fields) = s.fields() else {
+        return None;
+    };
+
+    let params = fields
+        .fields()
+        .iter()
+        .filter(|(_, field)| field.ty().is_some())
+        .map(|(name, field)| {
+            if field.flatten() {
+                return None;
+            }
+            let (ty, nullable) = match field.ty()? {
+                DataType::Nullable(ty) => (&**ty, true),
+                ty => (ty, false),
+            };
+            matches!(ty, DataType::Primitive(_)).then_some(QueryParam {
+                name,
+                ty,
+                optional: field.optional() || nullable,
+            })
+        })
+        .collect::<Option<Vec<_>>>()?;
+    (!params.is_empty()).then_some(params)
+}
+
+/// Build the input of a procedure from it's query string parameters, converting each one to the type of it's field.
+///
+/// A value which doesn't parse as it's field's type is passed through as a string, so it fails to deserialize with the usual error.
+pub(crate) fn decode_query_params<'a>(
+    params: &[QueryParam],
+    query: impl IntoIterator<Item = (&'a str, &'a str)>,
+) -> Value {
+    let mut input = Map::new();
+    for (name, value) in query {
+        let Some(param) = params.iter().find(|param| param.name == name) else {
+            continue;
+        };
+        let parsed = match param.ty {
+            DataType::Primitive(PrimitiveType::bool) => value.parse().ok().map(Value::Bool),
+            DataType::Primitive(PrimitiveType::String | PrimitiveType::char) => None,
+            DataType::Primitive(_) => value.parse::<Number>().ok().map(Value::Number),
+            _ => None,
+        };
+        input.insert(
+            name.to_string(),
+            parsed.unwrap_or_else(|| Value::String(value.to_string())),
+        );
+    }
+    Value::Object(input)
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use serde::Deserialize;
+    use serde_json::json;
+    use specta::Type;
+
+    use crate::{ExecKind, Router};
+
+    #[derive(Deserialize, Type)]
+    struct ListArgs {
+        page: u32,
+        search: Option<String>,
+        archived: bool,
+    }
+
+    #[derive(Deserialize, Type)]
+    struct SearchArgs {
+        #[allow(dead_code)]
+        tags: Vec<String>,
+    }
+
+    #[tokio::test]
+    async fn test_query_params() {
+        let router =
+            <Router>::new()
+                .query("users.list", |t| {
+                    t(|_, args: ListArgs| {
+                        format!("{} {:?} {}", args.page, args.search, args.archived)
+                    })
+                })
+                .query("users.search", |t| t(|_, _: SearchArgs| ()))
+                .build();
+
+        let bindings = router.ts_bindings().unwrap();
+        assert!(bindings.contains(
+            "export type QueryParams = {\n    \"users.list\": { page: number; search?: string; archived: boolean }\n};"
+        ));
+
+        let input = router
+            .query_params_input(
+                "users.list",
+                [("page", "2"), ("archived", "false"), ("unknown", "1")],
+            )
+            .unwrap();
+        assert_eq!(input, json!({ "page": 2, "archived": false }));
+        assert_eq!(
+            router
+                .exec((), ExecKind::Query, "users.list".into(), Some(input))
+                .await
+                .unwrap(),
+            json!("2 None false")
+        );
+
+        // Nested inputs can't be encoded as a query string
+        assert!(router
+            .query_params_input("users.search", [("tags", "a")])
+            .is_none());
+    }
+}
@@ -0,0 +1,298 @@
+use std::{
+    fmt,
+    marker::PhantomData,
+    mem,
+    pin::Pin,
+    task::{ready, Poll},
+};
+
+use base64::Engine;
+use futures::{stream, Stream, StreamExt, TryStreamExt};
+
+use crate::{
+    internal::{jsonrpc::set_http_body, LayerResult},
+    Error, ExecError, RequestLayer,
+};
+
+type ByteStream = Pin<Box<dyn Stream<Item = Result<Vec<u8>, Error>> + Send>>;
+
+// The most bytes a buffered stream holds before they're sent without waiting for a `RawChunk::Flush`
+const MAX_BUFFERED: usize = 64 * 1024;
+
+/// An item of a [`RawStream::buffered`] stream.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RawChunk {
+    /// Bytes which are buffered until the next [`RawChunk::Flush`].
+    Data(Vec<u8>),
+    /// Send every buffered byte to the client immediately, Eg. once a logically complete line of NDJSON is ready.
+    Flush,
+}
+
+/// A result which is sent as a raw stream of bytes instead of JSON.
+///
+/// This is useful for things like proxying file downloads without buffering the whole file in memory.
+///
+/// ## Transport behavior
+///
+///  - **HTTP**: the bytes are streamed directly as the response body with the given `Content-Type` (`application/octet-stream` by default). There is no JSON-RPC framing.
+///  - **Everything else** (Eg. WebSocket, Tauri): the stream is buffered and the bytes are returned as a single base64 encoded `string` result.
+///
+/// The exported type of the procedure is always `string` so your frontend must handle both.
+///
+/// ## Errors
+///
+/// Returning `Err` instead of a [`RawStream`] is a regular rspc error over every transport, so do any validation before constructing the stream.
+///
+/// Over HTTP the status code and headers are sent before the stream is polled, so an error yielded by the stream aborts the response instead.
+/// The client sees a truncated body (Eg. reading the `fetch` body rejects with a network error) rather than an rspc error. If the client must be able to detect truncation, include the expected length or a checksum in the data itself.
+/// Over every other transport the stream is buffered first, so an error yielded by it is returned as a regular rspc error.
+///
+/// ```rust
+/// use rspc::RawStream;
+///
+/// let router = <rspc::Router>::new()
+///     .query("download", |t| {
+///         t(|_, _: ()| {
+///             RawStream::new(futures::stream::iter([Ok(b"hello ".to_vec()), Ok(b"world".to_vec())]))
+///                 .content_type("text/plain")
+///         })
+///     })
+///     .build();
+/// ```
+pub struct RawStream {
+    content_type: String,
+    stream: ByteStream,
+}
+
+impl RawStream {
+    pub fn new<S, B>(stream: S) -> Self
+    where
+        S: Stream<Item = Result<B, Error>> + Send + 'static,
+        B: Into<Vec<u8>> + 'static,
+    {
+        Self {
+            content_type: "application/octet-stream".into(),
+            stream: Box::pin(stream.map_ok(Into::into)),
+        }
+    }
+
+    /// Create a stream whose bytes are buffered until the resolver yields a [`RawChunk::Flush`], so it controls latency (flushing often) vs throughput (flushing rarely).
+    ///
+    /// This is useful for streaming progress (Eg. as NDJSON) where the resolver knows when a logically complete chunk is ready.
+    /// The buffered bytes are also sent when the stream ends, before an error yielded by the stream and whenever 64 KiB are buffered, so the buffer can't grow without bound.
+    /// With [`RawStream::new`] every chunk is sent as soon as it's yielded.
+    ///
+    /// Note: Flushing only has an effect on the HTTP transport which streams the bytes. It's a no-op on every other transport as the whole stream is buffered before it's sent.
+    ///
+    /// ```rust
+    /// use rspc::{RawChunk, RawStream};
+    ///
+    /// let router = <rspc::Router>::new()
+    ///     .query("progress", |t| {
+    ///         t(|_, _: ()| {
+    ///             RawStream::buffered(futures::stream::iter([
+    ///                 Ok(RawChunk::Data(b"{\"progress\":".to_vec())),
+    ///                 Ok(RawChunk::Data(b"50}\n".to_vec())),
+    ///                 Ok(RawChunk::Flush),
+    ///             ]))
+    ///             .content_type("application/x-ndjson")
+    ///         })
+    ///     })
+    ///     .build();
+    /// ```
+    pub fn buffered<S>(stream: S) -> Self
+    where
+        S: Stream<Item = Result<RawChunk, Error>> + Send + 'static,
+    {
+        let mut stream = Box::pin(stream);
+        let mut buffered = Vec::new();
+        // An error from the stream which is sent after the bytes before it are flushed
+        let mut error = None;
+        let mut done = false;
+        Self::new(stream::poll_fn(move |cx| loop {
+            if let Some(err) = error.take() {
+                return Poll::Ready(Some(Err(err)));
+            }
+            if done {
+                return Poll::Ready(None);
+            }
+
+            let flush = match ready!(stream.poll_next_unpin(cx)) {
+                Some(Ok(RawChunk::Data(data))) => {
+                    buffered.extend(data);
+                    buffered.len() >= MAX_BUFFERED
+                }
+                Some(Ok(RawChunk::Flush)) => true,
+                Some(Err(err)) => {
+                    error = Some(err);
+                    true
+                }
+                None => {
+                    done = true;
+                    true
+                }
+            };
+            if flush && !buffered.is_empty() {
+                return Poll::Ready(Some(Ok(mem::take(&mut buffered))));
+            }
+        }))
+    }
+
+    /// Set the `Content-Type` of the HTTP response.
+    pub fn content_type(mut self, content_type: impl Into<String>) -> Self {
+        self.content_type = content_type.into();
+        self
+    }
+
+    /// Split this into it's content type and stream of bytes. This is intended for transports.
+    pub fn into_parts(self) -> (String, ByteStream) {
+        (self.content_type, self.stream)
+    }
+}
+
+impl fmt::Debug for RawStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RawStream")
+            .field("content_type", &self.content_type)
+            .finish_non_exhaustive()
+    }
+}
+
+pub struct RawStreamMarker(PhantomData<()>);
+impl RequestLayer<RawStreamMarker> for RawStream {
+    type Result = String;
+
+    fn into_layer_result(self) -> Result<LayerResult, ExecError> {
+        let stream = match set_http_body(self) {
+            Ok(()) => return Ok(LayerResult::Ready(Ok(serde_json::Value::Null))),
+            Err(raw) => raw.stream,
+        };
+
+        Ok(LayerResult::Future(Box::pin(async move {
+            let bytes = stream
+                .try_concat()
+                .await
+                .map_err(ExecError::ErrResolverError)?;
+            Ok(base64::engine::general_purpose::STANDARD
+                .encode(bytes)
+                .into())
+        })))
+    }
+}
+
+impl RequestLayer<RawStreamMarker> for Result<RawStream, Error> {
+    type Result = String;
+
+    fn into_layer_result(self) -> Result<LayerResult, ExecError> {
+        self.map_err(ExecError::ErrResolverError)?
+            .into_layer_result()
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use std::sync::Arc;
+
+    use base64::Engine;
+    use futures::{stream, StreamExt, TryStreamExt};
+    use serde_json::json;
+
+    use crate::{
+        internal::jsonrpc::{
+            self, handle_json_rpc, with_http_response, RequestId, Sender, SubscriptionMap,
+        },
+        Error, ErrorCode, ExecKind, RawChunk, RawStream, Router,
+    };
+
+    #[tokio::test]
+    async fn test_raw_stream() {
+        let router = Arc::new(
+            <Router>::new()
+                .query("download", |t| {
+                    t(|_, _: ()| {
+                        RawStream::new(stream::iter([
+                            Ok(b"hello ".to_vec()),
+                            Ok(b"world".to_vec()),
+                        ]))
+                        .content_type("text/plain")
+                    })
+                })
+                .build(),
+        );
+
+        // Non-HTTP transports get the bytes base64 encoded
+        let result = router
+            .exec((), ExecKind::Query, "download".into(), None)
+            .await;
+        assert_eq!(result.unwrap(), json!("aGVsbG8gd29ybGQ="));
+
+        let mut resp = Sender::Response(None);
+        let (_, http) = with_http_response(handle_json_rpc(
+            (),
+            jsonrpc::Request {
+                jsonrpc: None,
+                id: RequestId::Null,
+                inner: jsonrpc::RequestInner::Query {
+                    path: "download".into(),
+                    input: None,
+                },
+            },
+            &router,
+            &mut resp,
+            &mut SubscriptionMap::None,
+        ))
+        .await;
+        let (content_type, body) = http.body.unwrap().into_parts();
+        assert_eq!(content_type, "text/plain");
+        assert_eq!(body.try_concat().await.unwrap(), b"hello world");
+    }
+
+    #[tokio::test]
+    async fn test_raw_stream_flush() {
+        let chunks = || {
+            stream::iter([
+                Ok(RawChunk::Data(b"{\"progress\":".to_vec())),
+                Ok(RawChunk::Data(b"50}\n".to_vec())),
+                Ok(RawChunk::Flush),
+                Ok(RawChunk::Flush),
+                Ok(RawChunk::Data(b"{\"progress\":100}\n".to_vec())),
+            ])
+        };
+
+        // Bytes are only sent when flushed or once the stream ends
+        let (_, body) = RawStream::buffered(chunks()).into_parts();
+        assert_eq!(
+            body.try_collect::<Vec<_>>().await.unwrap(),
+            [
+                b"{\"progress\":50}\n".to_vec(),
+                b"{\"progress\":100}\n".to_vec()
+            ]
+        );
+
+        // The bytes before an error are still sent
+        let (_, body) = RawStream::buffered(stream::iter([
+            Ok(RawChunk::Data(b"partial".to_vec())),
+            Err(Error::new(ErrorCode::InternalServerError, "failed".into())),
+        ]))
+        .into_parts();
+        let body = body.collect::<Vec<_>>().await;
+        assert!(matches!(&body[..], [Ok(data), Err(_)] if data == b"partial"));
+
+        // Flushing is a no-op when the stream isn't sent over HTTP
+        let router = <Router>::new()
+            .query("progress", move |t| {
+                t(move |_, _: ()| RawStream::buffered(chunks()))
+            })
+            .build();
+        let result = router
+            .exec((), ExecKind::Query, "progress".into(), None)
+            .await
+            .unwrap();
+        assert_eq!(
+            result,
+            json!(base64::engine::general_purpose::STANDARD
+                .encode(b"{\"progress\":50}\n{\"progress\":100}\n"))
+        );
+    }
+}
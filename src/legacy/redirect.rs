@@ -0,0 +1,114 @@
+use std::marker::PhantomData;
+
+use crate::{
+    internal::{jsonrpc::set_http_redirect, LayerResult},
+    Error, ExecError, RequestLayer,
+};
+
+/// A result which redirects the client to another location.
+///
+/// ## Transport behavior
+///
+///  - **HTTP**: the response is a `302 Found` with the `Location` header set to the URL and no body.
+///  - **Everything else** (Eg. WebSocket, Tauri): the URL is returned as a regular `string` result.
+///
+/// The exported type of the procedure is always `string` so your frontend must handle both.
+///
+/// This can be returned from queries and mutations, directly or via `Result<Redirect, rspc::Error>` or a future.
+///
+/// ```rust
+/// use rspc::Redirect;
+///
+/// let router = <rspc::Router>::new()
+///     .query("link", |t| t(|_, slug: String| Redirect::to(format!("https://example.com/{slug}"))))
+///     .build();
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Redirect(String);
+
+impl Redirect {
+    pub fn to(url: impl Into<String>) -> Self {
+        Self(url.into())
+    }
+
+    pub fn url(&self) -> &str {
+        &self.0
+    }
+}
+
+pub struct RedirectMarker(PhantomData<()>);
+impl RequestLayer<RedirectMarker> for Redirect {
+    type Result = String;
+
+    fn into_layer_result(self) -> Result<LayerResult, ExecError> {
+        set_http_redirect(&self.0);
+        Ok(LayerResult::Ready(Ok(self.0.into())))
+    }
+}
+
+impl RequestLayer<RedirectMarker> for Result<Redirect, Error> {
+    type Result = String;
+
+    fn into_layer_result(self) -> Result<LayerResult, ExecError> {
+        self.map_err(ExecError::ErrResolverError)?
+            .into_layer_result()
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use std::sync::Arc;
+
+    use serde_json::json;
+
+    use crate::{
+        internal::jsonrpc::{
+            self, handle_json_rpc, with_http_response, RequestId, ResponseInner, Sender,
+            SubscriptionMap,
+        },
+        ExecKind, Redirect, Router,
+    };
+
+    #[tokio::test]
+    async fn test_redirect() {
+        let router = Arc::new(
+            <Router>::new()
+                .query("link", |t| {
+                    t(|_, slug: String| async move { Redirect::to(format!("/s/{slug}")) })
+                })
+                .build(),
+        );
+
+        // Non-HTTP transports just get the URL
+        let result = router
+            .exec((), ExecKind::Query, "link".into(), Some(json!("abc")))
+            .await;
+        assert_eq!(result.unwrap(), json!("/s/abc"));
+
+        let mut resp = Sender::Response(None);
+        let (_, http) = with_http_response(handle_json_rpc(
+            (),
+            jsonrpc::Request {
+                jsonrpc: None,
+                id: RequestId::Null,
+                inner: jsonrpc::RequestInner::Query {
+                    path: "link".into(),
+                    input: Some(json!("abc")),
+                },
+            },
+            &router,
+            &mut resp,
+            &mut SubscriptionMap::None,
+        ))
+        .await;
+        assert_eq!(http.redirect.as_deref(), Some("/s/abc"));
+        assert!(matches!(
+            resp,
+            Sender::Response(Some(jsonrpc::Response {
+                result: ResponseInner::Response(_),
+                ..
+            }))
+        ));
+    }
+}
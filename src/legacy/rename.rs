@@ -0,0 +1,681 @@
+use std::{
+    borrow::{Borrow, Cow},
+    collections::{BTreeSet, HashMap},
+    sync::Arc,
+};
+
+use futures::StreamExt;
+use serde_json::{Map, Value};
+use specta::{
+    datatype::{
+        DataTypeReference, EnumRepr, EnumType, EnumVariant, EnumVariants, Field, NamedDataType,
+        StructFields,
+    },
+    internal::construct,
+    DataType, SpectaID, Type, TypeMap,
+};
+
+use crate::{
+    internal::{
+        jsonrpc::{map_logs, map_trailer},
+        Layer, LayerResult, Procedure, ProcedureDataType, ProcedureStore, RequestContext,
+        ValueOrStream,
+    },
+    ExecError,
+};
+
+use super::validate::Env;
+
+/// A naming convention applied to the fields of every type sent over the wire. See [`Config::rename_fields`](crate::Config::rename_fields).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum RenameRule {
+    /// Rename `snake_case` fields to `camelCase`, Eg. `created_at` becomes `createdAt`.
+    CamelCase,
+}
+
+impl RenameRule {
+    /// Apply the convention to a field name.
+    pub fn apply(self, name: &str) -> String {
+        match self {
+            Self::CamelCase => {
+                let mut renamed = String::with_capacity(name.len());
+                let mut upper = false;
+                for c in name.chars() {
+                    match c {
+                        '_' => upper = !renamed.is_empty(),
+                        c if upper => {
+                            renamed.extend(c.to_uppercase());
+                            upper = false;
+                        }
+                        c => renamed.push(c),
+                    }
+                }
+                renamed
+            }
+        }
+    }
+}
+
+// Specta doesn't expose the id of an enum and it's not used when exporting, so anonymous enums are rebuilt with a placeholder.
+const ANONYMOUS_ENUM: SpectaID = construct::sid("rspc::RenameRule", "anonymous_enum");
+
+/// Rename the fields of every type in the `type_map`.
+pub(crate) fn rename_type_map(rule: RenameRule, type_map: &TypeMap) -> TypeMap {
+    let mut renamed = TypeMap::default();
+    for (sid, ty) in type_map.iter() {
+        renamed.insert(sid, rename_named(rule, ty));
+    }
+    renamed
+}
+
+fn rename_named(rule: RenameRule, ty: &NamedDataType) -> NamedDataType {
+    let Some(ext) = ty.ext() else {
+        return ty.clone();
+    };
+
+    let inner = match &ty.inner {
+        DataType::Enum(e) => DataType::Enum(rename_enum(rule, e, *ext.sid())),
+        inner => rename_datatype(rule, inner),
+    };
+    construct::named_data_type(
+        ty.name().clone(),
+        ty.docs().clone(),
+        ty.deprecated().cloned(),
+        *ext.sid(),
+        *ext.impl_location(),
+        inner,
+    )
+}
+
+/// Rename the fields of a type. References are renamed by [`rename_type_map`].
+pub(crate) fn rename_datatype(rule: RenameRule, ty: &DataType) -> DataType {
+    match ty {
+        DataType::Any
+        | DataType::Unknown
+        | DataType::Primitive(_)
+        | DataType::Literal(_)
+        | DataType::Generic(_) => ty.clone(),
+        DataType::List(l) => {
+            let item = rename_datatype(rule, l.ty());
+            if item == *l.ty() {
+                return ty.clone();
+            }
+            // Specta only allows constructing lists through their `Type` implementations
+            match (l.length(), l.unique()) {
+                (Some(len), _) => DataType::Tuple(construct::tuple(vec![item; len])),
+                (None, true) => {
+                    <BTreeSet<()> as Type>::reference(&mut TypeMap::default(), &[item]).inner
+                }
+                (None, false) => {
+                    <Vec<()> as Type>::reference(&mut TypeMap::default(), &[item]).inner
+                }
+            }
+        }
+        DataType::Map(m) => {
+            let value = rename_datatype(rule, m.value_ty());
+            if value == *m.value_ty() {
+                return ty.clone();
+            }
+            <HashMap<(), ()> as Type>::reference(
+                &mut TypeMap::default(),
+                &[m.key_ty().clone(), value],
+            )
+            .inner
+        }
+        DataType::Nullable(ty) => DataType::Nullable(Box::new(rename_datatype(rule, ty))),
+        DataType::Struct(s) => DataType::Struct(construct::r#struct(
+            s.name().clone(),
+            s.sid().copied(),
+            s.generics().clone(),
+            match s.fields() {
+                StructFields::Unit => construct::struct_unit(),
+                StructFields::Unnamed(f) => {
+                    construct::struct_unnamed(rename_unnamed(rule, f.fields()))
+                }
+                StructFields::Named(f) => {
+                    construct::struct_named(rename_named_fields(rule, f.fields()), f.tag().clone())
+                }
+            },
+        )),
+        DataType::Enum(e) => DataType::Enum(rename_enum(rule, e, ANONYMOUS_ENUM)),
+        DataType::Tuple(t) => DataType::Tuple(construct::tuple(
+            t.elements()
+                .iter()
+                .map(|ty| rename_datatype(rule, ty))
+                .collect(),
+        )),
+        DataType::Reference(r) => DataType::Reference(rename_reference(rule, r)),
+    }
+}
+
+fn rename_reference(rule: RenameRule, r: &DataTypeReference) -> DataTypeReference {
+    construct::data_type_reference(
+        r.name().clone(),
+        r.sid(),
+        r.generics()
+            .iter()
+            .map(|(generic, ty)| (generic.clone(), rename_datatype(rule, ty)))
+            .collect(),
+    )
+}
+
+fn rename_enum(rule: RenameRule, e: &EnumType, sid: SpectaID) -> EnumType {
+    construct::r#enum(
+        e.name().clone(),
+        sid,
+        e.repr().clone(),
+        e.skip_bigint_checks(),
+        e.generics().clone(),
+        e.variants()
+            .iter()
+            .map(|(name, variant)| {
+                let inner = match variant.inner() {
+                    EnumVariants::Unit => construct::enum_variant_unit(),
+                    EnumVariants::Unnamed(f) => {
+                        construct::enum_variant_unnamed(rename_unnamed(rule, f.fields()))
+                    }
+                    EnumVariants::Named(f) => construct::enum_variant_named(
+                        rename_named_fields(rule, f.fields()),
+                        f.tag().clone(),
+                    ),
+                };
+                (name.clone(), rename_variant(variant, inner))
+            })
+            .collect(),
+    )
+}
+
+fn rename_variant(variant: &EnumVariant, inner: EnumVariants) -> EnumVariant {
+    construct::enum_variant(
+        variant.skip(),
+        variant.deprecated().cloned(),
+        variant.docs().clone(),
+        inner,
+    )
+}
+
+fn rename_field(rule: RenameRule, field: &Field) -> Field {
+    construct::field(
+        field.optional(),
+        field.flatten(),
+        field.deprecated().cloned(),
+        field.docs().clone(),
+        field.ty().map(|ty| rename_datatype(rule, ty)),
+    )
+}
+
+fn rename_unnamed(rule: RenameRule, fields: &[Field]) -> Vec<Field> {
+    fields.iter().map(|f| rename_field(rule, f)).collect()
+}
+
+fn rename_named_fields(
+    rule: RenameRule,
+    fields: &[(Cow<'static, str>, Field)],
+) -> Vec<(Cow<'static, str>, Field)> {
+    fields
+        .iter()
+        .map(|(name, field)| (Cow::Owned(rule.apply(name)), rename_field(rule, field)))
+        .collect()
+}
+
+#[derive(Clone, Copy)]
+enum Direction {
+    /// Values are being sent to the client, so fields are renamed from their Rust name.
+    Output,
+    /// Values were received from the client, so fields are renamed back to their Rust name.
+    Input,
+}
+
+/// Renames the keys of a [`Value`] guided by the *original* (not renamed) type it was serialized from.
+struct Renamer<'a> {
+    rule: RenameRule,
+    direction: Direction,
+    type_map: &'a TypeMap,
+}
+
+impl Renamer<'_> {
+    fn rename(&self, ty: &DataType, value: &mut Value, env: &Env) {
+        match ty {
+            DataType::List(l) => {
+                if let Some(items) = value.as_array_mut() {
+                    for item in items {
+                        self.rename(l.ty(), item, env);
+                    }
+                }
+            }
+            DataType::Map(m) => {
+                if let Some(entries) = value.as_object_mut() {
+                    for value in entries.values_mut() {
+                        self.rename(m.value_ty(), value, env);
+                    }
+                }
+            }
+            DataType::Nullable(ty) => self.rename(ty, value, env),
+            DataType::Struct(s) => match s.fields() {
+                StructFields::Unit => {}
+                StructFields::Unnamed(f) => self.unnamed(f.fields(), value, env),
+                StructFields::Named(f) => self.object(f.fields(), value, env),
+            },
+            DataType::Enum(e) => self.enumeration(e, value, env),
+            DataType::Tuple(t) => self.tuple(t.elements(), value, env),
+            DataType::Reference(r) => {
+                if let Some(named) = self.type_map.get(r.sid()) {
+                    let env = Env {
+                        vars: r.generics(),
+                        parent: Some(env),
+                    };
+                    self.rename(&named.inner, value, &env);
+                }
+            }
+            DataType::Generic(g) => {
+                let name: &str = g.borrow();
+                if let Some((_, ty)) = env
+                    .vars
+                    .iter()
+                    .find(|(var, _)| Borrow::<str>::borrow(var) == name)
+                {
+                    self.rename(ty, value, env.parent.unwrap_or(env));
+                }
+            }
+            DataType::Any | DataType::Unknown | DataType::Primitive(_) | DataType::Literal(_) => {}
+        }
+    }
+
+    fn tuple(&self, elements: &[DataType], value: &mut Value, env: &Env) {
+        if let Some(items) = value.as_array_mut() {
+            for (ty, item) in elements.iter().zip(items) {
+                self.rename(ty, item, env);
+            }
+        }
+    }
+
+    fn unnamed(&self, fields: &[Field], value: &mut Value, env: &Env) {
+        let fields = fields
+            .iter()
+            .filter_map(|f| f.ty())
+            .cloned()
+            .collect::<Vec<_>>();
+        match &fields[..] {
+            // Serde represents newtypes as their inner value
+            [ty] => self.rename(ty, value, env),
+            fields => self.tuple(fields, value, env),
+        }
+    }
+
+    fn object(&self, fields: &[(Cow<'static, str>, Field)], value: &mut Value, env: &Env) {
+        if let Some(object) = value.as_object_mut() {
+            let mut remaining = std::mem::take(object);
+            self.fields(fields, &mut remaining, object, env);
+            // Keys which aren't fields, Eg. the tag of an enum
+            object.append(&mut remaining);
+        }
+    }
+
+    // Move the `fields` from `from` into `to` under their new names.
+    fn fields(
+        &self,
+        fields: &[(Cow<'static, str>, Field)],
+        from: &mut Map<String, Value>,
+        to: &mut Map<String, Value>,
+        env: &Env,
+    ) {
+        for (name, field) in fields {
+            let Some(ty) = field.ty() else { continue };
+
+            if field.flatten() {
+                self.flattened(ty, from, to, env);
+                continue;
+            }
+
+            let (old, new) = self.names(name);
+            if let Some(mut value) = from.remove(&old) {
+                self.rename(ty, &mut value, env);
+                to.insert(new, value);
+            }
+        }
+    }
+
+    // The fields of a flattened type share the object of their parent.
+    fn flattened(
+        &self,
+        ty: &DataType,
+        from: &mut Map<String, Value>,
+        to: &mut Map<String, Value>,
+        env: &Env,
+    ) {
+        match ty {
+            DataType::Nullable(ty) => self.flattened(ty, from, to, env),
+            DataType::Struct(s) => match s.fields() {
+                StructFields::Named(f) => self.fields(f.fields(), from, to, env),
+                StructFields::Unnamed(f) => {
+                    if let [ty] = &f.fields().iter().filter_map(|f| f.ty()).collect::<Vec<_>>()[..]
+                    {
+                        self.flattened(ty, from, to, env);
+                    }
+                }
+                StructFields::Unit => {}
+            },
+            DataType::Enum(e) => {
+                if let EnumRepr::Internal { tag } = e.repr() {
+                    let name = from.get(tag.as_ref()).and_then(Value::as_str);
+                    let variant = e.variants().iter().find(|(n, _)| Some(n.as_ref()) == name);
+                    match variant.map(|(_, v)| v.inner()) {
+                        Some(EnumVariants::Named(f)) => self.fields(f.fields(), from, to, env),
+                        Some(EnumVariants::Unnamed(f)) => {
+                            if let [ty] =
+                                &f.fields().iter().filter_map(|f| f.ty()).collect::<Vec<_>>()[..]
+                            {
+                                self.flattened(ty, from, to, env);
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            DataType::Reference(r) => {
+                if let Some(named) = self.type_map.get(r.sid()) {
+                    let env = Env {
+                        vars: r.generics(),
+                        parent: Some(env),
+                    };
+                    self.flattened(&named.inner, from, to, &env);
+                }
+            }
+            DataType::Generic(g) => {
+                let name: &str = g.borrow();
+                if let Some((_, ty)) = env
+                    .vars
+                    .iter()
+                    .find(|(var, _)| Borrow::<str>::borrow(var) == name)
+                {
+                    self.flattened(ty, from, to, env.parent.unwrap_or(env));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // The `(current, new)` key of a field.
+    fn names(&self, name: &str) -> (String, String) {
+        let renamed = self.rule.apply(name);
+        match self.direction {
+            Direction::Output => (name.to_string(), renamed),
+            Direction::Input => (renamed, name.to_string()),
+        }
+    }
+
+    fn variant(&self, inner: &EnumVariants, value: &mut Value, env: &Env) {
+        match inner {
+            EnumVariants::Unit => {}
+            EnumVariants::Named(f) => self.object(f.fields(), value, env),
+            EnumVariants::Unnamed(f) => self.unnamed(f.fields(), value, env),
+        }
+    }
+
+    fn enumeration(&self, e: &EnumType, value: &mut Value, env: &Env) {
+        let mut variants = e.variants().iter().filter(|(_, v)| !v.skip());
+
+        match e.repr() {
+            EnumRepr::External => {
+                let Some(object) = value.as_object_mut().filter(|o| o.len() == 1) else {
+                    return;
+                };
+                if let Some((key, inner)) = object.iter_mut().next() {
+                    if let Some((_, variant)) = variants.find(|(n, _)| n == key) {
+                        self.variant(variant.inner(), inner, env);
+                    }
+                }
+            }
+            EnumRepr::Internal { tag } => {
+                let name = value.get(tag.as_ref()).and_then(Value::as_str);
+                let variant = variants.find(|(n, _)| Some(n.as_ref()) == name);
+                if let Some((_, variant)) = variant {
+                    self.variant(variant.inner(), value, env);
+                }
+            }
+            EnumRepr::Adjacent { tag, content } => {
+                let name = value.get(tag.as_ref()).and_then(Value::as_str);
+                let variant = variants.find(|(n, _)| Some(n.as_ref()) == name);
+                if let (Some((_, variant)), Some(inner)) =
+                    (variant, value.get_mut(content.as_ref()))
+                {
+                    self.variant(variant.inner(), inner, env);
+                }
+            }
+            // The value doesn't say which variant it is, so use the first one it has the required fields of.
+            EnumRepr::Untagged => {
+                if let Some((_, variant)) = variants.find(|(_, v)| self.matches(v.inner(), value)) {
+                    self.variant(variant.inner(), value, env);
+                }
+            }
+        }
+    }
+
+    fn matches(&self, inner: &EnumVariants, value: &Value) -> bool {
+        match inner {
+            EnumVariants::Unit => value.is_null(),
+            EnumVariants::Unnamed(_) => !value.is_object(),
+            EnumVariants::Named(f) => value.as_object().is_some_and(|object| {
+                f.fields().iter().all(|(name, field)| match field.ty() {
+                    Some(ty) if !field.flatten() && !field.optional() => {
+                        matches!(ty, DataType::Nullable(_))
+                            || object.contains_key(&self.names(name).0)
+                    }
+                    _ => true,
+                })
+            }),
+        }
+    }
+}
+
+/// Wrap every procedure in the store with a [`RenameLayer`] and rename the fields of it's types. Used by [`Config::rename_fields`](crate::Config::rename_fields).
+///
+/// The `type_map` must be the router's type map *before* it's renamed.
+pub(crate) fn rename_fields<TCtx: 'static>(
+    rule: RenameRule,
+    mut procedures: ProcedureStore<TCtx>,
+    type_map: &Arc<TypeMap>,
+) -> ProcedureStore<TCtx> {
+    procedures.store = std::mem::take(&mut procedures.store)
+        .into_iter()
+        .map(|(key, procedure)| {
+            let ty = ProcedureDataType {
+                arg_ty: rename_datatype(rule, &procedure.ty.arg_ty),
+                result_ty: rename_datatype(rule, &procedure.ty.result_ty),
+                logs_ty: procedure
+                    .ty
+                    .logs_ty
+                    .as_ref()
+                    .map(|ty| rename_datatype(rule, ty)),
+                trailer_ty: procedure
+                    .ty
+                    .trailer_ty
+                    .as_ref()
+                    .map(|ty| rename_datatype(rule, ty)),
+                no_content: procedure.ty.no_content,
+                description: procedure.ty.description.clone(),
+                deprecated: procedure.ty.deprecated.clone(),
+                tags: procedure.ty.tags.clone(),
+                error_ty: procedure
+                    .ty
+                    .error_ty
+                    .as_ref()
+                    .map(|ty| rename_datatype(rule, ty)),
+                cache_control: procedure.ty.cache_control.clone(),
+                sla: procedure.ty.sla,
+            };
+            let exec = Box::new(RenameLayer {
+                next: procedure.exec,
+                rule,
+                ty: Arc::new(procedure.ty),
+                type_map: type_map.clone(),
+            });
+            (
+                key,
+                Procedure {
+                    exec,
+                    ty,
+                    visible: procedure.visible,
+                    typed: false,
+                },
+            )
+        })
+        .collect();
+    procedures
+}
+
+/// Renames the fields of a procedure's input, result, logs and trailer.
+struct RenameLayer<TCtx: 'static> {
+    next: Box<dyn Layer<TCtx>>,
+    rule: RenameRule,
+    // The original types of the procedure
+    ty: Arc<ProcedureDataType>,
+    type_map: Arc<TypeMap>,
+}
+
+impl<TCtx: 'static> Layer<TCtx> for RenameLayer<TCtx> {
+    fn call(
+        &self,
+        ctx: TCtx,
+        mut input: Value,
+        req: RequestContext,
+    ) -> Result<LayerResult, ExecError> {
+        Renamer {
+            rule: self.rule,
+            direction: Direction::Input,
+            type_map: &self.type_map,
+        }
+        .rename(&self.ty.arg_ty, &mut input, &Env::default());
+        let result = self.next.call(ctx, input, req)?;
+
+        let (rule, ty, type_map) = (self.rule, self.ty.clone(), self.type_map.clone());
+        let rename = move |ty: &DataType, mut value: Value| {
+            Renamer {
+                rule,
+                direction: Direction::Output,
+                type_map: &type_map,
+            }
+            .rename(ty, &mut value, &Env::default());
+            value
+        };
+
+        Ok(LayerResult::FutureValueOrStream(Box::pin(async move {
+            let result = match &ty.logs_ty {
+                Some(logs_ty) => {
+                    map_logs(result.into_value_or_stream(), |log| rename(logs_ty, log)).await
+                }
+                None => result.into_value_or_stream().await,
+            };
+
+            Ok(match result? {
+                ValueOrStream::Value(value) => ValueOrStream::Value(rename(&ty.result_ty, value)),
+                ValueOrStream::Stream(stream) => {
+                    let trailer_ty = ty.trailer_ty.clone();
+                    let stream = stream.map({
+                        let rename = rename.clone();
+                        move |item| item.map(|value| rename(&ty.result_ty, value))
+                    });
+                    ValueOrStream::Stream(match trailer_ty {
+                        Some(trailer_ty) => Box::pin(map_trailer(stream, move |trailer| {
+                            rename(&trailer_ty, trailer)
+                        })),
+                        None => Box::pin(stream),
+                    })
+                }
+            })
+        })))
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+    use serde_json::json;
+    use specta::Type;
+
+    use crate::{internal::ProcedureKind, validate_value, Config, ExecKind, RenameRule, Router};
+
+    #[derive(Serialize, Deserialize, Type)]
+    struct Filter {
+        owner_id: u32,
+    }
+
+    #[derive(Serialize, Type)]
+    struct Page<T> {
+        items: Vec<T>,
+        next_cursor: Option<u32>,
+    }
+
+    #[derive(Serialize, Type)]
+    #[serde(tag = "type")]
+    enum Event {
+        Created { created_at: String },
+    }
+
+    #[derive(Serialize, Type)]
+    struct Project {
+        project_id: u32,
+        last_event: Event,
+    }
+
+    #[test]
+    fn test_camel_case() {
+        let rule = RenameRule::CamelCase;
+        assert_eq!(rule.apply("created_at"), "createdAt");
+        assert_eq!(rule.apply("_private_field"), "privateField");
+        assert_eq!(rule.apply("already"), "already");
+    }
+
+    #[tokio::test]
+    async fn test_rename_fields() {
+        let router = <Router>::new()
+            .config(Config::new().rename_fields(RenameRule::CamelCase))
+            .query("projects", |t| {
+                t(|_, filter: Filter| Page {
+                    items: vec![Project {
+                        project_id: filter.owner_id,
+                        last_event: Event::Created {
+                            created_at: "today".into(),
+                        },
+                    }],
+                    next_cursor: None,
+                })
+            })
+            .build();
+
+        let result = router
+            .exec(
+                (),
+                ExecKind::Query,
+                "projects".into(),
+                Some(json!({ "ownerId": 42 })),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            result,
+            json!({
+                "items": [{
+                    "projectId": 42,
+                    "lastEvent": { "type": "Created", "createdAt": "today" },
+                }],
+                "nextCursor": null,
+            })
+        );
+
+        // The serialized result matches the exported type
+        let ty = router
+            .result_type(ProcedureKind::Query, "projects")
+            .unwrap();
+        validate_value(ty, &router.type_map(), &result).unwrap();
+
+        let bindings = router.ts_bindings().unwrap();
+        assert!(bindings.contains("nextCursor: number | null"));
+        assert!(bindings.contains("projectId: number"));
+        assert!(bindings.contains(r#"{ type: "Created"; createdAt: string }"#));
+        assert!(bindings.contains("ownerId: number"));
+        assert!(!bindings.contains("_id"));
+    }
+}
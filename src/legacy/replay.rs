@@ -0,0 +1,304 @@
+use std::{
+    collections::VecDeque,
+    fmt,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use futures::Stream;
+
+use crate::internal::jsonrpc::send_caught_up;
+
+type BoxedSource<T> = Pin<Box<dyn Stream<Item = T> + Send + Sync>>;
+
+type SequenceFn<T> = Arc<dyn Fn(&T) -> u64 + Send + Sync>;
+
+/// Build a subscription stream which replays historical events from a log before switching to live events, for event-sourced systems.
+///
+/// ## Correctness
+///
+/// To guarantee there are no gaps at the seam the live stream must be subscribed to *before* the log is read, so every event committed while the replay is in progress is also delivered by the live stream.
+/// This means events can be delivered by both streams, so live events are deduplicated by their sequence number: any live event with a sequence number less than or equal to the last replayed event is dropped.
+/// Both streams must yield events in increasing sequence order.
+///
+/// Once the replay has ended a `caughtUp` frame is sent to the client (over transports which support multiple frames per request, Eg. WebSocket), before the first live event. It can be used to hide a loading indicator.
+///
+/// While the replay is in progress the live stream is drained into an in-memory buffer, so it doesn't fall behind (Eg. a lagging broadcast channel). The buffer is unbounded so avoid replaying from very old cursors while the live stream is busy.
+///
+/// ```rust
+/// use futures::stream;
+/// use rspc::Replay;
+///
+/// #[derive(Clone, serde::Serialize, specta::Type)]
+/// struct Event { seq: u32 }
+///
+/// let router = <rspc::Router>::new()
+///     .subscription("events", |t| {
+///         t(|_, from: u32| {
+///             // Subscribe to live events, then read the log
+///             let live = stream::iter([Event { seq: 2 }, Event { seq: 3 }]);
+///             let replay = stream::iter([Event { seq: 1 }, Event { seq: 2 }]);
+///             Replay::new(|event: &Event| event.seq as u64)
+///                 .from_sequence(from as u64)
+///                 .stream(replay, live)
+///         })
+///     })
+///     .build();
+/// ```
+pub struct Replay<T> {
+    sequence: SequenceFn<T>,
+    from: u64,
+}
+
+impl<T> Replay<T> {
+    /// Start building a replay stream. `sequence` returns the sequence number of an event.
+    pub fn new(sequence: impl Fn(&T) -> u64 + Send + Sync + 'static) -> Self {
+        Self {
+            sequence: Arc::new(sequence),
+            from: 0,
+        }
+    }
+
+    /// Skip events with a sequence number lower than `from` (Eg. the cursor sent by the client) from both streams.
+    pub fn from_sequence(mut self, from: u64) -> Self {
+        self.from = from;
+        self
+    }
+
+    /// Construct the stream from the historical `replay` stream (Eg. a read of the log starting at the cursor) and the `live` stream. See [`Replay`] for the requirements of these.
+    pub fn stream<R, L>(self, replay: R, live: L) -> ReplayStream<T>
+    where
+        R: Stream<Item = T> + Send + Sync + 'static,
+        L: Stream<Item = T> + Send + Sync + 'static,
+    {
+        ReplayStream {
+            sequence: self.sequence,
+            from: self.from,
+            last: None,
+            replay: Some(Box::pin(replay)),
+            live: Some(Box::pin(live)),
+            buffer: VecDeque::new(),
+            caught_up: false,
+        }
+    }
+}
+
+impl<T> fmt::Debug for Replay<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Replay")
+            .field("from", &self.from)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The stream returned by [`Replay::stream`].
+pub struct ReplayStream<T> {
+    sequence: SequenceFn<T>,
+    from: u64,
+    // The sequence number of the last emitted event
+    last: Option<u64>,
+    // `None` once the stream has ended
+    replay: Option<BoxedSource<T>>,
+    live: Option<BoxedSource<T>>,
+    // Live events received while replaying
+    buffer: VecDeque<T>,
+    // Whether the end of the replay has been reported to the client
+    caught_up: bool,
+}
+
+impl<T> ReplayStream<T> {
+    // Whether `item` should be emitted, recording it as the last event if so.
+    fn accept(&mut self, item: &T) -> bool {
+        let seq = (self.sequence)(item);
+        if seq < self.from || self.last.is_some_and(|last| seq <= last) {
+            return false;
+        }
+        self.last = Some(seq);
+        true
+    }
+}
+
+impl<T> Stream for ReplayStream<T> {
+    type Item = T;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        let this = self.get_mut();
+
+        while let Some(replay) = &mut this.replay {
+            if let Some(live) = &mut this.live {
+                loop {
+                    match live.as_mut().poll_next(cx) {
+                        Poll::Ready(Some(item)) => this.buffer.push_back(item),
+                        Poll::Ready(None) => {
+                            this.live = None;
+                            break;
+                        }
+                        Poll::Pending => break,
+                    }
+                }
+            }
+
+            match replay.as_mut().poll_next(cx) {
+                Poll::Ready(Some(item)) => {
+                    if this.accept(&item) {
+                        return Poll::Ready(Some(item));
+                    }
+                }
+                Poll::Ready(None) => this.replay = None,
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+
+        if !this.caught_up {
+            this.caught_up = true;
+            if send_caught_up(cx) {
+                return Poll::Pending;
+            }
+        }
+
+        while let Some(item) = this.buffer.pop_front() {
+            if this.accept(&item) {
+                return Poll::Ready(Some(item));
+            }
+        }
+
+        while let Some(live) = &mut this.live {
+            match live.as_mut().poll_next(cx) {
+                Poll::Ready(Some(item)) => {
+                    if this.accept(&item) {
+                        return Poll::Ready(Some(item));
+                    }
+                }
+                Poll::Ready(None) => this.live = None,
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+
+        Poll::Ready(None)
+    }
+}
+
+// `ReplayStream` never hands out pinned references to it's fields.
+impl<T> Unpin for ReplayStream<T> {}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+// Some of the tests use the default runtime, so their helpers are unused without it
+#[cfg_attr(not(feature = "runtime-tokio"), allow(unused))]
+mod tests {
+    use std::collections::HashMap;
+
+    use futures::{stream, FutureExt, StreamExt};
+    use serde_json::json;
+    use tokio::sync::{mpsc, Mutex};
+
+    use super::*;
+    use crate::{
+        internal::jsonrpc::{self, handle_json_rpc, RequestId, Sender, SubscriptionMap},
+        Router,
+    };
+
+    #[tokio::test]
+    async fn test_replay_seam() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let live = stream::poll_fn(move |cx| rx.poll_recv(cx));
+
+        // Events 3 and 4 were committed while the log was being read so both streams have them
+        for seq in [3, 4] {
+            tx.send(seq).unwrap();
+        }
+        let mut events = Replay::new(|seq: &u64| *seq)
+            .from_sequence(2)
+            .stream(stream::iter([2, 3, 4]), live);
+
+        let mut received = Vec::new();
+        for _ in 0..3 {
+            received.push(events.next().await.unwrap());
+        }
+        assert_eq!(received, [2, 3, 4]);
+        assert_eq!(events.next().now_or_never(), None);
+
+        tx.send(5).unwrap();
+        assert_eq!(events.next().await, Some(5));
+        drop(tx);
+        assert_eq!(events.next().await, None);
+
+        // Live events before the cursor are skipped when there is nothing to replay
+        let events = Replay::new(|seq: &u64| *seq)
+            .from_sequence(3)
+            .stream(stream::empty(), stream::iter([1, 3, 4]))
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(events, [3, 4]);
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "runtime-tokio")]
+    async fn test_caught_up() {
+        let router = Arc::new(
+            <Router>::new()
+                .subscription("events", |t| {
+                    t(|_, _: ()| {
+                        Replay::new(|seq: &u64| *seq)
+                            .stream(stream::iter([1, 2]), stream::iter([2, 3, 4]))
+                    })
+                })
+                .subscription("counter", |t| {
+                    t.snapshot_then_stream(|_, _: ()| 0, |_, _: ()| stream::iter([1, 2]))
+                })
+                .build(),
+        );
+
+        let (mut tx, mut rx) = mpsc::unbounded_channel();
+        let subscriptions = Mutex::new(HashMap::new());
+        for (id, path) in [(1, "events"), (2, "counter")] {
+            handle_json_rpc(
+                (),
+                jsonrpc::Request {
+                    jsonrpc: None,
+                    id: RequestId::Null,
+                    inner: jsonrpc::RequestInner::Subscription {
+                        path: path.into(),
+                        input: (RequestId::Number(id), None),
+                    },
+                },
+                &router,
+                &mut Sender::ResponseChannel(&mut tx),
+                &mut SubscriptionMap::Mutex(&subscriptions),
+            )
+            .await;
+        }
+        drop(tx);
+
+        let mut frames = HashMap::<_, Vec<_>>::new();
+        while let Some(resp) = rx.recv().await {
+            frames
+                .entry(resp.id)
+                .or_default()
+                .push(serde_json::to_value(resp.result).unwrap());
+        }
+        let caught_up = json!({ "type": "caughtUp" });
+        assert_eq!(
+            frames[&RequestId::Number(1)],
+            [
+                json!({ "type": "event", "data": 1 }),
+                json!({ "type": "event", "data": 2 }),
+                caught_up.clone(),
+                json!({ "type": "event", "data": 3 }),
+                json!({ "type": "event", "data": 4 }),
+                json!({ "type": "complete" }),
+            ]
+        );
+        assert_eq!(
+            frames[&RequestId::Number(2)],
+            [
+                json!({ "type": "event", "data": { "type": "snapshot", "data": 0 } }),
+                caught_up,
+                json!({ "type": "event", "data": { "type": "update", "data": 1 } }),
+                json!({ "type": "event", "data": { "type": "update", "data": 2 } }),
+                json!({ "type": "complete" }),
+            ]
+        );
+    }
+}
@@ -0,0 +1,140 @@
+use crate::headers;
+
+/// The media type parameter of the `Accept` header which selects the representation of the result.
+const PARAMETER: &str = "representation";
+
+/// The representation of the result the client asked for, so a procedure can return either a compact or a detailed form of the same data.
+///
+/// The client sends it as a `representation` parameter of the `Accept` header (Eg. `Accept: application/json; representation=compact`). The first `Accept` value with the parameter is used, and the name is lowercased.
+/// For WebSocket requests it's the `Accept` header of the request which opened the connection, so every request of a connection gets the same representation.
+///
+/// Returns `None` when the client didn't ask for one (or outside of a request), in which case the procedure should return it's default representation.
+///
+/// Results cached with [`.cache()`](crate::internal::BuiltProcedureBuilder::cache) and requests collapsed by [`Dedup`](crate::Dedup) are keyed by the representation, so clients never get a representation they didn't ask for.
+/// The HTTP transport sends `Vary: Accept` with every response which has a `Cache-Control` header, so HTTP caches key them by it too.
+///
+/// Return an `#[serde(untagged)]` enum of the representations so the exported result type is the union of them (Eg. `UserSummary | User`) and the client has to narrow it.
+///
+/// ```rust
+/// #[derive(serde::Serialize, specta::Type)]
+/// struct UserSummary { id: u32 }
+///
+/// #[derive(serde::Serialize, specta::Type)]
+/// struct User { id: u32, name: String, bio: String }
+///
+/// #[derive(serde::Serialize, specta::Type)]
+/// #[serde(untagged)]
+/// enum UserRepresentation {
+///     Compact(UserSummary),
+///     Detailed(User),
+/// }
+///
+/// let router = <rspc::Router>::new()
+///     .query("user", |t| {
+///         t(|_, id: u32| match rspc::representation().as_deref() {
+///             Some("compact") => UserRepresentation::Compact(UserSummary { id }),
+///             _ => UserRepresentation::Detailed(User { id, name: "Oscar".into(), bio: "".into() }),
+///         })
+///     })
+///     .build();
+/// ```
+pub fn representation() -> Option<String> {
+    headers().get_all("accept").find_map(|accept| {
+        accept
+            .split([',', ';'])
+            .filter_map(|param| param.split_once('='))
+            .find(|(name, _)| name.trim().eq_ignore_ascii_case(PARAMETER))
+            .map(|(_, value)| value.trim().trim_matches('"').to_ascii_lowercase())
+    })
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use std::time::Duration;
+
+    use serde::Serialize;
+    use serde_json::json;
+    use specta::Type;
+
+    use crate::{
+        internal::jsonrpc::{with_headers, Headers},
+        ExecKind, Router,
+    };
+
+    #[derive(Serialize, Type)]
+    struct UserSummary {
+        id: u32,
+    }
+
+    #[derive(Serialize, Type)]
+    struct User {
+        id: u32,
+        name: &'static str,
+        bio: &'static str,
+    }
+
+    #[derive(Serialize, Type)]
+    #[serde(untagged)]
+    enum UserRepresentation {
+        Compact(UserSummary),
+        Detailed(User),
+    }
+
+    #[tokio::test]
+    async fn test_representation() {
+        let router = <Router>::new()
+            .query("user", |t| {
+                t(|_, id: u32| match crate::representation().as_deref() {
+                    Some("compact") => UserRepresentation::Compact(UserSummary { id }),
+                    _ => UserRepresentation::Detailed(User {
+                        id,
+                        name: "Oscar",
+                        bio: "Likes rust",
+                    }),
+                })
+                // Each representation is cached separately
+                .cache(Duration::from_secs(60))
+            })
+            .build();
+
+        // Both representations are exported
+        let bindings = router.ts_bindings().unwrap();
+        assert!(bindings.contains("export type UserSummary = "));
+        assert!(bindings.contains("export type User = "));
+        assert!(bindings.contains("UserSummary | User"));
+
+        let exec = |accept: Option<&str>| {
+            let headers = accept
+                .map(|accept| ("Accept", accept))
+                .into_iter()
+                .collect::<Headers>();
+            with_headers(
+                headers,
+                router.exec((), ExecKind::Query, "user".into(), Some(json!(1))),
+            )
+        };
+
+        let detailed = json!({ "id": 1, "name": "Oscar", "bio": "Likes rust" });
+        assert_eq!(exec(None).await.unwrap(), detailed);
+        assert_eq!(
+            exec(Some("application/json; representation=compact"))
+                .await
+                .unwrap(),
+            json!({ "id": 1 })
+        );
+        // Parameter names are case-insensitive and values may be quoted
+        assert_eq!(
+            exec(Some(
+                "text/html, application/json;q=0.9;Representation=\"Compact\""
+            ))
+            .await
+            .unwrap(),
+            json!({ "id": 1 })
+        );
+        assert_eq!(exec(Some("application/json")).await.unwrap(), detailed);
+
+        // There is no representation outside of a request
+        assert_eq!(crate::representation(), None);
+    }
+}
@@ -0,0 +1,318 @@
+use std::{
+    fmt,
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use futures::Stream;
+
+use crate::{
+    internal::{LayerResult, RequestContext, ValueOrStream, ValueOrStreamOrFutureStream},
+    ExecError, Sampler,
+};
+
+use super::{config::OnSerializeFn, serialize_timer::SerializeTimer, trace_context::TraceContext};
+
+/// Add an attribute (Eg. `user.tier`) to the tracing span of the request currently being executed.
+///
+/// Every procedure is executed within a `rspc.request` span. As `tracing` requires a span's fields to be known upfront, the attributes are recorded together in it's `rspc.attributes` field, formatted as `key=value` pairs sorted by key.
+/// Recording the same key twice replaces it's value.
+///
+/// Note: This does nothing when the `tracing` feature is disabled, when it's called outside of a request or when the request's trace isn't sampled (see [`traceparent`](crate::traceparent)).
+///
+/// ```rust
+/// let router = <rspc::Router>::new()
+///     .query("users.list", |t| {
+///         t(|_, _: ()| {
+///             let users = vec!["alice", "bob"];
+///             rspc::record("query.rows", users.len());
+///             users
+///         })
+///     })
+///     .build();
+/// ```
+pub fn record(key: &'static str, value: impl fmt::Display) {
+    #[cfg(feature = "tracing")]
+    {
+        let _ = traced::REQUEST_SPAN.try_with(|span| span.record(key, value.to_string()));
+    }
+    #[cfg(not(feature = "tracing"))]
+    {
+        let _ = (key, value);
+    }
+}
+
+/// Execute a procedure within it's trace, and it's `rspc.request` span if the trace is sampled. See [`record`].
+pub(crate) fn instrument(
+    req: RequestContext,
+    sampler: Option<&Arc<dyn Sampler>>,
+    on_serialize: Option<&OnSerializeFn>,
+    call: impl FnOnce(RequestContext) -> Result<LayerResult, ExecError>,
+) -> Result<LayerResult, ExecError> {
+    let trace = TraceContext::for_request(&req, sampler);
+    let scope = Scope {
+        #[cfg(feature = "tracing")]
+        span: traced::RequestSpan::new(&req, trace),
+        trace,
+        serialize_timer: SerializeTimer::new(on_serialize, &req),
+    };
+
+    Ok(match scope.scoped(|| call(req))? {
+        LayerResult::Ready(result) => LayerResult::Ready(result),
+        LayerResult::Future(fut) => LayerResult::Future(Box::pin(Scoped(fut, scope))),
+        LayerResult::Stream(stream) => LayerResult::Stream(Box::pin(Scoped(stream, scope))),
+        LayerResult::FutureValueOrStream(fut) => {
+            LayerResult::FutureValueOrStream(Box::pin(async move {
+                Ok(match Scoped(fut, scope.clone()).await? {
+                    ValueOrStream::Stream(stream) => {
+                        ValueOrStream::Stream(Box::pin(Scoped(stream, scope)))
+                    }
+                    value => value,
+                })
+            }))
+        }
+        LayerResult::FutureValueOrStreamOrFutureStream(fut) => {
+            LayerResult::FutureValueOrStreamOrFutureStream(Box::pin(async move {
+                Ok(match Scoped(fut, scope.clone()).await? {
+                    ValueOrStreamOrFutureStream::Stream(stream) => {
+                        ValueOrStreamOrFutureStream::Stream(Box::pin(Scoped(stream, scope)))
+                    }
+                    value => value,
+                })
+            }))
+        }
+    })
+}
+
+// What the resolver of a request can access while it's executing
+#[derive(Clone)]
+struct Scope {
+    trace: TraceContext,
+    #[cfg(feature = "tracing")]
+    span: traced::RequestSpan,
+    serialize_timer: SerializeTimer,
+}
+
+impl Scope {
+    fn scoped<T>(&self, f: impl FnOnce() -> T) -> T {
+        let f = || self.serialize_timer.scoped(f);
+        #[cfg(feature = "tracing")]
+        {
+            self.span.scoped(|| self.trace.scoped(f))
+        }
+        #[cfg(not(feature = "tracing"))]
+        {
+            self.trace.scoped(f)
+        }
+    }
+}
+
+// Polls a future or stream within the request's scope, so it's available to `record` and `traceparent` from the resolver
+struct Scoped<T>(T, Scope);
+
+impl<T: Future + Unpin> Future for Scoped<T> {
+    type Output = T::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let Self(inner, scope) = &mut *self;
+        scope.scoped(|| Pin::new(inner).poll(cx))
+    }
+}
+
+impl<T: Stream + Unpin> Stream for Scoped<T> {
+    type Item = T::Item;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let Self(inner, scope) = &mut *self;
+        scope.scoped(|| Pin::new(inner).poll_next(cx))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+#[cfg(feature = "tracing")]
+mod traced {
+    use std::{
+        collections::BTreeMap,
+        sync::{Arc, Mutex},
+    };
+
+    use crate::internal::RequestContext;
+
+    use super::TraceContext;
+
+    tokio::task_local! {
+        pub(super) static REQUEST_SPAN: RequestSpan;
+    }
+
+    #[derive(Clone)]
+    pub(super) struct RequestSpan(Arc<Inner>);
+
+    struct Inner {
+        span: tracing::Span,
+        attributes: Mutex<BTreeMap<&'static str, String>>,
+    }
+
+    impl RequestSpan {
+        // A trace which isn't sampled gets a disabled span, so it's not exported by the subscriber
+        pub(super) fn new(req: &RequestContext, trace: TraceContext) -> Self {
+            let span = match trace.sampled {
+                true => tracing::info_span!(
+                    "rspc.request",
+                    rspc.kind = ?req.kind,
+                    rspc.path = req.path,
+                    rspc.trace_id = format_args!("{:032x}", trace.trace_id),
+                    rspc.attributes = tracing::field::Empty,
+                ),
+                false => tracing::Span::none(),
+            };
+            Self(Arc::new(Inner {
+                span,
+                attributes: Mutex::new(BTreeMap::new()),
+            }))
+        }
+
+        pub(super) fn record(&self, key: &'static str, value: String) {
+            let Ok(mut attributes) = self.0.attributes.lock() else {
+                return;
+            };
+            attributes.insert(key, value);
+            let attributes = attributes
+                .iter()
+                .map(|(key, value)| format!("{key}={value}"))
+                .collect::<Vec<_>>()
+                .join(" ");
+            self.0.span.record("rspc.attributes", attributes);
+        }
+
+        pub(super) fn scoped<T>(&self, f: impl FnOnce() -> T) -> T {
+            let _entered = self.0.span.enter();
+            REQUEST_SPAN.sync_scope(self.clone(), f)
+        }
+    }
+}
+
+#[cfg(all(test, feature = "tracing"))]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use std::{
+        fmt,
+        sync::{
+            atomic::{AtomicU64, Ordering},
+            Arc, Mutex,
+        },
+    };
+
+    use tracing::{
+        field::{Field, Visit},
+        span, Event, Metadata, Subscriber,
+    };
+
+    use crate::{Config, ExecKind, Router};
+
+    // Collects the value of every field recorded on a span after it was created
+    #[derive(Default)]
+    struct Recorder {
+        next_id: AtomicU64,
+        recorded: Arc<Mutex<Vec<(String, String)>>>,
+    }
+
+    impl Visit for &Recorder {
+        fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+            self.recorded
+                .lock()
+                .unwrap()
+                .push((field.name().to_string(), format!("{value:?}")));
+        }
+
+        fn record_str(&mut self, field: &Field, value: &str) {
+            self.recorded
+                .lock()
+                .unwrap()
+                .push((field.name().to_string(), value.to_string()));
+        }
+    }
+
+    impl Subscriber for Recorder {
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, _: &span::Attributes<'_>) -> span::Id {
+            span::Id::from_u64(self.next_id.fetch_add(1, Ordering::SeqCst) + 1)
+        }
+
+        fn record(&self, _: &span::Id, values: &span::Record<'_>) {
+            values.record(&mut &*self);
+        }
+
+        fn record_follows_from(&self, _: &span::Id, _: &span::Id) {}
+        fn event(&self, _: &Event<'_>) {}
+        fn enter(&self, _: &span::Id) {}
+        fn exit(&self, _: &span::Id) {}
+    }
+
+    #[tokio::test]
+    async fn test_record() {
+        let recorder = Recorder::default();
+        let recorded = recorder.recorded.clone();
+        let _guard = tracing::subscriber::set_default(recorder);
+
+        let router = <Router>::new()
+            .query("users.list", |t| {
+                t(|_, _: ()| async {
+                    crate::record("user.tier", "gold");
+                    tokio::task::yield_now().await;
+                    crate::record("query.rows", 2);
+                    vec!["alice", "bob"]
+                })
+            })
+            .build();
+        router
+            .exec((), ExecKind::Query, "users.list".into(), None)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            recorded.lock().unwrap().last().unwrap(),
+            &(
+                "rspc.attributes".to_string(),
+                "query.rows=2 user.tier=gold".to_string()
+            )
+        );
+
+        // Outside of a request there is no span to record to
+        crate::record("user.tier", "silver");
+        assert_eq!(recorded.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_unsampled_request_has_no_span() {
+        let recorder = Recorder::default();
+        let _guard = tracing::subscriber::set_default(recorder);
+
+        let router = <Router>::new()
+            .config(Config::new().trace_sampler(|_: &_| false))
+            .query("users.list", |t| {
+                t(|_, _: ()| {
+                    crate::record("user.tier", "gold");
+                    vec!["alice", "bob"]
+                })
+            })
+            .build();
+        router
+            .exec((), ExecKind::Query, "users.list".into(), None)
+            .await
+            .unwrap();
+
+        tracing::dispatcher::get_default(|dispatch| {
+            let recorder = dispatch.downcast_ref::<Recorder>().unwrap();
+            assert_eq!(recorder.next_id.load(Ordering::SeqCst), 0);
+            assert!(recorder.recorded.lock().unwrap().is_empty());
+        });
+    }
+}
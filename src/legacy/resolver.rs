@@ -6,6 +6,7 @@ use serde_json::Value;
 use specta::Type;
 use specta::TypeMap;
 
+use super::{deserialize::deserialize_input, serialize_timer};
 use crate::{
     internal::{LayerResult, ProcedureDataType},
     ExecError, RequestLayer,
@@ -80,15 +81,24 @@ where
     type Result = TResult;
 
     fn exec(&self, ctx: TCtx, input: Value) -> Result<LayerResult, ExecError> {
-        let input = serde_json::from_value(input).map_err(ExecError::DeserializingArgErr)?;
+        let input = deserialize_input(input)?;
         self(ctx, input).into_layer_result()
     }
 
     fn typedef(defs: &mut TypeMap) -> ProcedureDataType {
-        typedef::<TArg, TResult::Result>(defs)
+        ProcedureDataType {
+            logs_ty: TResult::logs_type(defs),
+            no_content: TResult::no_content(),
+            ..typedef::<TArg, TResult::Result>(defs)
+        }
     }
 }
 
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` is not a valid subscription resolver",
+    label = "invalid subscription resolver",
+    note = "a subscription resolver must be a function `Fn(ctx, input) -> impl Stream` where the input implements `DeserializeOwned + Type`, the stream is `Send + Sync + 'static` and it's items implement `Serialize + Type`"
+)]
 pub trait StreamResolver<TCtx, TMarker> {
     fn exec(&self, ctx: TCtx, input: Value) -> Result<LayerResult, ExecError>;
 
@@ -107,10 +117,10 @@ where
     TResult: Serialize + Type,
 {
     fn exec(&self, ctx: TCtx, input: Value) -> Result<LayerResult, ExecError> {
-        let input = serde_json::from_value(input).map_err(ExecError::DeserializingArgErr)?;
-        Ok(LayerResult::Stream(Box::pin(self(ctx, input).map(|v| {
-            serde_json::to_value(&v).map_err(ExecError::SerializingResultErr)
-        }))))
+        let input = deserialize_input(input)?;
+        Ok(LayerResult::Stream(Box::pin(
+            self(ctx, input).map(|v| serialize_timer::to_value(&v)),
+        )))
     }
 
     fn typedef(defs: &mut TypeMap) -> ProcedureDataType {
@@ -119,8 +129,19 @@ where
 }
 
 pub fn typedef<TArg: Type, TResult: Type>(defs: &mut TypeMap) -> ProcedureDataType {
-    let mut type_map = TypeMap::default();
-    let arg_ty = TArg::reference(&mut type_map, &[]).inner;
-    let result_ty = TResult::reference(&mut type_map, &[]).inner;
-    ProcedureDataType { arg_ty, result_ty }
+    let arg_ty = TArg::reference(defs, &[]).inner;
+    let result_ty = TResult::reference(defs, &[]).inner;
+    ProcedureDataType {
+        arg_ty,
+        result_ty,
+        logs_ty: None,
+        trailer_ty: None,
+        no_content: false,
+        description: None,
+        deprecated: None,
+        tags: Vec::new(),
+        error_ty: None,
+        cache_control: None,
+        sla: None,
+    }
 }
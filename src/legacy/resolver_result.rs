@@ -1,45 +1,84 @@
-use std::{future::Future, marker::PhantomData};
+use std::{cell::RefCell, future::Future, marker::PhantomData};
 
 use serde::Serialize;
-use specta::Type;
+use serde_json::Value;
+use specta::{DataType, Type, TypeMap};
 
+use super::serialize_timer;
 use crate::{
     internal::{LayerResult, ValueOrStream},
     Error, ExecError,
 };
 
+tokio::task_local! {
+    /// Set by [`Router::exec_into`](crate::Router::exec_into) to receive the result of a procedure before it's converted to a [`Value`].
+    pub(crate) static TYPED_RESULT: RefCell<TypedResult>;
+}
+
+pub(crate) enum TypedResult {
+    /// The result is converted to a [`Value`] as normal.
+    Off,
+    /// The next result is stored instead of being converted.
+    Waiting,
+    Ready(Box<dyn erased_serde::Serialize + Send>),
+}
+
+/// Convert a resolver's result to a [`Value`], unless [`TYPED_RESULT`] is waiting for it in which case it's stored there and `null` is returned in it's place.
+fn into_value<T: Serialize + Send + 'static>(result: T) -> Result<Value, ExecError> {
+    let mut result = Some(result);
+    let _ = TYPED_RESULT.try_with(|slot| {
+        let mut slot = slot.borrow_mut();
+        if matches!(*slot, TypedResult::Waiting) {
+            if let Some(result) = result.take() {
+                *slot = TypedResult::Ready(Box::new(result));
+            }
+        }
+    });
+    match result {
+        Some(result) => serialize_timer::to_value(&result),
+        None => Ok(Value::Null),
+    }
+}
+
 pub trait RequestLayer<TMarker> {
     type Result: Type;
 
     fn into_layer_result(self) -> Result<LayerResult, ExecError>;
+
+    /// The type of the log lines streamed before the result. This is only set by [`WithLogs`](crate::WithLogs).
+    fn logs_type(_defs: &mut TypeMap) -> Option<DataType> {
+        None
+    }
+
+    /// Whether the result is always empty. This is only set by [`NoContent`](crate::NoContent).
+    fn no_content() -> bool {
+        false
+    }
 }
 
 pub struct SerializeMarker(PhantomData<()>);
 impl<T> RequestLayer<SerializeMarker> for T
 where
-    T: Serialize + Type,
+    T: Serialize + Type + Send + 'static,
 {
     type Result = T;
 
     fn into_layer_result(self) -> Result<LayerResult, ExecError> {
-        Ok(LayerResult::Ready(Ok(
-            serde_json::to_value(self).map_err(ExecError::SerializingResultErr)?
-        )))
+        Ok(LayerResult::Ready(Ok(into_value(self)?)))
     }
 }
 
 pub struct ResultMarker(PhantomData<()>);
 impl<T> RequestLayer<ResultMarker> for Result<T, Error>
 where
-    T: Serialize + Type,
+    T: Serialize + Type + Send + 'static,
 {
     type Result = T;
 
     fn into_layer_result(self) -> Result<LayerResult, ExecError> {
-        Ok(LayerResult::Ready(Ok(serde_json::to_value(
+        Ok(LayerResult::Ready(Ok(into_value(
             self.map_err(ExecError::ErrResolverError)?,
-        )
-        .map_err(ExecError::SerializingResultErr)?)))
+        )?)))
     }
 }
 
@@ -64,4 +103,12 @@ where
             }
         })))
     }
+
+    fn logs_type(defs: &mut TypeMap) -> Option<DataType> {
+        T::logs_type(defs)
+    }
+
+    fn no_content() -> bool {
+        T::no_content()
+    }
 }
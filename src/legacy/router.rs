@@ -1,21 +1,38 @@
 use std::{
-    collections::BTreeMap,
-    fs::{self, File},
-    io::Write,
+    cell::RefCell,
+    collections::{BTreeMap, BTreeSet},
+    fs, io,
     marker::PhantomData,
     path::{Path, PathBuf},
     pin::Pin,
     sync::Arc,
 };
 
-use futures::Stream;
+use futures::{future::join_all, Stream, StreamExt};
+use serde::{ser::SerializeSeq, Serialize, Serializer};
 use serde_json::Value;
-use specta::{datatype::FunctionResultVariant, DataType, TypeMap};
+use specta::{
+    datatype::{EnumVariants, Field, FunctionResultVariant, StructFields},
+    DataType, SpectaID, TypeMap,
+};
 use specta_typescript::{self as ts, datatype, Typescript};
 
+use super::{
+    active_subscriptions::ActiveSubscriptions,
+    cache::Caches,
+    openrpc,
+    query_params::{decode_query_params, query_params},
+    request_span,
+    resolver_result::{TypedResult, TYPED_RESULT},
+    visibility::VisibleFn,
+    warmup::WarmupFn,
+    zod,
+};
 use crate::{
-    internal::{Procedure, ProcedureKind, ProcedureStore, RequestContext, ValueOrStream},
-    Config, ExecError, ExportError,
+    internal::{
+        Layer, LayerResult, Procedure, ProcedureKind, ProcedureStore, RequestContext, ValueOrStream,
+    },
+    BuildError, Config, Error, ExecError, ExecIntoError, ExportError, OpenRpcInfo, Priority,
 };
 
 /// TODO
@@ -27,7 +44,15 @@ where
     pub(crate) queries: ProcedureStore<TCtx>,
     pub(crate) mutations: ProcedureStore<TCtx>,
     pub(crate) subscriptions: ProcedureStore<TCtx>,
+    pub(crate) caches: Caches,
+    pub(crate) priorities: BTreeMap<String, Priority>,
+    pub(crate) active_subscriptions: ActiveSubscriptions,
+    pub(crate) warmups: Vec<(String, WarmupFn<TCtx>)>,
+    pub(crate) fallback: Option<Box<dyn Layer<TCtx>>>,
+    pub(crate) ignored_options: Vec<(ProcedureKind, String, &'static str)>,
     pub(crate) type_map: TypeMap,
+    // Routers mounted by a [`RouterGroup`](crate::RouterGroup), keyed by their namespace
+    pub(crate) namespaces: BTreeMap<&'static str, Router<TCtx, TMeta>>,
     pub(crate) phantom: PhantomData<TMeta>,
 }
 
@@ -49,26 +74,20 @@ where
         key: String,
         input: Option<Value>,
     ) -> Result<Value, ExecError> {
-        let (operations, kind) = match kind {
-            ExecKind::Query => (&self.queries.store, ProcedureKind::Query),
-            ExecKind::Mutation => (&self.mutations.store, ProcedureKind::Mutation),
+        let kind = match kind {
+            ExecKind::Query => ProcedureKind::Query,
+            ExecKind::Mutation => ProcedureKind::Mutation,
         };
 
-        match operations
-            .get(&key)
-            .ok_or_else(|| ExecError::OperationNotFound(key.clone()))?
-            .exec
-            .call(
-                ctx,
-                input.unwrap_or(Value::Null),
-                RequestContext {
-                    kind,
-                    path: key.clone(),
-                },
-            )?
-            .into_value_or_stream()
-            .await?
-        {
+        // A procedure executed from within `exec_into` mustn't take it's result
+        let result = TYPED_RESULT
+            .scope(RefCell::new(TypedResult::Off), async {
+                self.call(ctx, kind, key.clone(), input.unwrap_or(Value::Null))?
+                    .into_value_or_stream()
+                    .await
+            })
+            .await;
+        match result? {
             ValueOrStream::Value(v) => Ok(v),
             ValueOrStream::Stream(_) => Err(ExecError::UnsupportedMethod(key)),
         }
@@ -81,18 +100,11 @@ where
         input: Option<Value>,
     ) -> Result<Pin<Box<dyn Stream<Item = Result<Value, ExecError>> + Send>>, ExecError> {
         match self
-            .subscriptions
-            .store
-            .get(&key)
-            .ok_or_else(|| ExecError::OperationNotFound(key.clone()))?
-            .exec
             .call(
                 ctx,
+                ProcedureKind::Subscription,
+                key.clone(),
                 input.unwrap_or(Value::Null),
-                RequestContext {
-                    kind: ProcedureKind::Subscription,
-                    path: key.clone(),
-                },
             )?
             .into_value_or_stream()
             .await?
@@ -102,6 +114,155 @@ where
         }
     }
 
+    /// Execute a procedure, serializing it's result directly into `serializer` instead of returning it, Eg. to write it into a larger JSON document which is being streamed.
+    ///
+    /// ## Single values and streams
+    ///
+    /// The result of a query or mutation is serialized as a single value once it resolves.
+    /// A subscription is serialized as a sequence with an element for each event, which is serialized as soon as it arrives. So this only completes once the subscription's stream ends and it should not be used for subscriptions which never end.
+    /// If the subscription yields an error the sequence is left unfinished (so the output is incomplete) and the error is returned.
+    ///
+    /// ## Typed results
+    ///
+    /// When nothing can read or rewrite the result of a query or mutation, it's serialized straight from the resolver's return type into `serializer` without building a [`Value`].
+    /// That's the case when the router has no middleware, the procedure doesn't use `cache`, `restrict_field` or `virtual_fields` and the router doesn't use [`Config::rename_fields`](crate::Config::rename_fields), [`Config::transform_responses`](crate::Config::transform_responses), [`Config::validate_results`](crate::Config::validate_results), [`Config::after_mutation`](crate::Config::after_mutation) or [`Config::deprecation_warnings`](crate::Config::deprecation_warnings).
+    ///
+    /// Note: Otherwise the result is built as a [`Value`] (as the layers above operate on one) and serialized from that, which only avoids the copies of building an owned result and embedding it into the document. Subscription events are always built as a [`Value`].
+    pub async fn exec_into<S: Serializer>(
+        &self,
+        ctx: TCtx,
+        kind: ProcedureKind,
+        key: String,
+        input: Option<Value>,
+        serializer: S,
+    ) -> Result<S::Ok, ExecIntoError<S::Error>> {
+        let slot = match self.procedure(kind, &key).is_some_and(|p| p.typed) {
+            true => TypedResult::Waiting,
+            false => TypedResult::Off,
+        };
+        let (result, typed) = TYPED_RESULT
+            .scope(RefCell::new(slot), async {
+                let result = match self.call(ctx, kind, key, input.unwrap_or(Value::Null)) {
+                    Ok(result) => result.into_value_or_stream().await,
+                    Err(err) => Err(err),
+                };
+                (
+                    result,
+                    TYPED_RESULT.with(|slot| slot.replace(TypedResult::Off)),
+                )
+            })
+            .await;
+        match result? {
+            ValueOrStream::Value(value) => match typed {
+                TypedResult::Ready(result) => erased_serde::serialize(&*result, serializer),
+                _ => value.serialize(serializer),
+            }
+            .map_err(ExecIntoError::Serialize),
+            ValueOrStream::Stream(mut stream) => {
+                let mut seq = serializer
+                    .serialize_seq(None)
+                    .map_err(ExecIntoError::Serialize)?;
+                while let Some(event) = stream.next().await {
+                    seq.serialize_element(&event?)
+                        .map_err(ExecIntoError::Serialize)?;
+                }
+                seq.end().map_err(ExecIntoError::Serialize)
+            }
+        }
+    }
+
+    /// Call the procedure at `path`, or the fallback if there is no such query or mutation.
+    ///
+    /// If `path` starts with the namespace of a router mounted by a [`RouterGroup`](crate::RouterGroup) the rest of it is called on that router.
+    pub(crate) fn call(
+        &self,
+        ctx: TCtx,
+        kind: ProcedureKind,
+        path: String,
+        input: Value,
+    ) -> Result<LayerResult, ExecError> {
+        if let Some((namespace, key)) = path.split_once('.') {
+            if let Some(router) = self.namespaces.get(namespace) {
+                return router
+                    .call(ctx, kind, key.to_string(), input)
+                    .map_err(|err| match err {
+                        ExecError::OperationNotFound(key) => {
+                            ExecError::OperationNotFound(format!("{namespace}.{key}"))
+                        }
+                        err => err,
+                    });
+            }
+        }
+
+        if let Some((key, info)) = &self.config.introspection {
+            if kind == ProcedureKind::Query
+                && path == *key
+                && !self.queries.store.contains_key(&path)
+            {
+                let document =
+                    self.openrpc_filtered(info, &|procedure| is_visible(procedure, &ctx));
+                return Ok(LayerResult::Ready(Ok(document)));
+            }
+        }
+
+        let procedures = match kind {
+            ProcedureKind::Query => &self.queries,
+            ProcedureKind::Mutation => &self.mutations,
+            ProcedureKind::Subscription => &self.subscriptions,
+        };
+        let exec = match (procedures.store.get(&path), &self.fallback) {
+            (Some(procedure), _) => {
+                // A predicate on the router's context is checked before any middleware runs, so a middleware rejecting the request can't leak the procedure's existence
+                if procedure
+                    .visible
+                    .as_ref()
+                    .and_then(|visible| visible.downcast_ref::<VisibleFn<TCtx>>())
+                    .is_some_and(|visible| !visible(&ctx))
+                {
+                    return Err(ExecError::OperationNotFound(path));
+                }
+                &procedure.exec
+            }
+            (None, Some(fallback)) if !matches!(kind, ProcedureKind::Subscription) => fallback,
+            (None, _) => return Err(ExecError::OperationNotFound(path)),
+        };
+        let params = Arc::new(input.clone());
+        request_span::instrument(
+            RequestContext {
+                kind,
+                path,
+                params,
+                runtime: self.config.runtime_or_default(),
+                unsubscribe_timeout: self.config.unsubscribe_timeout_or_default(),
+            },
+            self.config.trace_sampler.as_ref(),
+            self.config.on_serialize.as_ref(),
+            |req| exec.call(ctx, input, req),
+        )
+    }
+
+    /// Whether the procedure at `path` returns [`NoContent`](crate::NoContent), following the namespaces of [`Router::call`].
+    pub(crate) fn no_content(&self, kind: ProcedureKind, path: &str) -> bool {
+        self.procedure(kind, path)
+            .is_some_and(|procedure| procedure.ty.no_content)
+    }
+
+    /// Get the procedure at `path`, following the namespaces of [`Router::call`].
+    fn procedure(&self, kind: ProcedureKind, path: &str) -> Option<&Procedure<TCtx>> {
+        if let Some((namespace, key)) = path.split_once('.') {
+            if let Some(router) = self.namespaces.get(namespace) {
+                return router.procedure(kind, key);
+            }
+        }
+
+        let procedures = match kind {
+            ProcedureKind::Query => &self.queries,
+            ProcedureKind::Mutation => &self.mutations,
+            ProcedureKind::Subscription => &self.subscriptions,
+        };
+        procedures.store.get(path)
+    }
+
     pub fn arced(self) -> Arc<Self> {
         Arc::new(self)
     }
@@ -127,32 +288,422 @@ where
         &self.subscriptions.store
     }
 
-    #[allow(clippy::unwrap_used)] // TODO
+    /// Get the declared result type of a procedure. For subscriptions this is the type of each event.
+    ///
+    /// References in the type can be resolved using [`Router::type_map`], Eg. to check a value using [`validate_value`](crate::validate_value).
+    pub fn result_type(&self, kind: ProcedureKind, key: &str) -> Option<&DataType> {
+        let procedures = match kind {
+            ProcedureKind::Query => &self.queries,
+            ProcedureKind::Mutation => &self.mutations,
+            ProcedureKind::Subscription => &self.subscriptions,
+        };
+        procedures.store.get(key).map(|p| &p.ty.result_ty)
+    }
+
+    /// Build the input of the query `key` from the parameters of a query string (Eg. `?page=2&search=rspc`), for transports which support REST-style `GET` requests.
+    ///
+    /// This is only possible when the query's input is a struct whose fields are all strings, numbers or booleans (or optional versions of them), otherwise `None` is returned. These queries are listed in the `QueryParams` type of the bindings.
+    /// Each parameter is converted to the type of it's field and parameters which aren't a field are ignored.
+    ///
+    /// Note: Nested inputs (Eg. a field containing an array or another struct) can't be encoded as a query string. They must be sent as JSON instead.
+    pub fn query_params_input<'a>(
+        &self,
+        key: &str,
+        query: impl IntoIterator<Item = (&'a str, &'a str)>,
+    ) -> Option<Value> {
+        if let Some((namespace, key)) = key.split_once('.') {
+            if let Some(router) = self.namespaces.get(namespace) {
+                return router.query_params_input(key, query);
+            }
+        }
+
+        let procedure = self.queries.store.get(key)?;
+        let params = query_params(&procedure.ty.arg_ty, &self.type_map)?;
+        Some(decode_query_params(&params, query))
+    }
+
+    /// Check the router for common mistakes, returning every problem found instead of stopping at the first one.
+    ///
+    /// This reports:
+    ///  - keys which are registered as more than one kind of procedure, as they are ambiguous to consumers which only know the method name (Eg. a custom [`Config::method_parser`](crate::Config::method_parser) which ignores the kind).
+    ///  - procedure options which don't apply to the procedure's kind and are silently ignored (Eg. `.cache` on a mutation). A resolver which doesn't match it's kind (Eg. a stream registered as a query) is already rejected by the compiler.
+    ///  - procedure and named types which can't be exported to TypeScript (Eg. an `i64` field), which would otherwise panic when the bindings are exported.
+    ///
+    /// Call it from a test (or a build script which constructs your router) so these are caught before deploying.
+    ///
+    /// ```rust
+    /// let router = <rspc::Router>::new()
+    ///     .query("version", |t| t(|_, _: ()| env!("CARGO_PKG_VERSION")))
+    ///     .build();
+    /// router.validate().expect("router is invalid");
+    /// ```
+    pub fn validate(&self) -> Result<(), Vec<BuildError>> {
+        let mut errors = Vec::new();
+        let procedures = [
+            (ProcedureKind::Query, &self.queries.store),
+            (ProcedureKind::Mutation, &self.mutations.store),
+            (ProcedureKind::Subscription, &self.subscriptions.store),
+        ];
+
+        let mut kinds = BTreeMap::<&str, Vec<ProcedureKind>>::new();
+        for (kind, store) in procedures {
+            for key in store.keys() {
+                kinds.entry(key).or_default().push(kind);
+            }
+        }
+        errors.extend(kinds.into_iter().filter(|(_, kinds)| kinds.len() > 1).map(
+            |(key, kinds)| BuildError::KeyCollision {
+                key: key.into(),
+                kinds,
+            },
+        ));
+
+        errors.extend(self.ignored_options.iter().map(|(kind, key, option)| {
+            BuildError::KindMismatch {
+                kind: *kind,
+                key: key.clone(),
+                option,
+            }
+        }));
+
+        let config = ts_config();
+        for (kind, store) in procedures {
+            for (key, procedure) in store {
+                let ty = &procedure.ty;
+                for ty in [
+                    Some(&ty.arg_ty),
+                    Some(&ty.result_ty),
+                    ty.logs_ty.as_ref(),
+                    ty.trailer_ty.as_ref(),
+                    ty.error_ty.as_ref(),
+                ]
+                .into_iter()
+                .flatten()
+                {
+                    if let Err(err) = datatype(
+                        &config,
+                        &FunctionResultVariant::Value(ty.clone()),
+                        &self.type_map,
+                    ) {
+                        errors.push(BuildError::ProcedureExport {
+                            kind,
+                            key: key.clone(),
+                            error: err.to_string(),
+                        });
+                    }
+                }
+            }
+        }
+
+        let mut types = self.type_map.iter().map(|(_, ty)| ty).collect::<Vec<_>>();
+        types.sort_by(|a, b| a.name().cmp(b.name()));
+        for ty in types {
+            if let Err(err) = ts::export_named_datatype(&config, ty, &self.type_map) {
+                errors.push(BuildError::TypeExport {
+                    name: ty.name().to_string(),
+                    error: err.to_string(),
+                });
+            }
+        }
+
+        match errors.is_empty() {
+            true => Ok(()),
+            false => Err(errors),
+        }
+    }
+
+    /// Get a handle to the result caches of this router's procedures for runtime invalidation.
+    pub fn caches(&self) -> Caches {
+        self.caches.clone()
+    }
+
+    /// Get a handle to the subscriptions which are currently running on every connection, for cancelling them from the server.
+    pub fn active_subscriptions(&self) -> ActiveSubscriptions {
+        self.active_subscriptions.clone()
+    }
+
+    /// Run the `.warmup` hook of every procedure concurrently, using a context created with [`Default`].
+    ///
+    /// Call this during startup (Eg. before binding the listener) so the first requests don't pay for initializing lazy state. All hooks run to completion even if some fail and the errors are returned keyed by the procedure's key.
+    ///
+    /// If your context can't be synthesized (Eg. it holds a database connection) use [`Router::warmup_with`] instead.
+    pub async fn warmup(&self) -> Result<(), Vec<(String, Error)>>
+    where
+        TCtx: Default,
+    {
+        self.run_warmups(TCtx::default).await
+    }
+
+    /// Run the `.warmup` hook of every procedure concurrently, each with a clone of `ctx`. See [`Router::warmup`].
+    pub async fn warmup_with(&self, ctx: TCtx) -> Result<(), Vec<(String, Error)>>
+    where
+        TCtx: Clone,
+    {
+        self.run_warmups(|| ctx.clone()).await
+    }
+
+    async fn run_warmups(&self, ctx: impl Fn() -> TCtx) -> Result<(), Vec<(String, Error)>> {
+        let results = join_all(self.warmups.iter().map(|(key, warmup)| {
+            let fut = warmup(ctx());
+            async move { fut.await.map_err(|err| (key.clone(), err)) }
+        }))
+        .await;
+
+        let errors = results
+            .into_iter()
+            .filter_map(Result::err)
+            .collect::<Vec<_>>();
+        match errors.is_empty() {
+            true => Ok(()),
+            false => Err(errors),
+        }
+    }
+
+    /// Generate an [OpenRPC](https://open-rpc.org) document describing every procedure of this router.
+    ///
+    /// Each procedure is documented as a method named by it's kind and key (Eg. `query:users.get`) which takes it's input as the by-name param `input`.
+    /// These methods are dispatched by the JSON-RPC transports unless a custom [`Config::method_parser`](crate::Config::method_parser) is set.
+    /// The procedure's kind is set in the `x-rspc-kind` extension and subscriptions are additionally marked with `x-rspc-subscription`, in which case the result schema describes each event.
+    /// The JSON Schemas are generated from the Specta types, with named types placed in `components.schemas`.
+    pub fn openrpc(&self, info: &OpenRpcInfo) -> Value {
+        self.openrpc_filtered(info, &|_| true)
+    }
+
+    fn openrpc_filtered(
+        &self,
+        info: &OpenRpcInfo,
+        filter: &dyn Fn(&Procedure<TCtx>) -> bool,
+    ) -> Value {
+        openrpc::generate(
+            info,
+            [
+                (ProcedureKind::Query, &self.queries.store),
+                (ProcedureKind::Mutation, &self.mutations.store),
+                (ProcedureKind::Subscription, &self.subscriptions.store),
+            ],
+            filter,
+            &self.type_map,
+        )
+    }
+
+    /// Write the document generated by [`Router::openrpc`] to a file.
+    pub fn export_openrpc<TPath: AsRef<Path>>(
+        &self,
+        export_path: TPath,
+        info: &OpenRpcInfo,
+    ) -> Result<(), ExportError> {
+        let export_path = PathBuf::from(export_path.as_ref());
+        if let Some(export_dir) = export_path.parent() {
+            fs::create_dir_all(export_dir)?;
+        }
+        let doc =
+            serde_json::to_string_pretty(&self.openrpc(info)).map_err(std::io::Error::from)?;
+        fs::write(export_path, doc)?;
+        Ok(())
+    }
+
+    /// Generate [Zod](https://zod.dev) schemas for every named type and the input and result of every procedure, for validating responses at runtime.
+    ///
+    /// Each named type is exported as a schema of the same name, sorted by name like the Typescript bindings, and the procedures are exported as `procedures` keyed by their kind and key.
+    /// Generic types are inlined where they're used, as a schema can't be generic.
+    ///
+    /// Note: Routers mounted with a [`RouterGroup`](crate::RouterGroup) aren't included.
+    pub fn zod_bindings(&self) -> String {
+        zod::generate(
+            self.config.bindings_header,
+            [
+                (ProcedureKind::Query, &self.queries.store),
+                (ProcedureKind::Mutation, &self.mutations.store),
+                (ProcedureKind::Subscription, &self.subscriptions.store),
+            ],
+            &self.type_map,
+        )
+    }
+
+    /// Write the schemas generated by [`Router::zod_bindings`] to a file, Eg. `bindings.zod.ts` next to your Typescript bindings.
+    pub fn export_zod<TPath: AsRef<Path>>(&self, export_path: TPath) -> Result<(), ExportError> {
+        let bindings = self.zod_bindings();
+
+        let export_path = PathBuf::from(export_path.as_ref());
+        if fs::read_to_string(&export_path).is_ok_and(|existing| existing == bindings) {
+            return Ok(());
+        }
+        if let Some(export_dir) = export_path.parent() {
+            fs::create_dir_all(export_dir)?;
+        }
+        fs::write(export_path, bindings)?;
+        Ok(())
+    }
+
     pub fn export_ts<TPath: AsRef<Path>>(&self, export_path: TPath) -> Result<(), ExportError> {
+        self.export_ts_inner(export_path, |_, _| true, false)
+    }
+
+    /// Export the bindings of only the procedures for which `filter` returns `true`, Eg. to generate separate bindings for your public and internal APIs from the same router.
+    ///
+    /// The filter receives the key of each procedure and the tags added to it with [`.tag(tag)`](crate::internal::BuiltProcedureBuilder::tag).
+    /// Unlike [`Router::export_ts_for`], named types which are only referenced by the omitted procedures are also omitted, so internal types don't leak into the public bindings.
+    ///
+    /// ```rust,no_run
+    /// let router = <rspc::Router>::new()
+    ///     .query("version", |t| t(|_, _: ()| "1.0.0").tag("public"))
+    ///     .query("users.ban", |t| t(|_, _: String| ()).tag("internal"))
+    ///     .build();
+    ///
+    /// router.export_ts_filtered("./bindings.public.ts", |_, tags| tags.contains(&"public")).unwrap();
+    /// router.export_ts_filtered("./bindings.internal.ts", |_, tags| tags.contains(&"internal")).unwrap();
+    /// ```
+    pub fn export_ts_filtered<TPath: AsRef<Path>>(
+        &self,
+        export_path: TPath,
+        filter: impl Fn(&str, &[&'static str]) -> bool,
+    ) -> Result<(), ExportError> {
+        self.export_ts_inner(
+            export_path,
+            |key, procedure| filter(key, &procedure.ty.tags),
+            true,
+        )
+    }
+
+    /// Export the bindings as they are visible to the given context.
+    ///
+    /// Procedures registered with `.visible_when` are omitted when their predicate returns `false` for `ctx`.
+    /// The predicate can only be evaluated if it takes the router's context, so procedures with a predicate on a context created by middleware (Eg. within a merged router using `with_ctx`) are always omitted.
+    /// All named types are still exported.
+    pub fn export_ts_for<TPath: AsRef<Path>>(
+        &self,
+        ctx: &TCtx,
+        export_path: TPath,
+    ) -> Result<(), ExportError> {
+        self.export_ts_inner(
+            export_path,
+            |_, procedure| is_visible(procedure, ctx),
+            false,
+        )
+    }
+
+    /// Generate the Typescript bindings without writing them to disk.
+    ///
+    /// The output is deterministic. Procedures are sorted by key and types by name, so it's suitable for committing to version control and diffing.
+    pub fn ts_bindings(&self) -> Result<String, ExportError> {
+        self.ts_bindings_inner(|_, _| true, false)
+    }
+
+    /// Generate the Typescript bindings of only the procedures for which `filter` returns `true` without writing them to disk. See [`Router::export_ts_filtered`].
+    pub fn ts_bindings_filtered(
+        &self,
+        filter: impl Fn(&str, &[&'static str]) -> bool,
+    ) -> Result<String, ExportError> {
+        self.ts_bindings_inner(|key, procedure| filter(key, &procedure.ty.tags), true)
+    }
+
+    /// Write the Typescript bindings to `writer`.
+    ///
+    /// Unlike [`Router::ts_bindings`] each type is written as soon as it's exported, so the whole file is never held in memory. This is useful for routers with very large schemas.
+    /// The output is identical to [`Router::ts_bindings`]. If `writer` is unbuffered (Eg. a [`File`](std::fs::File)) you should wrap it in a [`BufWriter`](std::io::BufWriter).
+    pub fn export_to_writer(&self, writer: impl io::Write) -> Result<(), ExportError> {
+        self.write_ts_bindings(writer, |_, _| true, false)
+    }
+
+    /// Check the bindings at `path` match the ones which would be exported for this router.
+    ///
+    /// This is intended for when you commit your bindings instead of exporting them on startup.
+    /// Call it from a test (or your CI) and regenerate the file with [`Router::export_ts`] whenever it fails.
+    ///
+    /// ```rust,no_run
+    /// let router = <rspc::Router>::new().build();
+    /// router.check_ts_bindings("./bindings.ts").expect("bindings are out of date");
+    /// ```
+    pub fn check_ts_bindings<TPath: AsRef<Path>>(&self, path: TPath) -> Result<(), ExportError> {
+        let path = path.as_ref();
+        match fs::read_to_string(path) {
+            Ok(existing) if existing == self.ts_bindings()? => Ok(()),
+            Ok(_) => Err(ExportError::Outdated(path.to_path_buf())),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                Err(ExportError::Outdated(path.to_path_buf()))
+            }
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    fn export_ts_inner<TPath: AsRef<Path>>(
+        &self,
+        export_path: TPath,
+        filter: impl Fn(&str, &Procedure<TCtx>) -> bool,
+        prune_types: bool,
+    ) -> Result<(), ExportError> {
+        let bindings = self.ts_bindings_inner(filter, prune_types)?;
+
         let export_path = PathBuf::from(export_path.as_ref());
+        // Skip the write when nothing changed so file watchers (Eg. Vite) aren't triggered on every startup.
+        if fs::read_to_string(&export_path).is_ok_and(|existing| existing == bindings) {
+            return Ok(());
+        }
         if let Some(export_dir) = export_path.parent() {
             fs::create_dir_all(export_dir)?;
         }
-        let mut file = File::create(export_path)?;
+        fs::write(export_path, bindings)?;
+        Ok(())
+    }
+
+    fn ts_bindings_inner(
+        &self,
+        filter: impl Fn(&str, &Procedure<TCtx>) -> bool,
+        prune_types: bool,
+    ) -> Result<String, ExportError> {
+        let mut bindings = Vec::new();
+        self.write_ts_bindings(&mut bindings, filter, prune_types)?;
+        Ok(String::from_utf8(bindings).expect("rspc: bindings are always valid UTF-8"))
+    }
+
+    fn write_ts_bindings(
+        &self,
+        mut writer: impl io::Write,
+        filter: impl Fn(&str, &Procedure<TCtx>) -> bool,
+        // Only export the named types which are reachable from the exported procedures
+        prune_types: bool,
+    ) -> Result<(), ExportError> {
         if let Some(header) = &self.config.bindings_header {
-            writeln!(file, "{}", header)?;
+            writeln!(writer, "{header}")?;
         }
-        writeln!(file, "// This file was generated by [rspc](https://github.com/specta-rs/rspc). Do not edit this file manually.")?;
+        writeln!(writer, "// This file was generated by [rspc](https://github.com/specta-rs/rspc). Do not edit this file manually.")?;
 
-        let config = Typescript::new().bigint(
-            ts::BigIntExportBehavior::FailWithReason(
-                "rspc does not support exporting bigint types (i64, u64, i128, u128) because they are lossily decoded by `JSON.parse` on the frontend. Tracking issue: https://github.com/specta-rs/rspc/issues/93",
-            )
-        );
+        self.write_ts_procedures(&mut writer, &filter, prune_types)
+    }
+
+    // Write everything after the header. This is also the body of the namespace of each router mounted by a `RouterGroup`.
+    #[allow(clippy::unwrap_used)] // TODO
+    fn write_ts_procedures(
+        &self,
+        writer: &mut dyn io::Write,
+        filter: &dyn Fn(&str, &Procedure<TCtx>) -> bool,
+        prune_types: bool,
+    ) -> Result<(), ExportError> {
+        let config = ts_config();
 
-        let queries_ts = generate_procedures_ts(&config, &self.queries.store, &self.type_map);
-        let mutations_ts = generate_procedures_ts(&config, &self.mutations.store, &self.type_map);
-        let subscriptions_ts =
-            generate_procedures_ts(&config, &self.subscriptions.store, &self.type_map);
+        let procedures_ts = |procedures: &ProcedureStore<TCtx>, kind: &str| {
+            let mut variants = vec![generate_procedures_ts(
+                &config,
+                &procedures.store,
+                filter,
+                &self.type_map,
+            )];
+            variants.retain(|ts| ts != "never");
+            variants.extend(self.namespaces.keys().map(|namespace| {
+                format!(r#"Namespaced<"{namespace}", {namespace}.Procedures["{kind}"]>"#)
+            }));
+            match variants.is_empty() {
+                true => "never".to_string(),
+                false => variants.join(" | "),
+            }
+        };
+        let queries_ts = procedures_ts(&self.queries, "queries");
+        let mutations_ts = procedures_ts(&self.mutations, "mutations");
+        let subscriptions_ts = procedures_ts(&self.subscriptions, "subscriptions");
 
         // TODO: Specta API
         writeln!(
-            file,
+            writer,
             r#"
 export type Procedures = {{
     queries: {queries_ts},
@@ -161,31 +712,171 @@ export type Procedures = {{
 }};"#
         )?;
 
-        for export in self.type_map.iter().map(|(_, ty)| {
-            datatype(
-                &config,
-                &FunctionResultVariant::Value(ty.inner.clone()),
+        // The queries whose input can be sent as a query string, see `Router::query_params_input`
+        let query_params_ts = self
+            .queries
+            .store
+            .iter()
+            .filter(|(key, procedure)| filter(key, procedure))
+            .filter_map(|(key, procedure)| {
+                let params = query_params(&procedure.ty.arg_ty, &self.type_map)?
+                    .into_iter()
+                    .map(|param| {
+                        let ty = datatype(
+                            &config,
+                            &FunctionResultVariant::Value(param.ty.clone()),
+                            &self.type_map,
+                        )
+                        .unwrap();
+                        match param.optional {
+                            true => format!("{}?: {ty}", param.name),
+                            false => format!("{}: {ty}", param.name),
+                        }
+                    })
+                    .collect::<Vec<_>>();
+                Some(format!(r#"    "{key}": {{ {} }}"#, params.join("; ")))
+            })
+            .collect::<Vec<_>>();
+        if !query_params_ts.is_empty() {
+            writeln!(
+                writer,
+                "\nexport type QueryParams = {{\n{}\n}};",
+                query_params_ts.join(",\n")
+            )?;
+        }
+
+        // The result of each procedure which declares an `error_type`, as either it's result or the `data` of it's error
+        for (kind, procedures) in [
+            ("Query", &self.queries),
+            ("Mutation", &self.mutations),
+            ("Subscription", &self.subscriptions),
+        ] {
+            for (key, procedure) in &procedures.store {
+                let Some(error_ty) = procedure
+                    .ty
+                    .error_ty
+                    .as_ref()
+                    .filter(|_| filter(key, procedure))
+                else {
+                    continue;
+                };
+                let success = match procedure.ty.no_content {
+                    true => "void".into(),
+                    false => datatype(
+                        &config,
+                        &FunctionResultVariant::Value(procedure.ty.result_ty.clone()),
+                        &self.type_map,
+                    )
+                    .unwrap(),
+                };
+                let error = datatype(
+                    &config,
+                    &FunctionResultVariant::Value(error_ty.clone()),
+                    &self.type_map,
+                )
+                .unwrap();
+                writeln!(
+                    writer,
+                    "\nexport type {}{kind}Result = {{ success: {success} }} | {{ error: {error} }};",
+                    pascal_case(key)
+                )?;
+            }
+        }
+
+        if !self.namespaces.is_empty() {
+            writeln!(
+                writer,
+                r#"
+type Namespaced<TPrefix extends string, TProcedure> = TProcedure extends {{ key: infer TKey extends string }}
+    ? Omit<TProcedure, "key"> & {{ key: `${{TPrefix}}.${{TKey}}` }}
+    : never;"#
+            )?;
+        }
+
+        // Sorted by name so the order doesn't depend on the type's `SpectaID`.
+        // Each type is written as soon as it's exported so the whole file is never held in memory.
+        let reachable = prune_types.then(|| {
+            reachable_types(
                 &self.type_map,
+                [&self.queries, &self.mutations, &self.subscriptions]
+                    .into_iter()
+                    .flat_map(|procedures| &procedures.store)
+                    .filter(|(key, procedure)| filter(key, procedure))
+                    .flat_map(|(_, procedure)| {
+                        [&procedure.ty.arg_ty, &procedure.ty.result_ty]
+                            .into_iter()
+                            .chain(&procedure.ty.logs_ty)
+                            .chain(&procedure.ty.trailer_ty)
+                            .chain(&procedure.ty.error_ty)
+                    }),
             )
-            .unwrap()
-        }) {
-            writeln!(file, "\n{}", export)?;
+        });
+        let mut types = self
+            .type_map
+            .iter()
+            .filter(|(sid, _)| reachable.as_ref().is_none_or(|r| r.contains(sid)))
+            .map(|(_, ty)| ty)
+            .collect::<Vec<_>>();
+        types.sort_by(|a, b| a.name().cmp(b.name()));
+        for group in types.chunk_by(|a, b| a.name() == b.name()) {
+            // Types with the same name (from different modules) are ordered by their output
+            let mut exports = group
+                .iter()
+                .map(|ty| ts::export_named_datatype(&config, ty, &self.type_map).unwrap())
+                .collect::<Vec<_>>();
+            exports.sort();
+            for export in exports {
+                writeln!(writer, "\n{export}")?;
+            }
+        }
+
+        // Each router's types are kept in it's own namespace so types with the same name in different routers don't conflict
+        for (namespace, router) in &self.namespaces {
+            let mut body = Vec::new();
+            router.write_ts_procedures(
+                &mut body,
+                &|key, procedure| filter(&format!("{namespace}.{key}"), procedure),
+                prune_types,
+            )?;
+            let body = String::from_utf8(body).expect("rspc: bindings are always valid UTF-8");
+
+            writeln!(writer, "\nexport namespace {namespace} {{")?;
+            for line in body.trim().lines() {
+                match line.is_empty() {
+                    true => writeln!(writer)?,
+                    false => writeln!(writer, "    {line}")?,
+                }
+            }
+            writeln!(writer, "}}")?;
         }
 
         Ok(())
     }
 }
 
+fn ts_config() -> Typescript {
+    Typescript::new().bigint(
+        ts::BigIntExportBehavior::FailWithReason(
+            "rspc does not support exporting bigint types (i64, u64, i128, u128) because they are lossily decoded by `JSON.parse` on the frontend. Tracking issue: https://github.com/specta-rs/rspc/issues/93",
+        )
+    )
+}
+
 // TODO: Move this out into a Specta API
 fn generate_procedures_ts<Ctx>(
     config: &Typescript,
     procedures: &BTreeMap<String, Procedure<Ctx>>,
+    filter: impl Fn(&str, &Procedure<Ctx>) -> bool,
     type_map: &TypeMap,
 ) -> String {
+    let procedures = procedures
+        .iter()
+        .filter(|(key, procedure)| filter(key, procedure))
+        .collect::<Vec<_>>();
     match procedures.len() {
         0 => "never".to_string(),
         _ => procedures
-            .iter()
+            .into_iter()
             .map(|(key, operation)| {
                 let input = match &operation.ty.arg_ty {
                     DataType::Tuple(def)
@@ -198,20 +889,474 @@ fn generate_procedures_ts<Ctx>(
                     ty => datatype(config,  &FunctionResultVariant::Value(ty.clone()), type_map).unwrap(),
                 };
                 #[allow(clippy::unwrap_used)] // TODO
-                let result_ts = datatype(
-                    config,
-                    &FunctionResultVariant::Value(operation.ty.result_ty.clone()),
-                    type_map,
-                )
-                .unwrap();
+                let result_ts = match operation.ty.no_content {
+                    true => "void".into(),
+                    false => datatype(
+                        config,
+                        &FunctionResultVariant::Value(operation.ty.result_ty.clone()),
+                        type_map,
+                    )
+                    .unwrap(),
+                };
+                #[allow(clippy::unwrap_used)] // TODO
+                let logs_ts = match &operation.ty.logs_ty {
+                    Some(ty) => format!(
+                        ", logs: {}",
+                        datatype(config, &FunctionResultVariant::Value(ty.clone()), type_map).unwrap()
+                    ),
+                    None => String::new(),
+                };
+                #[allow(clippy::unwrap_used)] // TODO
+                let trailer_ts = match &operation.ty.trailer_ty {
+                    Some(ty) => format!(
+                        ", trailer: {}",
+                        datatype(config, &FunctionResultVariant::Value(ty.clone()), type_map).unwrap()
+                    ),
+                    None => String::new(),
+                };
+
+                #[allow(clippy::unwrap_used)] // TODO
+                let error_ts = match &operation.ty.error_ty {
+                    Some(ty) => format!(
+                        ", error: {}",
+                        datatype(config, &FunctionResultVariant::Value(ty.clone()), type_map).unwrap()
+                    ),
+                    None => String::new(),
+                };
+
+                let mut docs = operation
+                    .ty
+                    .description
+                    .as_deref()
+                    .unwrap_or_default()
+                    .trim()
+                    .to_string();
+                if let Some(deprecated) = &operation.ty.deprecated {
+                    if !docs.is_empty() {
+                        docs.push_str("\n\n");
+                    }
+                    docs.push_str(&format!("@deprecated {deprecated}"));
+                }
+                let docs = match docs.is_empty() {
+                    true => String::new(),
+                    false => js_doc(&docs),
+                };
 
                 // TODO: Specta API
                 format!(
-                    r#"
-        {{ key: "{key}", input: {input}, result: {result_ts} }}"#
+                    r#"{docs}
+        {{ key: "{key}", input: {input}, result: {result_ts}{logs_ts}{trailer_ts}{error_ts} }}"#
                 )
             })
             .collect::<Vec<_>>()
             .join(" | "),
     }
 }
+
+// Convert a procedure key into a type name, Eg. `users.get` becomes `UsersGet`.
+fn pascal_case(key: &str) -> String {
+    key.split(|c: char| !c.is_ascii_alphanumeric())
+        .flat_map(|word| {
+            let mut chars = word.chars();
+            chars
+                .next()
+                .map(|first| first.to_ascii_uppercase())
+                .into_iter()
+                .chain(chars)
+        })
+        .collect()
+}
+
+// Whether `procedure` is visible to `ctx`. See `Router::export_ts_for` for when it can't be evaluated.
+fn is_visible<TCtx: 'static>(procedure: &Procedure<TCtx>, ctx: &TCtx) -> bool {
+    match &procedure.visible {
+        Some(visible) => visible
+            .downcast_ref::<VisibleFn<TCtx>>()
+            .is_some_and(|visible| visible(ctx)),
+        None => true,
+    }
+}
+
+// Find the named types referenced (directly or through other named types) by `roots`.
+fn reachable_types<'a>(
+    type_map: &'a TypeMap,
+    roots: impl IntoIterator<Item = &'a DataType>,
+) -> BTreeSet<SpectaID> {
+    fn fields(fields: &[Field]) -> impl Iterator<Item = &DataType> {
+        fields.iter().filter_map(Field::ty)
+    }
+
+    let mut reachable = BTreeSet::new();
+    let mut stack = roots.into_iter().collect::<Vec<_>>();
+    while let Some(ty) = stack.pop() {
+        match ty {
+            DataType::Any
+            | DataType::Unknown
+            | DataType::Primitive(_)
+            | DataType::Literal(_)
+            | DataType::Generic(_) => {}
+            DataType::List(l) => stack.push(l.ty()),
+            DataType::Map(m) => stack.extend([m.key_ty(), m.value_ty()]),
+            DataType::Nullable(ty) => stack.push(ty),
+            DataType::Struct(s) => match s.fields() {
+                StructFields::Unit => {}
+                StructFields::Unnamed(f) => stack.extend(fields(f.fields())),
+                StructFields::Named(f) => {
+                    stack.extend(f.fields().iter().filter_map(|(_, field)| field.ty()))
+                }
+            },
+            DataType::Enum(e) => {
+                for (_, variant) in e.variants() {
+                    match variant.inner() {
+                        EnumVariants::Unit => {}
+                        EnumVariants::Unnamed(f) => stack.extend(fields(f.fields())),
+                        EnumVariants::Named(f) => {
+                            stack.extend(f.fields().iter().filter_map(|(_, field)| field.ty()))
+                        }
+                    }
+                }
+            }
+            DataType::Tuple(t) => stack.extend(t.elements()),
+            DataType::Reference(r) => {
+                stack.extend(r.generics().iter().map(|(_, ty)| ty));
+                // Each named type is only walked once, so recursive types terminate
+                if reachable.insert(r.sid()) {
+                    if let Some(ty) = type_map.get(r.sid()) {
+                        stack.push(&ty.inner);
+                    }
+                }
+            }
+        }
+    }
+    reachable
+}
+
+// Format a procedure's description as a JSDoc comment, indented to match the procedure.
+fn js_doc(description: &str) -> String {
+    let mut docs = "\n        /**".to_string();
+    for line in description.trim().lines() {
+        // A `*/` would end the comment early
+        let line = line.trim_end().replace("*/", "*\\/");
+        match line.is_empty() {
+            true => docs.push_str("\n         *"),
+            false => docs.push_str(&format!("\n         * {line}")),
+        }
+    }
+    docs.push_str("\n         */");
+    docs
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use std::{any::type_name, sync::Mutex};
+
+    use serde::{Serialize, Serializer};
+    use serde_json::json;
+    use specta::Type;
+
+    use crate::{
+        internal::ProcedureKind, BuildError, Error, ErrorCode, ExecError, ExecKind, ExportError,
+        Router,
+    };
+
+    #[derive(Serialize, Type)]
+    struct Zebra(i32);
+
+    #[derive(Serialize, Type)]
+    struct Apple(i32);
+
+    #[derive(Serialize, Type)]
+    struct Counter {
+        count: i64,
+    }
+
+    fn router(with_mutation: bool) -> Router {
+        let router = <Router>::new()
+            .query("zebra", |t| t(|_, _: ()| Zebra(1)))
+            .query("apple", |t| t(|_, _: ()| Apple(1)));
+        match with_mutation {
+            true => router.mutation("noop", |t| t(|_, _: ()| ())),
+            false => router,
+        }
+        .build()
+    }
+
+    #[test]
+    fn test_check_ts_bindings() {
+        let bindings = router(false).ts_bindings().unwrap();
+        assert_eq!(bindings, router(false).ts_bindings().unwrap());
+        assert!(
+            bindings.find("export type Apple").unwrap()
+                < bindings.find("export type Zebra").unwrap()
+        );
+
+        let mut written = Vec::new();
+        router(false).export_to_writer(&mut written).unwrap();
+        assert_eq!(String::from_utf8(written).unwrap(), bindings);
+
+        let path = std::env::temp_dir().join("rspc_test_check_ts_bindings.ts");
+        router(false).export_ts(&path).unwrap();
+        router(false).check_ts_bindings(&path).unwrap();
+        assert!(matches!(
+            router(true).check_ts_bindings(&path),
+            Err(ExportError::Outdated(_))
+        ));
+    }
+
+    #[test]
+    fn test_procedure_description() {
+        let router = <Router>::new()
+            .query("apple", |t| {
+                t(|_, _: ()| Apple(1)).description("Get an apple.\n\nNever returns `*/` early.")
+            })
+            .build();
+
+        let bindings = router.ts_bindings().unwrap();
+        assert!(bindings.contains(
+            r#"
+        /**
+         * Get an apple.
+         *
+         * Never returns `*\/` early.
+         */
+        { key: "apple", input: never, result: Apple }"#
+        ));
+    }
+
+    #[test]
+    fn test_ts_bindings_filtered() {
+        #[derive(Serialize, Type)]
+        struct AuditLog {
+            entries: Vec<AuditEntry>,
+            apple: Apple,
+        }
+
+        #[derive(Serialize, Type)]
+        struct AuditEntry(String);
+
+        let router = <Router>::new()
+            .query("zebra", |t| t(|_, _: ()| Zebra(1)).tag("public"))
+            .query("apple", |t| t(|_, _: ()| Apple(1)).tag("public"))
+            .query("audit", |t| {
+                t(|_, _: ()| AuditLog {
+                    entries: Vec::new(),
+                    apple: Apple(1),
+                })
+                .tag("internal")
+            })
+            .build();
+
+        let public = router
+            .ts_bindings_filtered(|_, tags| tags.contains(&"public"))
+            .unwrap();
+        assert!(public.contains("export type Zebra"));
+        assert!(public.contains("export type Apple"));
+        assert!(!public.contains(r#"key: "audit""#));
+        // Types only referenced by the omitted procedures (including through other types) are omitted
+        assert!(!public.contains("AuditLog"));
+        assert!(!public.contains("AuditEntry"));
+
+        let internal = router
+            .ts_bindings_filtered(|_, tags| tags.contains(&"internal"))
+            .unwrap();
+        assert!(internal.contains("export type AuditLog"));
+        assert!(internal.contains("export type AuditEntry"));
+        assert!(internal.contains("export type Apple"));
+        assert!(!internal.contains("Zebra"));
+
+        // Every type is still exported without a filter
+        assert!(router.ts_bindings().unwrap().contains("export type Zebra"));
+    }
+
+    #[tokio::test]
+    async fn test_fallback() {
+        let router = Router::<u32>::new()
+            .query("apple", |t| t(|_, _: ()| Apple(1)))
+            .fallback(|ctx, method, params| async move {
+                match method.as_str() {
+                    "legacy.echo" => Ok(json!({ "ctx": ctx, "params": params })),
+                    _ => Err(Error::new(ErrorCode::NotFound, format!("no '{method}'"))),
+                }
+            })
+            .subscription("ticks", |t| t(|_, _: ()| futures::stream::iter([1])))
+            .build();
+
+        // Exact matches win
+        let result = router.exec(1, ExecKind::Query, "apple".into(), None).await;
+        assert_eq!(result.unwrap(), json!(1));
+
+        let result = router
+            .exec(
+                1,
+                ExecKind::Mutation,
+                "legacy.echo".into(),
+                Some(json!([2])),
+            )
+            .await;
+        assert_eq!(result.unwrap(), json!({ "ctx": 1, "params": [2] }));
+
+        let result = router
+            .exec(1, ExecKind::Query, "missing".into(), None)
+            .await;
+        assert!(
+            matches!(result, Err(ExecError::ErrResolverError(err)) if err.message == "no 'missing'")
+        );
+
+        let result = router
+            .exec_subscription(1, "legacy.echo".into(), None)
+            .await;
+        assert!(matches!(result, Err(ExecError::OperationNotFound(_))));
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(router(true).validate().is_ok());
+
+        let router = <Router>::new()
+            .query("user", |t| t(|_, _: ()| ()))
+            .mutation("user", |t| {
+                t(|_, _: ()| ()).cache(std::time::Duration::from_secs(1))
+            })
+            .query("counter", |t| t(|_, _: ()| Counter { count: 0 }))
+            .query("total", |t| t(|_, _: ()| 0i64))
+            .build();
+        let errors = router.validate().unwrap_err();
+        assert_eq!(errors.len(), 4, "{errors:?}");
+        assert_eq!(
+            errors[..2],
+            [
+                BuildError::KeyCollision {
+                    key: "user".into(),
+                    kinds: vec![ProcedureKind::Query, ProcedureKind::Mutation],
+                },
+                BuildError::KindMismatch {
+                    kind: ProcedureKind::Mutation,
+                    key: "user".into(),
+                    option: "cache",
+                },
+            ]
+        );
+        assert!(matches!(
+            &errors[2],
+            BuildError::ProcedureExport { kind: ProcedureKind::Query, key, .. } if key == "total"
+        ));
+        assert!(matches!(&errors[3], BuildError::TypeExport { name, .. } if name == "Counter"));
+
+        // The options ignored by a merged router are reported under their prefixed key
+        let router = <Router>::new()
+            .merge(
+                "users.",
+                <Router>::new().mutation("create", |t| {
+                    t(|_, _: ()| ()).cache(std::time::Duration::from_secs(1))
+                }),
+            )
+            .build();
+        assert_eq!(
+            router.validate().unwrap_err(),
+            [BuildError::KindMismatch {
+                kind: ProcedureKind::Mutation,
+                key: "users.create".into(),
+                option: "cache",
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_exec_into() {
+        let router = <Router>::new()
+            .query("apple", |t| t(|_, id: i32| Apple(id)))
+            .subscription("ticks", |t| t(|_, _: ()| futures::stream::iter([1, 2, 3])))
+            .build();
+
+        // Embedded into a larger document
+        let mut out = b"{\"apple\":".to_vec();
+        router
+            .exec_into(
+                (),
+                ProcedureKind::Query,
+                "apple".into(),
+                Some(json!(4)),
+                &mut serde_json::Serializer::new(&mut out),
+            )
+            .await
+            .unwrap();
+        out.push(b'}');
+        assert_eq!(String::from_utf8(out).unwrap(), r#"{"apple":4}"#);
+
+        let mut out = Vec::new();
+        router
+            .exec_into(
+                (),
+                ProcedureKind::Subscription,
+                "ticks".into(),
+                None,
+                &mut serde_json::Serializer::new(&mut out),
+            )
+            .await
+            .unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "[1,2,3]");
+
+        let result = router
+            .exec_into(
+                (),
+                ProcedureKind::Query,
+                "missing".into(),
+                None,
+                &mut serde_json::Serializer::new(Vec::new()),
+            )
+            .await;
+        assert!(matches!(
+            result,
+            Err(crate::ExecIntoError::Exec(ExecError::OperationNotFound(_)))
+        ));
+    }
+
+    // Records the serializers it's serialized with
+    #[derive(Type)]
+    struct Recorded(i32);
+
+    static SERIALIZERS: Mutex<Vec<&'static str>> = Mutex::new(Vec::new());
+
+    impl Serialize for Recorded {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            SERIALIZERS.lock().unwrap().push(type_name::<S>());
+            self.0.serialize(serializer)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_exec_into_typed() {
+        async fn exec_into(router: &Router) -> String {
+            SERIALIZERS.lock().unwrap().clear();
+            let mut out = Vec::new();
+            router
+                .exec_into(
+                    (),
+                    ProcedureKind::Query,
+                    "recorded".into(),
+                    None,
+                    &mut serde_json::Serializer::new(&mut out),
+                )
+                .await
+                .unwrap();
+            String::from_utf8(out).unwrap()
+        }
+        let built_as_value =
+            || (SERIALIZERS.lock().unwrap().iter()).any(|s| s.contains("serde_json::value"));
+
+        let router = <Router>::new()
+            .query("recorded", |t| t(|_, _: ()| Recorded(4)))
+            .build();
+        assert_eq!(exec_into(&router).await, "4");
+        assert!(!built_as_value());
+
+        // A middleware could rewrite the result so it must be built as a `Value`
+        let router = <Router>::new()
+            .middleware(|mw| mw.middleware(|mw| async move { Ok(mw) }))
+            .query("recorded", |t| t(|_, _: ()| Recorded(4)))
+            .build();
+        assert_eq!(exec_into(&router).await, "4");
+        assert!(built_as_value());
+    }
+}
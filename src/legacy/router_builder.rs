@@ -1,19 +1,87 @@
-use std::marker::PhantomData;
+use std::{collections::BTreeMap, future::Future, marker::PhantomData, mem, pin::Pin, sync::Arc};
 
-use futures::Stream;
+use futures::{Stream, StreamExt, TryFutureExt};
 use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
 use specta::Type;
 use specta::TypeMap;
 
+use super::{
+    buffer::Buffer,
+    cache::{CacheLayer, Caches, ProcedureCache},
+    cache_control::CacheControlLayer,
+    concurrent_serialize::{self, SerializeFn},
+    deserialize::deserialize_input,
+    field_access::FieldAccessLayer,
+    schema_version::SchemaVersionLayer,
+    serialize_timer::{self, SerializeTimer},
+    spawn::spawn_stream,
+    subscription_hooks::{Complete, SubscriptionHooks, Unsubscribe},
+    virtual_fields::VirtualFieldsLayer,
+    visibility::VisibilityLayer,
+    warmup::{downcast_warmups, AnyWarmupFn},
+};
 use crate::{
     internal::{
-        BaseMiddleware, BuiltProcedureBuilder, MiddlewareBuilderLike, MiddlewareLayerBuilder,
-        MiddlewareMerger, ProcedureStore, ResolverLayer, UnbuiltProcedureBuilder,
+        dyn_layer, BaseMiddleware, BuiltProcedureBuilder, InsertLayerResult, Layer, LayerPosition,
+        LayerResult, MiddlewareBuilderLike, MiddlewareLayerBuilder, MiddlewareMerger,
+        ProcedureDataType, ProcedureKind, ProcedureOptions, ProcedureStore, RequestContext,
+        ResolverLayer, UnbuiltProcedureBuilder,
     },
-    Config, DoubleArgStreamMarker, ExecError, MiddlewareBuilder, MiddlewareLike, RequestLayer,
-    Resolver, Router, StreamResolver,
+    Config, DoubleArgStreamMarker, Error, ErrorKind, ExecError, FieldError, MiddlewareBuilder,
+    MiddlewareLike, MiddlewareStack, Priority, RequestLayer, Resolver, Router, StreamResolver,
 };
 
+// The middleware of a router once a `MiddlewareStack` has been applied to it
+type StackLayerBuilder<TCtx, TLayerCtx, TMiddleware> =
+    MiddlewareLayerBuilder<TCtx, TLayerCtx, TLayerCtx, TMiddleware, MiddlewareStack<TLayerCtx>>;
+
+// Removes an option from a procedure's options, returning whether it was set
+type TakeOption = fn(&mut ProcedureOptions) -> bool;
+
+// The options which only apply to some kinds of procedure and the kinds they apply to
+const KIND_OPTIONS: [(&str, &[ProcedureKind], TakeOption); 11] = [
+    ("cache", &[ProcedureKind::Query], |o| {
+        o.cache.take().is_some()
+    }),
+    ("cache_control", &[ProcedureKind::Query], |o| {
+        o.cache_control.take().is_some()
+    }),
+    (
+        "sla",
+        &[ProcedureKind::Query, ProcedureKind::Mutation],
+        |o| o.sla.take().is_some(),
+    ),
+    (
+        "virtual_fields",
+        &[ProcedureKind::Query, ProcedureKind::Mutation],
+        |o| !mem::take(&mut o.virtual_fields).is_empty(),
+    ),
+    ("map_item", &[ProcedureKind::Subscription], |o| {
+        o.map_item.take().is_some()
+    }),
+    ("buffer", &[ProcedureKind::Subscription], |o| {
+        o.buffer.take().is_some()
+    }),
+    ("spawn", &[ProcedureKind::Subscription], |o| {
+        mem::take(&mut o.spawn)
+    }),
+    (
+        "serialize_concurrently",
+        &[ProcedureKind::Subscription],
+        |o| o.serialize_concurrently.take().is_some(),
+    ),
+    ("on_subscribe", &[ProcedureKind::Subscription], |o| {
+        o.on_subscribe.take().is_some()
+    }),
+    ("on_unsubscribe", &[ProcedureKind::Subscription], |o| {
+        o.on_unsubscribe.take().is_some()
+    }),
+    ("on_complete", &[ProcedureKind::Subscription], |o| {
+        o.on_complete.take().is_some()
+    }),
+];
+
 pub struct RouterBuilder<
     TCtx = (), // The is the context the current router was initialised with
     TMeta = (),
@@ -28,6 +96,13 @@ pub struct RouterBuilder<
     queries: ProcedureStore<TCtx>,
     mutations: ProcedureStore<TCtx>,
     subscriptions: ProcedureStore<TCtx>,
+    caches: BTreeMap<String, Arc<ProcedureCache>>,
+    priorities: BTreeMap<String, Priority>,
+    warmups: Vec<(String, AnyWarmupFn)>,
+    // The subscriptions with an `on_unsubscribe` hook, whose cleanup needs a runtime to time out
+    unsubscribe_hooks: Vec<String>,
+    fallback: Option<Box<dyn Layer<TCtx>>>,
+    ignored_options: Vec<(ProcedureKind, String, &'static str)>,
     type_map: TypeMap,
     phantom: PhantomData<TMeta>,
 }
@@ -56,6 +131,12 @@ where
             queries: ProcedureStore::new("query"),
             mutations: ProcedureStore::new("mutation"),
             subscriptions: ProcedureStore::new("subscription"),
+            caches: Default::default(),
+            priorities: Default::default(),
+            warmups: Vec::new(),
+            unsubscribe_hooks: Vec::new(),
+            fallback: None,
+            ignored_options: Vec::new(),
             type_map: TypeMap::default(),
             phantom: PhantomData,
         }
@@ -75,6 +156,54 @@ where
         self
     }
 
+    /// Add a middleware to the router. It will only apply to procedures registered after it.
+    ///
+    /// Middleware run in the order they are added. The first middleware added is the outermost layer so it sees the request first and the response last. The resolver is always the innermost layer.
+    ///
+    /// ```text
+    /// .middleware(a).middleware(b).query(..)  =>  a -> b -> resolver -> b -> a
+    /// ```
+    ///
+    /// ## Changing the context
+    ///
+    /// A middleware can replace the context using [`MiddlewareContext::with_ctx`](crate::MiddlewareContext::with_ctx), Eg. to upgrade it once the user has been authorized. The router's type tracks the context produced by the last middleware, so every procedure registered after it receives the new context.
+    /// This means a procedure states the context it requires by the type of it's resolver's first argument and it can only be registered where the middleware provide that context. To elevate only some procedures register them on a separate router and [`merge`](RouterBuilder::merge) it.
+    ///
+    /// ```rust
+    /// use rspc::{Error, ErrorCode, Router};
+    ///
+    /// struct UserCtx { user_id: u32, is_admin: bool }
+    /// struct AdminCtx { user_id: u32 }
+    ///
+    /// // Only admins get past the middleware, so every procedure after it receives an `AdminCtx`
+    /// let admin = Router::<UserCtx>::new()
+    ///     .middleware(|mw| {
+    ///         mw.middleware(|mw| async move {
+    ///             if !mw.ctx.is_admin {
+    ///                 return Err(Error::new(ErrorCode::Forbidden, "admins only".into()));
+    ///             }
+    ///             let ctx = AdminCtx { user_id: mw.ctx.user_id };
+    ///             Ok(mw.with_ctx(ctx))
+    ///         })
+    ///     })
+    ///     .mutation("banUser", |t| t(|ctx: AdminCtx, user_id: u32| user_id != ctx.user_id));
+    ///
+    /// let router = Router::<UserCtx>::new()
+    ///     .query("me", |t| t(|ctx: UserCtx, _: ()| ctx.user_id))
+    ///     .merge("admin.", admin)
+    ///     .build();
+    /// ```
+    ///
+    /// A procedure which requires an `AdminCtx` fails to compile where only a `UserCtx` is available.
+    ///
+    /// ```rust,compile_fail
+    /// struct UserCtx { user_id: u32 }
+    /// struct AdminCtx { user_id: u32 }
+    ///
+    /// // error: expected a closure which takes `UserCtx`, found one which takes `AdminCtx`
+    /// rspc::Router::<UserCtx>::new()
+    ///     .mutation("banUser", |t| t(|ctx: AdminCtx, user_id: u32| user_id != ctx.user_id));
+    /// ```
     pub fn middleware<TNewMiddleware, TNewLayerCtx>(
         self,
         builder: impl Fn(MiddlewareBuilder<TLayerCtx>) -> TNewMiddleware,
@@ -83,6 +212,138 @@ where
         TMeta,
         MiddlewareLayerBuilder<TCtx, TLayerCtx, TNewLayerCtx, TMiddleware, TNewMiddleware>,
     >
+    where
+        TNewLayerCtx: Send + Sync + 'static,
+        TNewMiddleware: MiddlewareLike<TLayerCtx, NewCtx = TNewLayerCtx> + Send + Sync + 'static,
+    {
+        self.middleware_inner(None, builder)
+    }
+
+    /// Add a middleware with a name, so other layers can be positioned relative to it using [`RouterBuilder::layer_before`] and [`RouterBuilder::layer_after`].
+    ///
+    /// Apart from the name this is identical to [`RouterBuilder::middleware`]. Names must be unique within a router.
+    pub fn named_middleware<TNewMiddleware, TNewLayerCtx>(
+        self,
+        name: &'static str,
+        builder: impl Fn(MiddlewareBuilder<TLayerCtx>) -> TNewMiddleware,
+    ) -> RouterBuilder<
+        TCtx,
+        TMeta,
+        MiddlewareLayerBuilder<TCtx, TLayerCtx, TNewLayerCtx, TMiddleware, TNewMiddleware>,
+    >
+    where
+        TNewLayerCtx: Send + Sync + 'static,
+        TNewMiddleware: MiddlewareLike<TLayerCtx, NewCtx = TNewLayerCtx> + Send + Sync + 'static,
+    {
+        #[allow(clippy::panic)]
+        if self.middleware.contains_layer(name) {
+            panic!(
+                "rspc error: attempted to add a middleware with the name '{}', however a middleware with this name already exists.",
+                name
+            );
+        }
+
+        self.middleware_inner(Some(name), builder)
+    }
+
+    /// Apply a [`MiddlewareStack`](crate::MiddlewareStack) to every procedure registered after it.
+    ///
+    /// The stack is added like a single middleware, so it's middleware run after those added before it and before those added after it.
+    pub fn stack(
+        self,
+        stack: &MiddlewareStack<TLayerCtx>,
+    ) -> RouterBuilder<TCtx, TMeta, StackLayerBuilder<TCtx, TLayerCtx, TMiddleware>> {
+        self.middleware_inner(None, |_| stack.clone())
+    }
+
+    /// Insert a middleware immediately before the middleware with the given name so it runs just before it.
+    ///
+    /// The inserted middleware sees the context as it is before the named middleware runs, so it must not change the context. If you insert multiple layers before the same name the most recently inserted one is closest to the named middleware.
+    ///
+    /// Like [`RouterBuilder::middleware`] this only applies to procedures registered after it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no middleware with the given name exists or if the middleware doesn't operate on the context at that point.
+    pub fn layer_before<TLayerMiddlewareCtx, TNewMiddleware>(
+        self,
+        name: &'static str,
+        builder: impl Fn(MiddlewareBuilder<TLayerMiddlewareCtx>) -> TNewMiddleware,
+    ) -> Self
+    where
+        TLayerMiddlewareCtx: Send + Sync + 'static,
+        TNewMiddleware: MiddlewareLike<TLayerMiddlewareCtx, NewCtx = TLayerMiddlewareCtx>
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.insert_layer(name, LayerPosition::Before, builder)
+    }
+
+    /// Insert a middleware immediately after the middleware with the given name so it runs just after it.
+    ///
+    /// The inserted middleware sees the context produced by the named middleware, so it must not change the context. If you insert multiple layers after the same name the most recently inserted one is closest to the named middleware.
+    ///
+    /// Like [`RouterBuilder::middleware`] this only applies to procedures registered after it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no middleware with the given name exists or if the middleware doesn't operate on the context at that point.
+    pub fn layer_after<TLayerMiddlewareCtx, TNewMiddleware>(
+        self,
+        name: &'static str,
+        builder: impl Fn(MiddlewareBuilder<TLayerMiddlewareCtx>) -> TNewMiddleware,
+    ) -> Self
+    where
+        TLayerMiddlewareCtx: Send + Sync + 'static,
+        TNewMiddleware: MiddlewareLike<TLayerMiddlewareCtx, NewCtx = TLayerMiddlewareCtx>
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.insert_layer(name, LayerPosition::After, builder)
+    }
+
+    fn insert_layer<TLayerMiddlewareCtx, TNewMiddleware>(
+        mut self,
+        name: &'static str,
+        position: LayerPosition,
+        builder: impl Fn(MiddlewareBuilder<TLayerMiddlewareCtx>) -> TNewMiddleware,
+    ) -> Self
+    where
+        TLayerMiddlewareCtx: Send + Sync + 'static,
+        TNewMiddleware: MiddlewareLike<TLayerMiddlewareCtx, NewCtx = TLayerMiddlewareCtx>
+            + Send
+            + Sync
+            + 'static,
+    {
+        let layer = dyn_layer(builder(MiddlewareBuilder(PhantomData)));
+
+        #[allow(clippy::panic)]
+        match self.middleware.insert_layer(name, position, &layer) {
+            InsertLayerResult::Inserted => {}
+            InsertLayerResult::NotFound => panic!(
+                "rspc error: attempted to insert a layer relative to the middleware '{}', however no middleware with this name exists.",
+                name
+            ),
+            InsertLayerResult::ContextMismatch => panic!(
+                "rspc error: attempted to insert a layer relative to the middleware '{}', however the layer's context type doesn't match the context at that point.",
+                name
+            ),
+        }
+
+        self
+    }
+
+    fn middleware_inner<TNewMiddleware, TNewLayerCtx>(
+        self,
+        name: Option<&'static str>,
+        builder: impl Fn(MiddlewareBuilder<TLayerCtx>) -> TNewMiddleware,
+    ) -> RouterBuilder<
+        TCtx,
+        TMeta,
+        MiddlewareLayerBuilder<TCtx, TLayerCtx, TNewLayerCtx, TMiddleware, TNewMiddleware>,
+    >
     where
         TNewLayerCtx: Send + Sync + 'static,
         TNewMiddleware: MiddlewareLike<TLayerCtx, NewCtx = TNewLayerCtx> + Send + Sync + 'static,
@@ -93,6 +354,12 @@ where
             queries,
             mutations,
             subscriptions,
+            caches,
+            priorities,
+            warmups,
+            unsubscribe_hooks,
+            fallback,
+            ignored_options,
             type_map: typ_store,
             ..
         } = self;
@@ -103,11 +370,20 @@ where
             middleware: MiddlewareLayerBuilder {
                 middleware,
                 mw,
+                name,
+                before: Vec::new(),
+                after: Vec::new(),
                 phantom: PhantomData,
             },
             queries,
             mutations,
             subscriptions,
+            caches,
+            priorities,
+            warmups,
+            unsubscribe_hooks,
+            fallback,
+            ignored_options,
             type_map: typ_store,
             phantom: PhantomData,
         }
@@ -125,20 +401,17 @@ where
         TResult: RequestLayer<TResultMarker>,
         TResolver: Fn(TLayerCtx, TArg) -> TResult + Send + Sync + 'static,
     {
-        let resolver = builder(UnbuiltProcedureBuilder::default()).resolver;
-        self.queries.append(
-            key.into(),
-            self.middleware.build(ResolverLayer {
-                func: move |ctx, input, _| {
-                    resolver.exec(
-                        ctx,
-                        serde_json::from_value(input).map_err(ExecError::DeserializingArgErr)?,
-                    )
-                },
-                phantom: PhantomData,
-            }),
-            TResolver::typedef(&mut self.type_map),
-        );
+        let BuiltProcedureBuilder {
+            resolver,
+            mut options,
+        } = builder(UnbuiltProcedureBuilder::default());
+        let input = mem::take(&mut options.input);
+        let layer = Box::new(ResolverLayer {
+            func: move |ctx, value, _| resolver.exec(ctx, input.apply(value)?),
+            phantom: PhantomData,
+        });
+        let ty = TResolver::typedef(&mut self.type_map);
+        self.build_layers(ProcedureKind::Query, key, options, layer, ty);
         self
     }
 
@@ -154,23 +427,39 @@ where
         TResult: RequestLayer<TResultMarker>,
         TResolver: Fn(TLayerCtx, TArg) -> TResult + Send + Sync + 'static,
     {
-        let resolver = builder(UnbuiltProcedureBuilder::default()).resolver;
-        self.mutations.append(
-            key.into(),
-            self.middleware.build(ResolverLayer {
-                func: move |ctx, input, _| {
-                    resolver.exec(
-                        ctx,
-                        serde_json::from_value(input).map_err(ExecError::DeserializingArgErr)?,
-                    )
-                },
-                phantom: PhantomData,
-            }),
-            TResolver::typedef(&mut self.type_map),
-        );
+        let BuiltProcedureBuilder {
+            resolver,
+            mut options,
+        } = builder(UnbuiltProcedureBuilder::default());
+        let input = mem::take(&mut options.input);
+        let layer = Box::new(ResolverLayer {
+            func: move |ctx, value, _| resolver.exec(ctx, input.apply(value)?),
+            phantom: PhantomData,
+        });
+        let ty = TResolver::typedef(&mut self.type_map);
+        self.build_layers(ProcedureKind::Mutation, key, options, layer, ty);
         self
     }
 
+    /// Register a subscription. The resolver returns a [`Stream`] and each item it yields is sent to the client as an event.
+    ///
+    /// A resolver which has nothing to send for the context (Eg. the user can't see any of the items) can return an already completed stream, like [`stream::empty`](futures::stream::empty), as one side of an [`Either`](futures::future::Either).
+    /// A stream which reports it won't yield any items through it's [`size_hint`](Stream::size_hint) and has already ended is completed immediately with a [`Complete`](crate::internal::jsonrpc::ResponseInner::Complete) frame, without starting a background task for it.
+    ///
+    /// The stream is driven on a background task for as long as the subscription is active, so it must be `Send + Sync + 'static`.
+    /// This means it can't capture a non-thread-safe value (Eg. an [`Rc`](std::rc::Rc) or a [`Cell`](std::cell::Cell)), or borrow from the context or input. Move owned (or [`Arc`]'d) values into the stream instead.
+    ///
+    /// ```rust,compile_fail
+    /// use std::cell::Cell;
+    ///
+    /// <rspc::Router>::new().subscription("counter", |t| {
+    ///     t(|_, _: ()| {
+    ///         let count = Cell::new(0);
+    ///         // error: `Cell<i32>` cannot be shared between threads safely
+    ///         futures::stream::once(async move { count.get() })
+    ///     })
+    /// });
+    /// ```
     pub fn subscription<TResolver, TArg, TStream, TResult, TResultMarker>(
         mut self,
         key: &'static str,
@@ -179,29 +468,256 @@ where
         ) -> BuiltProcedureBuilder<TResolver>,
     ) -> Self
     where
-        TArg: DeserializeOwned + Type,
-        TStream: Stream<Item = TResult> + Send + 'static,
-        TResult: Serialize + Type,
+        TArg: DeserializeOwned + Type + 'static,
+        TStream: Stream<Item = TResult> + Send + Sync + 'static,
+        TResult: Serialize + Type + 'static,
         TResolver: Fn(TLayerCtx, TArg) -> TStream
             + StreamResolver<TLayerCtx, DoubleArgStreamMarker<TArg, TResultMarker, TStream>>
             + Send
             + Sync
             + 'static,
     {
-        let resolver = builder(UnbuiltProcedureBuilder::default()).resolver;
-        self.subscriptions.append(
-            key.into(),
-            self.middleware.build(ResolverLayer {
-                func: move |ctx, input, _| {
-                    resolver.exec(
-                        ctx,
-                        serde_json::from_value(input).map_err(ExecError::DeserializingArgErr)?,
-                    )
-                },
-                phantom: PhantomData,
-            }),
-            TResolver::typedef(&mut self.type_map),
+        let BuiltProcedureBuilder {
+            resolver,
+            mut options,
+        } = builder(UnbuiltProcedureBuilder::default());
+        let trailer_ty = options
+            .on_complete
+            .as_ref()
+            .map(|on_complete| (on_complete.typedef)(&mut self.type_map));
+        let ty = match &options.map_item {
+            Some(map_item) => ProcedureDataType {
+                arg_ty: TArg::reference(&mut self.type_map, &[]).inner,
+                result_ty: (map_item.typedef)(&mut self.type_map),
+                logs_ty: None,
+                trailer_ty,
+                no_content: false,
+                description: None,
+                deprecated: None,
+                tags: Vec::new(),
+                error_ty: None,
+                cache_control: None,
+                sla: None,
+            },
+            None => ProcedureDataType {
+                trailer_ty,
+                ..TResolver::typedef(&mut self.type_map)
+            },
+        };
+        let ty = match options.buffer {
+            Some(_) => ProcedureDataType {
+                result_ty: <Vec<()> as Type>::reference(&mut TypeMap::default(), &[ty.result_ty])
+                    .inner,
+                ..ty
+            },
+            None => ty,
+        };
+        if options.on_unsubscribe.is_some() {
+            self.unsubscribe_hooks.push(key.into());
+        }
+        let hooks = SubscriptionHooks::<TLayerCtx, TArg>::new(
+            options.on_subscribe.take(),
+            options.on_unsubscribe.take(),
+            options
+                .on_complete
+                .take()
+                .map(|on_complete| on_complete.hook),
         );
+        let input = mem::take(&mut options.input);
+        let map_item = options.map_item.take();
+        let buffer = options.buffer.take();
+        let spawn = mem::take(&mut options.spawn);
+        let serialize_concurrently = options.serialize_concurrently.take();
+        let layer = Box::new(ResolverLayer {
+            func: move |ctx, value, req: RequestContext| {
+                let input: TArg = deserialize_input(input.apply(value)?)?;
+                let on_unsubscribe = hooks.start(&ctx, &input);
+                let on_complete = hooks.complete(&ctx);
+                // Events can be serialized on another task, outside of the request's scope
+                let timer = SerializeTimer::current();
+                let stream = resolver(ctx, input);
+                // There's nothing to spawn or buffer for a stream which won't yield any items
+                let completed = stream.size_hint() == (0, Some(0));
+                let stream: Pin<Box<dyn Stream<Item = _> + Send>> =
+                    match (&serialize_concurrently, &map_item) {
+                        (Some(concurrently), _) if !completed => {
+                            let serialize: SerializeFn = match &map_item {
+                                Some(map_item) => {
+                                    let map = map_item.map.clone();
+                                    Arc::new(move |item| timer.scoped(|| map(item)))
+                                }
+                                None => Arc::new(move |item| {
+                                    let item = item
+                                        .downcast::<TResult>()
+                                        .expect("rspc: subscription item type mismatch");
+                                    timer.scoped(|| serialize_timer::to_value(&*item))
+                                }),
+                            };
+                            let into_send = concurrently.into_send;
+                            concurrent_serialize::serialize_concurrently(
+                                stream.map(move |item| into_send(Box::new(item))),
+                                concurrently.concurrency,
+                                serialize,
+                                req.runtime.clone(),
+                            )
+                        }
+                        (_, Some(map_item)) => {
+                            let map = map_item.map.clone();
+                            Box::pin(stream.map(move |item| timer.scoped(|| map(Box::new(item)))))
+                        }
+                        (_, None) => Box::pin(
+                            stream.map(move |v| timer.scoped(|| serialize_timer::to_value(&v))),
+                        ),
+                    };
+                let stream = match spawn && !completed {
+                    true => spawn_stream(stream, req.runtime.clone()),
+                    false => stream,
+                };
+                let stream: Pin<Box<dyn Stream<Item = _> + Send>> = match buffer {
+                    Some((count, max_delay)) if !completed => {
+                        Box::pin(Buffer::new(stream, count, max_delay, req.runtime.clone()))
+                    }
+                    _ => stream,
+                };
+                let stream: Pin<Box<dyn Stream<Item = _> + Send>> = match on_complete {
+                    Some(on_complete) => Box::pin(Complete {
+                        stream,
+                        on_complete: Some(on_complete),
+                    }),
+                    None => stream,
+                };
+                // `RouterBuilder::build` checks there's a runtime for every subscription with an `on_unsubscribe` hook
+                Ok(LayerResult::Stream(match (on_unsubscribe, req.runtime) {
+                    (Some(on_unsubscribe), Some(runtime)) => Box::pin(Unsubscribe {
+                        stream,
+                        on_unsubscribe: Some(on_unsubscribe),
+                        runtime,
+                        timeout: req.unsubscribe_timeout,
+                    }),
+                    _ => stream,
+                }))
+            },
+            phantom: PhantomData,
+        });
+        self.build_layers(ProcedureKind::Subscription, key, options, layer, ty);
+        self
+    }
+
+    // Wrap a procedure's resolver in the layers of it's options and register it.
+    fn build_layers(
+        &mut self,
+        kind: ProcedureKind,
+        key: &'static str,
+        mut options: ProcedureOptions,
+        resolver: Box<dyn Layer<TLayerCtx>>,
+        ty: ProcedureDataType,
+    ) {
+        self.ignore_options(kind, key, &mut options);
+        let ProcedureOptions {
+            cache,
+            cache_control,
+            sla,
+            visible,
+            field_access,
+            schema_version,
+            virtual_fields,
+            priority,
+            warmup,
+            description,
+            deprecated,
+            tags,
+            error_ty,
+            aliases,
+            ..
+        } = options;
+
+        // Layers which read or rewrite the result need it as a `Value`
+        let typed = self.middleware.is_empty()
+            && cache.is_none()
+            && field_access.is_none()
+            && virtual_fields.is_empty();
+        let mut layer = VirtualFieldsLayer::wrap(
+            SchemaVersionLayer::wrap(resolver, schema_version),
+            virtual_fields,
+        );
+        if let Some((ttl, context_key)) = cache {
+            let cache = Arc::new(ProcedureCache::new(ttl));
+            for key in aliases.iter().chain([&key]) {
+                self.caches.insert(key.to_string(), cache.clone());
+            }
+            layer = CacheLayer::wrap(layer, cache, context_key.as_ref());
+        }
+        if let Some(directive) = &cache_control {
+            layer = Box::new(CacheControlLayer {
+                next: layer,
+                directive: directive.clone(),
+            });
+        }
+        let layer = VisibilityLayer::wrap(
+            FieldAccessLayer::wrap(layer, field_access),
+            visible.as_ref(),
+        );
+        if let Some(warmup) = warmup {
+            self.warmups.push((key.into(), warmup));
+        }
+        if let Some(priority) = priority {
+            for key in aliases.iter().chain([&key]) {
+                self.priorities.insert(key.to_string(), priority);
+            }
+        }
+
+        let exec = self.middleware.build(layer);
+        let ty = ProcedureDataType {
+            description,
+            deprecated,
+            tags,
+            error_ty: error_ty.map(|error_ty| error_ty(&mut self.type_map)),
+            cache_control,
+            sla,
+            ..ty
+        };
+        let store = match kind {
+            ProcedureKind::Query => &mut self.queries,
+            ProcedureKind::Mutation => &mut self.mutations,
+            ProcedureKind::Subscription => &mut self.subscriptions,
+        };
+        store.append_with_aliases(key.into(), &aliases, exec, ty, visible, typed);
+    }
+
+    // Record the options which were set on a procedure but don't apply to it's kind for `Router::validate`, and drop them.
+    fn ignore_options(&mut self, kind: ProcedureKind, key: &str, options: &mut ProcedureOptions) {
+        for (option, kinds, take) in KIND_OPTIONS {
+            if !kinds.contains(&kind) && take(options) {
+                self.ignored_options.push((kind, key.into(), option));
+            }
+        }
+    }
+
+    /// Handle requests for queries and mutations which don't exist, Eg. to proxy them to a legacy system or return a custom error, instead of failing with [`ExecError::OperationNotFound`].
+    ///
+    /// The handler receives the context, the requested method name and the raw input. It's result (or error) is sent as a normal response.
+    /// Like procedures, the fallback runs behind the middleware registered before it. Calling this again replaces the previous fallback.
+    ///
+    /// ## Precedence
+    ///
+    /// An exact match always wins, so the fallback is only used when no procedure with the method name exists. A procedure hidden with [`BuiltProcedureBuilder::visible_when`](crate::internal::BuiltProcedureBuilder::visible_when) still exists, so it isn't passed to the fallback.
+    /// Subscriptions can't use the fallback as it returns a single value, so unknown subscriptions always fail with [`ExecError::OperationNotFound`].
+    /// The fallbacks of routers merged into this one are ignored.
+    pub fn fallback<TFut>(
+        mut self,
+        fallback: impl Fn(TLayerCtx, String, Value) -> TFut + Send + Sync + 'static,
+    ) -> Self
+    where
+        TFut: Future<Output = Result<Value, Error>> + Send + 'static,
+    {
+        self.fallback = Some(self.middleware.build(ResolverLayer {
+            func: move |ctx, input, req: RequestContext| {
+                Ok(LayerResult::Future(Box::pin(
+                    fallback(ctx, req.path, input).map_err(ExecError::from),
+                )))
+            },
+            phantom: PhantomData,
+        }));
         self
     }
 
@@ -231,6 +747,8 @@ where
                 format!("{}{}", prefix, key),
                 self.middleware.build(query.exec),
                 query.ty,
+                query.visible,
+                query.typed && self.middleware.is_empty(),
             );
         }
 
@@ -240,6 +758,8 @@ where
                 format!("{}{}", prefix, key),
                 self.middleware.build(mutation.exec),
                 mutation.ty,
+                mutation.visible,
+                mutation.typed && self.middleware.is_empty(),
             );
         }
 
@@ -249,9 +769,33 @@ where
                 format!("{}{}", prefix, key),
                 self.middleware.build(subscription.exec),
                 subscription.ty,
+                subscription.visible,
+                subscription.typed && self.middleware.is_empty(),
             );
         }
 
+        for (key, cache) in router.caches {
+            self.caches.insert(format!("{}{}", prefix, key), cache);
+        }
+
+        for (key, priority) in router.priorities {
+            self.priorities
+                .insert(format!("{}{}", prefix, key), priority);
+        }
+
+        for (key, warmup) in router.warmups {
+            self.warmups.push((format!("{}{}", prefix, key), warmup));
+        }
+
+        for key in router.unsubscribe_hooks {
+            self.unsubscribe_hooks.push(format!("{}{}", prefix, key));
+        }
+
+        for (kind, key, option) in router.ignored_options {
+            self.ignored_options
+                .push((kind, format!("{}{}", prefix, key), option));
+        }
+
         for (name, typ) in router.type_map.iter() {
             self.type_map.insert(name, typ.clone());
         }
@@ -292,6 +836,12 @@ where
             mut queries,
             mut mutations,
             mut subscriptions,
+            mut caches,
+            mut priorities,
+            mut warmups,
+            mut unsubscribe_hooks,
+            fallback,
+            mut ignored_options,
             type_map: mut typ_store,
             ..
         } = self;
@@ -301,6 +851,8 @@ where
                 format!("{}{}", prefix, key),
                 middleware.build(query.exec),
                 query.ty,
+                query.visible,
+                query.typed && middleware.is_empty(),
             );
         }
 
@@ -309,6 +861,8 @@ where
                 format!("{}{}", prefix, key),
                 middleware.build(mutation.exec),
                 mutation.ty,
+                mutation.visible,
+                mutation.typed && middleware.is_empty(),
             );
         }
 
@@ -317,9 +871,31 @@ where
                 format!("{}{}", prefix, key),
                 middleware.build(subscription.exec),
                 subscription.ty,
+                subscription.visible,
+                subscription.typed && middleware.is_empty(),
             );
         }
 
+        for (key, cache) in router.caches {
+            caches.insert(format!("{}{}", prefix, key), cache);
+        }
+
+        for (key, priority) in router.priorities {
+            priorities.insert(format!("{}{}", prefix, key), priority);
+        }
+
+        for (key, warmup) in router.warmups {
+            warmups.push((format!("{}{}", prefix, key), warmup));
+        }
+
+        for key in router.unsubscribe_hooks {
+            unsubscribe_hooks.push(format!("{}{}", prefix, key));
+        }
+
+        for (kind, key, option) in router.ignored_options {
+            ignored_options.push((kind, format!("{}{}", prefix, key), option));
+        }
+
         for (name, typ) in router.type_map.iter() {
             typ_store.insert(name, typ.clone());
         }
@@ -334,6 +910,12 @@ where
             queries,
             mutations,
             subscriptions,
+            caches,
+            priorities,
+            warmups,
+            unsubscribe_hooks,
+            fallback,
+            ignored_options,
             type_map: typ_store,
             phantom: PhantomData,
         }
@@ -345,17 +927,99 @@ where
             queries,
             mutations,
             subscriptions,
-            type_map: typ_store,
+            caches,
+            priorities,
+            warmups,
+            unsubscribe_hooks,
+            fallback,
+            ignored_options,
+            type_map: mut typ_store,
             ..
         } = self;
 
+        if let (Some(key), None) = (unsubscribe_hooks.first(), config.runtime_or_default()) {
+            #[allow(clippy::panic)]
+            {
+                panic!("rspc error: the subscription '{key}' has an on_unsubscribe hook but no runtime is configured to time out it's cleanup. Enable the `runtime-tokio` feature or set `Config::runtime`.");
+            }
+        }
+
+        // So the frontend can match on the `kind` of errors and read input validation errors.
+        ErrorKind::reference(&mut typ_store, &[]);
+        FieldError::reference(&mut typ_store, &[]);
+
+        #[cfg(debug_assertions)]
+        let (queries, mutations, subscriptions) = match config.validate_results {
+            true => {
+                let type_map = Arc::new(typ_store.clone());
+                (
+                    super::validate::validate_results(queries, &type_map),
+                    super::validate::validate_results(mutations, &type_map),
+                    super::validate::validate_results(subscriptions, &type_map),
+                )
+            }
+            false => (queries, mutations, subscriptions),
+        };
+
+        let (queries, mutations, subscriptions) = match config.rename_fields {
+            Some(rule) => {
+                let type_map = Arc::new(typ_store);
+                typ_store = super::rename::rename_type_map(rule, &type_map);
+                (
+                    super::rename::rename_fields(rule, queries, &type_map),
+                    super::rename::rename_fields(rule, mutations, &type_map),
+                    super::rename::rename_fields(rule, subscriptions, &type_map),
+                )
+            }
+            None => (queries, mutations, subscriptions),
+        };
+
+        let (queries, subscriptions) = match &config.transform_responses {
+            Some(transform) => (
+                super::transform::transform_responses(transform, queries),
+                match config.transform_subscription_events {
+                    true => super::transform::transform_responses(transform, subscriptions),
+                    false => subscriptions,
+                },
+            ),
+            None => (queries, subscriptions),
+        };
+
+        let mutations = match &config.after_mutation {
+            Some(hook) => super::mutation_hook::after_mutation(hook, mutations),
+            None => mutations,
+        };
+
+        let (queries, mutations) = match config.deprecation_warnings {
+            true => (
+                super::transform::deprecation_warnings(queries),
+                super::transform::deprecation_warnings(mutations),
+            ),
+            false => (queries, mutations),
+        };
+
+        let (queries, mutations) = match &config.on_sla_breach {
+            Some(hook) => (
+                super::sla::sla_breaches(hook, queries),
+                super::sla::sla_breaches(hook, mutations),
+            ),
+            None => (queries, mutations),
+        };
+
         let export_path = config.export_bindings_on_build.clone();
         let router = Router {
             config,
             queries,
             mutations,
             subscriptions,
+            caches: Caches(Arc::new(caches)),
+            priorities,
+            active_subscriptions: Default::default(),
+            warmups: downcast_warmups(warmups),
+            fallback,
+            ignored_options,
             type_map: typ_store,
+            namespaces: BTreeMap::new(),
             phantom: PhantomData,
         };
 
@@ -368,3 +1032,71 @@ where
         router
     }
 }
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use serde_json::Value;
+
+    use crate::{
+        internal::{Layer, LayerResult, RequestContext},
+        ExecError, ExecKind, MiddlewareLike, Router,
+    };
+
+    #[derive(Clone)]
+    struct Record(Arc<Mutex<Vec<&'static str>>>, &'static str);
+
+    impl MiddlewareLike<()> for Record {
+        type State = ();
+        type NewCtx = ();
+
+        fn handle<TMiddleware: Layer<()> + 'static>(
+            &self,
+            ctx: (),
+            input: Value,
+            req: RequestContext,
+            next: Arc<TMiddleware>,
+        ) -> Result<LayerResult, ExecError> {
+            self.0.lock().unwrap().push(self.1);
+            next.call(ctx, input, req)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_named_layers() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let record = |name| {
+            let log = log.clone();
+            move |_| Record(log.clone(), name)
+        };
+        let router = <Router>::new()
+            .middleware(record("a"))
+            .named_middleware("auth", record("auth"))
+            .middleware(record("b"))
+            .layer_before("auth", record("before1"))
+            .layer_before("auth", record("before2"))
+            .layer_after("auth", record("after"))
+            .query("q", |t| t(|_, _: ()| ()))
+            .build();
+
+        router
+            .exec((), ExecKind::Query, "q".into(), None)
+            .await
+            .unwrap();
+        assert_eq!(
+            *log.lock().unwrap(),
+            ["a", "before1", "before2", "auth", "after", "b"]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "no middleware with this name exists")]
+    fn test_layer_missing_name() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let _ = <Router>::new()
+            .named_middleware("auth", |_| Record(log.clone(), "auth"))
+            .layer_after("missing", |_| Record(log.clone(), "after"));
+    }
+}
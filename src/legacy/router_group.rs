@@ -0,0 +1,169 @@
+use std::collections::BTreeMap;
+
+use crate::{Config, Router, RouterBuilder};
+
+/// Serve multiple independent routers from a single transport, Eg. to serve `v1` and `v2` of your API from the same endpoint while they are migrated.
+///
+/// Each router is mounted under a namespace and it's procedures are called by prefixing their key with it, so `version` on the router mounted as `v1` is called as `v1.version`.
+/// Unlike [`RouterBuilder::merge`] the routers are built separately, so each keeps it's own middleware, [`Config`] and types. A request for a namespace which isn't mounted fails with [`ExecError::OperationNotFound`](crate::ExecError::OperationNotFound) (using the full key) just like an unknown procedure.
+///
+/// The exported bindings contain the types of each router within a TypeScript namespace of the same name (so types with the same name in different routers don't conflict) and a top-level `Procedures` type containing the procedures of every router with their prefixed keys.
+///
+/// ```rust
+/// let v1 = <rspc::Router>::new()
+///     .query("version", |t| t(|_, _: ()| "1.0.0"))
+///     .build();
+/// let v2 = <rspc::Router>::new()
+///     .query("version", |t| t(|_, _: ()| "2.0.0"))
+///     .build();
+///
+/// let router = rspc::RouterGroup::new().mount("v1", v1).mount("v2", v2).build();
+/// ```
+///
+/// Note: Connection level options (Eg. [`Config::max_bytes_per_connection`]) are taken from the group's config and the handles of the mounted routers (Eg. [`Router::caches`]) should be taken before they are mounted. Other methods which inspect the built router's procedures (Eg. [`Router::openrpc`]) don't include the mounted routers.
+pub struct RouterGroup<TCtx = (), TMeta = ()>
+where
+    TCtx: 'static,
+{
+    config: Config,
+    routers: BTreeMap<&'static str, Router<TCtx, TMeta>>,
+}
+
+#[allow(clippy::new_without_default)]
+impl<TCtx, TMeta> RouterGroup<TCtx, TMeta>
+where
+    TCtx: Send + Sync + 'static,
+    TMeta: Send + 'static,
+{
+    pub fn new() -> Self {
+        Self {
+            config: Config::new(),
+            routers: BTreeMap::new(),
+        }
+    }
+
+    /// Attach a configuration to the group. Calling this multiple times will overwrite the previous config.
+    pub fn config(mut self, config: Config) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Mount `router` under `namespace`.
+    ///
+    /// The namespace must be a valid TypeScript identifier as it's also the name of the namespace in the bindings.
+    pub fn mount(mut self, namespace: &'static str, router: Router<TCtx, TMeta>) -> Self {
+        let is_ident = namespace
+            .chars()
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            && namespace
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_');
+
+        #[allow(clippy::panic)]
+        if !is_ident {
+            panic!(
+                "rspc error: attempted to mount a router with the namespace '{}', however namespaces must be a valid TypeScript identifier.",
+                namespace
+            );
+        }
+
+        #[allow(clippy::panic)]
+        if self.routers.contains_key(namespace) {
+            panic!(
+                "rspc error: attempted to mount a router with the namespace '{}', however a router is already mounted with this namespace.",
+                namespace
+            );
+        }
+
+        self.routers.insert(namespace, router);
+        self
+    }
+
+    pub fn build(self) -> Router<TCtx, TMeta> {
+        let Self {
+            mut config,
+            routers,
+        } = self;
+
+        let export_path = config.export_bindings_on_build.take();
+        let mut router = RouterBuilder::<TCtx, TMeta, _>::new()
+            .config(config)
+            .build();
+
+        for (namespace, mut mounted) in routers {
+            for (key, priority) in &mounted.priorities {
+                router
+                    .priorities
+                    .insert(format!("{namespace}.{key}"), *priority);
+            }
+            for (key, warmup) in std::mem::take(&mut mounted.warmups) {
+                router.warmups.push((format!("{namespace}.{key}"), warmup));
+            }
+            router.namespaces.insert(namespace, mounted);
+        }
+
+        #[cfg(debug_assertions)]
+        #[allow(clippy::unwrap_used)]
+        if let Some(export_path) = export_path {
+            router.export_ts(export_path).unwrap();
+        }
+
+        router
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use serde_json::json;
+
+    use crate::{ExecError, ExecKind, Router, RouterGroup};
+
+    #[tokio::test]
+    async fn test_router_group() {
+        let v1 = <Router>::new()
+            .query("version", |t| t(|_, _: ()| "1.0.0"))
+            .build();
+        let v2 = <Router>::new()
+            .query("version", |t| t(|_, _: ()| "2.0.0"))
+            .mutation("bump", |t| t(|_, version: i32| version + 1))
+            .build();
+        let router = RouterGroup::new().mount("v1", v1).mount("v2", v2).build();
+
+        let exec = |key: &str| router.exec((), ExecKind::Query, key.into(), None);
+        assert_eq!(exec("v1.version").await.unwrap(), json!("1.0.0"));
+        assert_eq!(exec("v2.version").await.unwrap(), json!("2.0.0"));
+        assert_eq!(
+            router
+                .exec((), ExecKind::Mutation, "v2.bump".into(), Some(json!(2)))
+                .await
+                .unwrap(),
+            json!(3)
+        );
+        assert!(matches!(
+            exec("v3.version").await,
+            Err(ExecError::OperationNotFound(key)) if key == "v3.version"
+        ));
+        assert!(matches!(
+            exec("v1.missing").await,
+            Err(ExecError::OperationNotFound(key)) if key == "v1.missing"
+        ));
+
+        let bindings = router.ts_bindings().unwrap();
+        assert!(bindings.contains(
+            r#"queries: Namespaced<"v1", v1.Procedures["queries"]> | Namespaced<"v2", v2.Procedures["queries"]>,"#
+        ));
+        assert!(bindings.contains("key: `${TPrefix}.${TKey}`"));
+        assert!(bindings.contains("export namespace v1 {\n    export type Procedures = {"));
+        assert!(bindings.contains(r#"        { key: "bump", input: number, result: number }"#));
+    }
+
+    #[test]
+    #[should_panic(expected = "already mounted")]
+    fn test_router_group_duplicate_namespace() {
+        RouterGroup::<()>::new()
+            .mount("v1", <Router>::new().build())
+            .mount("v1", <Router>::new().build());
+    }
+}
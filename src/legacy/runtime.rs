@@ -0,0 +1,146 @@
+use std::{fmt, future::Future, pin::Pin, time::Duration};
+
+/// The async runtime rspc uses to run background tasks. Configure it with [`Config::runtime`](crate::Config::runtime).
+///
+/// rspc only needs a runtime for:
+///  - spawning a task driving each subscription started through [`handle_json_rpc`](crate::internal::jsonrpc::handle_json_rpc) (Eg. over a WebSocket), so it can keep receiving requests on the connection while the subscription is active.
+///  - the timer of [`Config::context_timeout`](crate::Config::context_timeout).
+///  - the timer of subscriptions which [`buffer`](crate::internal::BuiltProcedureBuilder::buffer) their items.
+///  - the task polling the stream of subscriptions which are [`spawn`](crate::internal::BuiltProcedureBuilder::spawn)ed.
+///
+/// Everything else (queries, mutations, [`Router::exec`](crate::Router::exec) and [`Router::exec_subscription`](crate::Router::exec_subscription)) runs on the caller's task and works on any runtime.
+///
+/// With the `runtime-tokio` feature (enabled by default) [`TokioRuntime`] is used when no runtime is configured. Without it, the features above fail with [`ExecError::NoRuntime`](crate::ExecError::NoRuntime) unless a runtime is configured.
+///
+/// ```rust
+/// use std::{future::Future, pin::Pin, time::Duration};
+///
+/// struct AsyncStd;
+///
+/// impl rspc::Runtime for AsyncStd {
+///     fn spawn(&self, fut: Pin<Box<dyn Future<Output = ()> + Send>>) {
+///         # let _ = fut;
+///         // async_std::task::spawn(fut);
+///     }
+///
+///     fn sleep(&self, duration: Duration) -> Pin<Box<dyn Future<Output = ()> + Send>> {
+///         # let _ = duration;
+///         // Box::pin(async_std::task::sleep(duration))
+///         # Box::pin(std::future::pending())
+///     }
+/// }
+///
+/// let router = <rspc::Router>::new()
+///     .config(rspc::Config::new().runtime(AsyncStd))
+///     .build();
+/// ```
+pub trait Runtime: Send + Sync + 'static {
+    /// Run `fut` to completion in the background. The returned handle isn't needed, rspc stops the task by completing it.
+    fn spawn(&self, fut: Pin<Box<dyn Future<Output = ()> + Send>>);
+
+    /// Returns a future which completes after `duration`.
+    fn sleep(&self, duration: Duration) -> Pin<Box<dyn Future<Output = ()> + Send>>;
+}
+
+/// A [`Runtime`] which spawns tasks onto the current Tokio runtime.
+///
+/// This must be used from within a Tokio runtime.
+#[cfg(feature = "runtime-tokio")]
+#[cfg_attr(docsrs, doc(cfg(feature = "runtime-tokio")))]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct TokioRuntime;
+
+#[cfg(feature = "runtime-tokio")]
+impl Runtime for TokioRuntime {
+    fn spawn(&self, fut: Pin<Box<dyn Future<Output = ()> + Send>>) {
+        tokio::spawn(fut);
+    }
+
+    fn sleep(&self, duration: Duration) -> Pin<Box<dyn Future<Output = ()> + Send>> {
+        Box::pin(tokio::time::sleep(duration))
+    }
+}
+
+impl fmt::Debug for dyn Runtime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Runtime").finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use std::{
+        future::Future,
+        pin::Pin,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    use futures::stream;
+    use serde_json::json;
+    use tokio::sync::mpsc;
+
+    use super::Runtime;
+    use crate::{
+        internal::jsonrpc::{
+            self, handle_json_rpc, RequestId, ResponseInner, Sender, SubscriptionMap,
+        },
+        Config, Router,
+    };
+
+    struct CountingRuntime(Arc<AtomicUsize>);
+
+    impl Runtime for CountingRuntime {
+        fn spawn(&self, fut: Pin<Box<dyn Future<Output = ()> + Send>>) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            tokio::spawn(fut);
+        }
+
+        fn sleep(&self, duration: Duration) -> Pin<Box<dyn Future<Output = ()> + Send>> {
+            Box::pin(tokio::time::sleep(duration))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_custom_runtime() {
+        let spawned = Arc::new(AtomicUsize::new(0));
+        let router = Arc::new(
+            <Router>::new()
+                .config(Config::new().runtime(CountingRuntime(spawned.clone())))
+                .subscription("numbers", |t| t(|_, _: ()| stream::iter([1, 2])))
+                .build(),
+        );
+
+        let (mut tx, mut rx) = mpsc::unbounded_channel();
+        let mut subscriptions = Default::default();
+        handle_json_rpc(
+            (),
+            jsonrpc::Request {
+                jsonrpc: None,
+                id: RequestId::Number(1),
+                inner: jsonrpc::RequestInner::Subscription {
+                    path: "numbers".into(),
+                    input: (RequestId::Number(1), None),
+                },
+            },
+            &router,
+            &mut Sender::ResponseChannel(&mut tx),
+            &mut SubscriptionMap::Ref(&mut subscriptions),
+        )
+        .await;
+        drop(tx);
+
+        let mut events = Vec::new();
+        while let Some(resp) = rx.recv().await {
+            if let ResponseInner::Event(event) = resp.result {
+                events.push(event);
+            }
+        }
+        assert_eq!(events, [json!(1), json!(2)]);
+        assert_eq!(spawned.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,271 @@
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use futures::StreamExt;
+use serde_json::Value;
+
+use crate::{
+    internal::{Layer, LayerResult, ProcedureKind, RequestContext, ValueOrStream},
+    ExecError, MiddlewareLike,
+};
+
+/// Decides whether a request is logged by [`SampledLogger`].
+///
+/// It's called once when the request arrives and the decision applies to it's result, or for subscriptions every event.
+pub trait Sampler: Send + Sync + 'static {
+    fn sample(&self, req: &RequestContext) -> bool;
+}
+
+impl<F> Sampler for F
+where
+    F: Fn(&RequestContext) -> bool + Send + Sync + 'static,
+{
+    fn sample(&self, req: &RequestContext) -> bool {
+        self(req)
+    }
+}
+
+/// A [`Sampler`] which logs a random `rate` (between `0.0` and `1.0`) of requests.
+#[derive(Debug, Clone, Copy)]
+pub struct RandomSampler {
+    rate: f64,
+}
+
+impl RandomSampler {
+    pub fn new(rate: f64) -> Self {
+        Self { rate }
+    }
+}
+
+impl Sampler for RandomSampler {
+    fn sample(&self, _: &RequestContext) -> bool {
+        ((random_u64() >> 11) as f64 / (1u64 << 53) as f64) < self.rate
+    }
+}
+
+/// A uniformly random `u64`.
+pub(crate) fn random_u64() -> u64 {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    // `RandomState` is randomly seeded, so hashing a counter with it gives a uniform value without depending on `rand`
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u64(COUNTER.fetch_add(1, Ordering::Relaxed));
+    hasher.finish()
+}
+
+/// A request (or subscription event) logged by [`SampledLogger`].
+#[derive(Debug)]
+pub struct RequestLog<'a> {
+    pub kind: ProcedureKind,
+    pub path: &'a str,
+    /// The raw `params` of the request, exactly as the client sent them.
+    pub params: &'a Value,
+    /// The result of the request. For subscriptions this is the event being logged.
+    pub result: Result<&'a Value, &'a ExecError>,
+    /// The time since the request arrived.
+    pub duration: Duration,
+    /// Whether the request was sampled. If `false` it's only logged because it failed.
+    pub sampled: bool,
+}
+
+type LogFn = Arc<dyn Fn(&RequestLog) + Send + Sync>;
+
+/// Middleware which logs the full details of a sample of requests, plus every request which fails.
+///
+/// Logging every request is often too noisy (and expensive) for a busy server, so by default only a random `rate` of requests are logged. See [`SampledLogger::sampler`] to choose which requests are logged yourself.
+///
+/// ```rust
+/// let router = <rspc::Router>::new()
+///     .middleware(|_| {
+///         rspc::SampledLogger::new(0.01, |log| {
+///             println!("{} {} took {:?}: {:?}", log.path, log.params, log.duration, log.result)
+///         })
+///     })
+///     .query("version", |t| t(|_, _: ()| env!("CARGO_PKG_VERSION")))
+///     .build();
+/// ```
+#[derive(Clone)]
+pub struct SampledLogger {
+    sampler: Arc<dyn Sampler>,
+    log: LogFn,
+}
+
+impl SampledLogger {
+    /// Construct a logger which calls `log` for a random `rate` (between `0.0` and `1.0`) of requests and every request which fails.
+    pub fn new(rate: f64, log: impl Fn(&RequestLog) + Send + Sync + 'static) -> Self {
+        Self {
+            sampler: Arc::new(RandomSampler::new(rate)),
+            log: Arc::new(log),
+        }
+    }
+
+    /// Replace the [`RandomSampler`] with `sampler`, Eg. to always log the requests of a specific procedure.
+    pub fn sampler(mut self, sampler: impl Sampler) -> Self {
+        self.sampler = Arc::new(sampler);
+        self
+    }
+}
+
+// What's needed to log a request once it's result is known
+struct Entry {
+    kind: ProcedureKind,
+    path: String,
+    params: Arc<Value>,
+    started: Instant,
+    sampled: bool,
+    log: LogFn,
+}
+
+impl Entry {
+    fn log(&self, result: Result<&Value, &ExecError>) {
+        if self.sampled || result.is_err() {
+            (self.log)(&RequestLog {
+                kind: self.kind,
+                path: &self.path,
+                params: &self.params,
+                result,
+                duration: self.started.elapsed(),
+                sampled: self.sampled,
+            });
+        }
+    }
+}
+
+impl<TCtx> MiddlewareLike<TCtx> for SampledLogger
+where
+    TCtx: Send + 'static,
+{
+    type State = ();
+    type NewCtx = TCtx;
+
+    fn handle<TMiddleware: Layer<Self::NewCtx> + 'static>(
+        &self,
+        ctx: TCtx,
+        input: Value,
+        req: RequestContext,
+        next: Arc<TMiddleware>,
+    ) -> Result<LayerResult, ExecError> {
+        // The decision is made once, so a subscription's events are either all logged or none are
+        let entry = Entry {
+            kind: req.kind,
+            path: req.path.clone(),
+            params: req.params.clone(),
+            started: Instant::now(),
+            sampled: self.sampler.sample(&req),
+            log: self.log.clone(),
+        };
+
+        let result = match next.call(ctx, input, req) {
+            Ok(result) => result,
+            Err(err) => {
+                entry.log(Err(&err));
+                return Err(err);
+            }
+        };
+
+        Ok(LayerResult::FutureValueOrStream(Box::pin(async move {
+            match result.into_value_or_stream().await {
+                Ok(ValueOrStream::Value(value)) => {
+                    entry.log(Ok(&value));
+                    Ok(ValueOrStream::Value(value))
+                }
+                Ok(ValueOrStream::Stream(stream)) => Ok(ValueOrStream::Stream(Box::pin(
+                    stream.inspect(move |event| entry.log(event.as_ref())),
+                ))),
+                Err(err) => {
+                    entry.log(Err(&err));
+                    Err(err)
+                }
+            }
+        })))
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    };
+
+    use futures::{stream, StreamExt};
+
+    use crate::{Error, ErrorCode, ExecKind, Router, SampledLogger};
+
+    fn router(rate: f64, logs: Arc<Mutex<Vec<(String, bool)>>>) -> Router {
+        <Router>::new()
+            .middleware(move |_| {
+                let logs = logs.clone();
+                SampledLogger::new(rate, move |log| {
+                    logs.lock()
+                        .unwrap()
+                        .push((log.path.to_string(), log.result.is_ok()))
+                })
+            })
+            .query("ok", |t| t(|_, _: ()| "ok"))
+            .query("fail", |t| {
+                t(|_, _: ()| Err::<(), _>(Error::new(ErrorCode::BadRequest, "nope".into())))
+            })
+            .build()
+    }
+
+    #[tokio::test]
+    async fn test_sampled_logger_always_logs_errors() {
+        for (rate, ok_logged) in [(0.0, false), (1.0, true)] {
+            let logs = Arc::new(Mutex::new(Vec::new()));
+            let router = router(rate, logs.clone());
+            for key in ["ok", "fail", "ok", "fail"] {
+                let _ = router.exec((), ExecKind::Query, key.into(), None).await;
+            }
+
+            let logs = logs.lock().unwrap();
+            assert_eq!(
+                logs.iter()
+                    .filter(|(path, ok)| path == "fail" && !ok)
+                    .count(),
+                2
+            );
+            assert_eq!(
+                logs.iter().filter(|(path, ok)| path == "ok" && *ok).count(),
+                if ok_logged { 2 } else { 0 }
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sampled_logger_decides_once() {
+        let (samples, logged) = (Arc::new(AtomicUsize::new(0)), Arc::new(AtomicUsize::new(0)));
+        let router = <Router>::new()
+            .middleware({
+                let (samples, logged) = (samples.clone(), logged.clone());
+                move |_| {
+                    let samples = samples.clone();
+                    let logged = logged.clone();
+                    SampledLogger::new(0.0, move |_| {
+                        logged.fetch_add(1, Ordering::SeqCst);
+                    })
+                    .sampler(move |_: &_| samples.fetch_add(1, Ordering::SeqCst) == 0)
+                }
+            })
+            .subscription("count", |t| t(|_, _: ()| stream::iter(0..3)))
+            .build();
+
+        let events = router
+            .exec_subscription((), "count".into(), None)
+            .await
+            .unwrap()
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(events.len(), 3);
+        assert_eq!(samples.load(Ordering::SeqCst), 1);
+        assert_eq!(logged.load(Ordering::SeqCst), 3);
+    }
+}
@@ -0,0 +1,109 @@
+use serde_json::Value;
+
+use crate::{
+    internal::{Layer, LayerResult, RequestContext},
+    ExecError,
+};
+
+/// The field of the input object which declares it's schema version.
+pub(crate) const SCHEMA_VERSION_FIELD: &str = "schema_version";
+
+/// Rejects requests whose declared `schema_version` doesn't match the one the procedure expects.
+///
+/// This runs before the input is deserialized so an outdated client receives [`ExecError::VersionMismatch`] instead of a deserialization error.
+pub(crate) struct SchemaVersionLayer<TLayerCtx: 'static> {
+    pub next: Box<dyn Layer<TLayerCtx>>,
+    pub version: u32,
+}
+
+impl<TLayerCtx: 'static> SchemaVersionLayer<TLayerCtx> {
+    /// Wrap `next` if the procedure has a schema version.
+    pub fn wrap(
+        next: Box<dyn Layer<TLayerCtx>>,
+        version: Option<u32>,
+    ) -> Box<dyn Layer<TLayerCtx>> {
+        match version {
+            Some(version) => Box::new(Self { next, version }),
+            None => next,
+        }
+    }
+}
+
+impl<TLayerCtx: 'static> Layer<TLayerCtx> for SchemaVersionLayer<TLayerCtx> {
+    fn call(
+        &self,
+        ctx: TLayerCtx,
+        input: Value,
+        req: RequestContext,
+    ) -> Result<LayerResult, ExecError> {
+        let received = input
+            .get(SCHEMA_VERSION_FIELD)
+            .and_then(Value::as_u64)
+            .and_then(|v| u32::try_from(v).ok());
+        if received != Some(self.version) {
+            return Err(ExecError::VersionMismatch {
+                expected: self.version,
+                received,
+            });
+        }
+
+        self.next.call(ctx, input, req)
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use serde::Deserialize;
+    use serde_json::json;
+    use specta::Type;
+
+    use crate::{ErrorKind, ExecError, ExecKind, Router};
+
+    #[derive(Deserialize, Type)]
+    struct CreateUser {
+        name: String,
+        // Added without a version bump as older clients can omit it
+        #[serde(default)]
+        nickname: Option<String>,
+    }
+
+    #[tokio::test]
+    async fn test_schema_version() {
+        let router = <Router>::new()
+            .mutation("createUser", |t| {
+                t(|_, input: CreateUser| {
+                    format!("{}{}", input.name, input.nickname.unwrap_or_default())
+                })
+                .schema_version(2)
+            })
+            .build();
+
+        let exec = |input| router.exec((), ExecKind::Mutation, "createUser".into(), Some(input));
+
+        assert_eq!(
+            exec(json!({ "schema_version": 2, "name": "Monty" }))
+                .await
+                .unwrap(),
+            json!("Monty")
+        );
+
+        // The version is checked before the input is deserialized
+        let err = exec(json!({ "schema_version": 1, "username": "Monty" }))
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            ExecError::VersionMismatch {
+                expected: 2,
+                received: Some(1)
+            }
+        ));
+        assert_eq!(err.kind(), ErrorKind::Validation);
+
+        assert!(matches!(
+            exec(json!({ "name": "Monty" })).await,
+            Err(ExecError::VersionMismatch { received: None, .. })
+        ));
+    }
+}
@@ -0,0 +1,112 @@
+use std::{sync::Arc, time::Instant};
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::{
+    internal::{ProcedureKind, RequestContext},
+    ExecError,
+};
+
+use super::config::OnSerializeFn;
+
+tokio::task_local! {
+    static SERIALIZE_TIMER: SerializeTimer;
+}
+
+/// Reports the time spent serializing the results of a request to [`Config::on_serialize`](crate::Config::on_serialize).
+#[derive(Clone, Default)]
+pub(crate) struct SerializeTimer(Option<Arc<Inner>>);
+
+struct Inner {
+    hook: OnSerializeFn,
+    kind: ProcedureKind,
+    key: Arc<str>,
+}
+
+impl SerializeTimer {
+    pub(crate) fn new(hook: Option<&OnSerializeFn>, req: &RequestContext) -> Self {
+        Self(hook.map(|hook| {
+            Arc::new(Inner {
+                hook: hook.clone(),
+                kind: req.kind,
+                key: req.path.as_str().into(),
+            })
+        }))
+    }
+
+    /// The timer of the request currently being executed, Eg. to time the events of a subscription which are serialized on another task.
+    pub(crate) fn current() -> Self {
+        SERIALIZE_TIMER.try_with(Clone::clone).unwrap_or_default()
+    }
+
+    /// Run `f` with this as the current timer, so the results it serializes with [`to_value`] are timed.
+    pub(crate) fn scoped<T>(&self, f: impl FnOnce() -> T) -> T {
+        SERIALIZE_TIMER.sync_scope(self.clone(), f)
+    }
+
+    fn record(&self, started: Instant) {
+        if let Some(timer) = &self.0 {
+            (timer.hook)(timer.kind, &timer.key, started.elapsed());
+        }
+    }
+}
+
+/// Serialize a result (or subscription event) into a [`Value`], reporting the time it took to the current [`SerializeTimer`].
+pub(crate) fn to_value<T: Serialize + ?Sized>(value: &T) -> Result<Value, ExecError> {
+    let started = SERIALIZE_TIMER
+        .try_with(|timer| timer.0.is_some())
+        .unwrap_or_default()
+        .then(Instant::now);
+    let result = serde_json::to_value(value).map_err(ExecError::SerializingResultErr);
+    if let Some(started) = started {
+        SERIALIZE_TIMER.with(|timer| timer.record(started));
+    }
+    result
+}
+
+// The tests use the default runtime
+#[cfg(all(test, feature = "runtime-tokio"))]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use futures::StreamExt;
+
+    use crate::{internal::ProcedureKind, Config, ExecKind, Router};
+
+    #[tokio::test]
+    async fn test_on_serialize() {
+        let serialized = Arc::new(Mutex::new(Vec::new()));
+        let router = <Router>::new()
+            .config(Config::new().on_serialize({
+                let serialized = serialized.clone();
+                move |kind, key, _| serialized.lock().unwrap().push((kind, key.to_string()))
+            }))
+            .query("user", |t| t(|_, _: ()| async { "alice" }))
+            .subscription("ticks", |t| t(|_, _: ()| futures::stream::iter([1, 2])))
+            .subscription("concurrent", |t| {
+                t(|_, _: ()| futures::stream::iter([1, 2])).serialize_concurrently(2)
+            })
+            .build();
+
+        let result = router.exec((), ExecKind::Query, "user".into(), None).await;
+        assert_eq!(result.unwrap(), "alice");
+        for key in ["ticks", "concurrent"] {
+            let stream = router.exec_subscription((), key.into(), None).await;
+            assert_eq!(stream.unwrap().count().await, 2);
+        }
+
+        // Every event is recorded, even when it's serialized on another task
+        let serialized = serialized.lock().unwrap();
+        let count = |kind, key| {
+            (serialized.iter())
+                .filter(|serialized| **serialized == (kind, String::from(key)))
+                .count()
+        };
+        assert_eq!(count(ProcedureKind::Query, "user"), 1);
+        assert_eq!(count(ProcedureKind::Subscription, "ticks"), 2);
+        assert_eq!(count(ProcedureKind::Subscription, "concurrent"), 2);
+        assert_eq!(serialized.len(), 5);
+    }
+}
@@ -0,0 +1,115 @@
+use std::{sync::Arc, time::Duration};
+
+use serde_json::Value;
+
+use crate::{
+    internal::{Layer, LayerResult, Procedure, ProcedureStore, RequestContext},
+    ExecError,
+};
+
+/// A hook registered with [`Config::on_sla_breach`](crate::Config::on_sla_breach).
+pub(crate) type SlaBreachFn = Arc<dyn Fn(&str, Duration, Duration) + Send + Sync>;
+
+/// Time every procedure which has an SLA, calling `hook` when it's exceeded.
+pub(crate) fn sla_breaches<TCtx: 'static>(
+    hook: &SlaBreachFn,
+    mut procedures: ProcedureStore<TCtx>,
+) -> ProcedureStore<TCtx> {
+    procedures.store = std::mem::take(&mut procedures.store)
+        .into_iter()
+        .map(|(key, procedure)| {
+            let Some(sla) = procedure.ty.sla else {
+                return (key, procedure);
+            };
+            let exec = Box::new(SlaLayer {
+                next: procedure.exec,
+                key: key.as_str().into(),
+                sla,
+                hook: hook.clone(),
+            });
+            (
+                key,
+                Procedure {
+                    exec,
+                    ty: procedure.ty,
+                    visible: procedure.visible,
+                    typed: procedure.typed,
+                },
+            )
+        })
+        .collect();
+    procedures
+}
+
+struct SlaLayer<TCtx: 'static> {
+    next: Box<dyn Layer<TCtx>>,
+    key: Arc<str>,
+    sla: Duration,
+    hook: SlaBreachFn,
+}
+
+impl<TCtx: 'static> Layer<TCtx> for SlaLayer<TCtx> {
+    fn call(&self, ctx: TCtx, input: Value, req: RequestContext) -> Result<LayerResult, ExecError> {
+        let started = std::time::Instant::now();
+        let result = self.next.call(ctx, input, req)?;
+        let (key, sla, hook) = (self.key.clone(), self.sla, self.hook.clone());
+
+        Ok(LayerResult::FutureValueOrStream(Box::pin(async move {
+            // Failed requests count too, the SLA is about the latency the client sees
+            let result = result.into_value_or_stream().await;
+            let latency = started.elapsed();
+            if latency > sla {
+                hook(&key, sla, latency);
+            }
+            result
+        })))
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use std::{
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    use crate::{Config, ExecKind, Router};
+
+    #[tokio::test]
+    async fn test_sla_breach() {
+        let breaches = Arc::new(Mutex::new(Vec::new()));
+        let router = <Router>::new()
+            .config(Config::new().on_sla_breach({
+                let breaches = breaches.clone();
+                move |key, sla, latency| {
+                    breaches
+                        .lock()
+                        .unwrap()
+                        .push((key.to_string(), sla, latency))
+                }
+            }))
+            .query("slow", |t| {
+                t(|_, _: ()| async {
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                    "done"
+                })
+                .sla(Duration::from_millis(10))
+            })
+            .query("fast", |t| {
+                t(|_, _: ()| "done").sla(Duration::from_secs(10))
+            })
+            .build();
+
+        // The request still completes
+        for key in ["slow", "fast"] {
+            let result = router.exec((), ExecKind::Query, key.into(), None).await;
+            assert_eq!(result.unwrap(), "done");
+        }
+        assert!(matches!(
+            &breaches.lock().unwrap()[..],
+            [(key, sla, latency)]
+                if key == "slow" && *sla == Duration::from_millis(10) && *latency >= Duration::from_millis(50)
+        ));
+    }
+}
@@ -0,0 +1,98 @@
+use std::{pin::Pin, task::Poll};
+
+use futures::{stream, Stream, StreamExt};
+use serde::Serialize;
+use specta::Type;
+
+use crate::internal::jsonrpc::send_caught_up;
+
+pub(crate) type SnapshotStream<TSnapshot, TUpdate> =
+    Pin<Box<dyn Stream<Item = SnapshotEvent<TSnapshot, TUpdate>> + Send + Sync>>;
+
+// Boxed so the resolver's type doesn't depend on the builder it's created from
+pub(crate) type SnapshotResolver<TCtx, TArg, TSnapshot, TUpdate> =
+    Box<dyn Fn(TCtx, TArg) -> SnapshotStream<TSnapshot, TUpdate> + Send + Sync>;
+
+/// An event of a subscription built with [`snapshot_then_stream`](crate::internal::UnbuiltProcedureBuilder::snapshot_then_stream).
+///
+/// The first event of the subscription is always a `snapshot` of the current state and every following event is an `update` to it. They're sent as:
+///
+/// ```json
+/// { "type": "snapshot", "data": <snapshot> }
+/// { "type": "update", "data": <update> }
+/// ```
+///
+/// A `caughtUp` frame is sent between the snapshot and the first update, over transports which support multiple frames per request (Eg. WebSocket).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Type)]
+#[serde(tag = "type", content = "data", rename_all = "camelCase")]
+pub enum SnapshotEvent<TSnapshot, TUpdate> {
+    Snapshot(TSnapshot),
+    Update(TUpdate),
+}
+
+pub(crate) fn snapshot_then_stream<TSnapshot, TUpdate>(
+    snapshot: TSnapshot,
+    updates: impl Stream<Item = TUpdate> + Send + Sync + 'static,
+) -> SnapshotStream<TSnapshot, TUpdate>
+where
+    TSnapshot: Send + Sync + 'static,
+    TUpdate: Send + Sync + 'static,
+{
+    let mut caught_up = false;
+    Box::pin(
+        stream::iter([SnapshotEvent::Snapshot(snapshot)])
+            .chain(stream::poll_fn(move |cx| {
+                if !caught_up {
+                    caught_up = true;
+                    if send_caught_up(cx) {
+                        return Poll::Pending;
+                    }
+                }
+                Poll::Ready(None)
+            }))
+            .chain(updates.map(SnapshotEvent::Update)),
+    )
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use futures::{stream, StreamExt};
+    use serde_json::json;
+
+    use crate::Router;
+
+    #[tokio::test]
+    async fn test_snapshot_then_stream() {
+        let router = <Router>::new()
+            .subscription("counter", |t| {
+                t.snapshot_then_stream(
+                    |_, start: u32| format!("count is {start}"),
+                    |_, start: u32| stream::iter([start + 1, start + 2]),
+                )
+            })
+            .build();
+
+        let bindings = router.ts_bindings().unwrap();
+        assert!(bindings.contains(
+            r#"{ key: "counter", input: number, result: SnapshotEvent<string, number> }"#
+        ));
+        assert!(bindings.contains("export type SnapshotEvent<TSnapshot, TUpdate>"));
+
+        let events = router
+            .exec_subscription((), "counter".into(), Some(json!(5)))
+            .await
+            .unwrap()
+            .map(|event| event.unwrap())
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(
+            events,
+            [
+                json!({ "type": "snapshot", "data": "count is 5" }),
+                json!({ "type": "update", "data": 6 }),
+                json!({ "type": "update", "data": 7 }),
+            ]
+        );
+    }
+}
@@ -0,0 +1,262 @@
+use std::{
+    fmt,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures::{stream, Stream, StreamExt};
+use serde::Serialize;
+use specta::Type;
+
+use crate::{merge_streams, Error, ErrorCode, MergeOrder};
+
+type BoxedEvents<TSnapshot, TUpdate> =
+    Pin<Box<dyn Stream<Item = MergedEvent<TSnapshot, TUpdate>> + Send + Sync>>;
+
+type SnapshotFuture<TSnapshot, TUpdate> =
+    Pin<Box<dyn Future<Output = MergedEvent<TSnapshot, TUpdate>> + Send + Sync>>;
+
+/// An event of a [`SnapshotMerge`] subscription. They're sent as:
+///
+/// ```json
+/// { "type": "snapshot", "data": <snapshot> }
+/// { "type": "live" }
+/// { "type": "update", "data": <update> }
+/// { "type": "sourceError", "data": { "source": "<name>", "code": "<code>", "message": "<message>" } }
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Type)]
+#[serde(tag = "type", content = "data", rename_all = "camelCase")]
+pub enum MergedEvent<TSnapshot, TUpdate> {
+    /// The result of one of the snapshot sources.
+    Snapshot(TSnapshot),
+    /// Sent once every snapshot source has been sent, before the first update.
+    Live,
+    /// An item of one of the live sources.
+    Update(TUpdate),
+    /// One of the sources failed. The other sources aren't affected.
+    SourceError(SourceError),
+}
+
+/// The failure of a single source of a [`SnapshotMerge`].
+#[derive(Debug, Clone, PartialEq, Serialize, Type)]
+pub struct SourceError {
+    pub source: &'static str,
+    pub code: ErrorCode,
+    pub message: String,
+}
+
+impl SourceError {
+    fn new(source: &'static str, error: Error) -> Self {
+        Self {
+            source,
+            code: error.code,
+            message: error.message,
+        }
+    }
+}
+
+/// A subscription which sends an initial state loaded from several snapshot sources (Eg. the queries making up a dashboard), then the updates of several live sources merged into one stream.
+///
+/// ## Ordering
+///
+/// The snapshot sources are loaded concurrently and sent in the order they were added, each as a [`MergedEvent::Snapshot`]. Once all of them have been sent a single [`MergedEvent::Live`] is sent, followed by the [`MergedEvent::Update`]s of every live source as soon as they're ready.
+/// The live sources aren't polled until then, so an update produced while the snapshots load is held back by it's source (Eg. a channel), not lost. An update may also be reflected in a snapshot, so updates should be idempotent.
+///
+/// ## Failures
+///
+/// A snapshot source or live item which fails is sent as a [`MergedEvent::SourceError`] with the name of the source, and every other source continues as usual. A live source keeps being polled after it yields an error.
+/// The subscription ends once every live source has ended.
+///
+/// The exported type of the subscription is `MergedEvent<TSnapshot, TUpdate>`. Use an enum for `TSnapshot` or `TUpdate` to combine sources of different types.
+///
+/// ```rust
+/// use futures::stream;
+/// use rspc::SnapshotMerge;
+///
+/// #[derive(serde::Serialize, specta::Type)]
+/// enum Snapshot { Users(Vec<String>), Revenue(u32) }
+///
+/// let router = <rspc::Router>::new()
+///     .subscription("dashboard", |t| {
+///         t(|_, _: ()| {
+///             SnapshotMerge::new()
+///                 .snapshot("users", async { Ok(Snapshot::Users(vec!["Oscar".into()])) })
+///                 .snapshot("revenue", async { Ok(Snapshot::Revenue(42)) })
+///                 .live("signups", stream::iter([Ok("Brendan".to_string())]))
+///         })
+///     })
+///     .build();
+/// ```
+pub struct SnapshotMerge<TSnapshot, TUpdate> {
+    snapshots: Vec<SnapshotFuture<TSnapshot, TUpdate>>,
+    live: Vec<BoxedEvents<TSnapshot, TUpdate>>,
+    // Built from the sources when the subscription is first polled
+    stream: Option<BoxedEvents<TSnapshot, TUpdate>>,
+}
+
+impl<TSnapshot, TUpdate> Default for SnapshotMerge<TSnapshot, TUpdate> {
+    fn default() -> Self {
+        Self {
+            snapshots: Vec::new(),
+            live: Vec::new(),
+            stream: None,
+        }
+    }
+}
+
+impl<TSnapshot, TUpdate> fmt::Debug for SnapshotMerge<TSnapshot, TUpdate> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SnapshotMerge")
+            .field("snapshots", &self.snapshots.len())
+            .field("live", &self.live.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<TSnapshot, TUpdate> SnapshotMerge<TSnapshot, TUpdate>
+where
+    TSnapshot: Send + Sync + 'static,
+    TUpdate: Send + Sync + 'static,
+{
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a source of the initial state. `name` identifies it in the [`MergedEvent::SourceError`] sent if it fails.
+    pub fn snapshot(
+        mut self,
+        name: &'static str,
+        snapshot: impl Future<Output = Result<TSnapshot, Error>> + Send + Sync + 'static,
+    ) -> Self {
+        self.snapshots.push(Box::pin(async move {
+            match snapshot.await {
+                Ok(snapshot) => MergedEvent::Snapshot(snapshot),
+                Err(err) => MergedEvent::SourceError(SourceError::new(name, err)),
+            }
+        }));
+        self
+    }
+
+    /// Add a source of updates. `name` identifies it in the [`MergedEvent::SourceError`] sent for each error it yields.
+    pub fn live(
+        mut self,
+        name: &'static str,
+        updates: impl Stream<Item = Result<TUpdate, Error>> + Send + Sync + 'static,
+    ) -> Self {
+        self.live
+            .push(Box::pin(updates.map(move |update| match update {
+                Ok(update) => MergedEvent::Update(update),
+                Err(err) => MergedEvent::SourceError(SourceError::new(name, err)),
+            })));
+        self
+    }
+}
+
+impl<TSnapshot, TUpdate> Stream for SnapshotMerge<TSnapshot, TUpdate>
+where
+    TSnapshot: Send + Sync + 'static,
+    TUpdate: Send + Sync + 'static,
+{
+    type Item = MergedEvent<TSnapshot, TUpdate>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        let stream = this.stream.get_or_insert_with(|| {
+            let snapshots = std::mem::take(&mut this.snapshots);
+            let concurrency = snapshots.len().max(1);
+            Box::pin(
+                stream::iter(snapshots)
+                    .buffered(concurrency)
+                    .chain(stream::iter([MergedEvent::Live]))
+                    .chain(merge_streams(
+                        MergeOrder::Unordered,
+                        std::mem::take(&mut this.live),
+                    )),
+            )
+        });
+        stream.as_mut().poll_next(cx)
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use futures::{stream, StreamExt};
+    use serde::Serialize;
+    use serde_json::json;
+    use specta::Type;
+    use tokio::sync::oneshot;
+
+    use crate::{Error, ErrorCode, Router, SnapshotMerge};
+
+    #[derive(Serialize, Type)]
+    #[serde(tag = "source", content = "value", rename_all = "camelCase")]
+    enum Snapshot {
+        Users(Vec<String>),
+        Revenue(u32),
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_merge() {
+        let router = <Router>::new()
+            .subscription("dashboard", |t| {
+                t(|_, _: ()| {
+                    // The users load last but are still sent first
+                    let (users_tx, users_rx) = oneshot::channel();
+                    SnapshotMerge::new()
+                        .snapshot("users", async move {
+                            Ok(Snapshot::Users(users_rx.await.unwrap()))
+                        })
+                        .snapshot("revenue", async move {
+                            users_tx.send(vec!["Oscar".to_string()]).unwrap();
+                            Ok(Snapshot::Revenue(42))
+                        })
+                        .snapshot("orders", async {
+                            Err(Error::new(ErrorCode::Timeout, "orders timed out".into()))
+                        })
+                        .live("signups", stream::iter([Ok(1), Ok(2)]))
+                        .live(
+                            "payments",
+                            stream::iter([Err(Error::new(
+                                ErrorCode::InternalServerError,
+                                "payments unavailable".into(),
+                            ))]),
+                        )
+                })
+            })
+            .build();
+
+        let bindings = router.ts_bindings().unwrap();
+        assert!(bindings.contains(
+            r#"{ key: "dashboard", input: never, result: MergedEvent<Snapshot, number> }"#
+        ));
+
+        let mut events = router
+            .exec_subscription((), "dashboard".into(), None)
+            .await
+            .unwrap()
+            .map(|event| event.unwrap())
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(
+            events.drain(..4).collect::<Vec<_>>(),
+            [
+                json!({ "type": "snapshot", "data": { "source": "users", "value": ["Oscar"] } }),
+                json!({ "type": "snapshot", "data": { "source": "revenue", "value": 42 } }),
+                json!({ "type": "sourceError", "data": { "source": "orders", "code": "Timeout", "message": "orders timed out" } }),
+                json!({ "type": "live" }),
+            ]
+        );
+        // The live sources are merged as their items are ready
+        events.sort_by_key(|event| event.to_string());
+        assert_eq!(
+            events,
+            [
+                json!({ "type": "update", "data": 1 }),
+                json!({ "type": "update", "data": 2 }),
+                json!({ "type": "sourceError", "data": { "source": "payments", "code": "InternalServerError", "message": "payments unavailable" } }),
+            ]
+        );
+    }
+}
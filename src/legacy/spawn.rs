@@ -0,0 +1,93 @@
+use std::{pin::Pin, sync::Arc};
+
+use futures::{stream, Stream, StreamExt};
+use serde_json::Value;
+use tokio::sync::mpsc;
+
+use crate::{ExecError, Runtime};
+
+type Items = Pin<Box<dyn Stream<Item = Result<Value, ExecError>> + Send>>;
+
+/// The number of items a [spawned](crate::internal::BuiltProcedureBuilder::spawn) subscription can get ahead of the transport before it's task stops polling the stream.
+pub(crate) const SPAWN_BUFFER: usize = 16;
+
+/// Poll `stream` on it's own task, forwarding it's items through a bounded channel. See [`BuiltProcedureBuilder::spawn`](crate::internal::BuiltProcedureBuilder::spawn).
+pub(crate) fn spawn_stream(mut stream: Items, runtime: Option<Arc<dyn Runtime>>) -> Items {
+    let Some(runtime) = runtime else {
+        return Box::pin(stream::once(async { Err(ExecError::NoRuntime) }));
+    };
+
+    let (tx, mut rx) = mpsc::channel(SPAWN_BUFFER);
+    runtime.spawn(Box::pin(async move {
+        loop {
+            // The task stops as soon as the subscription is dropped, even if the stream is idle
+            let item = tokio::select! {
+                item = stream.next() => item,
+                _ = tx.closed() => return,
+            };
+            let Some(item) = item else { return };
+            if tx.send(item).await.is_err() {
+                return;
+            }
+        }
+    }));
+    Box::pin(stream::poll_fn(move |cx| rx.poll_recv(cx)))
+}
+
+// The tests use the default runtime
+#[cfg(all(test, feature = "runtime-tokio"))]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use futures::{stream, StreamExt};
+    use serde_json::json;
+
+    use super::SPAWN_BUFFER;
+    use crate::Router;
+
+    #[tokio::test]
+    async fn test_spawn() {
+        let polled = Arc::new(AtomicUsize::new(0));
+        let router = <Router>::new()
+            .subscription("numbers", {
+                let polled = polled.clone();
+                move |t| {
+                    let polled = polled.clone();
+                    t(move |_, _: ()| {
+                        let polled = polled.clone();
+                        stream::iter(0..).map(move |i: u32| {
+                            polled.fetch_add(1, Ordering::Relaxed);
+                            i
+                        })
+                    })
+                    .spawn()
+                }
+            })
+            .build();
+
+        let mut stream = router
+            .exec_subscription((), "numbers".into(), None)
+            .await
+            .unwrap();
+        assert_eq!(stream.next().await.unwrap().unwrap(), json!(0));
+        assert_eq!(stream.next().await.unwrap().unwrap(), json!(1));
+
+        // The task stops polling once the buffer is full
+        for _ in 0..20 {
+            tokio::task::yield_now().await;
+        }
+        let ahead = polled.load(Ordering::Relaxed);
+        assert!(ahead <= 2 + SPAWN_BUFFER + 1, "polled {ahead} items");
+
+        // and stops completely once the subscription is dropped
+        drop(stream);
+        for _ in 0..20 {
+            tokio::task::yield_now().await;
+        }
+        assert_eq!(polled.load(Ordering::Relaxed), ahead);
+    }
+}
@@ -0,0 +1,505 @@
+use std::{
+    any::Any,
+    cell::RefCell,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use futures::{future::BoxFuture, ready, Stream};
+use serde_json::Value;
+use specta::{DataType, TypeMap};
+
+use crate::{
+    internal::jsonrpc::{frame_sink, ResponseInner},
+    ExecError, Runtime,
+};
+
+tokio::task_local! {
+    // The cleanups of the subscriptions dropped within `unsubscribe`, so they can be awaited
+    static CLEANUPS: RefCell<Vec<BoxFuture<'static, ()>>>;
+}
+
+/// A hook registered with `.on_subscribe`.
+pub(crate) type OnSubscribeFn<TCtx, TArg> = Arc<dyn Fn(&TCtx, &TArg) + Send + Sync>;
+
+/// An `.on_unsubscribe` hook bound to a single subscription. It returns the future doing the cleanup.
+pub(crate) type UnsubscribeFn = Box<dyn FnOnce() -> BoxFuture<'static, ()> + Send>;
+
+/// A hook registered with `.on_unsubscribe`. It's called when the subscription starts to capture the context and input for when it ends.
+pub(crate) type OnUnsubscribeFn<TCtx, TArg> =
+    Arc<dyn Fn(&TCtx, &TArg) -> UnsubscribeFn + Send + Sync>;
+
+/// An `.on_complete` hook bound to a single subscription. It returns the serialized trailer (if any).
+pub(crate) type CompleteFn = Box<dyn FnOnce() -> Option<Result<Value, ExecError>> + Send>;
+
+/// A hook registered with `.on_complete`. It's called when the subscription starts to capture the context for when it completes.
+pub(crate) type OnCompleteFn<TCtx> = Arc<dyn Fn(&TCtx) -> CompleteFn + Send + Sync>;
+
+/// A type erased [`OnSubscribeFn`], [`OnUnsubscribeFn`] or [`OnCompleteFn`]. It's context and argument types are the ones the procedure's resolver receives.
+pub(crate) type AnyHookFn = Arc<dyn Any + Send + Sync>;
+
+/// An `.on_complete` hook along with the type of the trailer it produces.
+#[derive(Clone)]
+pub(crate) struct OnComplete {
+    pub hook: AnyHookFn,
+    pub typedef: fn(&mut TypeMap) -> DataType,
+}
+
+/// The lifecycle hooks of a single subscription procedure.
+pub(crate) struct SubscriptionHooks<TCtx, TArg> {
+    on_subscribe: Option<OnSubscribeFn<TCtx, TArg>>,
+    on_unsubscribe: Option<OnUnsubscribeFn<TCtx, TArg>>,
+    on_complete: Option<OnCompleteFn<TCtx>>,
+}
+
+impl<TCtx: 'static, TArg: 'static> SubscriptionHooks<TCtx, TArg> {
+    pub fn new(
+        on_subscribe: Option<AnyHookFn>,
+        on_unsubscribe: Option<AnyHookFn>,
+        on_complete: Option<AnyHookFn>,
+    ) -> Self {
+        // These are guaranteed by the bounds on `BuiltProcedureBuilder::on_subscribe`, `BuiltProcedureBuilder::on_unsubscribe` and `BuiltProcedureBuilder::on_complete`
+        Self {
+            on_subscribe: on_subscribe.map(|f| {
+                f.downcast_ref::<OnSubscribeFn<TCtx, TArg>>()
+                    .expect("rspc: subscription hook type mismatch")
+                    .clone()
+            }),
+            on_unsubscribe: on_unsubscribe.map(|f| {
+                f.downcast_ref::<OnUnsubscribeFn<TCtx, TArg>>()
+                    .expect("rspc: subscription hook type mismatch")
+                    .clone()
+            }),
+            on_complete: on_complete.map(|f| {
+                f.downcast_ref::<OnCompleteFn<TCtx>>()
+                    .expect("rspc: subscription hook type mismatch")
+                    .clone()
+            }),
+        }
+    }
+
+    /// Run the `on_subscribe` hook and return the `on_unsubscribe` hook, bound to this subscription.
+    pub fn start(&self, ctx: &TCtx, arg: &TArg) -> Option<UnsubscribeFn> {
+        if let Some(on_subscribe) = &self.on_subscribe {
+            on_subscribe(ctx, arg);
+        }
+        self.on_unsubscribe
+            .as_ref()
+            .map(|on_unsubscribe| on_unsubscribe(ctx, arg))
+    }
+
+    /// Get the `on_complete` hook, bound to this subscription.
+    pub fn complete(&self, ctx: &TCtx) -> Option<CompleteFn> {
+        self.on_complete
+            .as_ref()
+            .map(|on_complete| on_complete(ctx))
+    }
+}
+
+/// A stream which runs it's `on_unsubscribe` hook when dropped.
+///
+/// Every way a subscription can end (the stream completing, the client stopping it or disconnecting) drops the stream, so the hook runs exactly once.
+/// The cleanup is bounded by `timeout`. When the stream is dropped by [`unsubscribe`] it's awaited there, otherwise it's spawned onto the runtime.
+pub(crate) struct Unsubscribe<S> {
+    pub stream: S,
+    pub on_unsubscribe: Option<UnsubscribeFn>,
+    pub runtime: Arc<dyn Runtime>,
+    pub timeout: Duration,
+}
+
+impl<S: Stream + Unpin> Stream for Unsubscribe<S> {
+    type Item = S::Item;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.stream).poll_next(cx)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.stream.size_hint()
+    }
+}
+
+impl<S> Drop for Unsubscribe<S> {
+    fn drop(&mut self) {
+        let Some(on_unsubscribe) = self.on_unsubscribe.take() else {
+            return;
+        };
+        let cleanup = on_unsubscribe();
+        let (runtime, timeout) = (self.runtime.clone(), self.timeout);
+        let mut cleanup: Option<BoxFuture<'static, ()>> = Some(Box::pin(async move {
+            tokio::select! {
+                biased;
+                _ = cleanup => {}
+                _ = runtime.sleep(timeout) => {
+                    #[cfg(feature = "tracing")]
+                    tracing::warn!("Subscription cleanup didn't finish within {:?}, dropping it", timeout);
+                }
+            }
+        }));
+
+        let _ = CLEANUPS.try_with(|cleanups| cleanups.borrow_mut().extend(cleanup.take()));
+        if let Some(cleanup) = cleanup {
+            self.runtime.spawn(cleanup);
+        }
+    }
+}
+
+/// Drop `stream` and wait for the `on_unsubscribe` hooks of the subscriptions in it to finish (or time out).
+pub(crate) async fn unsubscribe<S>(stream: S) {
+    let cleanups = CLEANUPS.sync_scope(Default::default(), || {
+        drop(stream);
+        CLEANUPS.with(RefCell::take)
+    });
+    futures::future::join_all(cleanups).await;
+}
+
+/// A stream which sends the trailer produced by it's `on_complete` hook once it completes.
+///
+/// The hook only runs if the stream ends by itself without yielding an error. A subscription which is stopped or disconnected is dropped before it completes, so it never runs.
+pub(crate) struct Complete<S> {
+    pub stream: S,
+    pub on_complete: Option<CompleteFn>,
+}
+
+impl<S: Stream<Item = Result<Value, ExecError>> + Unpin> Stream for Complete<S> {
+    type Item = S::Item;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let item = ready!(Pin::new(&mut self.stream).poll_next(cx));
+        match &item {
+            Some(Ok(_)) => {}
+            Some(Err(_)) => self.on_complete = None,
+            None => match self
+                .on_complete
+                .take()
+                .and_then(|on_complete| on_complete())
+            {
+                Some(Ok(trailer)) => {
+                    if let Some(sink) = frame_sink() {
+                        let _ = sink.send(ResponseInner::Trailer(trailer));
+                    } else {
+                        #[cfg(feature = "tracing")]
+                        tracing::warn!(
+                            "Dropping subscription trailer as the transport doesn't support it"
+                        );
+                    }
+                }
+                Some(Err(err)) => return Poll::Ready(Some(Err(err))),
+                None => {}
+            },
+        }
+        Poll::Ready(item)
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+// Some of the tests use the default runtime, so their helpers are unused without it
+#[cfg_attr(not(feature = "runtime-tokio"), allow(unused))]
+mod tests {
+    use std::{
+        collections::HashMap,
+        pin::Pin,
+        sync::{Arc, Mutex as StdMutex},
+        time::{Duration, Instant},
+    };
+
+    use futures::{stream, Stream};
+    use serde::{ser::Error as _, Serialize, Serializer};
+    use specta::Type;
+    use tokio::sync::{mpsc, oneshot, Mutex};
+
+    use serde_json::json;
+
+    use crate::{
+        internal::jsonrpc::{
+            self, handle_json_rpc, RequestId, ResponseInner, Sender, SubscriptionMap,
+        },
+        Router,
+    };
+
+    // `None` fails to serialize
+    #[derive(Type)]
+    struct Item(Option<i32>);
+
+    impl Serialize for Item {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            match self.0 {
+                Some(v) => v.serialize(serializer),
+                None => Err(S::Error::custom("failed to serialize")),
+            }
+        }
+    }
+
+    type ItemStream = Pin<Box<dyn Stream<Item = Item> + Send + Sync>>;
+
+    async fn request(
+        router: &Arc<Router<u32>>,
+        subscriptions: &Mutex<HashMap<RequestId, oneshot::Sender<()>>>,
+        ctx: u32,
+        inner: jsonrpc::RequestInner,
+    ) {
+        let (mut tx, _rx) = mpsc::unbounded_channel();
+        handle_json_rpc(
+            ctx,
+            jsonrpc::Request {
+                jsonrpc: None,
+                id: RequestId::Null,
+                inner,
+            },
+            router,
+            &mut Sender::ResponseChannel(&mut tx),
+            &mut SubscriptionMap::Mutex(subscriptions),
+        )
+        .await
+    }
+
+    async fn settle() {
+        for _ in 0..10 {
+            tokio::task::yield_now().await;
+        }
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "runtime-tokio")]
+    async fn test_subscription_hooks() {
+        let subscribed = Arc::new(StdMutex::new(Vec::new()));
+        let unsubscribed = Arc::new(StdMutex::new(Vec::new()));
+        let router = Arc::new(
+            <Router<u32>>::new()
+                .subscription("watch", {
+                    let (subscribed, unsubscribed) = (subscribed.clone(), unsubscribed.clone());
+                    move |t| {
+                        let (subscribed, unsubscribed) = (subscribed.clone(), unsubscribed.clone());
+                        t(|_, input: String| -> ItemStream {
+                            match input.as_str() {
+                                "complete" => Box::pin(stream::iter([Item(Some(1))])),
+                                "error" => Box::pin(stream::iter([Item(None)])),
+                                _ => Box::pin(stream::pending()),
+                            }
+                        })
+                        .on_subscribe(move |ctx, input| {
+                            subscribed.lock().unwrap().push((*ctx, input.clone()))
+                        })
+                        .on_unsubscribe(move |ctx, input| {
+                            let unsubscribed = unsubscribed.clone();
+                            async move { unsubscribed.lock().unwrap().push((ctx, input)) }
+                        })
+                    }
+                })
+                .build(),
+        );
+
+        let subscriptions = Mutex::new(HashMap::new());
+        for (id, input) in [
+            (1, "complete"),
+            (2, "error"),
+            (3, "stop"),
+            (4, "disconnect"),
+        ] {
+            request(
+                &router,
+                &subscriptions,
+                id,
+                jsonrpc::RequestInner::Subscription {
+                    path: "watch".into(),
+                    input: (RequestId::Number(id), Some(input.into())),
+                },
+            )
+            .await;
+        }
+        settle().await;
+        assert_eq!(subscribed.lock().unwrap().len(), 4);
+        assert_eq!(
+            *unsubscribed.lock().unwrap(),
+            [(1, "complete".into()), (2, "error".into())]
+        );
+
+        request(
+            &router,
+            &subscriptions,
+            3,
+            jsonrpc::RequestInner::SubscriptionStop {
+                input: RequestId::Number(3),
+            },
+        )
+        .await;
+        settle().await;
+        assert_eq!(unsubscribed.lock().unwrap().len(), 3);
+
+        // Dropping the connection's subscriptions stops the rest
+        subscriptions.lock().await.clear();
+        settle().await;
+        assert_eq!(unsubscribed.lock().unwrap()[3], (4, "disconnect".into()));
+        assert_eq!(*subscribed.lock().unwrap(), *unsubscribed.lock().unwrap());
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "runtime-tokio")]
+    async fn test_unsubscribe_timeout() {
+        let cleaned_up = Arc::new(StdMutex::new(Vec::new()));
+        let router = Arc::new(
+            <Router<u32>>::new()
+                .config(crate::Config::new().unsubscribe_timeout(Duration::from_millis(50)))
+                .subscription("watch", {
+                    let cleaned_up = cleaned_up.clone();
+                    move |t| {
+                        let cleaned_up = cleaned_up.clone();
+                        t(|_, _: String| stream::pending::<i32>()).on_unsubscribe(
+                            move |_, input: String| {
+                                let cleaned_up = cleaned_up.clone();
+                                async move {
+                                    if input == "slow" {
+                                        std::future::pending::<()>().await;
+                                    }
+                                    cleaned_up.lock().unwrap().push(input);
+                                }
+                            },
+                        )
+                    }
+                })
+                .build(),
+        );
+
+        let (mut tx, mut rx) = mpsc::unbounded_channel();
+        let subscriptions = Mutex::new(HashMap::new());
+        for (id, input) in [(1, "fast"), (2, "slow")] {
+            handle_json_rpc(
+                0,
+                jsonrpc::Request {
+                    jsonrpc: None,
+                    id: RequestId::Null,
+                    inner: jsonrpc::RequestInner::Subscription {
+                        path: "watch".into(),
+                        input: (RequestId::Number(id), Some(input.into())),
+                    },
+                },
+                &router,
+                &mut Sender::ResponseChannel(&mut tx),
+                &mut SubscriptionMap::Mutex(&subscriptions),
+            )
+            .await;
+        }
+        settle().await;
+
+        let started = Instant::now();
+        for id in [1, 2] {
+            handle_json_rpc(
+                0,
+                jsonrpc::Request {
+                    jsonrpc: None,
+                    id: RequestId::Null,
+                    inner: jsonrpc::RequestInner::SubscriptionStop {
+                        input: RequestId::Number(id),
+                    },
+                },
+                &router,
+                &mut Sender::ResponseChannel(&mut tx),
+                &mut SubscriptionMap::Mutex(&subscriptions),
+            )
+            .await;
+        }
+        drop(tx);
+
+        // Both stops are acknowledged, the slow one once it's cleanup timed out
+        let mut cancelled = Vec::new();
+        while let Some(resp) = rx.recv().await {
+            if matches!(resp.result, ResponseInner::Cancelled) {
+                cancelled.push(resp.id);
+            }
+        }
+        assert_eq!(cancelled, [RequestId::Number(1), RequestId::Number(2)]);
+        assert!(started.elapsed() >= Duration::from_millis(50));
+        assert_eq!(*cleaned_up.lock().unwrap(), ["fast"]);
+    }
+
+    #[derive(Serialize, Type)]
+    struct Summary {
+        item_count: u32,
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "runtime-tokio")]
+    async fn test_on_complete() {
+        let router = Arc::new(
+            <Router<u32>>::new()
+                .config(crate::Config::new().rename_fields(crate::RenameRule::CamelCase))
+                .subscription("watch", |t| {
+                    t(|_, input: String| -> ItemStream {
+                        match input.as_str() {
+                            "complete" => Box::pin(stream::iter([Item(Some(1)), Item(Some(2))])),
+                            "error" => Box::pin(stream::iter([Item(None)])),
+                            _ => Box::pin(stream::pending()),
+                        }
+                    })
+                    .on_complete(|ctx: u32| Some(Summary { item_count: ctx }))
+                })
+                .build(),
+        );
+
+        let (mut tx, mut rx) = mpsc::unbounded_channel();
+        let subscriptions = Mutex::new(HashMap::new());
+        for (id, input) in [(1, "complete"), (2, "error"), (3, "stop")] {
+            handle_json_rpc(
+                2,
+                jsonrpc::Request {
+                    jsonrpc: None,
+                    id: RequestId::Null,
+                    inner: jsonrpc::RequestInner::Subscription {
+                        path: "watch".into(),
+                        input: (RequestId::Number(id), Some(input.into())),
+                    },
+                },
+                &router,
+                &mut Sender::ResponseChannel(&mut tx),
+                &mut SubscriptionMap::Mutex(&subscriptions),
+            )
+            .await;
+        }
+        settle().await;
+        subscriptions.lock().await.clear();
+        settle().await;
+        drop(tx);
+
+        let mut frames = HashMap::<_, Vec<_>>::new();
+        while let Some(resp) = rx.recv().await {
+            frames.entry(resp.id).or_default().push(resp.result);
+        }
+        // Only the subscription which completed cleanly sends a trailer, after it's events
+        assert!(matches!(
+            &frames[&RequestId::Number(1)][..],
+            [
+                ResponseInner::Event(_),
+                ResponseInner::Event(_),
+                ResponseInner::Trailer(trailer),
+                ResponseInner::Complete,
+            ] if *trailer == json!({ "itemCount": 2 })
+        ));
+        assert!(matches!(
+            &frames[&RequestId::Number(2)][..],
+            [ResponseInner::Complete]
+        ));
+        // The stopped subscription is never completed
+        assert!(!frames.contains_key(&RequestId::Number(3)));
+
+        let bindings = std::env::temp_dir().join("rspc_test_on_complete.ts");
+        router.export_ts(&bindings).unwrap();
+        let bindings = std::fs::read_to_string(&bindings).unwrap();
+        assert!(bindings.contains("trailer: Summary"));
+        assert!(bindings.contains("itemCount: number"));
+    }
+
+    #[test]
+    #[cfg(not(feature = "runtime-tokio"))]
+    #[should_panic = "has an on_unsubscribe hook but no runtime"]
+    fn test_on_unsubscribe_requires_runtime() {
+        // Without a runtime the cleanup couldn't be timed out or run after a plain drop
+        <Router>::new()
+            .subscription("watch", |t| {
+                t(|_, _: ()| stream::pending::<i32>()).on_unsubscribe(|_, _| async {})
+            })
+            .build();
+    }
+}
@@ -0,0 +1,261 @@
+use std::sync::Arc;
+
+use crate::{
+    internal::{jsonrpc::headers, RequestContext},
+    Sampler,
+};
+
+use super::sampled_logger::random_u64;
+
+tokio::task_local! {
+    static TRACE_CONTEXT: TraceContext;
+}
+
+/// The `traceparent` header to send with calls made to downstream services while executing the current request, so they're part of the same distributed trace.
+///
+/// It's a [W3C Trace Context](https://www.w3.org/TR/trace-context/#traceparent-header) of the request's trace id, a span id for the request and it's sampling decision, which is made once per request:
+///  - A request with a valid `traceparent` header keeps it's trace id and sampled flag, so the decision of the service which started the trace is honored.
+///  - A request made from within another request (Eg. [`Router::exec`](crate::Router::exec) from a resolver) inherits the trace of the outer request.
+///  - Any other request starts a new trace, which is sampled if the [`Config::trace_sampler`](crate::Config::trace_sampler) accepts it (every request by default).
+///
+/// Returns `None` when it's called outside of a request.
+///
+/// ```rust
+/// let router = <rspc::Router>::new()
+///     .query("users.list", |t| {
+///         t(|_, _: ()| async {
+///             // Eg. `client.get(url).header("traceparent", traceparent)`
+///             let traceparent = rspc::traceparent();
+///             vec!["alice", "bob"]
+///         })
+///     })
+///     .build();
+/// ```
+pub fn traceparent() -> Option<String> {
+    TRACE_CONTEXT
+        .try_with(|trace| {
+            format!(
+                "00-{:032x}-{:016x}-{:02x}",
+                trace.trace_id,
+                trace.span_id,
+                u8::from(trace.sampled)
+            )
+        })
+        .ok()
+}
+
+/// The position of a request in it's distributed trace. See [`traceparent`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct TraceContext {
+    pub(crate) trace_id: u128,
+    pub(crate) span_id: u64,
+    pub(crate) sampled: bool,
+}
+
+impl TraceContext {
+    /// Join the trace of the request `req` belongs to, or start a new one sampled by `sampler`.
+    pub(crate) fn for_request(req: &RequestContext, sampler: Option<&Arc<dyn Sampler>>) -> Self {
+        let parent = TRACE_CONTEXT
+            .try_with(|trace| (trace.trace_id, trace.sampled))
+            .ok()
+            .or_else(|| parse(headers().get("traceparent")?));
+        let (trace_id, sampled) = parent.unwrap_or_else(|| {
+            (
+                random_id(),
+                sampler.is_none_or(|sampler| sampler.sample(req)),
+            )
+        });
+
+        Self {
+            trace_id,
+            span_id: random_u64().max(1),
+            sampled,
+        }
+    }
+
+    /// Run `f` with this as the trace of the current request.
+    pub(crate) fn scoped<T>(self, f: impl FnOnce() -> T) -> T {
+        TRACE_CONTEXT.sync_scope(self, f)
+    }
+}
+
+// An all zero id is invalid
+fn random_id() -> u128 {
+    ((u128::from(random_u64()) << 64) | u128::from(random_u64())).max(1)
+}
+
+// The trace id and sampled flag of a `traceparent` header, or `None` if it's invalid
+fn parse(traceparent: &str) -> Option<(u128, bool)> {
+    let mut parts = traceparent.trim().split('-');
+    let version = hex(parts.next()?, 2)?;
+    let trace_id = hex(parts.next()?, 32)?;
+    let parent_id = hex(parts.next()?, 16)?;
+    let flags = hex(parts.next()?, 2)?;
+
+    // Later versions may append fields, but `00` has exactly four
+    if version == 0xff
+        || (version == 0 && parts.next().is_some())
+        || trace_id == 0
+        || parent_id == 0
+    {
+        return None;
+    }
+    Some((trace_id, flags & 1 == 1))
+}
+
+fn hex(value: &str, len: usize) -> Option<u128> {
+    if value.len() != len || !value.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    u128::from_str_radix(value, 16).ok()
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use futures::{stream, StreamExt};
+    use serde_json::Value;
+
+    use crate::{
+        internal::jsonrpc::{with_headers, Headers},
+        Config, ExecKind, Router,
+    };
+
+    const TRACE_ID: &str = "4bf92f3577b34da6a3ce929d0e0e4736";
+
+    fn traceparent(value: Option<&str>) -> Headers {
+        value
+            .map(|value| ("traceparent", value))
+            .into_iter()
+            .collect()
+    }
+
+    // The trace id and sampled flag of a `traceparent` sent downstream
+    fn downstream(value: &Value) -> (String, bool) {
+        let parts = value.as_str().unwrap().split('-').collect::<Vec<_>>();
+        assert_eq!(parts[0], "00");
+        assert_eq!(parts[2].len(), 16);
+        (parts[1].to_string(), parts[3] == "01")
+    }
+
+    #[tokio::test]
+    async fn test_traceparent() {
+        let samples = Arc::new(AtomicUsize::new(0));
+        let router = Arc::new(
+            <Router>::new()
+                .config(Config::new().trace_sampler({
+                    let samples = samples.clone();
+                    // Only the first new trace is sampled
+                    move |_: &_| samples.fetch_add(1, Ordering::SeqCst) == 0
+                }))
+                .query("call", |t| {
+                    t(|_, _: ()| async {
+                        tokio::task::yield_now().await;
+                        crate::traceparent()
+                    })
+                })
+                .subscription("events", |t| {
+                    t(|_, _: ()| stream::iter(0..2).map(|_| crate::traceparent()))
+                })
+                .build(),
+        );
+        let call = |header: Option<String>| {
+            let router = router.clone();
+            async move {
+                with_headers(
+                    traceparent(header.as_deref()),
+                    router.exec((), ExecKind::Query, "call".into(), None),
+                )
+                .await
+                .unwrap()
+            }
+        };
+
+        // New traces are decided by the sampler
+        let (first, sampled) = downstream(&call(None).await);
+        assert!(sampled);
+        let (second, sampled) = downstream(&call(None).await);
+        assert!(!sampled);
+        assert_ne!(first, second);
+
+        // The decision of the upstream service is kept, without asking the sampler
+        let samples_before = samples.load(Ordering::SeqCst);
+        for (flags, sampled) in [("01", true), ("00", false), ("03", true)] {
+            let header = format!("00-{TRACE_ID}-00f067aa0ba902b7-{flags}");
+            let value = call(Some(header)).await;
+            assert_eq!(downstream(&value), (TRACE_ID.to_string(), sampled));
+            // The request is a new span of the trace
+            assert!(!value.as_str().unwrap().contains("00f067aa0ba902b7"));
+        }
+        assert_eq!(samples.load(Ordering::SeqCst), samples_before);
+
+        // An invalid header starts a new trace
+        for header in [
+            "00-00000000000000000000000000000000-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-extra",
+            "ff-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e473-00f067aa0ba902b7-01",
+        ] {
+            let (trace_id, sampled) = downstream(&call(Some(header.to_string())).await);
+            assert_ne!(trace_id, TRACE_ID);
+            assert!(!sampled);
+        }
+
+        // Every event of a subscription is in the same trace
+        let header = format!("00-{TRACE_ID}-00f067aa0ba902b7-01");
+        let events = with_headers(traceparent(Some(&header)), async {
+            router
+                .exec_subscription((), "events".into(), None)
+                .await
+                .unwrap()
+                .map(|event| downstream(&event.unwrap()))
+                .collect::<Vec<_>>()
+                .await
+        })
+        .await;
+        assert_eq!(events, vec![(TRACE_ID.to_string(), true); 2]);
+
+        // There is no trace outside of a request
+        assert_eq!(crate::traceparent(), None);
+    }
+
+    #[tokio::test]
+    async fn test_traceparent_nested() {
+        let inner = Arc::new(
+            <Router>::new()
+                .query("call", |t| t(|_, _: ()| crate::traceparent()))
+                .build(),
+        );
+        let router = <Router>::new()
+            .config(Config::new().trace_sampler(|_: &_| false))
+            .query("outer", move |t| {
+                let inner = inner.clone();
+                t(move |_, _: ()| {
+                    let inner = inner.clone();
+                    async move {
+                        let nested = inner
+                            .exec((), ExecKind::Query, "call".into(), None)
+                            .await
+                            .unwrap();
+                        (crate::traceparent(), nested)
+                    }
+                })
+            })
+            .build();
+
+        // The nested request joins the trace of the outer one, even though it's router samples every trace
+        let result = router
+            .exec((), ExecKind::Query, "outer".into(), None)
+            .await
+            .unwrap();
+        let (outer, nested) = (downstream(&result[0]), downstream(&result[1]));
+        assert_eq!(outer, nested);
+        assert!(!outer.1);
+        assert_ne!(result[0], result[1]);
+    }
+}
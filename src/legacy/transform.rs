@@ -0,0 +1,258 @@
+use std::sync::Arc;
+
+use futures::StreamExt;
+use serde_json::{json, Value};
+
+use crate::{
+    internal::{Layer, LayerResult, Procedure, ProcedureStore, RequestContext, ValueOrStream},
+    ExecError,
+};
+
+/// A response transformer. See [`Config::transform_responses`](crate::Config::transform_responses).
+pub(crate) type TransformFn = Arc<dyn Fn(&str, &Value, Value) -> Value + Send + Sync>;
+
+pub(crate) fn transform_responses<TCtx: 'static>(
+    transform: &TransformFn,
+    procedures: ProcedureStore<TCtx>,
+) -> ProcedureStore<TCtx> {
+    transform_each(procedures, |_| Some(transform.clone()))
+}
+
+/// Add a warning to the results of every deprecated procedure. See [`Config::deprecation_warnings`](crate::Config::deprecation_warnings).
+pub(crate) fn deprecation_warnings<TCtx: 'static>(
+    procedures: ProcedureStore<TCtx>,
+) -> ProcedureStore<TCtx> {
+    transform_each(procedures, |procedure| {
+        let message = procedure.ty.deprecated.clone()?;
+        Some(Arc::new(move |key: &str, _: &Value, mut value: Value| {
+            if let Value::Object(object) = &mut value {
+                let warning = json!({ "kind": "deprecated", "procedure": key, "message": message });
+                match object.get_mut("_warnings") {
+                    Some(Value::Array(warnings)) => warnings.push(warning),
+                    _ => {
+                        object.insert("_warnings".into(), json!([warning]));
+                    }
+                }
+            }
+            value
+        }) as TransformFn)
+    })
+}
+
+// Wrap every procedure which `transform` returns a transformer for in a `TransformLayer`.
+fn transform_each<TCtx: 'static>(
+    mut procedures: ProcedureStore<TCtx>,
+    transform: impl Fn(&Procedure<TCtx>) -> Option<TransformFn>,
+) -> ProcedureStore<TCtx> {
+    procedures.store = std::mem::take(&mut procedures.store)
+        .into_iter()
+        .map(|(key, procedure)| {
+            let Some(transform) = transform(&procedure) else {
+                return (key, procedure);
+            };
+            let exec = Box::new(TransformLayer {
+                next: procedure.exec,
+                key: key.as_str().into(),
+                transform,
+            });
+            (
+                key,
+                Procedure {
+                    exec,
+                    ty: procedure.ty,
+                    visible: procedure.visible,
+                    typed: false,
+                },
+            )
+        })
+        .collect();
+    procedures
+}
+
+/// Passes the results of a procedure through the response transformer.
+struct TransformLayer<TCtx: 'static> {
+    next: Box<dyn Layer<TCtx>>,
+    key: Arc<str>,
+    transform: TransformFn,
+}
+
+impl<TCtx: 'static> Layer<TCtx> for TransformLayer<TCtx> {
+    fn call(&self, ctx: TCtx, input: Value, req: RequestContext) -> Result<LayerResult, ExecError> {
+        let result = self.next.call(ctx, input.clone(), req)?;
+        let (key, transform) = (self.key.clone(), self.transform.clone());
+
+        Ok(LayerResult::FutureValueOrStream(Box::pin(async move {
+            Ok(match result.into_value_or_stream().await? {
+                ValueOrStream::Value(value) => ValueOrStream::Value(transform(&key, &input, value)),
+                ValueOrStream::Stream(stream) => ValueOrStream::Stream(Box::pin(
+                    stream.map(move |item| item.map(|value| transform(&key, &input, value))),
+                )),
+            })
+        })))
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use futures::{stream, StreamExt};
+    use serde::Serialize;
+    use serde_json::{json, Value};
+    use specta::Type;
+
+    use crate::{Config, ExecKind, Router};
+
+    #[derive(Serialize, Type)]
+    struct User {
+        id: u32,
+        name: String,
+    }
+
+    fn links(key: &str, input: &Value, mut value: Value) -> Value {
+        if let Value::Object(object) = &mut value {
+            object.insert(
+                "_links".into(),
+                json!({ "self": { "href": format!("/rpc/{key}?input={input}") } }),
+            );
+        }
+        value
+    }
+
+    #[tokio::test]
+    async fn test_transform_responses() {
+        let router = <Router>::new()
+            .config(Config::new().transform_responses(links))
+            .query("user", |t| {
+                t(|_, id: u32| User {
+                    id,
+                    name: "Oscar".into(),
+                })
+            })
+            .mutation("rename", |t| t(|_, name: String| User { id: 1, name }))
+            .subscription("users", |t| {
+                t(|_, _: ()| {
+                    stream::iter([User {
+                        id: 1,
+                        name: "Oscar".into(),
+                    }])
+                })
+            })
+            .build();
+
+        let result = router
+            .exec((), ExecKind::Query, "user".into(), Some(json!(1)))
+            .await
+            .unwrap();
+        assert_eq!(
+            result,
+            json!({ "id": 1, "name": "Oscar", "_links": { "self": { "href": "/rpc/user?input=1" } } })
+        );
+
+        // Mutations and subscription events aren't transformed by default
+        let result = router
+            .exec(
+                (),
+                ExecKind::Mutation,
+                "rename".into(),
+                Some(json!("Brendan")),
+            )
+            .await
+            .unwrap();
+        assert_eq!(result, json!({ "id": 1, "name": "Brendan" }));
+        let events = router
+            .exec_subscription((), "users".into(), None)
+            .await
+            .unwrap()
+            .map(|event| event.unwrap())
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(events, [json!({ "id": 1, "name": "Oscar" })]);
+
+        let router = <Router>::new()
+            .config(
+                Config::new()
+                    .transform_responses(links)
+                    .transform_subscription_events(),
+            )
+            .subscription("users", |t| {
+                t(|_, _: ()| {
+                    stream::iter([User {
+                        id: 1,
+                        name: "Oscar".into(),
+                    }])
+                })
+            })
+            .build();
+        let events = router
+            .exec_subscription((), "users".into(), None)
+            .await
+            .unwrap()
+            .map(|event| event.unwrap())
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(
+            events,
+            [
+                json!({ "id": 1, "name": "Oscar", "_links": { "self": { "href": "/rpc/users?input=null" } } })
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_deprecation_warnings() {
+        let procedures = |router: crate::RouterBuilder| {
+            router
+                .query("user", |t| {
+                    t(|_, id: u32| User {
+                        id,
+                        name: "Oscar".into(),
+                    })
+                    .deprecated("Use `users.get` instead.")
+                })
+                .mutation("ping", |t| t(|_, _: ()| "pong").deprecated("Don't."))
+                .query("users.get", |t| {
+                    t(|_, id: u32| User {
+                        id,
+                        name: "Oscar".into(),
+                    })
+                })
+                .build()
+        };
+
+        // Responses are unchanged unless the router opts in
+        let router = procedures(<Router>::new());
+        let result = router
+            .exec((), ExecKind::Query, "user".into(), Some(json!(1)))
+            .await
+            .unwrap();
+        assert_eq!(result, json!({ "id": 1, "name": "Oscar" }));
+
+        let router = procedures(<Router>::new().config(Config::new().deprecation_warnings()));
+        let result = router
+            .exec((), ExecKind::Query, "user".into(), Some(json!(1)))
+            .await
+            .unwrap();
+        assert_eq!(
+            result,
+            json!({
+                "id": 1,
+                "name": "Oscar",
+                "_warnings": [{ "kind": "deprecated", "procedure": "user", "message": "Use `users.get` instead." }]
+            })
+        );
+        let result = router
+            .exec((), ExecKind::Query, "users.get".into(), Some(json!(1)))
+            .await
+            .unwrap();
+        assert_eq!(result, json!({ "id": 1, "name": "Oscar" }));
+        // Results which aren't objects can't carry the warning
+        let result = router
+            .exec((), ExecKind::Mutation, "ping".into(), None)
+            .await
+            .unwrap();
+        assert_eq!(result, json!("pong"));
+
+        let bindings = router.ts_bindings().unwrap();
+        assert!(bindings.contains("@deprecated Use `users.get` instead."));
+    }
+}
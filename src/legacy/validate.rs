@@ -0,0 +1,553 @@
+use std::{
+    borrow::{Borrow, Cow},
+    fmt,
+    sync::Arc,
+};
+
+use futures::StreamExt;
+use serde_json::{Map, Value};
+use specta::{
+    datatype::{
+        EnumRepr, EnumType, EnumVariants, Field, GenericType, LiteralType, PrimitiveType,
+        StructFields,
+    },
+    DataType, TypeMap,
+};
+
+use crate::{
+    internal::{Layer, LayerResult, Procedure, ProcedureStore, RequestContext, ValueOrStream},
+    ExecError,
+};
+
+/// A [`Value`] didn't match the [`DataType`] it was checked against.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationError {
+    /// A JSON pointer to the offending value. Empty if it's the root value.
+    pub path: String,
+    pub message: String,
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.path.is_empty() {
+            true => write!(f, "{}", self.message),
+            false => write!(f, "at '{}': {}", self.path, self.message),
+        }
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+/// Check a [`Value`] against a Specta [`DataType`].
+///
+/// References are resolved using the `type_map`, which should be the one of the router the type came from (see [`Router::type_map`](crate::Router::type_map)).
+/// This checks the JSON shape which the TypeScript bindings promise to the frontend. It's not a full JSON Schema validator and unknown object keys are ignored.
+pub fn validate_value(
+    ty: &DataType,
+    type_map: &TypeMap,
+    value: &Value,
+) -> Result<(), ValidationError> {
+    Validator { type_map }.validate(ty, value, &Env::default(), &mut String::new())
+}
+
+// The generics in scope, with their values expressed in the scope of the parent.
+#[derive(Default)]
+pub(super) struct Env<'a> {
+    pub(super) vars: &'a [(GenericType, DataType)],
+    pub(super) parent: Option<&'a Env<'a>>,
+}
+
+struct Validator<'a> {
+    type_map: &'a TypeMap,
+}
+
+impl Validator<'_> {
+    fn validate(
+        &self,
+        ty: &DataType,
+        value: &Value,
+        env: &Env,
+        path: &mut String,
+    ) -> Result<(), ValidationError> {
+        let err = |path: &String, message: String| {
+            Err(ValidationError {
+                path: path.clone(),
+                message,
+            })
+        };
+        let expected = |path: &String, expected: &str| {
+            err(path, format!("expected {expected}, found '{value}'"))
+        };
+
+        match ty {
+            DataType::Any | DataType::Unknown => Ok(()),
+            DataType::Primitive(p) => match p {
+                PrimitiveType::i8
+                | PrimitiveType::i16
+                | PrimitiveType::i32
+                | PrimitiveType::i64
+                | PrimitiveType::i128
+                | PrimitiveType::isize
+                    if value.is_i64() || value.is_u64() =>
+                {
+                    Ok(())
+                }
+                PrimitiveType::u8
+                | PrimitiveType::u16
+                | PrimitiveType::u32
+                | PrimitiveType::u64
+                | PrimitiveType::u128
+                | PrimitiveType::usize
+                    if value.is_u64() =>
+                {
+                    Ok(())
+                }
+                // `serde_json` serializes non-finite floats as `null`
+                PrimitiveType::f32 | PrimitiveType::f64 if value.is_number() || value.is_null() => {
+                    Ok(())
+                }
+                PrimitiveType::bool if value.is_boolean() => Ok(()),
+                PrimitiveType::char if value.as_str().is_some_and(|s| s.chars().count() == 1) => {
+                    Ok(())
+                }
+                PrimitiveType::String if value.is_string() => Ok(()),
+                p => expected(path, p.to_rust_str()),
+            },
+            DataType::Literal(l) => {
+                let matches = match l {
+                    LiteralType::i8(v) => value.as_i64() == Some(*v as i64),
+                    LiteralType::i16(v) => value.as_i64() == Some(*v as i64),
+                    LiteralType::i32(v) => value.as_i64() == Some(*v as i64),
+                    LiteralType::u8(v) => value.as_u64() == Some(*v as u64),
+                    LiteralType::u16(v) => value.as_u64() == Some(*v as u64),
+                    LiteralType::u32(v) => value.as_u64() == Some(*v as u64),
+                    LiteralType::f32(v) => value.as_f64() == Some(*v as f64),
+                    LiteralType::f64(v) => value.as_f64() == Some(*v),
+                    LiteralType::bool(v) => value.as_bool() == Some(*v),
+                    LiteralType::String(v) => value.as_str() == Some(v),
+                    LiteralType::char(v) => value.as_str() == Some(v.to_string().as_str()),
+                    LiteralType::None => value.is_null(),
+                    _ => true,
+                };
+                match matches {
+                    true => Ok(()),
+                    false => expected(path, &format!("literal {l:?}")),
+                }
+            }
+            DataType::List(l) => {
+                let Some(items) = value.as_array() else {
+                    return expected(path, "array");
+                };
+                if let Some(len) = l.length() {
+                    if items.len() != len {
+                        return expected(path, &format!("array of length {len}"));
+                    }
+                }
+                for (i, item) in items.iter().enumerate() {
+                    self.child(l.ty(), item, env, path, &i.to_string())?;
+                }
+                Ok(())
+            }
+            DataType::Map(m) => {
+                let Some(entries) = value.as_object() else {
+                    return expected(path, "object");
+                };
+                for (key, value) in entries {
+                    self.child(m.value_ty(), value, env, path, key)?;
+                }
+                Ok(())
+            }
+            DataType::Nullable(_) if value.is_null() => Ok(()),
+            DataType::Nullable(ty) => self.validate(ty, value, env, path),
+            DataType::Struct(s) => {
+                match s.fields() {
+                    StructFields::Unit if value.is_null() => {}
+                    StructFields::Unit => return expected(path, "null"),
+                    StructFields::Unnamed(f) => self.unnamed(f.fields(), value, env, path)?,
+                    StructFields::Named(f) => self.object(f.fields(), value, env, path)?,
+                }
+                if let Some(tag) = s.tag() {
+                    if value.get(tag.as_ref()).and_then(Value::as_str) != Some(s.name()) {
+                        return err(path, format!("expected field '{tag}' to be '{}'", s.name()));
+                    }
+                }
+                Ok(())
+            }
+            DataType::Enum(e) => self.enumeration(e, value, env, path),
+            DataType::Tuple(t) => self.tuple(t.elements().iter(), value, env, path),
+            DataType::Reference(r) => match self.type_map.get(r.sid()) {
+                Some(named) => self.validate(
+                    &named.inner,
+                    value,
+                    &Env {
+                        vars: r.generics(),
+                        parent: Some(env),
+                    },
+                    path,
+                ),
+                None => Ok(()),
+            },
+            DataType::Generic(g) => {
+                let name: &str = g.borrow();
+                match env
+                    .vars
+                    .iter()
+                    .find(|(var, _)| Borrow::<str>::borrow(var) == name)
+                {
+                    Some((_, ty)) => self.validate(ty, value, env.parent.unwrap_or(env), path),
+                    None => Ok(()),
+                }
+            }
+        }
+    }
+
+    fn child(
+        &self,
+        ty: &DataType,
+        value: &Value,
+        env: &Env,
+        path: &mut String,
+        segment: &str,
+    ) -> Result<(), ValidationError> {
+        let len = path.len();
+        path.push('/');
+        path.push_str(&segment.replace('~', "~0").replace('/', "~1"));
+        let result = self.validate(ty, value, env, path);
+        path.truncate(len);
+        result
+    }
+
+    fn tuple<'a>(
+        &self,
+        elements: impl ExactSizeIterator<Item = &'a DataType>,
+        value: &Value,
+        env: &Env,
+        path: &mut String,
+    ) -> Result<(), ValidationError> {
+        let len = elements.len();
+        if len == 0 {
+            return match value.is_null() {
+                true => Ok(()),
+                false => Err(ValidationError {
+                    path: path.clone(),
+                    message: format!("expected null, found '{value}'"),
+                }),
+            };
+        }
+
+        match value.as_array() {
+            Some(items) if items.len() == len => {
+                for (i, (ty, item)) in elements.zip(items).enumerate() {
+                    self.child(ty, item, env, path, &i.to_string())?;
+                }
+                Ok(())
+            }
+            _ => Err(ValidationError {
+                path: path.clone(),
+                message: format!("expected array of length {len}, found '{value}'"),
+            }),
+        }
+    }
+
+    fn unnamed(
+        &self,
+        fields: &[Field],
+        value: &Value,
+        env: &Env,
+        path: &mut String,
+    ) -> Result<(), ValidationError> {
+        let fields = fields.iter().filter_map(|f| f.ty()).collect::<Vec<_>>();
+        match fields[..] {
+            // Serde represents newtypes as their inner value
+            [ty] => self.validate(ty, value, env, path),
+            _ => self.tuple(fields.into_iter(), value, env, path),
+        }
+    }
+
+    fn object(
+        &self,
+        fields: &[(Cow<'static, str>, Field)],
+        value: &Value,
+        env: &Env,
+        path: &mut String,
+    ) -> Result<(), ValidationError> {
+        let Some(object) = value.as_object() else {
+            return Err(ValidationError {
+                path: path.clone(),
+                message: format!("expected object, found '{value}'"),
+            });
+        };
+        self.fields(fields, object, value, env, path)
+    }
+
+    fn fields(
+        &self,
+        fields: &[(Cow<'static, str>, Field)],
+        object: &Map<String, Value>,
+        value: &Value,
+        env: &Env,
+        path: &mut String,
+    ) -> Result<(), ValidationError> {
+        for (name, field) in fields {
+            let Some(ty) = field.ty() else { continue };
+
+            if field.flatten() {
+                self.validate(ty, value, env, path)?;
+                continue;
+            }
+
+            match object.get(name.as_ref()) {
+                Some(v) => self.child(ty, v, env, path, name)?,
+                None if field.optional() || matches!(ty, DataType::Nullable(_)) => {}
+                None => {
+                    return Err(ValidationError {
+                        path: path.clone(),
+                        message: format!("missing field '{name}'"),
+                    })
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn variant(
+        &self,
+        inner: &EnumVariants,
+        value: &Value,
+        env: &Env,
+        path: &mut String,
+    ) -> Result<(), ValidationError> {
+        match inner {
+            EnumVariants::Unit if value.is_null() => Ok(()),
+            EnumVariants::Unit => Err(ValidationError {
+                path: path.clone(),
+                message: format!("expected null, found '{value}'"),
+            }),
+            EnumVariants::Named(f) => self.object(f.fields(), value, env, path),
+            EnumVariants::Unnamed(f) => self.unnamed(f.fields(), value, env, path),
+        }
+    }
+
+    fn enumeration(
+        &self,
+        e: &EnumType,
+        value: &Value,
+        env: &Env,
+        path: &mut String,
+    ) -> Result<(), ValidationError> {
+        let mut variants = e.variants().iter().filter(|(_, v)| !v.skip());
+        let no_match = |path: &String| {
+            Err(ValidationError {
+                path: path.clone(),
+                message: format!("'{value}' doesn't match any variant of '{}'", e.name()),
+            })
+        };
+
+        match e.repr() {
+            EnumRepr::Untagged => {
+                match variants.any(|(_, v)| self.variant(v.inner(), value, env, path).is_ok()) {
+                    true => Ok(()),
+                    false => no_match(path),
+                }
+            }
+            EnumRepr::External => {
+                if let Some(name) = value.as_str() {
+                    return match variants
+                        .any(|(n, v)| n == name && matches!(v.inner(), EnumVariants::Unit))
+                    {
+                        true => Ok(()),
+                        false => no_match(path),
+                    };
+                }
+
+                match value.as_object().filter(|o| o.len() == 1).and_then(|o| {
+                    let (key, inner) = o.iter().next()?;
+                    variants
+                        .find(|(n, _)| n == key)
+                        .map(|(_, v)| (key, v, inner))
+                }) {
+                    Some((key, variant, inner)) => {
+                        let len = path.len();
+                        path.push('/');
+                        path.push_str(key);
+                        let result = self.variant(variant.inner(), inner, env, path);
+                        path.truncate(len);
+                        result
+                    }
+                    None => no_match(path),
+                }
+            }
+            EnumRepr::Internal { tag } => {
+                let Some(name) = value.get(tag.as_ref()).and_then(Value::as_str) else {
+                    return no_match(path);
+                };
+                match variants.find(|(n, _)| n == name) {
+                    Some((_, variant)) => match variant.inner() {
+                        EnumVariants::Unit => Ok(()),
+                        inner => self.variant(inner, value, env, path),
+                    },
+                    None => no_match(path),
+                }
+            }
+            EnumRepr::Adjacent { tag, content } => {
+                let Some(name) = value.get(tag.as_ref()).and_then(Value::as_str) else {
+                    return no_match(path);
+                };
+                match variants.find(|(n, _)| n == name) {
+                    Some((_, variant)) => match (variant.inner(), value.get(content.as_ref())) {
+                        (EnumVariants::Unit, _) => Ok(()),
+                        (inner, Some(v)) => self.child_variant(inner, v, env, path, content),
+                        (_, None) => Err(ValidationError {
+                            path: path.clone(),
+                            message: format!("missing field '{content}'"),
+                        }),
+                    },
+                    None => no_match(path),
+                }
+            }
+        }
+    }
+
+    fn child_variant(
+        &self,
+        inner: &EnumVariants,
+        value: &Value,
+        env: &Env,
+        path: &mut String,
+        segment: &str,
+    ) -> Result<(), ValidationError> {
+        let len = path.len();
+        path.push('/');
+        path.push_str(segment);
+        let result = self.variant(inner, value, env, path);
+        path.truncate(len);
+        result
+    }
+}
+
+/// Wrap every procedure in the store with a [`ValidateLayer`]. Used by [`Config::validate_results`](crate::Config::validate_results).
+#[cfg_attr(not(debug_assertions), allow(dead_code))]
+pub(crate) fn validate_results<TCtx: 'static>(
+    mut procedures: ProcedureStore<TCtx>,
+    type_map: &Arc<TypeMap>,
+) -> ProcedureStore<TCtx> {
+    procedures.store = std::mem::take(&mut procedures.store)
+        .into_iter()
+        .map(|(key, procedure)| {
+            let exec = Box::new(ValidateLayer {
+                next: procedure.exec,
+                ty: Arc::new(procedure.ty.result_ty.clone()),
+                type_map: type_map.clone(),
+            });
+            (
+                key,
+                Procedure {
+                    exec,
+                    ty: procedure.ty,
+                    visible: procedure.visible,
+                    typed: false,
+                },
+            )
+        })
+        .collect();
+    procedures
+}
+
+/// Validates the results of a procedure against it's declared type.
+struct ValidateLayer<TCtx: 'static> {
+    next: Box<dyn Layer<TCtx>>,
+    ty: Arc<DataType>,
+    type_map: Arc<TypeMap>,
+}
+
+impl<TCtx: 'static> Layer<TCtx> for ValidateLayer<TCtx> {
+    fn call(&self, ctx: TCtx, input: Value, req: RequestContext) -> Result<LayerResult, ExecError> {
+        let result = self.next.call(ctx, input, req)?;
+        let ty = self.ty.clone();
+        let type_map = self.type_map.clone();
+        let validate = move |value: Value| match validate_value(&ty, &type_map, &value) {
+            Ok(()) => Ok(value),
+            Err(err) => Err(ExecError::InvalidResult(err)),
+        };
+
+        Ok(LayerResult::FutureValueOrStream(Box::pin(async move {
+            Ok(match result.into_value_or_stream().await? {
+                ValueOrStream::Value(value) => ValueOrStream::Value(validate(value)?),
+                ValueOrStream::Stream(stream) => ValueOrStream::Stream(Box::pin(
+                    stream.map(move |item| item.and_then(&validate)),
+                )),
+            })
+        })))
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use serde::{Serialize, Serializer};
+    use serde_json::json;
+    use specta::Type;
+
+    use crate::{internal::ProcedureKind, validate_value, Config, ExecKind, Router};
+
+    #[derive(Serialize, Type)]
+    struct Page<T> {
+        items: Vec<T>,
+        next: Option<u32>,
+    }
+
+    #[derive(Serialize, Type)]
+    #[serde(tag = "type")]
+    enum Shape {
+        Circle { radius: f64 },
+        Empty,
+    }
+
+    // Exports as a `number` but serializes as a string
+    #[derive(Type)]
+    struct Wrong(i32);
+
+    impl Serialize for Wrong {
+        fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+            s.serialize_str(&self.0.to_string())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_validate_results() {
+        let router = <Router>::new()
+            .config(Config::new().validate_results())
+            .query("page", |t| {
+                t(|_, _: ()| Page {
+                    items: vec![Shape::Circle { radius: 1.0 }, Shape::Empty],
+                    next: None,
+                })
+            })
+            .query("wrong", |t| t(|_, _: ()| Wrong(1)))
+            .build();
+
+        let ty = router.result_type(ProcedureKind::Query, "page").unwrap();
+        let type_map = router.type_map();
+        assert!(validate_value(ty, &type_map, &json!({ "items": [], "next": 5 })).is_ok());
+
+        let err = validate_value(
+            ty,
+            &type_map,
+            &json!({ "items": [{ "type": "Circle", "radius": "big" }], "next": null }),
+        )
+        .unwrap_err();
+        assert_eq!(err.path, "/items/0/radius");
+
+        let err = validate_value(ty, &type_map, &json!({ "items": [{ "type": "Square" }] }));
+        assert_eq!(err.unwrap_err().path, "/items/0");
+
+        assert!(router
+            .exec((), ExecKind::Query, "page".into(), None)
+            .await
+            .is_ok());
+        assert!(router
+            .exec((), ExecKind::Query, "wrong".into(), None)
+            .await
+            .is_err());
+    }
+}
@@ -0,0 +1,210 @@
+use std::sync::Arc;
+
+use serde_json::Value;
+
+use crate::{
+    internal::{Layer, LayerResult, RequestContext},
+    ExecError, FieldError,
+};
+
+/// The field of the input object which selects the virtual fields to compute.
+pub(crate) const VIRTUALS_FIELD: &str = "$virtuals";
+
+tokio::task_local! {
+    static VIRTUALS: Arc<[String]>;
+}
+
+/// Whether the client requested the virtual field `field` of the current request's result. See [`virtual_fields`](crate::internal::BuiltProcedureBuilder::virtual_fields).
+///
+/// Returns `false` when it's called outside of a procedure with virtual fields.
+pub fn virtual_requested(field: &str) -> bool {
+    VIRTUALS
+        .try_with(|virtuals| virtuals.iter().any(|virtual_| virtual_ == field))
+        .unwrap_or(false)
+}
+
+/// Compute the virtual field `field` with `resolve`, only if the client requested it. See [`virtual_fields`](crate::internal::BuiltProcedureBuilder::virtual_fields).
+///
+/// Use [`virtual_requested`] to compute it asynchronously.
+pub fn resolve_virtual<T>(field: &str, resolve: impl FnOnce() -> T) -> Option<T> {
+    virtual_requested(field).then(resolve)
+}
+
+/// Takes the virtual fields selected by the request out of it's input, making them available to the resolver.
+pub(crate) struct VirtualFieldsLayer<TLayerCtx: 'static> {
+    next: Box<dyn Layer<TLayerCtx>>,
+    fields: Arc<[&'static str]>,
+}
+
+impl<TLayerCtx: 'static> VirtualFieldsLayer<TLayerCtx> {
+    /// Wrap `next` if the procedure has virtual fields.
+    pub fn wrap(
+        next: Box<dyn Layer<TLayerCtx>>,
+        fields: Vec<&'static str>,
+    ) -> Box<dyn Layer<TLayerCtx>> {
+        match fields.is_empty() {
+            true => next,
+            false => Box::new(Self {
+                next,
+                fields: fields.into(),
+            }),
+        }
+    }
+
+    fn select(&self, selection: Value) -> Result<Arc<[String]>, ExecError> {
+        let Value::Array(selection) = selection else {
+            return Err(ExecError::InputValidation {
+                errors: vec![FieldError {
+                    path: format!("/{VIRTUALS_FIELD}"),
+                    message: "the virtual fields must be an array of field names".into(),
+                    expected: Some("a string[]".into()),
+                }],
+            });
+        };
+
+        let mut errors = Vec::new();
+        let mut virtuals = Vec::new();
+        for (i, field) in selection.into_iter().enumerate() {
+            match field {
+                Value::String(field) if self.fields.contains(&field.as_str()) => {
+                    virtuals.push(field)
+                }
+                field => errors.push(FieldError {
+                    path: format!("/{VIRTUALS_FIELD}/{i}"),
+                    message: format!("{field} isn't a virtual field of this procedure"),
+                    expected: Some(format!("one of {}", self.fields.join(", "))),
+                }),
+            }
+        }
+        match errors.is_empty() {
+            true => Ok(virtuals.into()),
+            false => Err(ExecError::InputValidation { errors }),
+        }
+    }
+}
+
+impl<TLayerCtx: 'static> Layer<TLayerCtx> for VirtualFieldsLayer<TLayerCtx> {
+    fn call(
+        &self,
+        ctx: TLayerCtx,
+        mut input: Value,
+        req: RequestContext,
+    ) -> Result<LayerResult, ExecError> {
+        let selection = match &mut input {
+            Value::Object(object) => object.remove(VIRTUALS_FIELD),
+            _ => None,
+        };
+        let virtuals = match selection {
+            Some(selection) => {
+                // An input which only held the selection is a procedure without input
+                if input.as_object().is_some_and(|object| object.is_empty()) {
+                    input = Value::Null;
+                }
+                self.select(selection)?
+            }
+            None => Arc::new([]),
+        };
+
+        let result = VIRTUALS.sync_scope(virtuals.clone(), || self.next.call(ctx, input, req))?;
+        Ok(match result {
+            LayerResult::Ready(result) => LayerResult::Ready(result),
+            result => LayerResult::FutureValueOrStream(Box::pin(
+                VIRTUALS.scope(virtuals, result.into_value_or_stream()),
+            )),
+        })
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use serde::Serialize;
+    use serde_json::json;
+    use specta::Type;
+
+    use crate::{ExecError, ExecKind, Router};
+
+    static PROFILES_COMPUTED: AtomicUsize = AtomicUsize::new(0);
+
+    #[derive(Serialize, Type)]
+    struct User {
+        name: &'static str,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        #[specta(optional)]
+        post_count: Option<u32>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        #[specta(optional)]
+        profile: Option<String>,
+    }
+
+    #[tokio::test]
+    async fn test_virtual_fields() {
+        let router = <Router>::new()
+            .query("user", |t| {
+                t(|_, _: ()| async {
+                    let profile = match crate::virtual_requested("profile") {
+                        true => {
+                            tokio::task::yield_now().await;
+                            PROFILES_COMPUTED.fetch_add(1, Ordering::SeqCst);
+                            Some("likes rust".to_string())
+                        }
+                        false => None,
+                    };
+                    User {
+                        name: "Monty",
+                        post_count: crate::resolve_virtual("post_count", || 42),
+                        profile,
+                    }
+                })
+                .virtual_fields(["post_count", "profile"])
+            })
+            .query("find", |t| {
+                t(|_, name: String| crate::resolve_virtual("greeting", || format!("hi {name}")))
+                    .virtual_fields(["greeting"])
+            })
+            .build();
+        let bindings = router.ts_bindings().unwrap();
+        assert!(bindings.contains("post_count?: number"));
+        assert!(bindings.contains("profile?: string"));
+
+        let exec = |input| router.exec((), ExecKind::Query, "user".into(), input);
+
+        // Only the requested virtuals are computed
+        assert_eq!(exec(None).await.unwrap(), json!({ "name": "Monty" }));
+        assert_eq!(
+            exec(Some(json!({ "$virtuals": ["post_count"] })))
+                .await
+                .unwrap(),
+            json!({ "name": "Monty", "post_count": 42 })
+        );
+        assert_eq!(PROFILES_COMPUTED.load(Ordering::SeqCst), 0);
+        assert_eq!(
+            exec(Some(json!({ "$virtuals": ["profile", "post_count"] })))
+                .await
+                .unwrap(),
+            json!({ "name": "Monty", "post_count": 42, "profile": "likes rust" })
+        );
+        assert_eq!(PROFILES_COMPUTED.load(Ordering::SeqCst), 1);
+
+        // Undeclared virtuals are rejected
+        let err = exec(Some(json!({ "$virtuals": ["post_count", "password"] })))
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            ExecError::InputValidation { errors } if errors.len() == 1 && errors[0].path == "/$virtuals/1"
+        ));
+
+        // Non-object inputs can't select virtuals, so they're only computed when the selection is sent with an object input
+        let result = router
+            .exec((), ExecKind::Query, "find".into(), Some(json!("Monty")))
+            .await
+            .unwrap();
+        assert_eq!(result, json!(null));
+
+        // Outside of a request nothing is requested
+        assert!(!crate::virtual_requested("profile"));
+    }
+}
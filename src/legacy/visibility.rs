@@ -0,0 +1,118 @@
+use std::{any::Any, sync::Arc};
+
+use serde_json::Value;
+
+use crate::{
+    internal::{Layer, LayerResult, RequestContext},
+    ExecError,
+};
+
+/// A procedure visibility predicate registered with `.visible_when`.
+pub(crate) type VisibleFn<TCtx> = Arc<dyn Fn(&TCtx) -> bool + Send + Sync>;
+
+/// A type erased [`VisibleFn`]. It's context type is the one the procedure's resolver receives.
+pub(crate) type AnyVisibleFn = Arc<dyn Any + Send + Sync>;
+
+/// Rejects requests to a procedure which is hidden for the current context as if it didn't exist.
+pub(crate) struct VisibilityLayer<TLayerCtx: 'static> {
+    pub next: Box<dyn Layer<TLayerCtx>>,
+    pub visible: VisibleFn<TLayerCtx>,
+}
+
+impl<TLayerCtx: 'static> VisibilityLayer<TLayerCtx> {
+    /// Wrap `next` if the procedure has a visibility predicate.
+    pub fn wrap(
+        next: Box<dyn Layer<TLayerCtx>>,
+        visible: Option<&AnyVisibleFn>,
+    ) -> Box<dyn Layer<TLayerCtx>> {
+        match visible {
+            Some(visible) => Box::new(Self {
+                next,
+                // This is guaranteed by the bounds on `BuiltProcedureBuilder::visible_when`
+                visible: visible
+                    .downcast_ref::<VisibleFn<TLayerCtx>>()
+                    .expect("rspc: procedure visibility context type mismatch")
+                    .clone(),
+            }),
+            None => next,
+        }
+    }
+}
+
+impl<TLayerCtx: 'static> Layer<TLayerCtx> for VisibilityLayer<TLayerCtx> {
+    fn call(
+        &self,
+        ctx: TLayerCtx,
+        input: Value,
+        req: RequestContext,
+    ) -> Result<LayerResult, ExecError> {
+        if !(self.visible)(&ctx) {
+            return Err(ExecError::OperationNotFound(req.path));
+        }
+
+        self.next.call(ctx, input, req)
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use crate::{Error, ErrorCode, ExecError, ExecKind, Router};
+
+    struct Ctx {
+        admin: bool,
+    }
+
+    #[tokio::test]
+    async fn test_visible_when() {
+        let router = Router::<Ctx>::new()
+            .query("public", |t| t(|_, _: ()| "public"))
+            .query("admin.stats", |t| {
+                t(|_, _: ()| "stats").visible_when(|ctx| ctx.admin)
+            })
+            .build();
+
+        let exec = |admin| router.exec(Ctx { admin }, ExecKind::Query, "admin.stats".into(), None);
+        assert_eq!(exec(true).await.unwrap(), "stats");
+        assert!(matches!(
+            exec(false).await,
+            Err(ExecError::OperationNotFound(path)) if path == "admin.stats"
+        ));
+
+        let path = std::env::temp_dir().join("rspc_test_visible_when.ts");
+        router.export_ts_for(&Ctx { admin: false }, &path).unwrap();
+        let bindings = std::fs::read_to_string(&path).unwrap();
+        assert!(bindings.contains("\"public\""));
+        assert!(!bindings.contains("admin.stats"));
+
+        router.export_ts_for(&Ctx { admin: true }, &path).unwrap();
+        let bindings = std::fs::read_to_string(&path).unwrap();
+        assert!(bindings.contains("admin.stats"));
+    }
+
+    #[tokio::test]
+    async fn test_visible_when_before_middleware() {
+        let router = Router::<Ctx>::new()
+            .middleware(|mw| {
+                mw.middleware(|mw| async move {
+                    Err::<_, Error>(Error::new(ErrorCode::Forbidden, "admins only".into()))?;
+                    Ok(mw)
+                })
+            })
+            .query("admin.stats", |t| {
+                t(|_, _: ()| "stats").visible_when(|ctx: &Ctx| ctx.admin)
+            })
+            .build();
+
+        // The hidden procedure isn't found, rather than being rejected by the middleware
+        let exec = |admin| router.exec(Ctx { admin }, ExecKind::Query, "admin.stats".into(), None);
+        assert!(matches!(
+            exec(false).await,
+            Err(ExecError::OperationNotFound(path)) if path == "admin.stats"
+        ));
+        assert!(matches!(
+            exec(true).await,
+            Err(ExecError::ErrResolverError(err)) if err.code == ErrorCode::Forbidden
+        ));
+    }
+}
@@ -0,0 +1,79 @@
+use std::{any::Any, sync::Arc};
+
+use futures::future::BoxFuture;
+
+use crate::Error;
+
+/// A hook registered with `.warmup`.
+pub(crate) type WarmupFn<TCtx> =
+    Arc<dyn Fn(TCtx) -> BoxFuture<'static, Result<(), Error>> + Send + Sync>;
+
+/// A type erased [`WarmupFn`]. It's context type is the one the procedure's resolver receives.
+pub(crate) type AnyWarmupFn = Arc<dyn Any + Send + Sync>;
+
+/// Resolve the warmup hooks registered on a router to the router's context type.
+pub(crate) fn downcast_warmups<TCtx: 'static>(
+    warmups: Vec<(String, AnyWarmupFn)>,
+) -> Vec<(String, WarmupFn<TCtx>)> {
+    warmups
+        .into_iter()
+        .map(|(key, warmup)| match warmup.downcast_ref::<WarmupFn<TCtx>>() {
+            Some(warmup) => (key, warmup.clone()),
+            #[allow(clippy::panic)]
+            None => panic!(
+                "rspc error: the procedure '{key}' has a warmup hook but it's context isn't the router's context. Warmup hooks can't be used after a middleware which changes the context."
+            ),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use crate::{Error, ErrorCode, Router};
+
+    #[tokio::test]
+    async fn test_warmup() {
+        let warmed = Arc::new(AtomicUsize::new(0));
+        let router = Router::<u32>::new()
+            .query("a", {
+                let warmed = warmed.clone();
+                move |t| {
+                    let warmed = warmed.clone();
+                    t(|_, _: ()| ()).warmup(move |ctx| {
+                        let warmed = warmed.clone();
+                        async move {
+                            warmed.fetch_add(ctx as usize, Ordering::SeqCst);
+                            Ok(())
+                        }
+                    })
+                }
+            })
+            .merge(
+                "nested.",
+                Router::<u32>::new().mutation("b", |t| {
+                    t(|_, _: ()| ()).warmup(|_| async {
+                        Err(Error::new(
+                            ErrorCode::InternalServerError,
+                            "unavailable".into(),
+                        ))
+                    })
+                }),
+            )
+            .query("c", |t| t(|_, _: ()| ()))
+            .build();
+
+        let errors = router.warmup().await.unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].0, "nested.b");
+        assert_eq!(errors[0].1.message, "unavailable");
+
+        assert!(router.warmup_with(5).await.is_err());
+        assert_eq!(warmed.load(Ordering::SeqCst), 5);
+    }
+}
@@ -0,0 +1,107 @@
+use serde::Serialize;
+use specta::Type;
+
+/// Wraps a successful result with non-fatal metadata such as warnings or a partial data flag.
+///
+/// This serializes as `{ data: T, meta: { warnings: string[], partial: boolean } }` and exports a matching generic type into your bindings so the frontend can surface the warnings while still using `data`.
+///
+/// ## Composition
+///
+/// [`WithMeta`] is a regular [`Serialize`] + [`Type`] value so it works with every existing resolver return type:
+///  - `WithMeta<T>` is handled by the [`SerializeMarker`](crate::SerializeMarker).
+///  - `Result<WithMeta<T>, rspc::Error>` is handled by the [`ResultMarker`](crate::ResultMarker). An `Err` is still returned as an error and no metadata is sent.
+///  - Either of the above can be returned from an `async` resolver via the [`FutureMarker`](crate::FutureMarker).
+///  - A subscription can yield `WithMeta<T>` items, in which case every event carries it's own metadata.
+///
+/// ```rust
+/// use rspc::WithMeta;
+///
+/// let router = <rspc::Router>::new()
+///     .query("users", |t| {
+///         t(|_, _: ()| WithMeta::new(vec!["Monty".to_string()]).warning("`users` is deprecated, use `users.list`"))
+///     })
+///     .build();
+/// ```
+#[derive(Debug, Clone, Serialize, Type)]
+pub struct WithMeta<T> {
+    pub data: T,
+    pub meta: ResultMeta,
+}
+
+/// The metadata sent alongside the `data` of a [`WithMeta`].
+#[derive(Debug, Clone, Default, Serialize, Type)]
+pub struct ResultMeta {
+    /// Human readable, non-fatal warnings about the result.
+    pub warnings: Vec<String>,
+    /// Whether `data` is incomplete (Eg. one of the underlying data sources failed).
+    pub partial: bool,
+}
+
+impl<T> WithMeta<T> {
+    pub fn new(data: T) -> Self {
+        Self {
+            data,
+            meta: Default::default(),
+        }
+    }
+
+    /// Attach a warning to the result.
+    pub fn warning(mut self, warning: impl Into<String>) -> Self {
+        self.meta.warnings.push(warning.into());
+        self
+    }
+
+    /// Mark the result as only containing partial data.
+    pub fn partial(mut self) -> Self {
+        self.meta.partial = true;
+        self
+    }
+}
+
+impl<T> From<T> for WithMeta<T> {
+    fn from(data: T) -> Self {
+        Self::new(data)
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use serde_json::json;
+
+    use super::WithMeta;
+    use crate::{ExecKind, Router};
+
+    #[tokio::test]
+    async fn test_with_meta() {
+        let router = <Router>::new()
+            .query("users", |t| {
+                t(|_, _: ()| {
+                    WithMeta::new(vec!["Monty"])
+                        .warning("`users` is deprecated")
+                        .partial()
+                })
+            })
+            .build();
+
+        let result = router
+            .exec((), ExecKind::Query, "users".into(), None)
+            .await
+            .unwrap();
+        assert_eq!(
+            result,
+            json!({
+                "data": ["Monty"],
+                "meta": { "warnings": ["`users` is deprecated"], "partial": true },
+            })
+        );
+
+        // The generic wrapper and it's metadata are exported as named types
+        let bindings = router.ts_bindings().unwrap();
+        assert!(bindings.contains("result: WithMeta<string[]>"));
+        assert!(bindings.contains("export type WithMeta<T> = { data: T; meta: ResultMeta }"));
+        assert!(bindings.contains("export type ResultMeta = "));
+        assert!(bindings.contains("warnings: string[]"));
+        assert!(bindings.contains("partial: boolean"));
+    }
+}
@@ -0,0 +1,352 @@
+use std::{borrow::Cow, collections::BTreeMap, fmt::Write};
+
+use specta::{
+    datatype::{
+        EnumRepr, EnumType, EnumVariants, Field, LiteralType, PrimitiveType, StructFields,
+        StructType,
+    },
+    DataType, TypeMap,
+};
+
+use crate::internal::{Procedure, ProcedureKind};
+
+pub(crate) fn generate<'a, TCtx: 'static>(
+    header: Option<&str>,
+    procedures: impl IntoIterator<Item = (ProcedureKind, &'a BTreeMap<String, Procedure<TCtx>>)>,
+    type_map: &TypeMap,
+) -> String {
+    let schema = Schema { type_map };
+    let mut out = String::new();
+    if let Some(header) = header {
+        out.push_str(header);
+        out.push('\n');
+    }
+    out.push_str("// This file was generated by [rspc](https://github.com/specta-rs/rspc). Do not edit this file manually.\n\nimport { z } from \"zod\";\n");
+
+    // Sorted by name, like the Typescript bindings. Generic types are inlined where they're used as a schema can't be generic.
+    let mut types = type_map
+        .iter()
+        .map(|(_, ty)| ty)
+        .filter(|ty| ty.inner.generics().is_none_or(|g| g.is_empty()))
+        .collect::<Vec<_>>();
+    types.sort_by(|a, b| a.name().cmp(b.name()));
+    for ty in types {
+        let _ = write!(
+            out,
+            "\nexport const {} = {};\n",
+            ty.name(),
+            schema.convert(&ty.inner, &[])
+        );
+    }
+
+    out.push_str("\nexport const procedures = {\n");
+    for (kind, procedures) in procedures {
+        let _ = writeln!(out, "    {}: {{", kind_name(kind));
+        for (key, procedure) in procedures {
+            let result = match procedure.ty.no_content {
+                true => "z.void()".into(),
+                false => schema.convert(&procedure.ty.result_ty, &[]),
+            };
+            let _ = writeln!(
+                out,
+                "        \"{key}\": {{ input: {}, result: {result} }},",
+                schema.convert(&procedure.ty.arg_ty, &[]),
+            );
+        }
+        out.push_str("    },\n");
+    }
+    out.push_str("};\n");
+    out
+}
+
+fn kind_name(kind: ProcedureKind) -> &'static str {
+    match kind {
+        ProcedureKind::Query => "queries",
+        ProcedureKind::Mutation => "mutations",
+        ProcedureKind::Subscription => "subscriptions",
+    }
+}
+
+/// Converts Specta types into Zod schemas.
+struct Schema<'a> {
+    type_map: &'a TypeMap,
+}
+
+type Generics = [(Cow<'static, str>, String)];
+
+impl Schema<'_> {
+    fn convert(&self, ty: &DataType, generics: &Generics) -> String {
+        match ty {
+            DataType::Any | DataType::Unknown => "z.unknown()".into(),
+            DataType::Primitive(p) => primitive(p).into(),
+            DataType::Literal(l) => literal(l),
+            DataType::List(l) => match l.length() {
+                Some(len) => format!("z.array({}).length({len})", self.convert(l.ty(), generics)),
+                None => format!("z.array({})", self.convert(l.ty(), generics)),
+            },
+            DataType::Map(m) => format!(
+                "z.record(z.string(), {})",
+                self.convert(m.value_ty(), generics)
+            ),
+            DataType::Nullable(t) => format!("{}.nullable()", self.convert(t, generics)),
+            DataType::Struct(s) => self.structure(s, generics),
+            DataType::Enum(e) => self.enumeration(e, generics),
+            DataType::Tuple(t) => self.tuple(t.elements().iter(), generics),
+            // Lazy so types can reference each other regardless of the order they're declared in (or recursively)
+            DataType::Reference(r) if r.generics().is_empty() => {
+                format!("z.lazy(() => {})", r.name())
+            }
+            DataType::Reference(r) => match self.type_map.get(r.sid()) {
+                Some(named) => {
+                    let generics = r
+                        .generics()
+                        .iter()
+                        .map(|(name, ty)| {
+                            (Cow::Owned(name.to_string()), self.convert(ty, generics))
+                        })
+                        .collect::<Vec<_>>();
+                    self.convert(&named.inner, &generics)
+                }
+                None => "z.unknown()".into(),
+            },
+            DataType::Generic(g) => generics
+                .iter()
+                .find(|(name, _)| *name == g.to_string())
+                .map(|(_, s)| s.clone())
+                .unwrap_or_else(|| "z.unknown()".into()),
+        }
+    }
+
+    fn tuple<'a>(
+        &self,
+        elements: impl ExactSizeIterator<Item = &'a DataType>,
+        generics: &Generics,
+    ) -> String {
+        match elements.len() {
+            0 => "z.null()".into(),
+            _ => format!(
+                "z.tuple([{}])",
+                elements
+                    .map(|ty| self.convert(ty, generics))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        }
+    }
+
+    fn unnamed(&self, fields: &[Field], generics: &Generics) -> String {
+        let fields = fields.iter().filter_map(|f| f.ty()).collect::<Vec<_>>();
+        match fields[..] {
+            // Serde represents newtypes as their inner value
+            [ty] => self.convert(ty, generics),
+            _ => self.tuple(fields.into_iter(), generics),
+        }
+    }
+
+    fn object<'a>(
+        &self,
+        fields: impl Iterator<Item = &'a (Cow<'static, str>, Field)>,
+        extra: Option<(&str, String)>,
+        generics: &Generics,
+    ) -> String {
+        let mut properties = Vec::new();
+        let mut flattened = Vec::new();
+        for (name, field) in fields {
+            let Some(ty) = field.ty() else { continue };
+
+            if field.flatten() {
+                flattened.push(self.convert(ty, generics));
+                continue;
+            }
+
+            let s = self.convert(ty, generics);
+            properties.push(match field.optional() {
+                true => format!("{}: {s}.optional()", property(name)),
+                false => format!("{}: {s}", property(name)),
+            });
+        }
+        if let Some((name, s)) = extra {
+            properties.push(format!("{}: {s}", property(name)));
+        }
+
+        let mut object = match properties.is_empty() {
+            true => "z.object({})".to_string(),
+            false => format!("z.object({{ {} }})", properties.join(", ")),
+        };
+        for flattened in flattened {
+            object.push_str(&format!(".and({flattened})"));
+        }
+        object
+    }
+
+    fn structure(&self, s: &StructType, generics: &Generics) -> String {
+        let tag = s.tag().map(|tag| (tag.as_ref(), literal_str(s.name())));
+        match s.fields() {
+            StructFields::Unit => "z.null()".into(),
+            StructFields::Unnamed(f) => self.unnamed(f.fields(), generics),
+            StructFields::Named(f) => self.object(f.fields().iter(), tag, generics),
+        }
+    }
+
+    fn enumeration(&self, e: &EnumType, generics: &Generics) -> String {
+        let variants = e
+            .variants()
+            .iter()
+            .filter(|(_, v)| !v.skip())
+            .map(|(name, variant)| {
+                let inner = match variant.inner() {
+                    EnumVariants::Unit => None,
+                    EnumVariants::Named(f) => Some(self.object(f.fields().iter(), None, generics)),
+                    EnumVariants::Unnamed(f) => Some(self.unnamed(f.fields(), generics)),
+                };
+
+                match (e.repr(), inner) {
+                    (EnumRepr::Untagged, None) => "z.null()".into(),
+                    (EnumRepr::Untagged, Some(inner)) => inner,
+                    (EnumRepr::External, None) => literal_str(name),
+                    (EnumRepr::External, Some(inner)) => {
+                        format!("z.object({{ {}: {inner} }})", property(name))
+                    }
+                    (EnumRepr::Internal { tag }, inner) => {
+                        let tag =
+                            format!("z.object({{ {}: {} }})", property(tag), literal_str(name));
+                        match inner {
+                            Some(inner) => format!("{tag}.and({inner})"),
+                            None => tag,
+                        }
+                    }
+                    (EnumRepr::Adjacent { tag, content }, inner) => {
+                        let mut properties =
+                            vec![format!("{}: {}", property(tag), literal_str(name))];
+                        if let Some(inner) = inner {
+                            properties.push(format!("{}: {inner}", property(content)));
+                        }
+                        format!("z.object({{ {} }})", properties.join(", "))
+                    }
+                }
+            })
+            .collect::<Vec<_>>();
+
+        match &variants[..] {
+            [] => "z.never()".into(),
+            [variant] => variant.clone(),
+            variants => format!("z.union([{}])", variants.join(", ")),
+        }
+    }
+}
+
+// Quote property names which aren't valid identifiers.
+fn property(name: &str) -> String {
+    let is_ident = name
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_' || c == '$')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$');
+    match is_ident {
+        true => name.to_string(),
+        false => serde_json::Value::from(name).to_string(),
+    }
+}
+
+fn literal_str(value: &str) -> String {
+    format!("z.literal({})", serde_json::Value::from(value))
+}
+
+fn primitive(p: &PrimitiveType) -> &'static str {
+    match p {
+        PrimitiveType::i8
+        | PrimitiveType::i16
+        | PrimitiveType::i32
+        | PrimitiveType::i64
+        | PrimitiveType::i128
+        | PrimitiveType::isize => "z.number().int()",
+        PrimitiveType::u8
+        | PrimitiveType::u16
+        | PrimitiveType::u32
+        | PrimitiveType::u64
+        | PrimitiveType::u128
+        | PrimitiveType::usize => "z.number().int().nonnegative()",
+        PrimitiveType::f32 | PrimitiveType::f64 => "z.number()",
+        PrimitiveType::bool => "z.boolean()",
+        PrimitiveType::char => "z.string().length(1)",
+        PrimitiveType::String => "z.string()",
+    }
+}
+
+fn literal(l: &LiteralType) -> String {
+    match l {
+        LiteralType::i8(v) => format!("z.literal({v})"),
+        LiteralType::i16(v) => format!("z.literal({v})"),
+        LiteralType::i32(v) => format!("z.literal({v})"),
+        LiteralType::u8(v) => format!("z.literal({v})"),
+        LiteralType::u16(v) => format!("z.literal({v})"),
+        LiteralType::u32(v) => format!("z.literal({v})"),
+        LiteralType::f32(v) => format!("z.literal({v})"),
+        LiteralType::f64(v) => format!("z.literal({v})"),
+        LiteralType::bool(v) => format!("z.literal({v})"),
+        LiteralType::String(v) => literal_str(v),
+        LiteralType::char(v) => literal_str(&v.to_string()),
+        LiteralType::None => "z.null()".into(),
+        _ => "z.unknown()".into(),
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+    use specta::Type;
+
+    use crate::Router;
+
+    #[derive(Serialize, Deserialize, Type)]
+    struct User {
+        id: u32,
+        name: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        #[specta(optional)]
+        email: Option<String>,
+        tags: Vec<String>,
+        location: (f64, f64),
+        role: Role,
+    }
+
+    #[derive(Serialize, Deserialize, Type)]
+    #[serde(tag = "type")]
+    enum Role {
+        Admin,
+        Member { team: String },
+    }
+
+    #[test]
+    fn test_zod_bindings() {
+        let router = <Router>::new()
+            .query("users.get", |t| {
+                t(|_, id: u32| -> Option<User> {
+                    let _ = id;
+                    None
+                })
+            })
+            .mutation("users.delete", |t| t(|_, _: u32| ()))
+            .build();
+
+        let bindings = router.zod_bindings();
+        assert!(bindings.contains("import { z } from \"zod\";"));
+        assert!(bindings.contains(
+            "export const User = z.object({ id: z.number().int().nonnegative(), name: z.string().nullable(), email: z.string().nullable().optional(), tags: z.array(z.string()), location: z.tuple([z.number(), z.number()]), role: z.lazy(() => Role) });"
+        ));
+        assert!(bindings.contains(
+            "export const Role = z.union([z.object({ type: z.literal(\"Admin\") }), z.object({ type: z.literal(\"Member\") }).and(z.object({ team: z.string() }))]);"
+        ));
+        assert!(bindings.contains(
+            "        \"users.get\": { input: z.number().int().nonnegative(), result: z.lazy(() => User).nullable() },"
+        ));
+        assert!(bindings.contains(
+            "        \"users.delete\": { input: z.number().int().nonnegative(), result: z.null() },"
+        ));
+        // Sorted by name like the Typescript bindings
+        assert!(bindings.find("const Role").unwrap() < bindings.find("const User").unwrap());
+    }
+}
@@ -6,6 +6,11 @@
 //!
 //! Checkout the official docs at <https://rspc.dev>. This documentation is generally written **for authors of middleware and adapter**.
 //!
+//! ## Feature flags
+//!
+//! - `runtime-tokio` (default) - Run subscriptions and timers on Tokio when no [`Config::runtime`] is set. This is the only part of rspc which requires an async runtime, see [`Runtime`] for using another one.
+//! - `tracing` - Log errors using [tracing](https://docs.rs/tracing).
+//!
 // #![forbid(unsafe_code)] // TODO
 #![warn(
     clippy::all,
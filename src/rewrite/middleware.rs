@@ -1,7 +1,9 @@
 mod middleware;
 mod next;
+mod rate_limit;
 
 pub use middleware::Middleware;
 pub use next::Next;
+pub use rate_limit::{RateLimited, RateLimiter};
 
 pub(crate) use middleware::MiddlewareHandler;
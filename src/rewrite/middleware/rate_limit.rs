@@ -0,0 +1,244 @@
+use std::{
+    collections::HashMap,
+    error, fmt,
+    hash::Hash,
+    sync::{Arc, Mutex, PoisonError},
+    time::{Duration, Instant},
+};
+
+use super::Middleware;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Bucket {
+    Procedure(String),
+    Category(String),
+}
+
+struct Window {
+    started: Instant,
+    count: u32,
+}
+
+// The number of windows at which expired windows are first swept.
+const MIN_SWEEP_AT: usize = 64;
+
+struct Buckets<TIdentity> {
+    windows: HashMap<(TIdentity, Bucket), Window>,
+    // Expired windows are only reset when their bucket is used again, so they're swept once there are this many.
+    // It's twice the number of windows left after the last sweep, so sweeping is amortised over the requests.
+    sweep_at: usize,
+}
+
+impl<TIdentity> Default for Buckets<TIdentity> {
+    fn default() -> Self {
+        Self {
+            windows: HashMap::new(),
+            sweep_at: 0,
+        }
+    }
+}
+
+/// The error returned by [`RateLimiter`] when a bucket is exhausted.
+///
+/// Your procedure's error type must implement `From<RateLimited>` so the middleware can return it. It's recommended to map it to a `429` status.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RateLimited {
+    /// How long until the bucket resets.
+    pub retry_after: Duration,
+}
+
+impl fmt::Display for RateLimited {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "rate limit exceeded, retry after {}ms",
+            self.retry_after.as_millis()
+        )
+    }
+}
+
+impl error::Error for RateLimited {}
+
+/// Limit how many requests each caller can make within a fixed window.
+///
+/// Requests are counted in a bucket keyed by `(identity, bucket)` where the identity is derived from the context. By default every procedure has it's own bucket.
+/// Procedures tagged with the same [`ProcedureBuilder::category`](crate::rewrite::procedure::ProcedureBuilder::category) share a single bucket instead (Eg. all search endpoints), so a caller can make `limit` requests across the whole category per window.
+///
+/// The limiter is cheap to clone and every clone shares the same buckets, so construct it once and apply [`RateLimiter::middleware`] to each procedure.
+pub struct RateLimiter<TCtx, TIdentity> {
+    limit: u32,
+    window: Duration,
+    identity: Arc<dyn Fn(&TCtx) -> TIdentity + Send + Sync>,
+    buckets: Arc<Mutex<Buckets<TIdentity>>>,
+}
+
+impl<TCtx, TIdentity> Clone for RateLimiter<TCtx, TIdentity> {
+    fn clone(&self) -> Self {
+        Self {
+            limit: self.limit,
+            window: self.window,
+            identity: self.identity.clone(),
+            buckets: self.buckets.clone(),
+        }
+    }
+}
+
+impl<TCtx, TIdentity> RateLimiter<TCtx, TIdentity>
+where
+    TCtx: Send + 'static,
+    TIdentity: Hash + Eq + Send + 'static,
+{
+    /// Allow `limit` requests per `window` for each identity and bucket.
+    ///
+    /// The `identity` function identifies the caller (Eg. the user or IP address) so callers never share a bucket.
+    pub fn new(
+        limit: u32,
+        window: Duration,
+        identity: impl Fn(&TCtx) -> TIdentity + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            limit,
+            window,
+            identity: Arc::new(identity),
+            buckets: Default::default(),
+        }
+    }
+
+    fn check(&self, identity: TIdentity, bucket: Bucket) -> Result<(), RateLimited> {
+        let mut buckets = self.buckets.lock().unwrap_or_else(PoisonError::into_inner);
+        let Buckets { windows, sweep_at } = &mut *buckets;
+        if windows.len() >= (*sweep_at).max(MIN_SWEEP_AT) {
+            windows.retain(|_, window| window.started.elapsed() < self.window);
+            *sweep_at = windows.len() * 2;
+        }
+
+        let window = windows.entry((identity, bucket)).or_insert(Window {
+            started: Instant::now(),
+            count: 0,
+        });
+        if window.started.elapsed() >= self.window {
+            *window = Window {
+                started: Instant::now(),
+                count: 0,
+            };
+        }
+        if window.count >= self.limit {
+            return Err(RateLimited {
+                retry_after: self.window.saturating_sub(window.started.elapsed()),
+            });
+        }
+        window.count += 1;
+        Ok(())
+    }
+
+    /// Construct the middleware to apply to a procedure with [`ProcedureBuilder::with`](crate::rewrite::procedure::ProcedureBuilder::with).
+    pub fn middleware<TError, TInput, TResult>(&self) -> Middleware<TError, TCtx, TInput, TResult>
+    where
+        TError: From<RateLimited> + Send + 'static,
+        TInput: Send + 'static,
+        TResult: Send + 'static,
+    {
+        let this = self.clone();
+        Middleware::new(move |ctx: TCtx, input: TInput, next| {
+            let meta = next.meta();
+            let bucket = match meta.category() {
+                Some(category) => Bucket::Category(category.to_string()),
+                None => Bucket::Procedure(meta.name().to_string()),
+            };
+            let result = this.check((this.identity)(&ctx), bucket);
+
+            async move {
+                result?;
+                next.exec(ctx, input).await
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use std::time::Duration;
+
+    use futures::StreamExt;
+    use serde::Serialize;
+    use specta::{Type, TypeMap};
+
+    use super::*;
+    use crate::rewrite::{procedure::Procedure, State};
+
+    #[derive(Debug, Serialize, Type)]
+    struct TestError(String);
+
+    impl fmt::Display for TestError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "{}", self.0)
+        }
+    }
+
+    impl error::Error for TestError {}
+
+    impl crate::rewrite::Error for TestError {}
+
+    impl From<RateLimited> for TestError {
+        fn from(err: RateLimited) -> Self {
+            Self(err.to_string())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_categories() {
+        let limiter = RateLimiter::new(2, Duration::from_secs(60), |ctx: &u32| *ctx);
+        let procedure = |category: Option<&'static str>| {
+            let builder =
+                Procedure::<u32>::builder::<(), (), TestError>().with(limiter.middleware());
+            let builder = match category {
+                Some(category) => builder.category(category),
+                None => builder,
+            };
+            builder.query(|_, _| async { Ok(()) })
+        };
+
+        let (mut state, mut types) = (State::default(), TypeMap::default());
+        let [search_a, search_b, other] = [
+            ("searchA", Some("search")),
+            ("searchB", Some("search")),
+            ("other", None),
+        ]
+        .map(|(key, category)| procedure(category).build(key.into(), &mut state, &mut types));
+
+        let call = |procedure: &Procedure<u32>, ctx| {
+            let stream = procedure.exec(ctx, serde_json::Value::Null).unwrap();
+            async move { stream.collect::<Vec<_>>().await.remove(0).is_ok() }
+        };
+
+        // Both search procedures share one bucket
+        assert!(call(&search_a, 1).await);
+        assert!(call(&search_b, 1).await);
+        assert!(!call(&search_a, 1).await);
+        assert!(!call(&search_b, 1).await);
+
+        // Uncategorised procedures and other callers have their own buckets
+        assert!(call(&other, 1).await);
+        assert!(call(&search_a, 2).await);
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_expired_windows_swept() {
+        let limiter = RateLimiter::new(1, Duration::from_millis(20), |ctx: &usize| *ctx);
+        let bucket = || Bucket::Procedure("search".into());
+        let windows = || limiter.buckets.lock().unwrap().windows.len();
+
+        for identity in 0..MIN_SWEEP_AT {
+            limiter.check(identity, bucket()).unwrap();
+        }
+        assert_eq!(windows(), MIN_SWEEP_AT);
+
+        // Windows of callers which never return are reclaimed once they expire
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        for identity in MIN_SWEEP_AT..MIN_SWEEP_AT * 2 {
+            limiter.check(identity, bucket()).unwrap();
+        }
+        assert_eq!(windows(), MIN_SWEEP_AT);
+    }
+}
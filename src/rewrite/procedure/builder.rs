@@ -1,4 +1,4 @@
-use std::{fmt, future::Future};
+use std::{borrow::Cow, fmt, future::Future};
 
 use futures::FutureExt;
 
@@ -14,6 +14,7 @@ pub struct ProcedureBuilder<TError, TCtx, TNextCtx, TInput, TResult> {
     pub(super) build: Box<
         dyn FnOnce(
             ProcedureKind,
+            Option<Cow<'static, str>>,
             Vec<Box<dyn FnOnce(&mut State, ProcedureMeta) + 'static>>,
             MiddlewareHandler<TError, TNextCtx, TInput, TResult>,
         ) -> UnbuiltProcedure<TCtx>,
@@ -47,21 +48,34 @@ where
         R: 'static,
     {
         ProcedureBuilder {
-            build: Box::new(|ty, mut setups, handler| {
+            build: Box::new(|ty, category, mut setups, handler| {
                 if let Some(setup) = mw.setup {
                     setups.push(setup);
                 }
 
-                (self.build)(ty, setups, (mw.inner)(handler))
+                (self.build)(ty, category, setups, (mw.inner)(handler))
             }),
         }
     }
 
     pub fn setup(self, func: impl FnOnce(&mut State, ProcedureMeta) + 'static) -> Self {
         Self {
-            build: Box::new(|ty, mut setups, handler| {
+            build: Box::new(|ty, category, mut setups, handler| {
                 setups.push(Box::new(func));
-                (self.build)(ty, setups, handler)
+                (self.build)(ty, category, setups, handler)
+            }),
+        }
+    }
+
+    /// Tag this procedure with a category, available to middleware through [`ProcedureMeta::category`].
+    ///
+    /// Procedures sharing a category are treated as a group, Eg. [`RateLimiter`](crate::rewrite::middleware::RateLimiter) gives every procedure in a category one shared bucket.
+    /// Calling this again replaces the previous category.
+    pub fn category(self, category: impl Into<Cow<'static, str>>) -> Self {
+        let category = category.into();
+        Self {
+            build: Box::new(|ty, inner, setups, handler| {
+                (self.build)(ty, inner.or(Some(category)), setups, handler)
             }),
         }
     }
@@ -72,6 +86,7 @@ where
     ) -> UnbuiltProcedure<TRootCtx> {
         (self.build)(
             ProcedureKind::Query,
+            None,
             Vec::new(),
             Box::new(move |ctx, input, _| Box::pin(handler(ctx, input))),
         )
@@ -83,6 +98,7 @@ where
     ) -> UnbuiltProcedure<TRootCtx> {
         (self.build)(
             ProcedureKind::Mutation,
+            None,
             Vec::new(),
             Box::new(move |ctx, input, _| Box::pin(handler(ctx, input))),
         )
@@ -104,6 +120,7 @@ where
     ) -> UnbuiltProcedure<TRootCtx> {
         (self.build)(
             ProcedureKind::Subscription,
+            None,
             Vec::new(),
             Box::new(move |ctx, input, _| {
                 Box::pin(handler(ctx, input).map(|s| s.map(|s| crate::rewrite::Stream(s))))
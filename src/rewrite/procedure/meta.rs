@@ -29,13 +29,19 @@ enum ProcedureName {
 pub struct ProcedureMeta {
     name: ProcedureName,
     kind: ProcedureKind,
+    category: Option<Cow<'static, str>>,
 }
 
 impl ProcedureMeta {
-    pub(crate) fn new(name: Cow<'static, str>, kind: ProcedureKind) -> Self {
+    pub(crate) fn new(
+        name: Cow<'static, str>,
+        kind: ProcedureKind,
+        category: Option<Cow<'static, str>>,
+    ) -> Self {
         Self {
             name: ProcedureName::Dynamic(Arc::new(name.into_owned())),
             kind,
+            category,
         }
     }
 }
@@ -51,4 +57,9 @@ impl ProcedureMeta {
     pub fn kind(&self) -> ProcedureKind {
         self.kind
     }
+
+    /// The category set with [`ProcedureBuilder::category`](super::ProcedureBuilder::category), if any.
+    pub fn category(&self) -> Option<&str> {
+        self.category.as_deref()
+    }
 }
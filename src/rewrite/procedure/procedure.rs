@@ -59,12 +59,12 @@ where
         R: ResolverOutput<TError>,
     {
         ProcedureBuilder {
-            build: Box::new(|kind, setups, handler| {
+            build: Box::new(|kind, category, setups, handler| {
                 // TODO: Don't be `Arc<Box<_>>` just `Arc<_>`
                 let handler = Arc::new(handler);
 
                 UnbuiltProcedure::new(move |key, state, type_map| {
-                    let meta = ProcedureMeta::new(key.clone(), kind);
+                    let meta = ProcedureMeta::new(key.clone(), kind, category);
                     for setup in setups {
                         setup(state, meta.clone());
                     }